@@ -1,10 +1,13 @@
 import { invoke } from '@tauri-apps/api/core';
 import { listen } from '@tauri-apps/api/event';
-import { open } from '@tauri-apps/plugin-dialog';
 import { open as openUrl } from '@tauri-apps/plugin-shell';
 import { initializeLayout } from './ui/layout';
 import { initializeToc } from './ui/toc';
 import { initializeSearch } from './ui/search';
+import { updateBacklinks } from './ui/backlinks';
+import { updateArchiveFiles } from './ui/archive';
+import { startPresentation } from './ui/presentation';
+import { renderChunks, clearChunks, revealAnchor, ChunkedContent } from './ui/chunks';
 import './styles/app.css';
 
 // Immediate console log to verify JavaScript is running
@@ -17,6 +20,51 @@ interface MarkdownDocument {
     raw_content: string;
     html_content: string;
     toc: TocItem[];
+    stats: DocumentStats;
+    profile: 'commonmark' | 'gfm' | 'extended';
+    custom_css?: string | null;
+    link_root?: string | null;
+    trust: 'trusted' | 'restricted';
+    chunked?: ChunkedContent | null;
+    compression?: 'gzip' | 'zstd' | 'bzip2' | 'xz' | null;
+    archive?: string | null;
+}
+
+interface LoadProgress {
+    path: string;
+    stage: 'reading' | 'rendering';
+    bytes_read: number;
+    total_bytes: number;
+}
+
+interface ConfigEntry {
+    key: string;
+    value: unknown;
+    source: { kind: 'default' } | { kind: 'settings' } | { kind: 'project'; path: string };
+}
+
+interface Theme {
+    id: string;
+    name: string;
+    appearance: 'light' | 'dark';
+    source: 'built_in' | 'user' | 'project';
+}
+
+interface ActiveTheme extends Theme {
+    css: string;
+}
+
+interface SectionStats {
+    words: number;
+    reading_minutes: number;
+    code_blocks: number;
+    tables: number;
+    images: number;
+    links: number;
+}
+
+interface DocumentStats extends SectionStats {
+    headings: number[];
 }
 
 interface TocItem {
@@ -24,6 +72,20 @@ interface TocItem {
     text: string;
     id: string;
     line_number?: number;
+    stats?: SectionStats;
+}
+
+interface CheckedLink {
+    kind: 'anchor' | 'file' | 'file_anchor' | 'external' | 'image';
+    url: string;
+    text: string;
+    line: number;
+    status: 'ok' | 'unchecked' | 'missing_file' | 'missing_anchor';
+}
+
+interface LinkReport {
+    path: string;
+    links: CheckedLink[];
 }
 
 interface NavigationState {
@@ -31,13 +93,21 @@ interface NavigationState {
     can_go_forward: boolean;
 }
 
+interface Exported {
+    path: string;
+    warning: string | null;
+}
+
 let currentDocument: MarkdownDocument | null = null;
+let brokenLinks = new Map<string, CheckedLink>();
+let loadSequence = 0;
 let currentZoom = 1.0;
 
 // DOM Elements
 const markdownContainer = document.getElementById('markdown-container')!;
 const tocNav = document.getElementById('toc-nav')!;
 const tocEmpty = document.getElementById('toc-empty')!;
+const docStats = document.getElementById('doc-stats')!;
 const btnOpen = document.getElementById('btn-open')!;
 const btnReload = document.getElementById('btn-reload')!;
 const btnPrevFile = document.getElementById('btn-prev-file')!;
@@ -48,14 +118,84 @@ const btnZoomOut = document.getElementById('btn-zoom-out')!;
 const btnZoomReset = document.getElementById('btn-zoom-reset')!;
 const zoomLevel = document.getElementById('zoom-level')!;
 
+/**
+ * Formats a word count and reading time, e.g. "1,250 words · 7 min".
+ */
+function formatLength(stats: SectionStats): string {
+    return `${stats.words.toLocaleString()} words · ${stats.reading_minutes} min`;
+}
+
+/**
+ * Invokes a command that loads a document and makes it current.
+ * A newer load cancels this one in the backend, so a superseded load resolves to null
+ * instead of rendering over the newer document or reporting its cancellation.
+ */
+async function loadDocument(command: string, args?: Record<string, unknown>): Promise<MarkdownDocument | null> {
+    const sequence = ++loadSequence;
+    try {
+        const doc = await invoke<MarkdownDocument | null>(command, args);
+        return sequence === loadSequence ? doc : null;
+    } catch (error) {
+        if (sequence !== loadSequence) return null;
+        throw error;
+    } finally {
+        if (sequence === loadSequence) hideLoadProgress();
+    }
+}
+
+/**
+ * Shows the progress of loading a large document.
+ */
+function showLoadProgress(progress: LoadProgress) {
+    const name = progress.path.split(/[\/\\]/).pop();
+    const text = document.getElementById('load-progress-text')!;
+    const bar = document.getElementById('load-progress-bar') as HTMLProgressElement;
+    if (progress.stage === 'reading') {
+        const percent = Math.round(progress.bytes_read / progress.total_bytes * 100);
+        text.textContent = `Loading ${name}… ${percent}%`;
+        bar.value = progress.bytes_read / progress.total_bytes;
+    } else {
+        text.textContent = `Rendering ${name}…`;
+        // Parsing can't report progress, so show an indeterminate bar
+        bar.removeAttribute('value');
+    }
+    document.getElementById('load-progress')!.style.display = 'flex';
+}
+
+/**
+ * Hides the load progress bar.
+ */
+function hideLoadProgress() {
+    document.getElementById('load-progress')!.style.display = 'none';
+}
+
 /**
  * Renders a loaded document.
  */
 function renderDocument(doc: MarkdownDocument) {
     currentDocument = doc;
     
-    // Render HTML content
-    markdownContainer.innerHTML = doc.html_content;
+    // Apply the project stylesheet, if any
+    let projectStyle = document.getElementById('project-css');
+    if (!projectStyle) {
+        projectStyle = document.createElement('style');
+        projectStyle.id = 'project-css';
+        document.head.appendChild(projectStyle);
+    }
+    projectStyle.textContent = doc.custom_css ?? '';
+    
+    // The project may select its own theme
+    refreshTheme();
+    
+    // Render HTML content; very large documents arrive in chunks that load as they scroll into view
+    brokenLinks = new Map();
+    if (doc.chunked) {
+        renderChunks(doc.chunked, setupContent);
+    } else {
+        clearChunks();
+        markdownContainer.innerHTML = doc.html_content;
+        setupContent(markdownContainer);
+    }
     markdownContainer.classList.add('markdown-content');
     
     // Apply current zoom level
@@ -69,8 +209,8 @@ function renderDocument(doc: MarkdownDocument) {
     // Render TOC
     if (doc.toc.length > 0) {
         tocNav.innerHTML = doc.toc.map(item => `
-            <a href="#${item.id}" class="toc-item level-${item.level}" data-id="${item.id}">
-                ${item.text}
+            <a href="#${item.id}" class="toc-item level-${item.level}" data-id="${item.id}"${item.stats ? ` title="${formatLength(item.stats)}"` : ''}>
+                ${item.text}${item.stats ? `<span class="toc-length">${item.stats.words.toLocaleString()}</span>` : ''}
             </a>
         `).join('');
         tocNav.style.display = 'flex';
@@ -80,19 +220,58 @@ function renderDocument(doc: MarkdownDocument) {
         tocEmpty.style.display = 'block';
     }
     
+    // Summarize the document above the TOC
+    const { stats } = doc;
+    docStats.textContent = formatLength(stats);
+    docStats.title = [
+        `${stats.code_blocks} code blocks`,
+        `${stats.tables} tables`,
+        `${stats.images} images`,
+        `${stats.links} links`,
+        `Headings: ${stats.headings.map((count, level) => `h${level + 1} ${count}`).join(', ')}`,
+        `Syntax: ${doc.profile}`,
+        ...(doc.compression ? [`Compressed: ${doc.compression}`] : [])
+    ].join('\n');
+    
     // Enable reload button
     (btnReload as HTMLButtonElement).disabled = false;
     
     // Update window title
     document.title = `mdview - ${doc.path}`;
     
-    // Setup link interception for external links
-    setupLinkHandling();
+    // Explain restricted mode and offer to trust the folder
+    updateTrustBar();
+    
+    // Flag broken local links before the user clicks them
+    markBrokenLinks();
+    
+    // Show documents that link here
+    updateBacklinks(doc.path, openBacklinkSource);
+    
+    // List the other documents of an archive
+    updateArchiveFiles(doc, path => openBacklinkSource(path));
     
     // Update navigation button states
     updateNavigationState();
 }
 
+/**
+ * Prepares newly rendered content: the whole document, or one chunk of a chunked document.
+ */
+function setupContent(root: HTMLElement) {
+    // Setup link interception for external links
+    rewriteLinks(root);
+    
+    // Let task checkboxes be ticked off when interactive tasks are enabled
+    setupTaskToggling(root);
+    
+    // Offer to load remote images that were blocked
+    updateRemoteImagesBar();
+    
+    // Flag broken links found by the last check
+    applyBrokenLinks(root);
+}
+
 /**
  * Updates the enabled/disabled state of navigation buttons.
  */
@@ -109,10 +288,10 @@ async function updateNavigationState() {
 }
 
 /**
- * Sets up link click handling to open external links in system browser and local files in app.
+ * Marks external and local file links so clicks on them can be handled by the app.
  */
-function setupLinkHandling() {
-    const links = markdownContainer.querySelectorAll('a');
+function rewriteLinks(root: HTMLElement) {
+    const links = root.querySelectorAll('a');
     
     links.forEach(link => {
         const href = link.getAttribute('href');
@@ -137,7 +316,12 @@ function setupLinkHandling() {
             link.classList.add('local-link');
         }
     });
-    
+}
+
+/**
+ * Sets up link click handling to open external links in system browser and local files in app.
+ */
+function setupLinkHandling() {
     // Use event delegation for handling clicks
     markdownContainer.addEventListener('click', async (e) => {
         const target = e.target as HTMLElement;
@@ -152,6 +336,11 @@ function setupLinkHandling() {
             e.preventDefault();
             e.stopPropagation();
             
+            if (currentDocument?.trust === 'restricted'
+                && !confirm(`This document is in restricted mode. Open this link?\n\n${externalUrl}`)) {
+                return;
+            }
+            
             try {
                 await openUrl(externalUrl);
             } catch (err: any) {
@@ -172,7 +361,8 @@ function setupLinkHandling() {
                 await openLocalFile(localFile);
             } catch (err: any) {
                 console.error('Failed to open local file:', err);
-                alert(`Failed to open file: ${err}`);
+                const msg = err && typeof err === 'object' && 'message' in err ? err.message : err;
+                alert(`Failed to open file: ${msg}`);
             }
             return;
         }
@@ -183,7 +373,7 @@ function setupLinkHandling() {
         if (href && href.startsWith('#')) {
             e.preventDefault();
             const targetId = href.substring(1);
-            let targetElement = markdownContainer.querySelector(`[id="${targetId}"]`);
+            let targetElement = await revealAnchor(targetId);
             
             if (targetElement) {
                 // If target is an anchor element, check parent or next sibling for heading
@@ -216,6 +406,159 @@ function setupLinkHandling() {
     });
 }
 
+/**
+ * Enables task list checkboxes that carry a source line and writes toggles back to the file.
+ * Checkboxes only carry source lines when `render.interactive_tasks` is set.
+ */
+function setupTaskToggling(root: HTMLElement) {
+    // Compressed files and files inside archives are never written back
+    if (currentDocument?.compression || currentDocument?.archive) return;
+    
+    root.querySelectorAll<HTMLInputElement>('li[data-sourcepos] > input[type="checkbox"]').forEach(checkbox => {
+        const sourcepos = checkbox.parentElement!.getAttribute('data-sourcepos')!;
+        const line = parseInt(sourcepos.split(':')[0], 10);
+        if (isNaN(line)) return;
+        
+        checkbox.disabled = false;
+        checkbox.classList.add('task-toggle');
+        checkbox.addEventListener('change', async () => {
+            checkbox.disabled = true;
+            try {
+                const contentArea = document.getElementById('content-area')!;
+                const scrollTop = contentArea.scrollTop;
+                const doc = await loadDocument('toggle_task', { line });
+                if (!doc) return;
+                renderDocument(doc);
+                contentArea.scrollTop = scrollTop;
+            } catch (error) {
+                console.error('Failed to toggle task:', error);
+                checkbox.checked = !checkbox.checked;
+                checkbox.disabled = false;
+                const msg = error && typeof error === 'object' && 'message' in error ? (error as any).message : error;
+                alert(`Failed to update task: ${msg}`);
+            }
+        });
+    });
+}
+
+/**
+ * Shows which remote images were blocked and offers to load them.
+ * Blocked images keep their URL in `data-remote-src`, which the webview never loads.
+ */
+function updateRemoteImagesBar() {
+    const bar = document.getElementById('remote-images-bar')!;
+    const blocked = Array.from(markdownContainer.querySelectorAll<HTMLImageElement>('img[data-remote-src]'));
+    // Restricted documents can't load remote images; the trust bar explains why
+    if (blocked.length === 0 || currentDocument?.trust === 'restricted') {
+        bar.style.display = 'none';
+        return;
+    }
+    
+    const hosts = new Set<string>();
+    blocked.forEach(img => {
+        const url = img.getAttribute('data-remote-src')!;
+        try {
+            const host = new URL(url, 'https://placeholder.invalid').hostname;
+            hosts.add(host);
+            img.title = `Remote image from ${host} (blocked)`;
+        } catch {
+            img.title = 'Remote image (blocked)';
+        }
+    });
+    
+    document.getElementById('remote-images-message')!.textContent =
+        `${blocked.length} remote image${blocked.length === 1 ? '' : 's'} blocked to protect your privacy.`;
+    const domains = document.getElementById('remote-images-domains')!;
+    domains.innerHTML = '';
+    hosts.forEach(host => {
+        const button = document.createElement('button');
+        button.textContent = `Always allow ${host}`;
+        button.addEventListener('click', () => allowRemoteImages(host));
+        domains.appendChild(button);
+    });
+    bar.style.display = 'flex';
+}
+
+/**
+ * Shows the restricted mode bar for documents outside the trusted folders.
+ */
+function updateTrustBar() {
+    const bar = document.getElementById('trust-bar')!;
+    bar.style.display = currentDocument?.trust === 'restricted' ? 'flex' : 'none';
+}
+
+/**
+ * Trusts the current document's folder after the backend confirms with the user, and re-renders.
+ */
+async function trustFolder() {
+    try {
+        const contentArea = document.getElementById('content-area')!;
+        const scrollTop = contentArea.scrollTop;
+        const doc = await loadDocument('trust_folder', { path: null });
+        if (doc) {
+            renderDocument(doc);
+            contentArea.scrollTop = scrollTop;
+        }
+    } catch (error) {
+        console.error('Failed to trust folder:', error);
+        const msg = error && typeof error === 'object' && 'message' in error ? (error as any).message : error;
+        alert(`Failed to trust folder: ${msg}`);
+    }
+}
+
+/**
+ * Allows remote images for the current document, or for a domain, and re-renders.
+ */
+async function allowRemoteImages(domain: string | null) {
+    try {
+        const contentArea = document.getElementById('content-area')!;
+        const scrollTop = contentArea.scrollTop;
+        const doc = await loadDocument('allow_remote_images', { domain });
+        if (!doc) return;
+        renderDocument(doc);
+        contentArea.scrollTop = scrollTop;
+    } catch (error) {
+        console.error('Failed to allow remote images:', error);
+        const msg = error && typeof error === 'object' && 'message' in error ? (error as any).message : error;
+        alert(`Failed to allow remote images: ${msg}`);
+    }
+}
+
+/**
+ * Asks the backend to check the current document's links and marks broken ones.
+ */
+async function markBrokenLinks() {
+    try {
+        const reports = await invoke<LinkReport[]>('check_links');
+        const broken = new Map<string, CheckedLink>();
+        reports.forEach(report => report.links
+            .filter(link => link.status === 'missing_file' || link.status === 'missing_anchor')
+            .forEach(link => broken.set(link.url, link)));
+        
+        // Kept for chunks of large documents that load later
+        brokenLinks = broken;
+        applyBrokenLinks(markdownContainer);
+    } catch (error) {
+        console.error('Failed to check links:', error);
+    }
+}
+
+/**
+ * Marks the broken links found by the last link check.
+ */
+function applyBrokenLinks(root: HTMLElement) {
+    if (brokenLinks.size === 0) return;
+    
+    root.querySelectorAll('a').forEach(link => {
+        const url = link.getAttribute('data-local-file') || link.getAttribute('href');
+        const result = url ? brokenLinks.get(url) : undefined;
+        if (result) {
+            link.classList.add('broken-link');
+            link.title = result.status === 'missing_file' ? 'Broken link: file not found' : 'Broken link: heading not found';
+        }
+    });
+}
+
 /**
  * Opens a local file referenced by a relative path in a markdown link.
  * Resolves the path relative to the current document.
@@ -225,8 +568,9 @@ async function openLocalFile(relativePath: string) {
         throw new Error('No document is currently loaded');
     }
     
-    // Extract the file path and anchor (if any)
-    const [filePath, anchor] = relativePath.split('#');
+    // Extract the file path and anchor (if any), undoing URL escapes such as %20
+    const [encodedPath, anchor] = relativePath.split('#');
+    const filePath = decodeURIComponent(encodedPath);
     
     // Get the directory of the current document
     const currentPath = currentDocument.path;
@@ -237,7 +581,10 @@ async function openLocalFile(relativePath: string) {
     let absolutePath: string;
     
     // Handle different path formats
-    if (filePath.startsWith('/')) {
+    if (filePath.startsWith('/') && currentDocument.link_root) {
+        // Rooted at the project's link root
+        absolutePath = currentDocument.link_root.replace(/[\/\\]+$/, '') + filePath;
+    } else if (filePath.startsWith('/')) {
         // Already absolute
         absolutePath = filePath;
     } else if (filePath.startsWith('./') || filePath.startsWith('../')) {
@@ -262,13 +609,14 @@ async function openLocalFile(relativePath: string) {
     }
     
     // Load the document
-    const doc = await invoke<MarkdownDocument>('open_document', { path: absolutePath });
+    const doc = await loadDocument('open_document', { path: absolutePath });
+    if (!doc) return;
     renderDocument(doc);
     
     // If there's an anchor, scroll to it after a brief delay
     if (anchor) {
-        setTimeout(() => {
-            const targetElement = markdownContainer.querySelector(`[id="${anchor}"]`);
+        setTimeout(async () => {
+            const targetElement = await revealAnchor(anchor);
             if (targetElement) {
                 targetElement.scrollIntoView({ behavior: 'smooth', block: 'start' });
             }
@@ -276,32 +624,44 @@ async function openLocalFile(relativePath: string) {
     }
 }
 
+/**
+ * Opens the document a backlink comes from, scrolled to the link's section.
+ */
+async function openBacklinkSource(path: string, headingId?: string) {
+    try {
+        const doc = await loadDocument('open_document', { path });
+        if (!doc) return;
+        renderDocument(doc);
+        
+        if (headingId) {
+            setTimeout(async () => {
+                const targetElement = await revealAnchor(headingId);
+                if (targetElement) {
+                    targetElement.scrollIntoView({ behavior: 'smooth', block: 'start' });
+                }
+            }, 100);
+        }
+    } catch (error) {
+        console.error('Failed to open backlink source:', error);
+        alert(`Failed to open file: ${error}`);
+    }
+}
+
 /**
  * Opens a file dialog and loads the selected Markdown file.
+ * The dialog runs in the backend so the chosen file is approved for opening.
  */
 async function openFile() {
     try {
-        const selected = await open({
-            multiple: false,
-            filters: [{
-                name: 'Markdown',
-                extensions: ['md', 'markdown', 'mdown', 'mkd', 'mdx']
-            }]
-        });
-        
-        console.log('File dialog returned:', selected, 'Type:', typeof selected);
-        
-        if (selected) {
-            // The dialog returns a string path directly
-            const path = typeof selected === 'string' ? selected : (selected as any).path || String(selected);
-            console.log('Opening file:', path);
-            const doc = await invoke<MarkdownDocument>('open_document', { path });
+        const doc = await loadDocument('choose_document');
+        if (doc) {
             console.log('Document loaded:', doc.path);
             renderDocument(doc);
         }
     } catch (error) {
         console.error('Failed to open file:', error);
-        alert(`Failed to open file: ${error}`);
+        const msg = error && typeof error === 'object' && 'message' in error ? (error as any).message : error;
+        alert(`Failed to open file: ${msg}`);
     }
 }
 
@@ -312,8 +672,8 @@ async function reloadDocument() {
     if (!currentDocument) return;
     
     try {
-        const doc = await invoke<MarkdownDocument>('reload_document');
-        renderDocument(doc);
+        const doc = await loadDocument('reload_document');
+        if (doc) renderDocument(doc);
     } catch (error) {
         console.error('Failed to reload document:', error);
         alert(`Failed to reload document: ${error}`);
@@ -325,8 +685,8 @@ async function reloadDocument() {
  */
 async function navigatePrevious() {
     try {
-        const doc = await invoke<MarkdownDocument>('navigate_previous');
-        renderDocument(doc);
+        const doc = await loadDocument('navigate_previous');
+        if (doc) renderDocument(doc);
     } catch (error) {
         console.error('Failed to navigate to previous file:', error);
         // Don't alert for "no previous file" errors
@@ -344,8 +704,8 @@ async function navigatePrevious() {
  */
 async function navigateNext() {
     try {
-        const doc = await invoke<MarkdownDocument>('navigate_next');
-        renderDocument(doc);
+        const doc = await loadDocument('navigate_next');
+        if (doc) renderDocument(doc);
     } catch (error) {
         console.error('Failed to navigate to next file:', error);
         // Don't alert for "no next file" errors
@@ -358,6 +718,130 @@ async function navigateNext() {
     }
 }
 
+/**
+ * Opens the current document in the external editor at the heading in view.
+ */
+async function openInEditor() {
+    if (!currentDocument) return;
+    
+    const activeItem = tocNav.querySelector('.toc-item.active');
+    const headingId = activeItem ? activeItem.getAttribute('data-id') : null;
+    
+    try {
+        await invoke('open_in_editor', { headingId });
+    } catch (error) {
+        console.error('Failed to open external editor:', error);
+        const msg = error && typeof error === 'object' && 'message' in error ? (error as any).message : error;
+        alert(`Failed to open external editor: ${msg}`);
+    }
+}
+
+/**
+ * Exports the current document as PDF; the backend asks for the destination.
+ */
+async function exportPdf() {
+    if (!currentDocument) return;
+    
+    try {
+        const exported = await invoke<Exported | null>('export_pdf');
+        if (exported?.warning) {
+            alert(`PDF exported. ${exported.warning}`);
+        }
+    } catch (error) {
+        console.error('Failed to export PDF:', error);
+        const msg = error && typeof error === 'object' && 'message' in error ? (error as any).message : error;
+        alert(`Failed to export PDF: ${msg}`);
+    }
+}
+
+/**
+ * Exports a folder of Markdown files as an EPUB book; the backend asks for
+ * the folder and the destination.
+ */
+async function exportEpub() {
+    try {
+        await invoke<Exported | null>('export_epub');
+    } catch (error) {
+        console.error('Failed to export EPUB:', error);
+        const msg = error && typeof error === 'object' && 'message' in error ? (error as any).message : error;
+        alert(`Failed to export EPUB: ${msg}`);
+    }
+}
+
+/**
+ * Exports the current document as an HTML slide deck; the backend asks for
+ * the destination.
+ */
+async function exportSlides() {
+    if (!currentDocument) return;
+    
+    try {
+        await invoke<Exported | null>('export_slides');
+    } catch (error) {
+        console.error('Failed to export slide deck:', error);
+        const msg = error && typeof error === 'object' && 'message' in error ? (error as any).message : error;
+        alert(`Failed to export slide deck: ${msg}`);
+    }
+}
+
+/**
+ * Shows the effective configuration and where each value came from.
+ */
+async function showEffectiveConfig() {
+    try {
+        const entries = await invoke<ConfigEntry[]>('get_effective_config');
+        const lines = entries.map(entry => {
+            const source = entry.source.kind === 'project' ? entry.source.path : entry.source.kind;
+            return `${entry.key} = ${JSON.stringify(entry.value)}  (${source})`;
+        });
+        alert(`Effective configuration\n\n${lines.join('\n')}`);
+    } catch (error) {
+        console.error('Failed to get configuration:', error);
+        const msg = error && typeof error === 'object' && 'message' in error ? (error as any).message : error;
+        alert(`Failed to get configuration: ${msg}`);
+    }
+}
+
+/**
+ * Applies a theme stylesheet and exposes its appearance for code block colors.
+ * The theme goes before the project stylesheet so projects can adjust it.
+ */
+function applyTheme(theme: ActiveTheme) {
+    let themeStyle = document.getElementById('theme-css');
+    if (!themeStyle) {
+        themeStyle = document.createElement('style');
+        themeStyle.id = 'theme-css';
+        document.head.insertBefore(themeStyle, document.getElementById('project-css'));
+    }
+    themeStyle.textContent = theme.css;
+    document.documentElement.dataset.appearance = theme.appearance;
+}
+
+/**
+ * Re-reads the theme in effect for the current document.
+ */
+async function refreshTheme() {
+    try {
+        applyTheme(await invoke<ActiveTheme>('get_theme'));
+    } catch (error) {
+        console.error('Failed to load theme:', error);
+    }
+}
+
+/**
+ * Selects a theme and applies it immediately. `null` follows the system's
+ * light or dark mode again.
+ */
+async function selectTheme(id: string | null) {
+    try {
+        applyTheme(await invoke<ActiveTheme>('set_theme', { id }));
+    } catch (error) {
+        console.error('Failed to set theme:', error);
+        const msg = error && typeof error === 'object' && 'message' in error ? (error as any).message : error;
+        alert(`Failed to set theme: ${msg}`);
+    }
+}
+
 /**
  * Copies selected text to clipboard.
  */
@@ -478,6 +962,7 @@ async function initialize() {
     initializeLayout();
     initializeToc();
     initializeSearch();
+    setupLinkHandling();
     
     // Set up event listeners FIRST, before any other setup
     // Listen for document loaded from CLI
@@ -492,12 +977,32 @@ async function initialize() {
         alert(`Failed to load document: ${event.payload}`);
     });
     
+    // Show progress while large documents load
+    await listen<LoadProgress>('load-progress', (event) => {
+        showLoadProgress(event.payload);
+    });
+    
     // Listen for menu events
     await listen('menu-open', () => {
         console.log('Menu: Open');
         openFile();
     });
     
+    await listen('menu-export-pdf', () => {
+        console.log('Menu: Export as PDF');
+        exportPdf();
+    });
+    
+    await listen('menu-export-epub', () => {
+        console.log('Menu: Export Folder as EPUB');
+        exportEpub();
+    });
+    
+    await listen('menu-export-slides', () => {
+        console.log('Menu: Export as Slide Deck');
+        exportSlides();
+    });
+    
     await listen('menu-copy', () => {
         console.log('Menu: Copy');
         copySelection();
@@ -510,6 +1015,11 @@ async function initialize() {
         (document.getElementById('search-input') as HTMLInputElement).focus();
     });
     
+    await listen('menu-open-in-editor', () => {
+        console.log('Menu: Edit in External Editor');
+        openInEditor();
+    });
+    
     await listen('menu-zoom-in', () => {
         console.log('Menu: Zoom In');
         setZoom(Math.min(currentZoom + 0.1, 3.0));
@@ -525,6 +1035,27 @@ async function initialize() {
         setZoom(1.0);
     });
     
+    await listen('menu-start-presentation', () => {
+        console.log('Menu: Start Presentation');
+        if (currentDocument) startPresentation();
+    });
+    
+    await listen('menu-show-config', () => {
+        console.log('Menu: Show Effective Configuration');
+        showEffectiveConfig();
+    });
+    
+    await listen<string | null>('menu-set-theme', (event) => {
+        console.log('Menu: Theme', event.payload);
+        selectTheme(event.payload);
+    });
+    
+    // The system switched between light and dark mode
+    await listen<ActiveTheme>('theme-changed', (event) => {
+        console.log('Theme changed:', event.payload.id);
+        applyTheme(event.payload);
+    });
+    
     await listen('menu-about', () => {
         console.log('Menu: About');
         alert('mdview v0.1.1\\n\\nA lightweight Markdown viewer\\n\\n© 2025 David Eidelman\\nLicensed under MIT');
@@ -542,6 +1073,9 @@ async function initialize() {
     
     console.log('Event listeners registered');
     
+    // Apply the selected theme before any document is shown
+    await refreshTheme();
+    
     // Set up toolbar button handlers
     btnOpen.addEventListener('click', openFile);
     btnReload.addEventListener('click', reloadDocument);
@@ -560,6 +1094,10 @@ async function initialize() {
     btnZoomOut.addEventListener('click', () => setZoom(Math.max(currentZoom - 0.1, 0.5)));
     btnZoomReset.addEventListener('click', () => setZoom(1.0));
     
+    // Remote images
+    document.getElementById('btn-allow-document-images')!.addEventListener('click', () => allowRemoteImages(null));
+    document.getElementById('btn-trust-folder')!.addEventListener('click', trustFolder);
+    
     // Global keyboard shortcuts
     document.addEventListener('keydown', (e) => {
         // Ctrl/Cmd+F for search
@@ -582,6 +1120,12 @@ async function initialize() {
             reloadDocument();
         }
         
+        // Ctrl/Cmd+E for external editor
+        if ((e.metaKey || e.ctrlKey) && e.key === 'e' && currentDocument) {
+            e.preventDefault();
+            openInEditor();
+        }
+        
         // Ctrl/Cmd+Plus for zoom in
         if ((e.metaKey || e.ctrlKey) && (e.key === '=' || e.key === '+')) {
             e.preventDefault();
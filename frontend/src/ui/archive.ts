@@ -0,0 +1,61 @@
+import { invoke } from '@tauri-apps/api/core';
+
+// Separates the archive from the file inside it, as in `bundle.zip!/docs/index.md`
+const ARCHIVE_SEPARATOR = '!/';
+
+/**
+ * Escapes text for safe insertion into HTML.
+ */
+function escapeHtml(text: string): string {
+    const div = document.createElement('div');
+    div.textContent = text;
+    return div.innerHTML;
+}
+
+/**
+ * Returns the path of a file within its archive.
+ */
+function entryName(path: string): string {
+    const separator = path.indexOf(ARCHIVE_SEPARATOR);
+    return separator < 0 ? path : path.substring(separator + ARCHIVE_SEPARATOR.length);
+}
+
+/**
+ * Lists the Markdown files of the archive the current document was read from,
+ * or hides the list for documents outside an archive.
+ * Clicking a file calls `openFile` with its path.
+ */
+export async function updateArchiveFiles(
+    doc: { path: string; archive?: string | null },
+    openFile: (path: string) => void
+) {
+    const archiveContainer = document.getElementById('archive-container')!;
+    const archiveNav = document.getElementById('archive-nav')!;
+
+    if (!doc.archive) {
+        archiveContainer.style.display = 'none';
+        archiveNav.innerHTML = '';
+        return;
+    }
+
+    let files: string[] = [];
+    try {
+        files = await invoke<string[]>('get_archive_files');
+    } catch (error) {
+        console.error('Failed to list archive files:', error);
+    }
+
+    archiveContainer.style.display = 'block';
+    archiveContainer.title = doc.archive;
+    archiveNav.innerHTML = files.map((path, index) => `
+        <a class="backlink-item archive-item" data-index="${index}"${path === doc.path ? ' aria-current="page"' : ''}>
+            ${escapeHtml(entryName(path))}
+        </a>
+    `).join('');
+
+    archiveNav.querySelectorAll('.archive-item').forEach(item => {
+        item.addEventListener('click', () => {
+            openFile(files[Number(item.getAttribute('data-index'))]);
+        });
+    });
+}
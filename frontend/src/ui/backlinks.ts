@@ -0,0 +1,67 @@
+import { invoke } from '@tauri-apps/api/core';
+
+interface Backlink {
+    source: string;
+    text: string;
+    line: number;
+    heading?: { id: string; text: string; level: number } | null;
+    anchor?: string | null;
+}
+
+/**
+ * Escapes text for safe insertion into HTML.
+ */
+function escapeHtml(text: string): string {
+    const div = document.createElement('div');
+    div.textContent = text;
+    return div.innerHTML;
+}
+
+/**
+ * Returns the file name portion of a path.
+ */
+function fileName(path: string): string {
+    const lastSlash = Math.max(path.lastIndexOf('/'), path.lastIndexOf('\\'));
+    return path.substring(lastSlash + 1);
+}
+
+/**
+ * Loads and renders the backlinks for a document.
+ * Clicking a backlink calls `openSource` with the referencing file and the
+ * heading the link appears under.
+ */
+export async function updateBacklinks(
+    path: string,
+    openSource: (path: string, headingId?: string) => void
+) {
+    const backlinksNav = document.getElementById('backlinks-nav')!;
+    const backlinksEmpty = document.getElementById('backlinks-empty')!;
+    
+    let backlinks: Backlink[] = [];
+    try {
+        backlinks = await invoke<Backlink[]>('get_backlinks', { path });
+    } catch (error) {
+        console.error('Failed to get backlinks:', error);
+    }
+    
+    if (backlinks.length === 0) {
+        backlinksNav.innerHTML = '';
+        backlinksEmpty.style.display = 'block';
+        return;
+    }
+    
+    backlinksEmpty.style.display = 'none';
+    backlinksNav.innerHTML = backlinks.map((link, index) => `
+        <a class="backlink-item" data-index="${index}" title="${escapeHtml(link.source)}:${link.line}">
+            ${escapeHtml(link.text || fileName(link.source))}
+            <span class="backlink-context">${escapeHtml(fileName(link.source))}${link.heading ? ' › ' + escapeHtml(link.heading.text) : ''}</span>
+        </a>
+    `).join('');
+    
+    backlinksNav.querySelectorAll('.backlink-item').forEach(item => {
+        item.addEventListener('click', () => {
+            const link = backlinks[Number(item.getAttribute('data-index'))];
+            openSource(link.source, link.heading?.id);
+        });
+    });
+}
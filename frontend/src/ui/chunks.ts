@@ -0,0 +1,168 @@
+import { invoke } from '@tauri-apps/api/core';
+
+export interface Chunk {
+    id: string;
+    index: number;
+    html: string;
+}
+
+export interface ChunkInfo {
+    id: string;
+    size: number;
+    anchors: string[];
+}
+
+export interface ChunkedContent {
+    chunks: ChunkInfo[];
+    initial: Chunk[];
+}
+
+// Rough height of rendered HTML per byte, for sizing chunks that aren't loaded yet
+const PIXELS_PER_BYTE = 0.05;
+
+// Chunks are fetched this far ahead of the viewport, and dropped again beyond the second margin
+const LOAD_MARGIN = '2000px 0px';
+const UNLOAD_MARGIN = '10000px 0px';
+
+let chunked: ChunkedContent | null = null;
+let onChunkRendered: (element: HTMLElement) => void = () => {};
+let loadObserver: IntersectionObserver | null = null;
+let unloadObserver: IntersectionObserver | null = null;
+let pending = new Set<number>();
+let generation = 0;
+
+/**
+ * Shows a chunked document: one placeholder per chunk, sized from its length,
+ * with the first chunks filled in. The others are fetched as they near the viewport
+ * and emptied again once far away, keeping their measured height.
+ */
+export function renderChunks(content: ChunkedContent, onRender: (element: HTMLElement) => void) {
+    const markdownContainer = document.getElementById('markdown-container')!;
+    clearChunks();
+    chunked = content;
+    onChunkRendered = onRender;
+
+    markdownContainer.innerHTML = '';
+    content.chunks.forEach((info, index) => {
+        const element = document.createElement('div');
+        element.className = 'md-chunk';
+        element.dataset.chunkIndex = String(index);
+        element.dataset.chunkId = info.id;
+        element.style.minHeight = `${Math.round(info.size * PIXELS_PER_BYTE)}px`;
+        markdownContainer.appendChild(element);
+    });
+    content.initial.forEach(fillChunk);
+
+    loadObserver = new IntersectionObserver(entries => {
+        entries
+            .filter(entry => entry.isIntersecting)
+            .forEach(entry => requestChunk(chunkIndex(entry.target)));
+    }, { root: markdownContainer, rootMargin: LOAD_MARGIN });
+    unloadObserver = new IntersectionObserver(entries => {
+        entries
+            .filter(entry => !entry.isIntersecting)
+            .forEach(entry => unloadChunk(entry.target as HTMLElement));
+    }, { root: markdownContainer, rootMargin: UNLOAD_MARGIN });
+
+    markdownContainer.querySelectorAll('.md-chunk').forEach(element => {
+        loadObserver!.observe(element);
+        unloadObserver!.observe(element);
+    });
+}
+
+/**
+ * Stops loading chunks of the previous document.
+ */
+export function clearChunks() {
+    loadObserver?.disconnect();
+    unloadObserver?.disconnect();
+    loadObserver = null;
+    unloadObserver = null;
+    chunked = null;
+    pending = new Set();
+    generation++;
+}
+
+/**
+ * Finds the element with an id, loading the chunk that contains it first if needed.
+ */
+export async function revealAnchor(id: string): Promise<Element | null> {
+    const markdownContainer = document.getElementById('markdown-container')!;
+    const found = markdownContainer.querySelector(`[id="${id}"]`);
+    if (found || !chunked) return found;
+
+    const index = chunked.chunks.findIndex(info => info.anchors.includes(id));
+    if (index < 0) return null;
+
+    // Load the neighbours too, so the target doesn't move as they replace their placeholders
+    await loadChunks(Math.max(index - 1, 0), index + 2);
+    return markdownContainer.querySelector(`[id="${id}"]`);
+}
+
+/**
+ * Queues a chunk for loading; chunks that come into range together are fetched in one request.
+ */
+function requestChunk(index: number) {
+    if (isLoaded(index)) return;
+    if (pending.size === 0) {
+        queueMicrotask(() => {
+            const indices = Array.from(pending);
+            pending = new Set();
+            if (indices.length > 0) {
+                loadChunks(Math.min(...indices), Math.max(...indices) + 1);
+            }
+        });
+    }
+    pending.add(index);
+}
+
+/**
+ * Fetches a range of chunks from the backend and fills their placeholders.
+ */
+async function loadChunks(start: number, end: number) {
+    const sequence = generation;
+    try {
+        const chunks = await invoke<Chunk[]>('get_chunks', { start, end });
+        // Another document may have been opened in the meantime
+        if (sequence !== generation) return;
+        chunks.filter(chunk => !isLoaded(chunk.index)).forEach(fillChunk);
+    } catch (error) {
+        console.error('Failed to load document chunks:', error);
+    }
+}
+
+/**
+ * Replaces a chunk's placeholder with its content.
+ */
+function fillChunk(chunk: Chunk) {
+    const element = chunkElement(chunk.index);
+    if (!element || element.dataset.chunkId !== chunk.id) return;
+
+    element.innerHTML = chunk.html;
+    element.style.minHeight = '';
+    element.dataset.loaded = 'true';
+    onChunkRendered(element);
+}
+
+/**
+ * Empties a chunk that is far from the viewport, keeping its height so the scroll position holds.
+ */
+function unloadChunk(element: HTMLElement) {
+    if (!element.dataset.loaded) return;
+
+    element.style.minHeight = `${element.offsetHeight}px`;
+    element.innerHTML = '';
+    delete element.dataset.loaded;
+}
+
+function chunkElement(index: number): HTMLElement | null {
+    return document.querySelector<HTMLElement>(`#markdown-container > .md-chunk[data-chunk-index="${index}"]`);
+}
+
+function chunkIndex(element: Element): number {
+    return Number((element as HTMLElement).dataset.chunkIndex);
+}
+
+function isLoaded(index: number): boolean {
+    return chunkElement(index)?.dataset.loaded === 'true';
+}
@@ -0,0 +1,98 @@
+import { invoke } from '@tauri-apps/api/core';
+
+interface Slide {
+    title: string | null;
+    html: string;
+    notes: string | null;
+}
+
+/**
+ * Shows the current document as a full-screen presentation.
+ *
+ * Arrow keys, Page Up/Down and Space move between slides, Home and End jump
+ * to the first and last slide, `n` toggles speaker notes and Escape ends
+ * the presentation.
+ */
+export async function startPresentation() {
+    if (document.getElementById('presentation')) return;
+
+    let slides: Slide[] = [];
+    try {
+        slides = await invoke<Slide[]>('get_slides');
+    } catch (error) {
+        console.error('Failed to get slides:', error);
+        const msg = error && typeof error === 'object' && 'message' in error ? (error as any).message : error;
+        alert(`Failed to start presentation: ${msg}`);
+        return;
+    }
+    if (slides.length === 0) return;
+
+    const overlay = document.createElement('div');
+    overlay.id = 'presentation';
+    overlay.innerHTML = `
+        <div class="presentation-slide markdown-content"></div>
+        <div class="presentation-notes markdown-content"></div>
+        <div class="presentation-counter"></div>
+    `;
+    document.body.appendChild(overlay);
+
+    const slideEl = overlay.querySelector<HTMLElement>('.presentation-slide')!;
+    const notesEl = overlay.querySelector<HTMLElement>('.presentation-notes')!;
+    const counterEl = overlay.querySelector<HTMLElement>('.presentation-counter')!;
+    let current = 0;
+
+    const show = (index: number) => {
+        current = Math.max(0, Math.min(slides.length - 1, index));
+        const slide = slides[current];
+        slideEl.innerHTML = slide.html;
+        slideEl.scrollTop = 0;
+        notesEl.innerHTML = slide.notes ?? '';
+        notesEl.classList.toggle('empty', !slide.notes);
+        counterEl.textContent = `${current + 1} / ${slides.length}`;
+    };
+
+    const close = () => {
+        document.removeEventListener('keydown', onKeyDown, true);
+        document.removeEventListener('fullscreenchange', onFullscreenChange);
+        overlay.remove();
+        if (document.fullscreenElement) {
+            document.exitFullscreen().catch(() => {});
+        }
+    };
+
+    const onKeyDown = (event: KeyboardEvent) => {
+        switch (event.key) {
+            case 'ArrowRight': case 'ArrowDown': case 'PageDown': case ' ':
+                show(current + 1); break;
+            case 'ArrowLeft': case 'ArrowUp': case 'PageUp': case 'Backspace':
+                show(current - 1); break;
+            case 'Home': show(0); break;
+            case 'End': show(slides.length - 1); break;
+            case 'n': overlay.classList.toggle('show-notes'); break;
+            case 'Escape': close(); break;
+            default: return;
+        }
+        event.preventDefault();
+        event.stopPropagation();
+    };
+
+    // Leaving full screen with the window controls also ends the presentation
+    const onFullscreenChange = () => {
+        if (!document.fullscreenElement) close();
+    };
+
+    overlay.addEventListener('click', (event) => {
+        if ((event.target as HTMLElement).closest('a')) return;
+        show(current + (event.clientX < window.innerWidth / 3 ? -1 : 1));
+    });
+    document.addEventListener('keydown', onKeyDown, true);
+
+    show(0);
+    try {
+        await overlay.requestFullscreen();
+        document.addEventListener('fullscreenchange', onFullscreenChange);
+    } catch (error) {
+        // The overlay still covers the window when full screen isn't available
+        console.warn('Full screen is not available:', error);
+    }
+}
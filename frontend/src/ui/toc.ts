@@ -1,3 +1,5 @@
+import { revealAnchor } from './chunks';
+
 /**
  * Initializes the table of contents navigation.
  */
@@ -6,14 +8,15 @@ export function initializeToc() {
     const markdownContainer = document.getElementById('markdown-container')!;
     
     // Handle TOC item clicks
-    tocNav.addEventListener('click', (e) => {
+    tocNav.addEventListener('click', async (e) => {
         const target = e.target as HTMLElement;
         if (target.classList.contains('toc-item')) {
             e.preventDefault();
             
             const id = target.getAttribute('data-id');
             if (id) {
-                const heading = markdownContainer.querySelector(`[id="${id}"]`);
+                // The heading may be in a chunk of a large document that isn't loaded yet
+                const heading = await revealAnchor(id);
                 if (heading) {
                     heading.scrollIntoView({ behavior: 'smooth', block: 'start' });
                     
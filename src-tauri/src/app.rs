@@ -1,10 +1,12 @@
 use crate::commands;
 use crate::history::FileHistory;
-use crate::md::MarkdownDocument;
+use crate::md::loader::MdLoadError;
 use crate::menu;
+use crate::settings::Settings;
 use crate::state::AppState;
+use crate::themes::{Appearance, ThemeWatcher};
 use std::sync::{Arc, Mutex};
-use tauri::{Emitter, Manager};
+use tauri::{AppHandle, Emitter, Manager, Runtime, WebviewWindow};
 
 /// Runs the Tauri application.
 ///
@@ -28,8 +30,19 @@ pub fn run(initial_file: Option<String>) {
                 Arc::new(Mutex::new(FileHistory::new()))
             };
 
-            // Initialize application state with history
-            app.manage(AppState::new(file_history.clone()));
+            // Load user settings from config directory
+            let settings = if let Ok(config_dir) = app_handle.path().app_config_dir() {
+                Settings::load(&config_dir)
+            } else {
+                Settings::new()
+            };
+
+            // Initialize application state with history and settings
+            let mut state = AppState::new(file_history.clone(), settings);
+            if let Ok(cache_dir) = app_handle.path().app_cache_dir() {
+                state = state.with_cache_dir(&cache_dir);
+            }
+            app.manage(state);
 
             // Build and set the menu
             let menu = menu::build_menu(&app_handle).expect("Failed to build menu");
@@ -38,56 +51,237 @@ pub fn run(initial_file: Option<String>) {
             // Setup menu event handlers
             menu::setup_menu_handlers(&app_handle);
 
+            // Follow the theme folders; refreshing re-targets the watcher, which
+            // can't happen on the watcher's own thread
+            let watcher_handle = app_handle.clone();
+            match ThemeWatcher::new(move || {
+                let app_handle = watcher_handle.clone();
+                tauri::async_runtime::spawn_blocking(move || themes_changed(&app_handle));
+            }) {
+                Ok(watcher) => {
+                    app.manage(Mutex::new(watcher));
+                }
+                Err(e) => eprintln!("{}", e),
+            }
+            if let Err(e) = menu::refresh_theme_menu(&app_handle) {
+                eprintln!("Failed to update the Theme menu: {}", e);
+            }
+
+            // Follow the system's light or dark mode; on Linux the desktop portal's
+            // preference takes over once the background watcher has read it
+            let window = app.get_webview_window("main");
+            *app.state::<AppState>().system_appearance.lock().unwrap() =
+                window_appearance(window.as_ref());
+
+            #[cfg(target_os = "linux")]
+            app.manage(PortalWatcher::spawn(app_handle.clone()));
+
             // Handle window close event to quit the application
             let app_handle_clone = app_handle.clone();
-            if let Some(window) = app.get_webview_window("main") {
-                window.on_window_event(move |event| {
-                    if let tauri::WindowEvent::CloseRequested { .. } = event {
+            if let Some(window) = window {
+                window.on_window_event(move |event| match event {
+                    tauri::WindowEvent::CloseRequested { .. } => {
+                        #[cfg(target_os = "linux")]
+                        if let Some(watcher) = app_handle_clone.try_state::<PortalWatcher>() {
+                            watcher.stop();
+                        }
                         app_handle_clone.exit(0);
                     }
+                    tauri::WindowEvent::ThemeChanged(theme) => {
+                        update_appearance(&app_handle_clone, from_window_theme(*theme));
+                    }
+                    _ => {}
                 });
             }
 
-            // Load initial file if provided
+            // Load initial file if provided, in the background so a large file doesn't delay startup
             if let Some(file_path) = initial_file {
-                let state = app.state::<AppState>();
-
-                // Load the document
-                match MarkdownDocument::from_file(&file_path) {
-                    Ok(document) => {
-                        // Update state
-                        let mut current_doc = state.current_document.lock().unwrap();
-                        *current_doc = Some(document.clone());
-                        drop(current_doc);
-
-                        // Emit event to frontend with the loaded document
-                        if let Err(e) = app_handle.emit("document-loaded", &document) {
-                            eprintln!("Failed to emit document-loaded event: {}", e);
+                let app_handle = app_handle.clone();
+                tauri::async_runtime::spawn_blocking(move || {
+                    let state = app_handle.state::<AppState>();
+
+                    // A file named on the command line was chosen by the user; an
+                    // archive resolves to its index document
+                    let file_path = match state.path_policy.approve_file(&file_path) {
+                        Ok(path) => path.to_string_lossy().into_owned(),
+                        Err(e) => {
+                            eprintln!("Failed to approve initial file '{}': {}", file_path, e);
+                            file_path
                         }
-                    }
-                    Err(e) => {
-                        eprintln!("Failed to load initial file '{}': {}", file_path, e);
-                        // Emit error event to frontend
-                        if let Err(emit_err) = app_handle.emit("document-load-error", e.to_string())
-                        {
-                            eprintln!("Failed to emit error event: {}", emit_err);
+                    };
+
+                    // Load the document
+                    let ticket = state.begin_load();
+                    let loaded = commands::load_with_confirmation(&app_handle, &file_path, ticket)
+                        .and_then(|document| state.set_current_document(document, ticket));
+
+                    match loaded {
+                        Ok(document) => {
+                            if let Err(e) = menu::refresh_theme_menu(&app_handle) {
+                                eprintln!("Failed to update the Theme menu: {}", e);
+                            }
+                            // Emit event to frontend with the loaded document
+                            if let Err(e) = app_handle.emit("document-loaded", &document) {
+                                eprintln!("Failed to emit document-loaded event: {}", e);
+                            }
+                        }
+                        // The user opened another file in the meantime
+                        Err(MdLoadError::Cancelled(_)) => {}
+                        Err(e) => {
+                            eprintln!("Failed to load initial file '{}': {}", file_path, e);
+                            // Emit error event to frontend
+                            if let Err(emit_err) =
+                                app_handle.emit("document-load-error", e.to_string())
+                            {
+                                eprintln!("Failed to emit error event: {}", emit_err);
+                            }
                         }
                     }
-                }
+                });
             }
 
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
             commands::open_document,
+            commands::choose_document,
             commands::reload_document,
             commands::set_zoom_factor,
             commands::get_zoom_factor,
             commands::get_current_document,
+            commands::get_chunks,
             commands::get_navigation_state,
             commands::navigate_previous,
             commands::navigate_next,
+            commands::open_in_editor,
+            commands::check_links,
+            commands::get_backlinks,
+            commands::get_archive_files,
+            commands::get_effective_config,
+            commands::get_render_cache_stats,
+            commands::list_themes,
+            commands::get_theme,
+            commands::set_theme,
+            commands::toggle_task,
+            commands::allow_remote_images,
+            commands::trust_folder,
+            commands::export_pdf,
+            commands::export_epub,
+            commands::export_slides,
+            commands::get_slides,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
+
+/// Background task following the desktop portal's color-scheme preference.
+///
+/// Managed by the app, which stops it on close; dropping it stops it too.
+#[cfg(target_os = "linux")]
+struct PortalWatcher(tauri::async_runtime::JoinHandle<()>);
+
+#[cfg(target_os = "linux")]
+impl PortalWatcher {
+    /// Starts watching the portal on the async runtime.
+    ///
+    /// "No preference" falls back to the window theme. Without a portal the
+    /// task logs the error and ends, leaving the window theme in charge.
+    fn spawn<R: Runtime>(app: AppHandle<R>) -> Self {
+        Self(tauri::async_runtime::spawn(async move {
+            let app_handle = app.clone();
+            let watched = crate::system_theme::watch_portal(move |appearance| {
+                let appearance = appearance.unwrap_or_else(|| {
+                    window_appearance(app_handle.get_webview_window("main").as_ref())
+                });
+                update_appearance(&app_handle, appearance);
+            })
+            .await;
+            if let Err(e) = watched {
+                eprintln!("Stopped following the desktop portal's color scheme: {}", e);
+            }
+        }))
+    }
+
+    /// Stops the watcher.
+    fn stop(&self) {
+        self.0.abort();
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl Drop for PortalWatcher {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Reads the light or dark mode of the window theme.
+///
+/// # Arguments
+///
+/// * `window` - The main window, if it exists
+///
+/// # Returns
+///
+/// * `Appearance` - The window's appearance, light when unknown
+fn window_appearance<R: Runtime>(window: Option<&WebviewWindow<R>>) -> Appearance {
+    window
+        .and_then(|window| window.theme().ok())
+        .map(from_window_theme)
+        .unwrap_or_default()
+}
+
+/// Converts a Tauri window theme into an appearance.
+fn from_window_theme(theme: tauri::Theme) -> Appearance {
+    match theme {
+        tauri::Theme::Dark => Appearance::Dark,
+        _ => Appearance::Light,
+    }
+}
+
+/// Records a change of the system appearance and emits `theme-changed` with
+/// the theme that now applies.
+///
+/// # Arguments
+///
+/// * `app` - The application handle
+/// * `appearance` - The new system appearance
+fn update_appearance<R: Runtime>(app: &AppHandle<R>, appearance: Appearance) {
+    let state = app.state::<AppState>();
+    {
+        let mut current = state.system_appearance.lock().unwrap();
+        if *current == appearance {
+            return;
+        }
+        *current = appearance;
+    }
+
+    emit_active_theme(app);
+}
+
+/// Updates the Theme menu after a change in a theme folder and emits
+/// `theme-changed`, since the theme in effect may have been edited.
+///
+/// # Arguments
+///
+/// * `app` - The application handle
+fn themes_changed<R: Runtime>(app: &AppHandle<R>) {
+    if let Err(e) = menu::refresh_theme_menu(app) {
+        eprintln!("Failed to update the Theme menu: {}", e);
+    }
+    emit_active_theme(app);
+}
+
+/// Emits `theme-changed` with the theme in effect for the current document.
+fn emit_active_theme<R: Runtime>(app: &AppHandle<R>) {
+    let state = app.state::<AppState>();
+    let config_dir = app.path().app_config_dir().ok();
+    match state.active_theme(config_dir.as_deref()) {
+        Ok(theme) => {
+            if let Err(e) = app.emit("theme-changed", &theme) {
+                eprintln!("Failed to emit theme-changed event: {}", e);
+            }
+        }
+        Err(e) => eprintln!("Failed to load theme: {}", e),
+    }
+}
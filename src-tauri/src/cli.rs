@@ -0,0 +1,290 @@
+use crate::export::{self, pdf::PdfOptions, ExportError};
+use crate::md::links::LinkChecker;
+use crate::md::loader::MdLoadError;
+use crate::md::parser::RenderConfig;
+use crate::md::slides::SlideSplit;
+use crate::md::trust::TrustLevel;
+use crate::md::MarkdownDocument;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Exit code when all checks pass.
+pub const EXIT_OK: i32 = 0;
+/// Exit code when broken links were found or a document couldn't be loaded.
+pub const EXIT_FAILURE: i32 = 1;
+
+/// Runs `mdview check`, printing broken links to stdout.
+///
+/// Each path may be a Markdown file or a directory, in which case every
+/// Markdown file below it is checked. Broken links are printed as
+/// `path:line: status: url` so editors and CI logs can link to them.
+///
+/// # Arguments
+///
+/// * `paths` - Files or directories to check
+///
+/// # Returns
+///
+/// * `i32` - The process exit code
+pub fn check(paths: &[String]) -> i32 {
+    // Paths named on the command line are the user's own choice, so their project files apply
+    let mut checker =
+        LinkChecker::new().with_trusted_folders(paths.iter().map(PathBuf::from).collect());
+    let mut documents = 0;
+    let mut broken = 0;
+    let mut errors = 0;
+
+    for path in paths {
+        for result in checker.check_path(path) {
+            match result {
+                Ok(report) => {
+                    documents += 1;
+                    for link in report.broken() {
+                        broken += 1;
+                        println!(
+                            "{}:{}: {}: {}",
+                            report.path, link.link.line, link.status, link.link.url
+                        );
+                    }
+                }
+                Err(e) => {
+                    errors += 1;
+                    eprintln!("error: {}", e);
+                }
+            }
+        }
+    }
+
+    println!(
+        "Checked {} document(s): {} broken link(s), {} error(s)",
+        documents, broken, errors
+    );
+
+    if broken > 0 || errors > 0 {
+        EXIT_FAILURE
+    } else {
+        EXIT_OK
+    }
+}
+
+/// Runs `mdview stats`, printing document statistics as JSON to stdout.
+///
+/// The output holds the document totals under `stats` and one entry per
+/// heading under `sections`, each with the counts for that section.
+///
+/// # Arguments
+///
+/// * `path` - The Markdown file to analyze
+///
+/// # Returns
+///
+/// * `i32` - The process exit code
+pub fn stats(path: &str) -> i32 {
+    match stats_report(path) {
+        Ok(report) => {
+            println!("{:#}", report);
+            EXIT_OK
+        }
+        Err(e) => {
+            eprintln!("error: {}", e);
+            EXIT_FAILURE
+        }
+    }
+}
+
+fn stats_report(path: &str) -> Result<serde_json::Value, MdLoadError> {
+    // A file named on the command line is the user's own choice
+    let document = MarkdownDocument::from_file_with_trust(
+        path,
+        &RenderConfig::default(),
+        TrustLevel::Trusted,
+    )?;
+    Ok(serde_json::json!({
+        "path": document.path,
+        "stats": document.stats,
+        "sections": document.toc,
+    }))
+}
+
+/// Output formats supported by `mdview export`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ExportFormat {
+    /// A paginated PDF with bookmarks for each heading
+    Pdf,
+    /// An EPUB 3 e-book with one chapter per document
+    Epub,
+    /// A standalone HTML slide deck
+    Slides,
+}
+
+impl ExportFormat {
+    /// The file extension of the format, without a dot.
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Pdf => "pdf",
+            ExportFormat::Epub => "epub",
+            ExportFormat::Slides => "html",
+        }
+    }
+}
+
+/// Format-specific options for `mdview export`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ExportOptions {
+    /// Page setup for PDF output
+    pub pdf: PdfOptions,
+    /// Where slide decks start new slides
+    pub slide_split: SlideSplit,
+}
+
+/// Runs `mdview export`, writing the exported document to disk.
+///
+/// # Arguments
+///
+/// * `path` - The Markdown file to export, or a folder of them for EPUB
+/// * `format` - The output format
+/// * `output` - The output path; defaults to the input path with the format's extension
+/// * `options` - Format-specific options
+///
+/// # Returns
+///
+/// * `i32` - The process exit code
+pub fn export(
+    path: &str,
+    format: ExportFormat,
+    output: Option<&str>,
+    options: &ExportOptions,
+) -> i32 {
+    let output = output
+        .map(PathBuf::from)
+        .unwrap_or_else(|| export::default_output_path(path, format.extension()));
+
+    match write_export(Path::new(path), format, &output, options) {
+        Ok(()) => {
+            println!("Exported {} to {}", path, output.display());
+            EXIT_OK
+        }
+        Err(e) => {
+            eprintln!("error: {}", e);
+            EXIT_FAILURE
+        }
+    }
+}
+
+fn write_export(
+    path: &Path,
+    format: ExportFormat,
+    output: &Path,
+    options: &ExportOptions,
+) -> Result<(), ExportError> {
+    let config = RenderConfig::default();
+    let bytes = match format {
+        ExportFormat::Pdf => {
+            let pdf = export::pdf::export_pdf(path, &options.pdf)?;
+            if let Some(warning) = pdf.warning() {
+                eprintln!("warning: {}", warning);
+            }
+            pdf.data
+        }
+        // Paths named on the command line are trusted, as for `check`
+        ExportFormat::Epub => export::epub::export_epub(path, &config, TrustLevel::Trusted)?,
+        ExportFormat::Slides => {
+            export::slides::export_slides(path, &config, TrustLevel::Trusted, options.slide_split)?
+                .into_bytes()
+        }
+    };
+    fs::write(output, bytes)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_check_exit_codes() {
+        let temp_dir = tempdir().unwrap();
+        let good = temp_dir.path().join("good.md");
+        let bad = temp_dir.path().join("bad.md");
+        fs::write(&good, "# Good\n\n[self](#good)\n").unwrap();
+        fs::write(&bad, "[missing](nowhere.md)\n").unwrap();
+
+        assert_eq!(check(&[good.display().to_string()]), EXIT_OK);
+        assert_eq!(check(&[bad.display().to_string()]), EXIT_FAILURE);
+        assert_eq!(
+            check(&[temp_dir.path().join("absent.md").display().to_string()]),
+            EXIT_FAILURE
+        );
+    }
+
+    #[test]
+    fn test_stats_report() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("guide.md");
+        fs::write(
+            &path,
+            "# Guide\n\nTwo words.\n\n## Setup\n\n```\ncode\n```\n",
+        )
+        .unwrap();
+        let path = path.display().to_string();
+
+        let report = stats_report(&path).unwrap();
+        assert_eq!(report["stats"]["words"], 4);
+        assert_eq!(report["stats"]["code_blocks"], 1);
+        assert_eq!(report["stats"]["headings"][1], 1);
+        assert_eq!(report["sections"][0]["stats"]["words"], 4);
+        assert_eq!(report["sections"][1]["text"], "Setup");
+
+        assert_eq!(stats(&path), EXIT_OK);
+        assert_eq!(
+            stats(&temp_dir.path().join("absent.md").display().to_string()),
+            EXIT_FAILURE
+        );
+    }
+
+    #[test]
+    fn test_export() {
+        let temp_dir = tempdir().unwrap();
+        let input = temp_dir.path().join("spec.md");
+        fs::write(&input, "# Spec\n\nBody text.\n").unwrap();
+        let input = input.display().to_string();
+
+        assert_eq!(
+            export(&input, ExportFormat::Pdf, None, &ExportOptions::default()),
+            EXIT_OK
+        );
+        let pdf = fs::read(temp_dir.path().join("spec.pdf")).unwrap();
+        assert!(pdf.starts_with(b"%PDF-"));
+
+        let folder = temp_dir.path().display().to_string();
+        let output = temp_dir.path().join("book.epub").display().to_string();
+        assert_eq!(
+            export(
+                &folder,
+                ExportFormat::Epub,
+                Some(&output),
+                &ExportOptions::default()
+            ),
+            EXIT_OK
+        );
+        assert!(fs::read(&output).unwrap().starts_with(b"PK"));
+
+        let options = ExportOptions {
+            slide_split: SlideSplit::H1,
+            ..ExportOptions::default()
+        };
+        assert_eq!(
+            export(&input, ExportFormat::Slides, None, &options),
+            EXIT_OK
+        );
+        let deck = fs::read_to_string(temp_dir.path().join("spec.html")).unwrap();
+        assert!(deck.contains("<section class=\"slide\" id=\"slide-1\">"));
+
+        let missing = temp_dir.path().join("missing.md").display().to_string();
+        assert_eq!(
+            export(&missing, ExportFormat::Pdf, None, &ExportOptions::default()),
+            EXIT_FAILURE
+        );
+    }
+}
@@ -1,23 +1,114 @@
+use crate::editor;
+use crate::export::{self, ExportError};
+use crate::md::archive::{self, ArchiveError, ArchivePath, ARCHIVE_EXTENSIONS};
+use crate::md::chunks::Chunk;
+use crate::md::graph::{Backlink, LinkGraph};
+use crate::md::links::{LinkChecker, LinkReport};
+use crate::md::loader::{LoadErrorKind, LoadLimits, MdLoadError};
+use crate::md::parser::RenderContext;
+use crate::md::project::{self, ConfigEntry, ProjectConfig};
+use crate::md::slides::{self, Slide, SlideSplit};
+use crate::md::tasks::{self, TaskError};
+use crate::md::trust::{self, TrustLevel};
+use crate::md::workspace::{self, COMPRESSED_EXTENSIONS, MARKDOWN_EXTENSIONS};
 use crate::md::MarkdownDocument;
-use crate::state::AppState;
-use tauri::{AppHandle, Manager, State};
+use crate::menu;
+use crate::path_policy::PathPolicyError;
+use crate::render_cache::CacheStats;
+use crate::state::{AppState, LoadProgress, LoadTicket};
+use crate::themes::{self, ActiveTheme, Theme, ThemeError};
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Emitter, Manager, State};
 
 /// Error type for command operations.
 #[derive(Debug, serde::Serialize)]
 pub struct CommandError {
     pub message: String,
+    /// What went wrong, if loading a file failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub load_error: Option<LoadFailure>,
+}
+
+/// Details of a failed file load, so the frontend can tell failures apart.
+#[derive(Debug, serde::Serialize)]
+pub struct LoadFailure {
+    pub kind: LoadErrorKind,
+    /// Canonical path of the file, if the error names one
+    pub path: Option<String>,
 }
 
 impl From<crate::md::loader::MdLoadError> for CommandError {
     fn from(err: crate::md::loader::MdLoadError) -> Self {
+        Self {
+            load_error: Some(LoadFailure {
+                kind: err.kind(),
+                path: err.path().map(str::to_string),
+            }),
+            message: err.to_string(),
+        }
+    }
+}
+
+impl From<editor::EditorError> for CommandError {
+    fn from(err: editor::EditorError) -> Self {
+        Self {
+            message: err.to_string(),
+            load_error: None,
+        }
+    }
+}
+
+impl From<TaskError> for CommandError {
+    fn from(err: TaskError) -> Self {
+        Self {
+            message: err.to_string(),
+            load_error: None,
+        }
+    }
+}
+
+impl From<ExportError> for CommandError {
+    fn from(err: ExportError) -> Self {
+        Self {
+            message: err.to_string(),
+            load_error: None,
+        }
+    }
+}
+
+impl From<PathPolicyError> for CommandError {
+    fn from(err: PathPolicyError) -> Self {
         Self {
             message: err.to_string(),
+            load_error: None,
+        }
+    }
+}
+
+impl From<ArchiveError> for CommandError {
+    fn from(err: ArchiveError) -> Self {
+        Self {
+            message: err.to_string(),
+            load_error: None,
+        }
+    }
+}
+
+impl From<ThemeError> for CommandError {
+    fn from(err: ThemeError) -> Self {
+        Self {
+            message: err.to_string(),
+            load_error: None,
         }
     }
 }
 
 /// Opens and loads a Markdown document.
 ///
+/// The path comes from the webview (e.g. a clicked link), so it must pass the
+/// path policy: a Markdown file under an approved folder or one the user
+/// opened explicitly.
+///
 /// # Arguments
 ///
 /// * `path` - The file path to open
@@ -33,37 +124,195 @@ pub async fn open_document(
     state: State<'_, AppState>,
     app: AppHandle,
 ) -> Result<MarkdownDocument, CommandError> {
-    // Load and parse the document
-    let document = MarkdownDocument::from_file(&path)?;
+    let path = state.path_policy.check(&path)?;
 
-    // Update state with the new document
-    let mut current_doc = state.current_document.lock().unwrap();
-    *current_doc = Some(document.clone());
-    drop(current_doc);
+    show_document(path.to_string_lossy().into_owned(), &state, &app).await
+}
 
-    // Add to history
-    {
-        let mut history = state.file_history.lock().unwrap();
-        history.add(path.clone());
-
-        // Save history
-        if let Ok(config_dir) = app.path().app_config_dir() {
-            if let Err(e) = history.save(&config_dir) {
-                eprintln!("Failed to save file history: {}", e);
-                // Show error dialog to user
-                if let Some(window) = app.get_webview_window("main") {
-                    use tauri_plugin_dialog::{DialogExt, MessageDialogKind};
-                    let _ = window
-                        .dialog()
-                        .message(&format!("Failed to save file history: {}", e))
-                        .kind(MessageDialogKind::Error)
-                        .blocking_show();
-                }
+/// Shows the open dialog and loads the chosen Markdown file.
+///
+/// A file chosen here is approved by the path policy, together with its workspace.
+/// Choosing a zip or tar archive opens its index document.
+///
+/// # Arguments
+///
+/// * `state` - Application state
+/// * `app` - Application handle used to show the dialog
+///
+/// # Returns
+///
+/// * `Result<Option<MarkdownDocument>, CommandError>` - The loaded document, None if cancelled, or an error
+#[tauri::command]
+pub async fn choose_document(
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Option<MarkdownDocument>, CommandError> {
+    use tauri_plugin_dialog::DialogExt;
+
+    let selected = app
+        .dialog()
+        .file()
+        .add_filter("Markdown", MARKDOWN_EXTENSIONS)
+        .add_filter("Compressed Markdown", COMPRESSED_EXTENSIONS)
+        .add_filter("Archives", ARCHIVE_EXTENSIONS)
+        .blocking_pick_file();
+    let Some(selected) = selected else {
+        return Ok(None);
+    };
+    let selected = selected.into_path().map_err(|e| CommandError {
+        message: e.to_string(),
+        load_error: None,
+    })?;
+    let path = state.path_policy.approve_file(&selected)?;
+
+    show_document(path.to_string_lossy().into_owned(), &state, &app)
+        .await
+        .map(Some)
+}
+
+/// Loads a document, makes it current and records it in the file history.
+async fn show_document(
+    path: String,
+    state: &AppState,
+    app: &AppHandle,
+) -> Result<MarkdownDocument, CommandError> {
+    let document = load_current(app, path.clone()).await?;
+
+    state.file_history.lock().unwrap().add(path);
+    save_history(state, app);
+
+    Ok(document)
+}
+
+/// Loads a document on the blocking thread pool and makes it current.
+///
+/// Starting a load supersedes the one in progress, which stops at its next
+/// check and fails with `MdLoadError::Cancelled`. The progress of large
+/// files is emitted to the frontend as `load-progress`.
+async fn load_current(app: &AppHandle, path: String) -> Result<MarkdownDocument, CommandError> {
+    let ticket = app.state::<AppState>().begin_load();
+    let app = app.clone();
+
+    tauri::async_runtime::spawn_blocking(move || -> Result<MarkdownDocument, CommandError> {
+        let document = load_with_confirmation(&app, &path, ticket)?;
+        let document = app
+            .state::<AppState>()
+            .set_current_document(document, ticket)?;
+        // The document's project may have its own themes
+        if let Err(e) = menu::refresh_theme_menu(&app) {
+            eprintln!("Failed to update the Theme menu: {}", e);
+        }
+        Ok(document)
+    })
+    .await
+    .map_err(|e| CommandError {
+        message: format!("Failed to load document: {}", e),
+        load_error: None,
+    })?
+}
+
+/// Runs a command's work on the blocking thread pool, like `load_current`,
+/// so file scans, exports and dialogs don't stall the async runtime.
+async fn run_blocking<T: Send + 'static>(
+    app: AppHandle,
+    work: impl FnOnce(&AppHandle) -> Result<T, CommandError> + Send + 'static,
+) -> Result<T, CommandError> {
+    tauri::async_runtime::spawn_blocking(move || work(&app))
+        .await
+        .map_err(|e| CommandError {
+            message: format!("Background task failed: {}", e),
+            load_error: None,
+        })?
+}
+
+/// Loads a document with the size limit from the settings, asking the user
+/// whether to load a file over the limit anyway.
+///
+/// Shows a native dialog, so it must run off the main thread.
+///
+/// # Arguments
+///
+/// * `app` - Application handle
+/// * `path` - The file path to load
+/// * `ticket` - The ticket from `AppState::begin_load`
+///
+/// # Returns
+///
+/// * `Result<MarkdownDocument, MdLoadError>` - The loaded document, or `MdLoadError::TooLarge` if the user declined
+pub fn load_with_confirmation(
+    app: &AppHandle,
+    path: &str,
+    ticket: LoadTicket,
+) -> Result<MarkdownDocument, MdLoadError> {
+    use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+
+    let state = app.state::<AppState>();
+    let limits = state.settings.lock().unwrap().limits;
+    let emit_progress = |progress: LoadProgress| {
+        if let Err(e) = app.emit("load-progress", &progress) {
+            eprintln!("Failed to emit load-progress event: {}", e);
+        }
+    };
+
+    match state.load_document(path, ticket, &limits, emit_progress) {
+        Err(error @ (MdLoadError::TooLarge { .. } | MdLoadError::TooLargeDecompressed { .. })) => {
+            let confirmed = app
+                .dialog()
+                .message(format!(
+                    "{}\n\nLarge files take a while to open and use a lot of memory. Open it anyway?",
+                    error
+                ))
+                .title("Large File")
+                .kind(MessageDialogKind::Warning)
+                .buttons(MessageDialogButtons::OkCancelCustom(
+                    "Open".to_string(),
+                    "Cancel".to_string(),
+                ))
+                .blocking_show();
+            if !confirmed {
+                return Err(error);
             }
+            state.load_document(path, ticket, &LoadLimits::unlimited(), emit_progress)
         }
+        result => result,
     }
+}
 
-    Ok(document)
+/// Saves the file history, showing an error dialog if that fails.
+///
+/// The history is copied first, so the lock isn't held while writing.
+fn save_history(state: &AppState, app: &AppHandle) {
+    let history = state.file_history.lock().unwrap().clone();
+
+    if let Ok(config_dir) = app.path().app_config_dir() {
+        if let Err(e) = history.save(&config_dir) {
+            eprintln!("Failed to save file history: {}", e);
+            // Show error dialog to user
+            if let Some(window) = app.get_webview_window("main") {
+                use tauri_plugin_dialog::{DialogExt, MessageDialogKind};
+                let _ = window
+                    .dialog()
+                    .message(&format!("Failed to save file history: {}", e))
+                    .kind(MessageDialogKind::Error)
+                    .blocking_show();
+            }
+        }
+    }
+}
+
+/// Saves the user settings.
+///
+/// The settings are copied first, so the lock isn't held while writing.
+fn save_settings(state: &AppState, app: &AppHandle) -> Result<(), CommandError> {
+    let settings = state.settings.lock().unwrap().clone();
+
+    if let Ok(config_dir) = app.path().app_config_dir() {
+        settings.save(&config_dir).map_err(|message| CommandError {
+            message,
+            load_error: None,
+        })?;
+    }
+    Ok(())
 }
 
 /// Reloads the current document from disk.
@@ -71,31 +320,29 @@ pub async fn open_document(
 /// # Arguments
 ///
 /// * `state` - Application state
+/// * `app` - Application handle used to load in the background
 ///
 /// # Returns
 ///
 /// * `Result<MarkdownDocument, CommandError>` - The reloaded document or an error
 #[tauri::command]
-pub async fn reload_document(state: State<'_, AppState>) -> Result<MarkdownDocument, CommandError> {
-    // Get the current document path
-    let current_doc = state.current_document.lock().unwrap();
-    let path = current_doc
+pub async fn reload_document(
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<MarkdownDocument, CommandError> {
+    // Get the current document path, releasing the lock before reloading
+    let path = state
+        .current_document
+        .lock()
+        .unwrap()
         .as_ref()
         .map(|doc| doc.path.clone())
         .ok_or_else(|| CommandError {
             message: "No document is currently loaded".to_string(),
+            load_error: None,
         })?;
 
-    drop(current_doc); // Release lock before reloading
-
-    // Reload the document
-    let document = MarkdownDocument::from_file(&path)?;
-
-    // Update state
-    let mut current_doc = state.current_document.lock().unwrap();
-    *current_doc = Some(document.clone());
-
-    Ok(document)
+    load_current(&app, path).await
 }
 
 /// Sets the zoom factor for the document view.
@@ -114,6 +361,7 @@ pub async fn set_zoom_factor(factor: f64, state: State<'_, AppState>) -> Result<
     if !(0.5..=3.0).contains(&factor) {
         return Err(CommandError {
             message: format!("Zoom factor must be between 0.5 and 3.0, got {}", factor),
+            load_error: None,
         });
     }
 
@@ -151,8 +399,27 @@ pub async fn get_zoom_factor(state: State<'_, AppState>) -> Result<f64, CommandE
 pub async fn get_current_document(
     state: State<'_, AppState>,
 ) -> Result<Option<MarkdownDocument>, CommandError> {
-    let current_doc = state.current_document.lock().unwrap();
-    Ok(current_doc.clone())
+    Ok(state.current_view())
+}
+
+/// Gets chunks of the current document, for documents too large to send at once.
+///
+/// # Arguments
+///
+/// * `start` - Index of the first chunk
+/// * `end` - Index just past the last chunk
+/// * `state` - Application state
+///
+/// # Returns
+///
+/// * `Result<Vec<Chunk>, CommandError>` - The chunks in the range, clipped to the document
+#[tauri::command]
+pub async fn get_chunks(
+    start: usize,
+    end: usize,
+    state: State<'_, AppState>,
+) -> Result<Vec<Chunk>, CommandError> {
+    Ok(state.chunks(start, end))
 }
 
 /// Navigation state for UI button management.
@@ -206,36 +473,20 @@ pub async fn navigate_previous(
 
     match path {
         Some(p) => {
-            // Load the document
-            let document = MarkdownDocument::from_file(&p)?;
+            // Files in the history were opened before, possibly in an earlier session
+            state.path_policy.approve_file(&p)?;
 
-            // Update state
-            let mut current_doc = state.current_document.lock().unwrap();
-            *current_doc = Some(document.clone());
-            drop(current_doc);
+            // Load the document
+            let document = load_current(&app, p).await?;
 
             // Save history (position changed)
-            {
-                let history = state.file_history.lock().unwrap();
-                if let Ok(config_dir) = app.path().app_config_dir() {
-                    if let Err(e) = history.save(&config_dir) {
-                        eprintln!("Failed to save file history: {}", e);
-                        if let Some(window) = app.get_webview_window("main") {
-                            use tauri_plugin_dialog::{DialogExt, MessageDialogKind};
-                            let _ = window
-                                .dialog()
-                                .message(&format!("Failed to save file history: {}", e))
-                                .kind(MessageDialogKind::Error)
-                                .blocking_show();
-                        }
-                    }
-                }
-            }
+            save_history(&state, &app);
 
             Ok(document)
         }
         None => Err(CommandError {
             message: "No previous file in history".to_string(),
+            load_error: None,
         }),
     }
 }
@@ -263,40 +514,769 @@ pub async fn navigate_next(
 
     match path {
         Some(p) => {
-            // Load the document
-            let document = MarkdownDocument::from_file(&p)?;
+            // Files in the history were opened before, possibly in an earlier session
+            state.path_policy.approve_file(&p)?;
 
-            // Update state
-            let mut current_doc = state.current_document.lock().unwrap();
-            *current_doc = Some(document.clone());
-            drop(current_doc);
+            // Load the document
+            let document = load_current(&app, p).await?;
 
             // Save history (position changed)
-            {
-                let history = state.file_history.lock().unwrap();
-                if let Ok(config_dir) = app.path().app_config_dir() {
-                    if let Err(e) = history.save(&config_dir) {
-                        eprintln!("Failed to save file history: {}", e);
-                        if let Some(window) = app.get_webview_window("main") {
-                            use tauri_plugin_dialog::{DialogExt, MessageDialogKind};
-                            let _ = window
-                                .dialog()
-                                .message(&format!("Failed to save file history: {}", e))
-                                .kind(MessageDialogKind::Error)
-                                .blocking_show();
-                        }
-                    }
-                }
-            }
+            save_history(&state, &app);
 
             Ok(document)
         }
         None => Err(CommandError {
             message: "No next file in history".to_string(),
+            load_error: None,
         }),
     }
 }
 
+/// Opens the current document in the user's external editor.
+///
+/// The editor is positioned at the line of the given heading, taken from the
+/// document's table of contents. The editor command comes from the
+/// `editor_command` setting, falling back to `$VISUAL`/`$EDITOR`.
+///
+/// # Arguments
+///
+/// * `heading_id` - Optional TOC ID of the heading currently in view
+/// * `state` - Application state
+///
+/// # Returns
+///
+/// * `Result<(), CommandError>` - Ok once the editor has been launched, or an error
+#[tauri::command]
+pub async fn open_in_editor(
+    heading_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<(), CommandError> {
+    let (path, line) = {
+        let current_doc = state.current_document.lock().unwrap();
+        let doc = current_doc.as_ref().ok_or_else(|| CommandError {
+            message: "No document is currently loaded".to_string(),
+            load_error: None,
+        })?;
+
+        let line = heading_id.and_then(|id| {
+            doc.toc
+                .iter()
+                .find(|item| item.id == id)
+                .and_then(|item| item.line_number)
+        });
+
+        (doc.path.clone(), line)
+    };
+    if ArchivePath::parse(&path).is_some() {
+        return Err(CommandError {
+            message: format!("Files inside archives can't be edited: {}", path),
+            load_error: None,
+        });
+    }
+
+    let template = state.settings.lock().unwrap().editor_command.clone();
+    let env_editor = editor::env_editor();
+
+    let command =
+        editor::resolve_editor_command(template.as_deref(), env_editor.as_deref(), &path, line)?;
+    editor::launch(&command)?;
+
+    Ok(())
+}
+
+/// Checks local links and anchors for the current document or a workspace.
+///
+/// A path from the webview must pass the path policy, like `open_document`.
+///
+/// # Arguments
+///
+/// * `path` - Optional Markdown file or workspace directory; defaults to the current document
+/// * `app` - Application handle used to check in the background
+///
+/// # Returns
+///
+/// * `Result<Vec<LinkReport>, CommandError>` - One report per checked document
+#[tauri::command]
+pub async fn check_links(
+    path: Option<String>,
+    app: AppHandle,
+) -> Result<Vec<LinkReport>, CommandError> {
+    run_blocking(app, move |app| {
+        let state = app.state::<AppState>();
+        let (extensions, trusted_folders) = {
+            let settings = state.settings.lock().unwrap();
+            (settings.render.extensions, settings.trusted_folders.clone())
+        };
+        let mut checker = LinkChecker::new()
+            .with_extensions(extensions)
+            .with_trusted_folders(trusted_folders);
+
+        let Some(path) = path else {
+            // Copy the document, releasing the lock before anchors are read from other files
+            let (path, content) = state
+                .current_document
+                .lock()
+                .unwrap()
+                .as_ref()
+                .map(|doc| (doc.path.clone(), doc.raw_content.clone()))
+                .ok_or_else(|| CommandError {
+                    message: "No document is currently loaded".to_string(),
+                    load_error: None,
+                })?;
+            let report = checker.check_markdown(Path::new(&path), &content);
+            return Ok(vec![report]);
+        };
+
+        if !Path::new(&path).is_dir() {
+            let path = state.path_policy.check(&path)?;
+            return Ok(vec![checker.check_file(&path)?]);
+        }
+        let path = state.path_policy.check_dir(&path)?;
+
+        // Skip unreadable documents so one bad file doesn't hide the rest of the workspace
+        let mut reports = Vec::new();
+        for result in checker.check_path(&path) {
+            match result {
+                Ok(report) => reports.push(report),
+                Err(e) => eprintln!("Failed to check links: {}", e),
+            }
+        }
+
+        Ok(reports)
+    })
+    .await
+}
+
+/// Gets the documents in the workspace that link to a document.
+///
+/// The workspace is the nearest ancestor directory containing `.git`, or the
+/// document's own directory; a workspace the path policy doesn't allow is
+/// narrowed to the document's directory. The link graph is built on first
+/// use and refreshed incrementally on later calls, so only changed files are
+/// rescanned.
+///
+/// # Arguments
+///
+/// * `path` - The target document
+/// * `app` - Application handle used to scan in the background
+///
+/// # Returns
+///
+/// * `Result<Vec<Backlink>, CommandError>` - Links pointing at the document
+#[tauri::command]
+pub async fn get_backlinks(path: String, app: AppHandle) -> Result<Vec<Backlink>, CommandError> {
+    run_blocking(app, move |app| {
+        let state = app.state::<AppState>();
+        let path = state.path_policy.check(&path)?;
+        // A document inside an archive has the archive, already approved, as its workspace
+        let (extensions, trusted_folders) = {
+            let settings = state.settings.lock().unwrap();
+            (settings.render.extensions, settings.trusted_folders.clone())
+        };
+        let root = project::workspace_root(&path, &trusted_folders);
+        let root = if ArchivePath::from_path(&path).is_some()
+            || state.path_policy.check_dir(&root).is_ok()
+        {
+            root
+        } else {
+            path.parent().map(Path::to_path_buf).unwrap_or_default()
+        };
+
+        // Scan outside the lock; a concurrent call simply builds its own graph
+        let cached = state.link_graph.lock().unwrap().take();
+        let graph = match cached {
+            Some(mut graph)
+                if graph.root() == root
+                    && *graph.extensions() == extensions
+                    && graph.trusted_folders() == trusted_folders.as_slice() =>
+            {
+                graph.refresh();
+                graph
+            }
+            _ => LinkGraph::build(&root, extensions, trusted_folders),
+        };
+        let backlinks = graph.backlinks(&path);
+        *state.link_graph.lock().unwrap() = Some(graph);
+
+        Ok(backlinks)
+    })
+    .await
+}
+
+/// Lists the Markdown files in the archive the current document was read from.
+///
+/// # Arguments
+///
+/// * `app` - Application handle used to read the archive in the background
+///
+/// # Returns
+///
+/// * `Result<Vec<String>, CommandError>` - Paths of the archive's Markdown files, empty if the
+///   current document isn't inside an archive
+#[tauri::command]
+pub async fn get_archive_files(app: AppHandle) -> Result<Vec<String>, CommandError> {
+    run_blocking(app, move |app| {
+        let state = app.state::<AppState>();
+        let archive = state
+            .current_document
+            .lock()
+            .unwrap()
+            .as_ref()
+            .and_then(|doc| doc.archive.clone());
+        let Some(archive) = archive else {
+            return Ok(Vec::new());
+        };
+
+        Ok(archive::find_markdown_files(&archive)?
+            .iter()
+            .map(ToString::to_string)
+            .collect())
+    })
+    .await
+}
+
+/// Toggles a task list checkbox in the current document and reloads it.
+///
+/// The checkbox is written back to the file on disk, provided the file
+/// hasn't changed since the document was loaded.
+///
+/// # Arguments
+///
+/// * `line` - The 1-based source line of the task item
+/// * `state` - Application state
+/// * `app` - Application handle used to reload in the background
+///
+/// # Returns
+///
+/// * `Result<MarkdownDocument, CommandError>` - The reloaded document or an error
+#[tauri::command]
+pub async fn toggle_task(
+    line: usize,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<MarkdownDocument, CommandError> {
+    let (path, loaded_content) = state
+        .current_document
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map(|doc| (doc.path.clone(), doc.raw_content.clone()))
+        .ok_or_else(|| CommandError {
+            message: "No document is currently loaded".to_string(),
+            load_error: None,
+        })?;
+
+    if state.current_trust() == Some(TrustLevel::Restricted) {
+        return Err(restricted_error("Task checkboxes can't be changed"));
+    }
+
+    let extensions = {
+        let settings = state.settings.lock().unwrap();
+        project::document_extensions(
+            &path,
+            &settings.render.extensions,
+            &settings.trusted_folders,
+        )
+    };
+    tasks::toggle_task_in_file(&path, &loaded_content, line, extensions)?;
+
+    load_current(&app, path).await
+}
+
+/// Allows remote images and re-renders the current document.
+///
+/// Without a domain, all remote images in the current document are allowed;
+/// with one, images from that host and its subdomains are allowed in every
+/// document. The choice is saved in the settings.
+///
+/// # Arguments
+///
+/// * `domain` - The host to allow, or None to allow the current document
+/// * `state` - Application state
+/// * `app` - Application handle used to locate the config directory
+///
+/// # Returns
+///
+/// * `Result<MarkdownDocument, CommandError>` - The re-rendered document or an error
+#[tauri::command]
+pub async fn allow_remote_images(
+    domain: Option<String>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<MarkdownDocument, CommandError> {
+    let path = state
+        .current_document
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map(|doc| doc.path.clone())
+        .ok_or_else(|| CommandError {
+            message: "No document is currently loaded".to_string(),
+            load_error: None,
+        })?;
+
+    if state.current_trust() == Some(TrustLevel::Restricted) {
+        return Err(restricted_error("Remote images can't be loaded"));
+    }
+
+    // Resolve the document path before taking the settings lock
+    let mut policy = state.settings.lock().unwrap().render.remote_images.clone();
+    match &domain {
+        Some(domain) => policy.allow_domain(domain),
+        None => policy.allow_document(Path::new(&path)),
+    }
+    state.settings.lock().unwrap().render.remote_images = policy;
+    save_settings(&state, &app)?;
+
+    load_current(&app, path).await
+}
+
+/// Trusts a folder after confirming with the user, and re-renders the current document.
+///
+/// Documents in trusted folders render raw HTML, may load allowed remote
+/// images and can write task checkboxes back. The folder is saved in the
+/// settings.
+///
+/// # Arguments
+///
+/// * `path` - The folder to trust, or None for the current document's workspace (or the
+///   folder containing its archive)
+/// * `state` - Application state
+/// * `app` - Application handle used for the confirmation dialog and config directory
+///
+/// # Returns
+///
+/// * `Result<Option<MarkdownDocument>, CommandError>` - The re-rendered document, None if the
+///   user declined or no document is loaded, or an error
+#[tauri::command]
+pub async fn trust_folder(
+    path: Option<String>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Option<MarkdownDocument>, CommandError> {
+    use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+
+    let current_path = state
+        .current_document
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map(|doc| doc.path.clone());
+    let folder = match (path, &current_path) {
+        (Some(path), _) => PathBuf::from(path),
+        // An archive isn't a folder, so trust the one it is in
+        (None, Some(current)) => match ArchivePath::parse(current) {
+            Some(archived) => archived
+                .archive
+                .parent()
+                .map(Path::to_path_buf)
+                .unwrap_or_default(),
+            // Not the project's `root`: the project file isn't trusted yet
+            None => workspace::find_root(current),
+        },
+        (None, None) => {
+            return Err(CommandError {
+                message: "No document is currently loaded".to_string(),
+                load_error: None,
+            })
+        }
+    };
+    if !folder.is_dir() {
+        return Err(CommandError {
+            message: format!("Not a folder: {}", folder.display()),
+            load_error: None,
+        });
+    }
+
+    // Ask in a native dialog, so a document can't grant itself trust
+    let confirmed = app
+        .dialog()
+        .message(format!(
+            "Do you trust the authors of the files in {}?\n\nTrusted documents can show raw HTML, load remote images you allow and change task checkboxes on disk.",
+            folder.display()
+        ))
+        .title("Trust Folder")
+        .kind(MessageDialogKind::Warning)
+        .buttons(MessageDialogButtons::OkCancelCustom(
+            "Trust".to_string(),
+            "Cancel".to_string(),
+        ))
+        .blocking_show();
+    if !confirmed {
+        return Ok(None);
+    }
+
+    // Resolve the folder before taking the settings lock
+    let mut trusted_folders = state.settings.lock().unwrap().trusted_folders.clone();
+    if trust::trust_folder(&mut trusted_folders, &folder) {
+        state.settings.lock().unwrap().trusted_folders = trusted_folders;
+        save_settings(&state, &app)?;
+    }
+
+    let Some(current_path) = current_path else {
+        return Ok(None);
+    };
+    load_current(&app, current_path).await.map(Some)
+}
+
+/// Builds the error returned for actions that restricted mode doesn't allow.
+fn restricted_error(action: &str) -> CommandError {
+    CommandError {
+        message: format!(
+            "{} in restricted mode. Trust the document's folder to enable this.",
+            action
+        ),
+        load_error: None,
+    }
+}
+
+/// Result of an export written to a file the user chose.
+#[derive(Debug, serde::Serialize)]
+pub struct Exported {
+    /// The file that was written
+    pub path: String,
+    /// Something the user should know about the output, e.g. replaced characters
+    pub warning: Option<String>,
+}
+
+/// Asks the user where to save an export with a native save dialog.
+///
+/// Export destinations only ever come from this dialog, never from the
+/// webview, so a compromised page can't write files.
+///
+/// # Arguments
+///
+/// * `app` - Application handle
+/// * `filter` - Name of the file type, e.g. "PDF"
+/// * `extension` - Extension of the file type
+/// * `source` - The exported document or folder, used for the suggested name
+///
+/// # Returns
+///
+/// * `Result<Option<PathBuf>, CommandError>` - The chosen file, or None if cancelled
+fn choose_export_file(
+    app: &AppHandle,
+    filter: &str,
+    extension: &str,
+    source: &Path,
+) -> Result<Option<PathBuf>, CommandError> {
+    use tauri_plugin_dialog::DialogExt;
+
+    let mut dialog = app.dialog().file().add_filter(filter, &[extension]);
+    if let Some(dir) = source.parent() {
+        dialog = dialog.set_directory(dir);
+    }
+    if let Some(stem) = source.file_stem() {
+        dialog = dialog.set_file_name(format!("{}.{}", stem.to_string_lossy(), extension));
+    }
+    let Some(selected) = dialog.blocking_save_file() else {
+        return Ok(None);
+    };
+    selected.into_path().map(Some).map_err(|e| CommandError {
+        message: e.to_string(),
+        load_error: None,
+    })
+}
+
+/// Exports the current document to a PDF file chosen with a save dialog.
+///
+/// Page setup comes from the `pdf` section of the settings.
+///
+/// # Arguments
+///
+/// * `app` - Application handle for the save dialog
+///
+/// # Returns
+///
+/// * `Result<Option<Exported>, CommandError>` - The written file, with a warning if some
+///   characters couldn't be shown and were replaced; None if cancelled; or an error
+#[tauri::command]
+pub async fn export_pdf(app: AppHandle) -> Result<Option<Exported>, CommandError> {
+    run_blocking(app, move |app| {
+        let state = app.state::<AppState>();
+        let (path, markdown) = state
+            .current_document
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|doc| (doc.path.clone(), doc.raw_content.clone()))
+            .ok_or_else(|| CommandError {
+                message: "No document is currently loaded".to_string(),
+                load_error: None,
+            })?;
+        let options = state.settings.lock().unwrap().pdf;
+
+        let Some(output) = choose_export_file(app, "PDF", "pdf", Path::new(&path))? else {
+            return Ok(None);
+        };
+        let base_dir = Path::new(&path).parent().unwrap_or_else(|| Path::new("."));
+        let pdf = export::pdf::render_pdf(&markdown, base_dir, &options);
+        std::fs::write(&output, &pdf.data).map_err(ExportError::from)?;
+
+        Ok(Some(Exported {
+            path: output.display().to_string(),
+            warning: pdf.warning(),
+        }))
+    })
+    .await
+}
+
+/// Exports a folder of Markdown files as an EPUB book.
+///
+/// Both the folder and the EPUB file are chosen with native dialogs, starting
+/// in the current document's folder.
+///
+/// # Arguments
+///
+/// * `app` - Application handle for the dialogs
+///
+/// # Returns
+///
+/// * `Result<Option<Exported>, CommandError>` - The written file, None if cancelled, or an error
+#[tauri::command]
+pub async fn export_epub(app: AppHandle) -> Result<Option<Exported>, CommandError> {
+    run_blocking(app, move |app| {
+        use tauri_plugin_dialog::DialogExt;
+
+        let state = app.state::<AppState>();
+        let current_dir = state
+            .current_document
+            .lock()
+            .unwrap()
+            .as_ref()
+            .and_then(|doc| Path::new(&doc.path).parent().map(Path::to_path_buf));
+        let mut dialog = app.dialog().file();
+        if let Some(dir) = current_dir {
+            dialog = dialog.set_directory(dir);
+        }
+        let Some(folder) = dialog.blocking_pick_folder() else {
+            return Ok(None);
+        };
+        let folder = folder.into_path().map_err(|e| CommandError {
+            message: e.to_string(),
+            load_error: None,
+        })?;
+        let Some(output) = choose_export_file(app, "EPUB", "epub", &folder)? else {
+            return Ok(None);
+        };
+        let settings = state.settings.lock().unwrap().clone();
+        let trust = trust::trust_level(&folder, &settings.trusted_folders);
+
+        let epub = export::epub::export_epub(&folder, &settings.render, trust)?;
+        std::fs::write(&output, epub).map_err(ExportError::from)?;
+
+        Ok(Some(Exported {
+            path: output.display().to_string(),
+            warning: None,
+        }))
+    })
+    .await
+}
+
+/// Lists the effective configuration for the current document and where each value came from.
+///
+/// User settings override the built-in defaults, and the nearest
+/// `.mdview.toml` or `mdview.json` above the document overrides both.
+/// Without a loaded document only the defaults and user settings apply.
+///
+/// # Arguments
+///
+/// * `state` - Application state
+///
+/// # Returns
+///
+/// * `Result<Vec<ConfigEntry>, CommandError>` - The effective values with their sources
+#[tauri::command]
+pub async fn get_effective_config(
+    state: State<'_, AppState>,
+) -> Result<Vec<ConfigEntry>, CommandError> {
+    let system = *state.system_appearance.lock().unwrap();
+    let (user, user_theme) = {
+        let settings = state.settings.lock().unwrap();
+        let user_theme = settings.theme.theme_for(system).map(str::to_string);
+        (settings.render.extensions, user_theme)
+    };
+    let project = state.current_project();
+
+    Ok(project::explain(
+        &user,
+        user_theme.as_deref(),
+        themes::default_theme(system),
+        project.as_ref(),
+    ))
+}
+
+/// Returns the render cache's hit and miss counters, for diagnostics.
+///
+/// # Arguments
+///
+/// * `state` - Application state
+///
+/// # Returns
+///
+/// * `Result<CacheStats, CommandError>` - The cache counters
+#[tauri::command]
+pub async fn get_render_cache_stats(
+    state: State<'_, AppState>,
+) -> Result<CacheStats, CommandError> {
+    Ok(state.render_cache_stats())
+}
+
+/// Splits the current document into presentation slides.
+///
+/// # Arguments
+///
+/// * `split` - Where to start new slides; defaults to the `slide_split` setting
+/// * `app` - Application handle used to split in the background
+///
+/// # Returns
+///
+/// * `Result<Vec<Slide>, CommandError>` - The slides in order or an error
+#[tauri::command]
+pub async fn get_slides(
+    split: Option<SlideSplit>,
+    app: AppHandle,
+) -> Result<Vec<Slide>, CommandError> {
+    run_blocking(app, move |app| {
+        let state = app.state::<AppState>();
+        let (path, markdown, trust) = state
+            .current_document
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|doc| (doc.path.clone(), doc.raw_content.clone(), doc.trust))
+            .ok_or_else(|| CommandError {
+                message: "No document is currently loaded".to_string(),
+                load_error: None,
+            })?;
+        let settings = state.settings.lock().unwrap().clone();
+
+        let context = RenderContext::for_document_with_trust(&path, &settings.render, trust);
+        Ok(slides::split_slides(
+            &markdown,
+            &context,
+            split.unwrap_or(settings.slide_split),
+        ))
+    })
+    .await
+}
+
+/// Exports the current document as a standalone HTML slide deck chosen with a save dialog.
+///
+/// # Arguments
+///
+/// * `app` - Application handle for the save dialog
+///
+/// # Returns
+///
+/// * `Result<Option<Exported>, CommandError>` - The written file, None if cancelled, or an error
+#[tauri::command]
+pub async fn export_slides(app: AppHandle) -> Result<Option<Exported>, CommandError> {
+    run_blocking(app, move |app| {
+        let state = app.state::<AppState>();
+        let (path, trust) = state
+            .current_document
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|doc| (doc.path.clone(), doc.trust))
+            .ok_or_else(|| CommandError {
+                message: "No document is currently loaded".to_string(),
+                load_error: None,
+            })?;
+        let Some(output) = choose_export_file(app, "HTML", "html", Path::new(&path))? else {
+            return Ok(None);
+        };
+        let settings = state.settings.lock().unwrap().clone();
+
+        let deck =
+            export::slides::export_slides(&path, &settings.render, trust, settings.slide_split)?;
+        std::fs::write(&output, deck).map_err(ExportError::from)?;
+
+        Ok(Some(Exported {
+            path: output.display().to_string(),
+            warning: None,
+        }))
+    })
+    .await
+}
+
+/// Lists the themes available for the current document.
+///
+/// # Arguments
+///
+/// * `state` - Application state
+/// * `app` - Application handle used to locate the user themes folder
+///
+/// # Returns
+///
+/// * `Result<Vec<Theme>, CommandError>` - Built-in, user and project themes
+#[tauri::command]
+pub async fn list_themes(
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Vec<Theme>, CommandError> {
+    let config_dir = app.path().app_config_dir().ok();
+    let project = state.current_project();
+
+    Ok(themes::list_themes(config_dir.as_deref(), project.as_ref()))
+}
+
+/// Returns the theme in effect for the current document with its stylesheet.
+///
+/// # Arguments
+///
+/// * `state` - Application state
+/// * `app` - Application handle used to locate the user themes folder
+///
+/// # Returns
+///
+/// * `Result<ActiveTheme, CommandError>` - The theme to apply
+#[tauri::command]
+pub async fn get_theme(
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<ActiveTheme, CommandError> {
+    let config_dir = app.path().app_config_dir().ok();
+
+    Ok(state.active_theme(config_dir.as_deref())?)
+}
+
+/// Selects a theme and saves the choice in the user settings.
+///
+/// Passing no id clears the selection so the theme follows the system's
+/// light or dark mode again. A project's `theme` still takes precedence for
+/// documents in that project.
+///
+/// # Arguments
+///
+/// * `id` - Identifier of the theme to select, or None to follow the system
+/// * `state` - Application state
+/// * `app` - Application handle used to locate the config directory
+///
+/// # Returns
+///
+/// * `Result<ActiveTheme, CommandError>` - The theme now in effect
+#[tauri::command]
+pub async fn set_theme(
+    id: Option<String>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<ActiveTheme, CommandError> {
+    let config_dir = app.path().app_config_dir().ok();
+
+    // Reject unknown ids before saving them
+    if let Some(id) = &id {
+        let project = state.current_project();
+        themes::load_theme(id, config_dir.as_deref(), project.as_ref())?;
+    }
+
+    state.settings.lock().unwrap().theme.theme = id;
+    save_settings(&state, &app)?;
+
+    Ok(state.active_theme(config_dir.as_deref())?)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -305,11 +1285,51 @@ mod tests {
     fn test_command_error_from_load_error() {
         use crate::md::loader::MdLoadError;
 
-        let load_err = MdLoadError::FileNotFound("/test/path.md".to_string());
+        let load_err = MdLoadError::NotFound("/test/path.md".to_string());
         let cmd_err: CommandError = load_err.into();
 
         assert!(cmd_err.message.contains("File not found"));
         assert!(cmd_err.message.contains("/test/path.md"));
+        let load_error = cmd_err.load_error.unwrap();
+        assert_eq!(load_error.kind, LoadErrorKind::NotFound);
+        assert_eq!(load_error.path.as_deref(), Some("/test/path.md"));
+    }
+
+    #[test]
+    fn test_command_error_from_editor_error() {
+        use crate::editor::EditorError;
+
+        let cmd_err: CommandError = EditorError::NotConfigured.into();
+
+        assert!(cmd_err.message.contains("No external editor configured"));
+    }
+
+    #[test]
+    fn test_command_error_from_task_error() {
+        let cmd_err: CommandError = TaskError::NotATask(7).into();
+
+        assert!(cmd_err.message.contains("Line 7"));
+    }
+
+    #[test]
+    fn test_command_error_from_export_error() {
+        let cmd_err: CommandError = ExportError::InvalidOption("page size".to_string()).into();
+
+        assert!(cmd_err.message.contains("Invalid export option: page size"));
+    }
+
+    #[test]
+    fn test_command_error_from_theme_error() {
+        let cmd_err: CommandError = ThemeError::NotFound("neon".to_string()).into();
+
+        assert!(cmd_err.message.contains("Unknown theme: neon"));
+    }
+
+    #[test]
+    fn test_command_error_from_path_policy_error() {
+        let cmd_err: CommandError = PathPolicyError::Denied("/etc/passwd.md".to_string()).into();
+
+        assert!(cmd_err.message.contains("Access denied: /etc/passwd.md"));
     }
 
     #[test]
@@ -0,0 +1,419 @@
+use std::io;
+use std::path::Path;
+use std::process::Command;
+
+/// Errors that can occur when launching an external editor.
+#[derive(Debug, thiserror::Error)]
+pub enum EditorError {
+    /// Neither a command template nor `$VISUAL`/`$EDITOR` is available
+    #[error("No external editor configured: set an editor command in settings or the VISUAL/EDITOR environment variable")]
+    NotConfigured,
+
+    /// The command template could not be parsed
+    #[error("Invalid editor command template: {0}")]
+    InvalidTemplate(String),
+
+    /// The editor process could not be started
+    #[error("Failed to launch editor '{program}': {source}")]
+    LaunchFailed {
+        program: String,
+        #[source]
+        source: io::Error,
+    },
+}
+
+/// A fully resolved editor invocation.
+///
+/// The program and its arguments are passed directly to the OS without a shell,
+/// so file paths containing spaces or shell metacharacters cannot change the command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EditorCommand {
+    /// The executable to run
+    pub program: String,
+    /// Arguments passed to the executable
+    pub args: Vec<String>,
+}
+
+/// Resolves the editor command for a file and optional line number.
+///
+/// A configured template takes precedence over the editor environment variable.
+/// Templates support the `{path}` and `{line}` placeholders (`{{` and `}}` for
+/// literal braces); placeholders are substituted after the template is split into
+/// arguments, so each substituted value stays a single argument. If the template
+/// has no `{path}` placeholder, the path is appended as the last argument.
+///
+/// # Arguments
+///
+/// * `template` - Optional command template from settings
+/// * `env_editor` - The value of `$VISUAL` or `$EDITOR`, if any
+/// * `path` - The file to open
+/// * `line` - Optional 1-based line number to position the editor at
+///
+/// # Returns
+///
+/// * `Result<EditorCommand, EditorError>` - The resolved command or an error
+pub fn resolve_editor_command(
+    template: Option<&str>,
+    env_editor: Option<&str>,
+    path: &str,
+    line: Option<usize>,
+) -> Result<EditorCommand, EditorError> {
+    if let Some(template) = template.filter(|t| !t.trim().is_empty()) {
+        return from_template(template, path, line);
+    }
+
+    if let Some(editor) = env_editor.filter(|e| !e.trim().is_empty()) {
+        return from_env_editor(editor, path, line);
+    }
+
+    Err(EditorError::NotConfigured)
+}
+
+/// Reads the preferred editor from `$VISUAL`, falling back to `$EDITOR`.
+pub fn env_editor() -> Option<String> {
+    ["VISUAL", "EDITOR"]
+        .iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|value| !value.trim().is_empty())
+}
+
+/// Launches the editor without waiting for it to exit.
+///
+/// # Arguments
+///
+/// * `command` - The resolved editor command
+///
+/// # Returns
+///
+/// * `Result<(), EditorError>` - Ok once the process has started, or the launch error
+pub fn launch(command: &EditorCommand) -> Result<(), EditorError> {
+    let mut child = Command::new(&command.program)
+        .args(&command.args)
+        .spawn()
+        .map_err(|source| EditorError::LaunchFailed {
+            program: command.program.clone(),
+            source,
+        })?;
+
+    // Reap the process when the editor exits so it doesn't linger as a zombie
+    std::thread::spawn(move || {
+        let _ = child.wait();
+    });
+
+    Ok(())
+}
+
+/// Builds a command from a user-supplied template.
+fn from_template(
+    template: &str,
+    path: &str,
+    line: Option<usize>,
+) -> Result<EditorCommand, EditorError> {
+    let tokens = split_arguments(template)?;
+    let line = line.unwrap_or(1).to_string();
+
+    let mut has_path = false;
+    let mut args = Vec::with_capacity(tokens.len() + 1);
+    for token in &tokens {
+        let (arg, used_path) = substitute(token, path, &line)?;
+        has_path |= used_path;
+        args.push(arg);
+    }
+
+    if !has_path {
+        args.push(path.to_string());
+    }
+
+    let program = args.remove(0);
+    Ok(EditorCommand { program, args })
+}
+
+/// Builds a command from `$VISUAL`/`$EDITOR`, using the line syntax of well-known editors.
+fn from_env_editor(
+    editor: &str,
+    path: &str,
+    line: Option<usize>,
+) -> Result<EditorCommand, EditorError> {
+    let mut args = split_arguments(editor)?;
+    let program = args.remove(0);
+
+    let name = Path::new(&program)
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+
+    match line {
+        Some(line) => match name.as_str() {
+            "code" | "code-insiders" | "codium" | "cursor" => {
+                args.push("-g".to_string());
+                args.push(format!("{}:{}", path, line));
+            }
+            "subl" | "sublime_text" | "zed" | "hx" | "helix" => {
+                args.push(format!("{}:{}", path, line));
+            }
+            "vi" | "vim" | "nvim" | "gvim" | "nano" | "emacs" | "emacsclient" | "micro" | "kak"
+            | "gedit" | "kate" | "mate" => {
+                args.push(format!("+{}", line));
+                args.push(path.to_string());
+            }
+            _ => args.push(path.to_string()),
+        },
+        None => args.push(path.to_string()),
+    }
+
+    Ok(EditorCommand { program, args })
+}
+
+/// Replaces placeholders in a single template argument.
+///
+/// Returns the substituted argument and whether `{path}` was used.
+fn substitute(token: &str, path: &str, line: &str) -> Result<(String, bool), EditorError> {
+    let mut result = String::new();
+    let mut used_path = false;
+    let mut chars = token.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                result.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                result.push('}');
+            }
+            '{' => {
+                let mut name = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => name.push(c),
+                        None => {
+                            return Err(EditorError::InvalidTemplate(format!(
+                                "unclosed placeholder in '{}'",
+                                token
+                            )))
+                        }
+                    }
+                }
+                match name.as_str() {
+                    "path" => {
+                        result.push_str(path);
+                        used_path = true;
+                    }
+                    "line" => result.push_str(line),
+                    other => {
+                        return Err(EditorError::InvalidTemplate(format!(
+                            "unknown placeholder '{{{}}}'",
+                            other
+                        )))
+                    }
+                }
+            }
+            '}' => {
+                return Err(EditorError::InvalidTemplate(format!(
+                    "unmatched '}}' in '{}'",
+                    token
+                )))
+            }
+            _ => result.push(c),
+        }
+    }
+
+    Ok((result, used_path))
+}
+
+/// Splits a command line into arguments.
+///
+/// Arguments are separated by whitespace. Single quotes preserve their contents
+/// literally; double quotes allow `\"` and `\\` escapes. Backslashes elsewhere are
+/// kept as-is so Windows paths don't need escaping.
+fn split_arguments(command: &str) -> Result<Vec<String>, EditorError> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut in_arg = false;
+    let mut chars = command.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' => {
+                in_arg = true;
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => current.push(c),
+                        None => {
+                            return Err(EditorError::InvalidTemplate(
+                                "unterminated single quote".to_string(),
+                            ))
+                        }
+                    }
+                }
+            }
+            '"' => {
+                in_arg = true;
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') if matches!(chars.peek(), Some('"') | Some('\\')) => {
+                            current.push(chars.next().unwrap());
+                        }
+                        Some(c) => current.push(c),
+                        None => {
+                            return Err(EditorError::InvalidTemplate(
+                                "unterminated double quote".to_string(),
+                            ))
+                        }
+                    }
+                }
+            }
+            c if c.is_whitespace() => {
+                if in_arg {
+                    args.push(std::mem::take(&mut current));
+                    in_arg = false;
+                }
+            }
+            c => {
+                in_arg = true;
+                current.push(c);
+            }
+        }
+    }
+
+    if in_arg {
+        args.push(current);
+    }
+
+    if args.is_empty() {
+        return Err(EditorError::InvalidTemplate("empty command".to_string()));
+    }
+
+    Ok(args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_template_substitutes_path_and_line() {
+        let cmd =
+            resolve_editor_command(Some("code -g {path}:{line}"), None, "/docs/a.md", Some(42))
+                .unwrap();
+
+        assert_eq!(cmd.program, "code");
+        assert_eq!(cmd.args, vec!["-g", "/docs/a.md:42"]);
+    }
+
+    #[test]
+    fn test_template_keeps_path_with_spaces_as_one_argument() {
+        let cmd =
+            resolve_editor_command(Some("vim +{line} {path}"), None, "/my docs/a b.md", Some(3))
+                .unwrap();
+
+        assert_eq!(cmd.args, vec!["+3", "/my docs/a b.md"]);
+    }
+
+    #[test]
+    fn test_template_does_not_interpret_shell_metacharacters() {
+        let path = "/tmp/$(rm -rf ~); echo `id` {line}.md";
+        let cmd = resolve_editor_command(Some("edit {path}"), None, path, Some(7)).unwrap();
+
+        assert_eq!(cmd.program, "edit");
+        assert_eq!(cmd.args, vec![path]);
+    }
+
+    #[test]
+    fn test_template_without_path_appends_it() {
+        let cmd = resolve_editor_command(
+            Some("\"C:\\Program Files\\Editor\\ed.exe\" --new"),
+            None,
+            "a.md",
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(cmd.program, "C:\\Program Files\\Editor\\ed.exe");
+        assert_eq!(cmd.args, vec!["--new", "a.md"]);
+    }
+
+    #[test]
+    fn test_template_missing_line_defaults_to_first() {
+        let cmd = resolve_editor_command(Some("subl {path}:{line}"), None, "a.md", None).unwrap();
+
+        assert_eq!(cmd.args, vec!["a.md:1"]);
+    }
+
+    #[test]
+    fn test_template_literal_braces() {
+        let cmd =
+            resolve_editor_command(Some("ed --opt={{x}} {path}"), None, "a.md", None).unwrap();
+
+        assert_eq!(cmd.args, vec!["--opt={x}", "a.md"]);
+    }
+
+    #[test]
+    fn test_template_unknown_placeholder() {
+        let result = resolve_editor_command(Some("ed {file}"), None, "a.md", None);
+
+        assert!(matches!(result, Err(EditorError::InvalidTemplate(_))));
+    }
+
+    #[test]
+    fn test_template_unterminated_quote() {
+        let result = resolve_editor_command(Some("ed '{path}"), None, "a.md", None);
+
+        assert!(matches!(result, Err(EditorError::InvalidTemplate(_))));
+    }
+
+    #[test]
+    fn test_template_takes_precedence_over_env() {
+        let cmd =
+            resolve_editor_command(Some("code -g {path}:{line}"), Some("vim"), "a.md", Some(2))
+                .unwrap();
+
+        assert_eq!(cmd.program, "code");
+    }
+
+    #[test]
+    fn test_env_editor_line_syntax() {
+        let vim = resolve_editor_command(None, Some("vim"), "a.md", Some(5)).unwrap();
+        assert_eq!(vim.args, vec!["+5", "a.md"]);
+
+        let code = resolve_editor_command(None, Some("code --wait"), "a.md", Some(5)).unwrap();
+        assert_eq!(code.program, "code");
+        assert_eq!(code.args, vec!["--wait", "-g", "a.md:5"]);
+
+        let unknown = resolve_editor_command(None, Some("/usr/bin/ed"), "a.md", Some(5)).unwrap();
+        assert_eq!(unknown.args, vec!["a.md"]);
+    }
+
+    #[test]
+    fn test_env_editor_without_line() {
+        let cmd = resolve_editor_command(None, Some("nvim"), "a.md", None).unwrap();
+
+        assert_eq!(cmd.args, vec!["a.md"]);
+    }
+
+    #[test]
+    fn test_not_configured() {
+        let result = resolve_editor_command(Some("  "), None, "a.md", Some(1));
+
+        assert!(matches!(result, Err(EditorError::NotConfigured)));
+    }
+
+    #[test]
+    fn test_launch_failure_is_reported() {
+        let cmd = EditorCommand {
+            program: "/nonexistent/mdview-test-editor".to_string(),
+            args: vec![],
+        };
+
+        match launch(&cmd) {
+            Err(EditorError::LaunchFailed { program, .. }) => {
+                assert_eq!(program, "/nonexistent/mdview-test-editor");
+            }
+            _ => panic!("Expected LaunchFailed error"),
+        }
+    }
+}
@@ -0,0 +1,588 @@
+use super::markup::{escape_xml, image_media_type, to_xhtml};
+use super::ExportError;
+use crate::md::parser::RenderConfig;
+use crate::md::trust::TrustLevel;
+use crate::md::{frontmatter, links, loader, project, workspace, MarkdownDocument, TocItem};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::io::{Cursor, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+/// File names that are placed first when exporting a folder.
+const INDEX_FILE_STEMS: &[&str] = &["index", "readme"];
+
+const CONTAINER_XML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+  <rootfiles>
+    <rootfile full-path="EPUB/package.opf" media-type="application/oebps-package+xml"/>
+  </rootfiles>
+</container>
+"#;
+
+const STYLESHEET: &str = "body { font-family: serif; line-height: 1.5; }
+h1, h2, h3, h4, h5, h6 { font-family: sans-serif; line-height: 1.2; }
+a.anchor { display: none; }
+pre { white-space: pre-wrap; font-size: 0.85em; background: #f6f8fa; padding: 0.6em; }
+code { font-family: monospace; }
+blockquote { margin-left: 0; padding-left: 1em; border-left: 0.25em solid #d0d7de; color: #57606a; }
+table { border-collapse: collapse; }
+th, td { border: 1px solid #d0d7de; padding: 0.2em 0.5em; }
+img { max-width: 100%; }
+.admonition { border-left: 0.25em solid #0969da; padding: 0.1em 0.8em; margin: 1em 0; }
+.admonition-title { font-weight: bold; }
+";
+
+/// A document converted into an EPUB chapter.
+struct Chapter {
+    file_name: String,
+    title: String,
+    toc: Vec<TocItem>,
+    body: String,
+    remote_resources: bool,
+}
+
+/// An image packaged into the book.
+struct PackagedImage {
+    href: String,
+    media_type: &'static str,
+    data: Vec<u8>,
+}
+
+/// Book-level metadata for the package document.
+struct BookMetadata {
+    identifier: String,
+    title: String,
+    author: Option<String>,
+    language: String,
+}
+
+/// Exports a Markdown file, or every Markdown file in a folder, as an EPUB 3 book.
+///
+/// Each document becomes a chapter, in path order with any `index.md` or
+/// `README.md` at the top of the folder first. The navigation document is
+/// built from the chapters' headings, local images inside the exported
+/// folder (or the file's folder) are packaged into the book, and the title
+/// and author come from the first front matter that sets them.
+///
+/// # Arguments
+///
+/// * `path` - A Markdown file or a folder of Markdown files
+/// * `config` - The rendering configuration
+/// * `trust` - The trust level of the exported folder; restricted documents lose
+///   their raw HTML and project settings
+///
+/// # Returns
+///
+/// * `Result<Vec<u8>, ExportError>` - The EPUB file contents or an error
+pub fn export_epub<P: AsRef<Path>>(
+    path: P,
+    config: &RenderConfig,
+    trust: TrustLevel,
+) -> Result<Vec<u8>, ExportError> {
+    let path = path.as_ref();
+    let files = chapter_files(path);
+    if files.is_empty() {
+        return Err(ExportError::NoDocuments(path.display().to_string()));
+    }
+    // Images are only packaged from inside the exported folder
+    let book_dir = if path.is_dir() {
+        path
+    } else {
+        path.parent().unwrap_or_else(|| Path::new("."))
+    };
+
+    // Task checkboxes can't be toggled in an e-book
+    let config = RenderConfig {
+        interactive_tasks: false,
+        ..config.for_export()
+    };
+
+    let chapter_names: HashMap<PathBuf, String> = files
+        .iter()
+        .enumerate()
+        .map(|(index, file)| (workspace::canonical_path(file), chapter_file_name(index)))
+        .collect();
+
+    let mut chapters = Vec::new();
+    let mut images = Vec::new();
+    let mut image_hrefs = HashMap::new();
+    let mut front_matters = Vec::new();
+
+    for (index, file) in files.iter().enumerate() {
+        // Front matter becomes the book's metadata, so it isn't shown in the chapter
+        let raw_content = loader::load_markdown_file(file)?;
+        let front_matter = frontmatter::parse(&raw_content).unwrap_or_default();
+        let document = MarkdownDocument::from_content_with_trust(
+            file,
+            frontmatter::blank(&raw_content).into_owned(),
+            &config,
+            trust,
+        );
+        let base_dir = file.parent().unwrap_or_else(|| Path::new("."));
+        let mut remote_resources = false;
+
+        let body = to_xhtml(&document.html_content, |tag, attribute, value| {
+            let url = value.replace("&amp;", "&");
+            match (tag, attribute) {
+                ("a", "href") if !url.starts_with('#') && !links::is_external(&url) => {
+                    // Links to other chapters point into the book; other local files are unreachable
+                    let (target, anchor) = links::split_local_url(&url);
+                    let chapter =
+                        chapter_names.get(&workspace::canonical_path(base_dir.join(target)))?;
+                    Some(match anchor {
+                        Some(anchor) => format!("{}#{}", chapter, escape_xml(&anchor)),
+                        None => chapter.clone(),
+                    })
+                }
+                ("img", "src") if links::is_external(&url) => {
+                    remote_resources = true;
+                    Some(value.to_string())
+                }
+                ("img", "src") => {
+                    let (target, _) = links::split_local_url(&url);
+                    let image_path = super::contained_file(book_dir, &base_dir.join(target));
+                    let href = image_path.and_then(|image_path| {
+                        image_hrefs
+                            .entry(image_path.clone())
+                            .or_insert_with(|| package_image(&image_path, &mut images))
+                            .clone()
+                    });
+                    Some(href.unwrap_or_else(|| value.to_string()))
+                }
+                _ => Some(value.to_string()),
+            }
+        });
+
+        let title = front_matter
+            .title
+            .clone()
+            .or_else(|| document.toc.first().map(|item| item.text.clone()))
+            .unwrap_or_else(|| file_stem(file));
+
+        chapters.push(Chapter {
+            file_name: chapter_file_name(index),
+            title,
+            toc: document.toc,
+            body,
+            remote_resources,
+        });
+        front_matters.push(front_matter);
+    }
+
+    let title = front_matters
+        .iter()
+        .find_map(|front_matter| front_matter.title.clone())
+        .unwrap_or_else(|| {
+            if path.is_dir() {
+                file_stem(&workspace::canonical_path(path))
+            } else {
+                chapters[0].title.clone()
+            }
+        });
+    let metadata = BookMetadata {
+        identifier: book_identifier(&title, &files),
+        author: front_matters
+            .iter()
+            .find_map(|front_matter| front_matter.author.clone()),
+        language: front_matters
+            .iter()
+            .find_map(|front_matter| front_matter.language.clone())
+            .unwrap_or_else(|| "en".to_string()),
+        title,
+    };
+
+    Ok(write_archive(&metadata, &chapters, &images)?)
+}
+
+/// Returns the Markdown files to export, in chapter order.
+fn chapter_files(path: &Path) -> Vec<PathBuf> {
+    if !path.is_dir() {
+        return vec![path.to_path_buf()];
+    }
+
+    let mut files = project::find_markdown_files(path);
+    let index = files.iter().position(|file| {
+        file.parent() == Some(path)
+            && INDEX_FILE_STEMS.contains(&file_stem(file).to_lowercase().as_str())
+    });
+    if let Some(index) = index {
+        let file = files.remove(index);
+        files.insert(0, file);
+    }
+    files
+}
+
+fn chapter_file_name(index: usize) -> String {
+    format!("chapter-{:03}.xhtml", index + 1)
+}
+
+fn file_stem(path: &Path) -> String {
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// Reads an image into the package, returning its href within the book.
+fn package_image(path: &Path, images: &mut Vec<PackagedImage>) -> Option<String> {
+    let media_type = image_media_type(path)?;
+    let extension = path.extension()?.to_string_lossy().to_lowercase();
+    let data = fs::read(path).ok()?;
+
+    let href = format!("images/image-{:03}.{}", images.len() + 1, extension);
+    images.push(PackagedImage {
+        href: href.clone(),
+        media_type,
+        data,
+    });
+    Some(href)
+}
+
+/// Builds a stable identifier for the book from its title and source files.
+fn book_identifier(title: &str, files: &[PathBuf]) -> String {
+    let mut hasher = DefaultHasher::new();
+    title.hash(&mut hasher);
+    for file in files {
+        file.file_name().hash(&mut hasher);
+    }
+    format!("urn:mdview:{:016x}", hasher.finish())
+}
+
+/// Writes the EPUB container.
+///
+/// The `mimetype` entry must come first and be stored uncompressed so
+/// readers can identify the file.
+fn write_archive(
+    metadata: &BookMetadata,
+    chapters: &[Chapter],
+    images: &[PackagedImage],
+) -> Result<Vec<u8>, zip::result::ZipError> {
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let stored = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+    let deflated = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    zip.start_file("mimetype", stored)?;
+    zip.write_all(b"application/epub+zip")?;
+
+    zip.start_file("META-INF/container.xml", deflated)?;
+    zip.write_all(CONTAINER_XML.as_bytes())?;
+
+    zip.start_file("EPUB/package.opf", deflated)?;
+    zip.write_all(package_document(metadata, chapters, images).as_bytes())?;
+
+    zip.start_file("EPUB/nav.xhtml", deflated)?;
+    zip.write_all(navigation_document(metadata, chapters).as_bytes())?;
+
+    zip.start_file("EPUB/style.css", deflated)?;
+    zip.write_all(STYLESHEET.as_bytes())?;
+
+    for chapter in chapters {
+        zip.start_file(format!("EPUB/{}", chapter.file_name), deflated)?;
+        let page = xhtml_page(&metadata.language, &chapter.title, &chapter.body);
+        zip.write_all(page.as_bytes())?;
+    }
+
+    for image in images {
+        // Compressed image formats gain nothing from deflating again
+        let options = if image.media_type == "image/svg+xml" {
+            deflated
+        } else {
+            stored
+        };
+        zip.start_file(format!("EPUB/{}", image.href), options)?;
+        zip.write_all(&image.data)?;
+    }
+
+    Ok(zip.finish()?.into_inner())
+}
+
+/// Builds the OPF package document listing the book's metadata and files.
+fn package_document(
+    metadata: &BookMetadata,
+    chapters: &[Chapter],
+    images: &[PackagedImage],
+) -> String {
+    let mut opf = String::new();
+    opf.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    opf.push_str(&format!(
+        "<package xmlns=\"http://www.idpf.org/2007/opf\" version=\"3.0\" unique-identifier=\"book-id\" xml:lang=\"{}\">\n",
+        escape_xml(&metadata.language)
+    ));
+    opf.push_str("  <metadata xmlns:dc=\"http://purl.org/dc/elements/1.1/\">\n");
+    opf.push_str(&format!(
+        "    <dc:identifier id=\"book-id\">{}</dc:identifier>\n",
+        escape_xml(&metadata.identifier)
+    ));
+    opf.push_str(&format!(
+        "    <dc:title>{}</dc:title>\n",
+        escape_xml(&metadata.title)
+    ));
+    if let Some(author) = &metadata.author {
+        opf.push_str(&format!(
+            "    <dc:creator>{}</dc:creator>\n",
+            escape_xml(author)
+        ));
+    }
+    opf.push_str(&format!(
+        "    <dc:language>{}</dc:language>\n",
+        escape_xml(&metadata.language)
+    ));
+    opf.push_str(&format!(
+        "    <meta property=\"dcterms:modified\">{}</meta>\n",
+        utc_timestamp(SystemTime::now())
+    ));
+    opf.push_str("  </metadata>\n  <manifest>\n");
+    opf.push_str("    <item id=\"nav\" href=\"nav.xhtml\" media-type=\"application/xhtml+xml\" properties=\"nav\"/>\n");
+    opf.push_str("    <item id=\"style\" href=\"style.css\" media-type=\"text/css\"/>\n");
+    for chapter in chapters {
+        let properties = if chapter.remote_resources {
+            " properties=\"remote-resources\""
+        } else {
+            ""
+        };
+        opf.push_str(&format!(
+            "    <item id=\"{}\" href=\"{}\" media-type=\"application/xhtml+xml\"{}/>\n",
+            item_id(&chapter.file_name),
+            chapter.file_name,
+            properties
+        ));
+    }
+    for image in images {
+        opf.push_str(&format!(
+            "    <item id=\"{}\" href=\"{}\" media-type=\"{}\"/>\n",
+            item_id(&image.href),
+            image.href,
+            image.media_type
+        ));
+    }
+    opf.push_str("  </manifest>\n  <spine>\n");
+    for chapter in chapters {
+        opf.push_str(&format!(
+            "    <itemref idref=\"{}\"/>\n",
+            item_id(&chapter.file_name)
+        ));
+    }
+    opf.push_str("  </spine>\n</package>\n");
+    opf
+}
+
+/// Returns the manifest id of a file: its name without directory or extension.
+fn item_id(href: &str) -> &str {
+    let name = href.rsplit('/').next().unwrap_or(href);
+    name.split('.').next().unwrap_or(name)
+}
+
+/// Builds the navigation document from the chapters and their headings.
+fn navigation_document(metadata: &BookMetadata, chapters: &[Chapter]) -> String {
+    let mut nav = String::from("<nav epub:type=\"toc\" id=\"toc\">\n<h1>Contents</h1>\n<ol>\n");
+    for chapter in chapters {
+        // A heading that repeats the chapter title would just duplicate the entry
+        let headings = match chapter.toc.first() {
+            Some(first) if first.text == chapter.title => &chapter.toc[1..],
+            _ => &chapter.toc[..],
+        };
+
+        nav.push_str(&format!(
+            "<li><a href=\"{}\">{}</a>",
+            chapter.file_name,
+            escape_xml(&chapter.title)
+        ));
+        if !headings.is_empty() {
+            nav.push_str("\n<ol>\n");
+            nav_items(&mut nav, &chapter.file_name, headings);
+            nav.push_str("</ol>\n");
+        }
+        nav.push_str("</li>\n");
+    }
+    nav.push_str("</ol>\n</nav>");
+
+    xhtml_page(&metadata.language, &metadata.title, &nav)
+}
+
+/// Writes headings as nested list items.
+///
+/// A heading's children are the headings after it with a deeper level, so
+/// skipped levels never produce empty lists.
+fn nav_items(nav: &mut String, file_name: &str, headings: &[TocItem]) {
+    let mut index = 0;
+    while index < headings.len() {
+        let heading = &headings[index];
+        let children = headings[index + 1..]
+            .iter()
+            .take_while(|child| child.level > heading.level)
+            .count();
+
+        nav.push_str(&format!(
+            "<li><a href=\"{}#{}\">{}</a>",
+            file_name,
+            escape_xml(&heading.id),
+            escape_xml(&heading.text)
+        ));
+        if children > 0 {
+            nav.push_str("\n<ol>\n");
+            nav_items(nav, file_name, &headings[index + 1..index + 1 + children]);
+            nav.push_str("</ol>\n");
+        }
+        nav.push_str("</li>\n");
+        index += 1 + children;
+    }
+}
+
+/// Wraps body content in an XHTML document.
+fn xhtml_page(language: &str, title: &str, body: &str) -> String {
+    let language = escape_xml(language);
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <!DOCTYPE html>\n\
+         <html xmlns=\"http://www.w3.org/1999/xhtml\" xmlns:epub=\"http://www.idpf.org/2007/ops\" lang=\"{language}\" xml:lang=\"{language}\">\n\
+         <head>\n<meta charset=\"UTF-8\"/>\n<title>{}</title>\n\
+         <link rel=\"stylesheet\" type=\"text/css\" href=\"style.css\"/>\n</head>\n\
+         <body>\n{}\n</body>\n</html>\n",
+        escape_xml(title),
+        body
+    )
+}
+
+/// Formats a time as an ISO 8601 UTC timestamp, as required by `dcterms:modified`.
+fn utc_timestamp(time: SystemTime) -> String {
+    let seconds = time
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0);
+    let days = (seconds / 86_400) as i64;
+    let remainder = seconds % 86_400;
+
+    // Civil date from days since the epoch (Howard Hinnant's algorithm)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        remainder / 3600,
+        remainder % 3600 / 60,
+        remainder % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use std::time::Duration;
+    use tempfile::tempdir;
+    use zip::ZipArchive;
+
+    fn read_entry(archive: &mut ZipArchive<Cursor<Vec<u8>>>, name: &str) -> String {
+        let mut content = String::new();
+        archive
+            .by_name(name)
+            .unwrap()
+            .read_to_string(&mut content)
+            .unwrap();
+        content
+    }
+
+    #[test]
+    fn test_export_folder() {
+        let temp_dir = tempdir().unwrap();
+        let outside = temp_dir.path();
+        fs::write(outside.join("outside.png"), b"\x89PNG\r\n\x1a\n").unwrap();
+        let root = &outside.join("book");
+        fs::create_dir(root).unwrap();
+        fs::write(
+            root.join("README.md"),
+            "---\ntitle: Field Guide\nauthor: Ada\n---\n# Welcome\n\nSee [setup](guide/setup.md#install). <kbd>K</kbd>\n",
+        )
+        .unwrap();
+        fs::create_dir(root.join("guide")).unwrap();
+        fs::write(
+            root.join("guide/setup.md"),
+            format!(
+                "# Setup\n\n## Install\n\n![Diagram](diagram.png)<br>\n\n### Linux\n\n## Configure\n\n![Up](../../outside.png) ![Absolute]({})\n",
+                outside.join("outside.png").display()
+            ),
+        )
+        .unwrap();
+        fs::write(root.join("guide/diagram.png"), b"\x89PNG\r\n\x1a\n").unwrap();
+
+        let epub = export_epub(root, &RenderConfig::default(), TrustLevel::Trusted).unwrap();
+        let mut archive = ZipArchive::new(Cursor::new(epub)).unwrap();
+
+        // The mimetype must be the first, uncompressed entry
+        let mimetype = archive.by_index(0).unwrap();
+        assert_eq!(mimetype.name(), "mimetype");
+        assert_eq!(mimetype.compression(), CompressionMethod::Stored);
+        drop(mimetype);
+
+        let opf = read_entry(&mut archive, "EPUB/package.opf");
+        assert!(opf.contains("<dc:title>Field Guide</dc:title>"));
+        assert!(opf.contains("<dc:creator>Ada</dc:creator>"));
+        assert!(opf.contains("href=\"images/image-001.png\" media-type=\"image/png\""));
+        assert!(
+            opf.contains("<itemref idref=\"chapter-001\"/>\n    <itemref idref=\"chapter-002\"/>")
+        );
+
+        let nav = read_entry(&mut archive, "EPUB/nav.xhtml");
+        assert!(nav.contains("<a href=\"chapter-001.xhtml\">Field Guide</a>"));
+        assert!(nav.contains("<a href=\"chapter-002.xhtml\">Setup</a>"));
+        assert!(nav.contains(
+            "<li><a href=\"chapter-002.xhtml#install\">Install</a>\n<ol>\n<li><a href=\"chapter-002.xhtml#linux\">Linux</a></li>\n</ol>\n</li>"
+        ));
+
+        let first = read_entry(&mut archive, "EPUB/chapter-001.xhtml");
+        assert!(first.contains("href=\"chapter-002.xhtml#install\""));
+        assert!(!first.contains("title: Field Guide"));
+        assert!(first.contains("<kbd>K</kbd>"));
+
+        let second = read_entry(&mut archive, "EPUB/chapter-002.xhtml");
+        assert!(second.contains("src=\"images/image-001.png\""));
+        assert!(second.contains("<br />"));
+        assert!(archive.by_name("EPUB/images/image-001.png").is_ok());
+        // Images outside the exported folder are never packaged
+        assert!(archive.by_name("EPUB/images/image-002.png").is_err());
+
+        // A restricted folder loses its raw HTML, as when it is shown
+        let epub = export_epub(root, &RenderConfig::default(), TrustLevel::Restricted).unwrap();
+        let mut archive = ZipArchive::new(Cursor::new(epub)).unwrap();
+        assert!(!read_entry(&mut archive, "EPUB/chapter-001.xhtml").contains("<kbd>"));
+    }
+
+    #[test]
+    fn test_export_empty_folder() {
+        let temp_dir = tempdir().unwrap();
+
+        let result = export_epub(
+            temp_dir.path(),
+            &RenderConfig::default(),
+            TrustLevel::Trusted,
+        );
+        assert!(matches!(result, Err(ExportError::NoDocuments(_))));
+    }
+
+    #[test]
+    fn test_utc_timestamp() {
+        let time = UNIX_EPOCH + Duration::from_secs(1_709_210_096);
+
+        assert_eq!(utc_timestamp(time), "2024-02-29T12:34:56Z");
+        assert_eq!(utc_timestamp(UNIX_EPOCH), "1970-01-01T00:00:00Z");
+    }
+}
@@ -0,0 +1,181 @@
+/// The standard PDF fonts used by the PDF exporter.
+///
+/// Standard fonts are built into every PDF reader, so nothing needs to be
+/// embedded. Text is encoded with WinAnsiEncoding; characters outside it are
+/// replaced with `?` (see `can_encode`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Font {
+    Regular,
+    Bold,
+    Italic,
+    BoldItalic,
+    Mono,
+}
+
+impl Font {
+    /// All fonts, in the order they are registered as page resources.
+    pub const ALL: [Font; 5] = [
+        Font::Regular,
+        Font::Bold,
+        Font::Italic,
+        Font::BoldItalic,
+        Font::Mono,
+    ];
+
+    /// The PostScript name of the standard font.
+    pub fn base_font(self) -> &'static [u8] {
+        match self {
+            Font::Regular => b"Helvetica",
+            Font::Bold => b"Helvetica-Bold",
+            Font::Italic => b"Helvetica-Oblique",
+            Font::BoldItalic => b"Helvetica-BoldOblique",
+            Font::Mono => b"Courier",
+        }
+    }
+
+    /// The resource name the font is registered under on each page.
+    pub fn resource_name(self) -> &'static [u8] {
+        match self {
+            Font::Regular => b"F1",
+            Font::Bold => b"F2",
+            Font::Italic => b"F3",
+            Font::BoldItalic => b"F4",
+            Font::Mono => b"F5",
+        }
+    }
+
+    /// Returns the font with bold and italic applied.
+    pub fn styled(bold: bool, italic: bool) -> Font {
+        match (bold, italic) {
+            (false, false) => Font::Regular,
+            (true, false) => Font::Bold,
+            (false, true) => Font::Italic,
+            (true, true) => Font::BoldItalic,
+        }
+    }
+
+    /// Returns the bold variant of the font.
+    pub fn bold(self) -> Font {
+        match self {
+            Font::Regular | Font::Bold => Font::Bold,
+            Font::Italic | Font::BoldItalic => Font::BoldItalic,
+            Font::Mono => Font::Mono,
+        }
+    }
+
+    /// Width of a string in points at the given font size.
+    pub fn text_width(self, text: &str, size: f32) -> f32 {
+        let units: u32 = text.chars().map(|c| self.char_width(c)).sum();
+        units as f32 * size / 1000.0
+    }
+
+    /// Width of a character in 1/1000 em.
+    fn char_width(self, c: char) -> u32 {
+        let bold = match self {
+            Font::Mono => return 600,
+            Font::Regular | Font::Italic => false,
+            Font::Bold | Font::BoldItalic => true,
+        };
+
+        let code = encode_char(c);
+        match code {
+            32..=126 => {
+                let index = (code - 32) as usize;
+                if bold {
+                    HELVETICA_BOLD_WIDTHS[index]
+                } else {
+                    HELVETICA_WIDTHS[index]
+                }
+            }
+            0x85 | 0x97 => 1000,
+            0x95 => 350,
+            0x91 | 0x92 | 0x82 => 222,
+            0x93 | 0x94 | 0x84 => 333,
+            _ => 556,
+        }
+    }
+}
+
+/// Encodes text in WinAnsiEncoding.
+pub fn encode(text: &str) -> Vec<u8> {
+    text.chars().map(encode_char).collect()
+}
+
+/// Returns true if the standard fonts can show the character.
+pub fn can_encode(c: char) -> bool {
+    win_ansi_code(c).is_some()
+}
+
+/// Encodes a character in WinAnsiEncoding, using `?` if it has no code.
+fn encode_char(c: char) -> u8 {
+    win_ansi_code(c).unwrap_or(b'?')
+}
+
+/// Returns the WinAnsiEncoding code of a character, if it has one.
+fn win_ansi_code(c: char) -> Option<u8> {
+    let code = match c {
+        ' '..='~' => c as u8,
+        '\t' => b' ',
+        '\u{a0}'..='\u{ff}' => c as u32 as u8,
+        '€' => 0x80,
+        '‚' => 0x82,
+        '„' => 0x84,
+        '…' => 0x85,
+        '‘' => 0x91,
+        '’' => 0x92,
+        '“' => 0x93,
+        '”' => 0x94,
+        '•' => 0x95,
+        '–' => 0x96,
+        '—' => 0x97,
+        '™' => 0x99,
+        _ => return None,
+    };
+    Some(code)
+}
+
+/// Helvetica glyph widths for ASCII 32-126.
+#[rustfmt::skip]
+const HELVETICA_WIDTHS: [u32; 95] = [
+    278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278,
+    556, 556, 556, 556, 556, 556, 556, 556, 556, 556, 278, 278, 584, 584, 584, 556,
+    1015, 667, 667, 722, 722, 667, 611, 778, 722, 278, 500, 667, 556, 833, 722, 778,
+    667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, 278, 278, 278, 469, 556,
+    333, 556, 556, 500, 556, 556, 278, 556, 556, 222, 222, 500, 222, 833, 556, 556,
+    556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, 334, 260, 334, 584,
+];
+
+/// Helvetica-Bold glyph widths for ASCII 32-126.
+#[rustfmt::skip]
+const HELVETICA_BOLD_WIDTHS: [u32; 95] = [
+    278, 333, 474, 556, 556, 889, 722, 238, 333, 333, 389, 584, 278, 333, 278, 278,
+    556, 556, 556, 556, 556, 556, 556, 556, 556, 556, 333, 333, 584, 584, 584, 611,
+    975, 722, 722, 722, 722, 667, 611, 778, 722, 278, 556, 722, 611, 833, 722, 778,
+    667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, 333, 278, 333, 584, 556,
+    333, 556, 611, 556, 611, 556, 333, 611, 611, 278, 278, 556, 278, 889, 611, 611,
+    611, 611, 389, 556, 333, 611, 556, 778, 556, 556, 500, 389, 280, 389, 584,
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_width() {
+        assert_eq!(Font::Mono.text_width("abcd", 10.0), 24.0);
+        assert_eq!(
+            Font::Regular.text_width("Hi", 10.0),
+            (722 + 222) as f32 / 100.0
+        );
+        assert!(Font::Bold.text_width("Hi", 10.0) > Font::Regular.text_width("Hi", 10.0));
+    }
+
+    #[test]
+    fn test_encode() {
+        assert_eq!(encode("a’b"), vec![b'a', 0x92, b'b']);
+        assert_eq!(encode("café"), vec![b'c', b'a', b'f', 0xe9]);
+        assert_eq!(encode("日本"), vec![b'?', b'?']);
+        assert!(can_encode('é'));
+        assert!(!can_encode('日'));
+    }
+}
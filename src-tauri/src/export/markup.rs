@@ -0,0 +1,207 @@
+use std::path::Path;
+
+/// Elements that have no content and must be self-closed in XHTML.
+const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track",
+    "wbr",
+];
+
+/// Image types that can be embedded in exports, by file extension.
+const IMAGE_MEDIA_TYPES: &[(&str, &str)] = &[
+    ("png", "image/png"),
+    ("jpg", "image/jpeg"),
+    ("jpeg", "image/jpeg"),
+    ("gif", "image/gif"),
+    ("svg", "image/svg+xml"),
+    ("webp", "image/webp"),
+];
+
+/// Returns the media type of an image file, if it is a supported type.
+pub(crate) fn image_media_type(path: &Path) -> Option<&'static str> {
+    let extension = path.extension()?.to_string_lossy().to_lowercase();
+    IMAGE_MEDIA_TYPES
+        .iter()
+        .find(|(known, _)| *known == extension)
+        .map(|(_, media_type)| *media_type)
+}
+
+/// Escapes text for use in XML content and attribute values.
+pub(crate) fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// A start or end tag parsed from rendered HTML.
+struct Tag<'a> {
+    name: &'a str,
+    closing: bool,
+    attributes: Vec<(&'a str, Option<&'a str>)>,
+}
+
+/// Converts rendered HTML to well-formed XHTML.
+///
+/// The renderer and sanitizer always quote attributes and escape text, so
+/// only void elements, boolean attributes and `&nbsp;` need fixing. Each
+/// attribute value is passed through `rewrite`, which returns the new
+/// (escaped) value or None to drop the attribute.
+pub(crate) fn to_xhtml<'a>(
+    html: &'a str,
+    mut rewrite: impl FnMut(&str, &str, &'a str) -> Option<String>,
+) -> String {
+    let mut xhtml = String::with_capacity(html.len());
+    let mut rest = html;
+
+    while let Some(start) = rest.find(['<', '&']) {
+        xhtml.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        if let Some(after) = rest.strip_prefix("&nbsp;") {
+            xhtml.push_str("&#160;");
+            rest = after;
+        } else if rest.starts_with('&') {
+            xhtml.push('&');
+            rest = &rest[1..];
+        } else if let Some(comment) = rest.strip_prefix("<!--") {
+            let end = comment.find("-->").map_or(comment.len(), |end| end + 3);
+            xhtml.push_str("<!--");
+            xhtml.push_str(&comment[..end]);
+            rest = &comment[end..];
+        } else if let Some((tag, length)) = parse_tag(rest) {
+            write_tag(&mut xhtml, &tag, &mut rewrite);
+            rest = &rest[length..];
+        } else {
+            xhtml.push_str("&lt;");
+            rest = &rest[1..];
+        }
+    }
+    xhtml.push_str(rest);
+    xhtml
+}
+
+fn write_tag<'a>(
+    xhtml: &mut String,
+    tag: &Tag<'a>,
+    rewrite: &mut impl FnMut(&str, &str, &'a str) -> Option<String>,
+) {
+    if tag.closing {
+        xhtml.push_str(&format!("</{}>", tag.name));
+        return;
+    }
+
+    xhtml.push('<');
+    xhtml.push_str(tag.name);
+    for &(name, value) in &tag.attributes {
+        // Boolean attributes need a value in XML
+        let Some(value) = rewrite(tag.name, name, value.unwrap_or(name)) else {
+            continue;
+        };
+        let value = value
+            .replace("&nbsp;", "&#160;")
+            .replace('<', "&lt;")
+            .replace('"', "&quot;");
+        xhtml.push_str(&format!(" {}=\"{}\"", name, value));
+    }
+    if VOID_ELEMENTS.contains(&tag.name) {
+        xhtml.push_str(" />");
+    } else {
+        xhtml.push('>');
+    }
+}
+
+/// Parses a tag at the start of `html`, returning it and its length in bytes.
+fn parse_tag(html: &str) -> Option<(Tag<'_>, usize)> {
+    let mut position = 1;
+    let closing = html[position..].starts_with('/');
+    if closing {
+        position += 1;
+    }
+
+    let name_length = html[position..]
+        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '-' || c == ':'))
+        .unwrap_or(html.len() - position);
+    if name_length == 0 || !html[position..].starts_with(|c: char| c.is_ascii_alphabetic()) {
+        return None;
+    }
+    let name = &html[position..position + name_length];
+    position += name_length;
+
+    let mut attributes = Vec::new();
+    loop {
+        position += whitespace_length(&html[position..]);
+        let rest = &html[position..];
+
+        if rest.starts_with("/>") {
+            return Some((
+                Tag {
+                    name,
+                    closing,
+                    attributes,
+                },
+                position + 2,
+            ));
+        }
+        if rest.starts_with('>') {
+            return Some((
+                Tag {
+                    name,
+                    closing,
+                    attributes,
+                },
+                position + 1,
+            ));
+        }
+
+        let attribute_length =
+            rest.find(|c: char| c.is_whitespace() || matches!(c, '=' | '>' | '/'))?;
+        if attribute_length == 0 {
+            return None;
+        }
+        let attribute = &rest[..attribute_length];
+        position += attribute_length;
+        position += whitespace_length(&html[position..]);
+
+        if !html[position..].starts_with('=') {
+            attributes.push((attribute, None));
+            continue;
+        }
+        position += 1;
+        position += whitespace_length(&html[position..]);
+
+        let rest = &html[position..];
+        let value = match rest.chars().next()? {
+            quote @ ('"' | '\'') => {
+                let end = rest[1..].find(quote)?;
+                position += end + 2;
+                &rest[1..end + 1]
+            }
+            _ => {
+                let end = rest.find(|c: char| c.is_whitespace() || c == '>')?;
+                position += end;
+                &rest[..end]
+            }
+        };
+        attributes.push((attribute, Some(value)));
+    }
+}
+
+fn whitespace_length(text: &str) -> usize {
+    text.len() - text.trim_start().len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_xhtml() {
+        let html = "<p>a&nbsp;b<br><input type=\"checkbox\" checked disabled=\"\"><img src='x.png' alt=\"1 < 2\"></p><!-- <br> -->";
+        let xhtml = to_xhtml(html, |_, _, value| Some(value.to_string()));
+
+        assert_eq!(
+            xhtml,
+            "<p>a&#160;b<br /><input type=\"checkbox\" checked=\"checked\" disabled=\"\" /><img src=\"x.png\" alt=\"1 &lt; 2\" /></p><!-- <br> -->"
+        );
+    }
+}
@@ -0,0 +1,113 @@
+pub mod epub;
+pub mod fonts;
+mod markup;
+pub mod pdf;
+pub mod slides;
+
+use crate::md::loader::MdLoadError;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Errors that can occur while exporting documents.
+#[derive(Debug, thiserror::Error)]
+pub enum ExportError {
+    /// A source document could not be loaded
+    #[error(transparent)]
+    Load(#[from] MdLoadError),
+
+    /// The output could not be written
+    #[error("IO error: {0}")]
+    Io(#[from] io::Error),
+
+    /// The output archive could not be written
+    #[error("Archive error: {0}")]
+    Archive(#[from] zip::result::ZipError),
+
+    /// There is nothing to export
+    #[error("No Markdown content found in {0}")]
+    NoDocuments(String),
+
+    /// An export option has an invalid value
+    #[error("Invalid export option: {0}")]
+    InvalidOption(String),
+}
+
+/// Returns the default output path for an export: the input path with a new extension.
+///
+/// # Arguments
+///
+/// * `input` - The exported document or folder
+/// * `extension` - The extension of the output format, without a dot
+///
+/// # Returns
+///
+/// * `PathBuf` - The output path next to the input
+pub fn default_output_path<P: AsRef<Path>>(input: P, extension: &str) -> PathBuf {
+    let input = input.as_ref();
+    if input.is_dir() {
+        // Folders export next to themselves, so `.` needs its real name
+        if let Ok(folder) = input.canonicalize() {
+            return folder.with_extension(extension);
+        }
+    }
+    input.with_extension(extension)
+}
+
+/// Resolves a local file, such as an image, that an export embeds.
+///
+/// Exported files are meant to be shared, so files outside the exported
+/// folder aren't embedded, even through absolute paths, `..` or symlinks.
+///
+/// # Arguments
+///
+/// * `folder` - The folder being exported, or the exported document's folder
+/// * `path` - The file to embed
+///
+/// # Returns
+///
+/// * `Option<PathBuf>` - The canonical path, or None if the file is missing or outside `folder`
+pub(crate) fn contained_file(folder: &Path, path: &Path) -> Option<PathBuf> {
+    let path = path.canonicalize().ok()?;
+    let folder = folder.canonicalize().ok()?;
+    path.starts_with(folder).then_some(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_default_output_path() {
+        assert_eq!(
+            default_output_path("docs/spec.md", "pdf"),
+            PathBuf::from("docs/spec.pdf")
+        );
+
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path().canonicalize().unwrap();
+        std::fs::create_dir(root.join("guide")).unwrap();
+        assert_eq!(
+            default_output_path(root.join("guide"), "epub"),
+            root.join("guide.epub")
+        );
+    }
+
+    #[test]
+    fn test_contained_file() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path().canonicalize().unwrap();
+        let docs = root.join("docs");
+        std::fs::create_dir(&docs).unwrap();
+        std::fs::write(docs.join("dot.png"), b"").unwrap();
+        std::fs::write(root.join("outside.png"), b"").unwrap();
+
+        assert_eq!(
+            contained_file(&docs, &docs.join("./dot.png")),
+            Some(docs.join("dot.png"))
+        );
+        assert_eq!(contained_file(&docs, &docs.join("../outside.png")), None);
+        assert_eq!(contained_file(&docs, &root.join("outside.png")), None);
+        assert_eq!(contained_file(&docs, &docs.join("missing.png")), None);
+    }
+}
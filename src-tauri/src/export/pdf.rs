@@ -0,0 +1,1444 @@
+use super::fonts::{self, Font};
+use super::ExportError;
+use crate::md::{admonitions, links, loader, parser, toc, TocItem};
+use comrak::nodes::{AstNode, ListDelimType, ListType, NodeList, NodeValue};
+use comrak::{parse_document, Arena};
+use pdf_writer::types::{ActionType, AnnotationType, PageMode};
+use pdf_writer::writers::Annotation;
+use pdf_writer::{Content, Filter, Finish, Name, Pdf, Rect, Ref, Str, TextStr};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Paper sizes supported by the PDF exporter.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PageSize {
+    #[default]
+    A4,
+    A5,
+    Letter,
+    Legal,
+}
+
+impl PageSize {
+    /// Returns the page width and height in points.
+    pub fn dimensions(self) -> (f32, f32) {
+        match self {
+            PageSize::A4 => (595.28, 841.89),
+            PageSize::A5 => (419.53, 595.28),
+            PageSize::Letter => (612.0, 792.0),
+            PageSize::Legal => (612.0, 1008.0),
+        }
+    }
+}
+
+impl FromStr for PageSize {
+    type Err = ExportError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "a4" => Ok(PageSize::A4),
+            "a5" => Ok(PageSize::A5),
+            "letter" => Ok(PageSize::Letter),
+            "legal" => Ok(PageSize::Legal),
+            _ => Err(ExportError::InvalidOption(format!(
+                "unknown page size '{}' (expected a4, a5, letter or legal)",
+                value
+            ))),
+        }
+    }
+}
+
+/// Page setup for PDF export.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct PdfOptions {
+    /// The paper size
+    pub page_size: PageSize,
+    /// The margin on all four sides, in millimetres
+    pub margin_mm: u32,
+}
+
+impl Default for PdfOptions {
+    fn default() -> Self {
+        Self {
+            page_size: PageSize::A4,
+            margin_mm: 20,
+        }
+    }
+}
+
+/// Body text size in points.
+const BODY_SIZE: f32 = 11.0;
+/// Line height as a multiple of the font size.
+const LINE_SPACING: f32 = 1.4;
+/// Code block text size in points.
+const CODE_SIZE: f32 = 9.0;
+/// Heading sizes for levels 1 to 6.
+const HEADING_SIZES: [f32; 6] = [22.0, 18.0, 15.0, 13.0, 11.5, 11.0];
+/// Indentation of list items and footnotes.
+const LIST_INDENT: f32 = 18.0;
+/// Indentation of block quotes.
+const QUOTE_INDENT: f32 = 14.0;
+/// Image size in points per pixel (96 dpi).
+const POINTS_PER_PIXEL: f32 = 0.75;
+/// Points per millimetre.
+const POINTS_PER_MM: f32 = 72.0 / 25.4;
+
+type Rgb = (f32, f32, f32);
+
+const TEXT_COLOR: Rgb = (0.14, 0.16, 0.18);
+const QUOTE_COLOR: Rgb = (0.42, 0.45, 0.49);
+const LINK_COLOR: Rgb = (0.1, 0.46, 0.82);
+const SHADE_COLOR: Rgb = (0.965, 0.973, 0.98);
+const RULE_COLOR: Rgb = (0.87, 0.89, 0.9);
+
+/// Most characters listed in the warning about replaced characters.
+const MAX_LISTED_CHARS: usize = 10;
+
+/// A PDF file rendered from Markdown.
+#[derive(Debug, Clone)]
+pub struct RenderedPdf {
+    /// The PDF file contents
+    pub data: Vec<u8>,
+    /// Characters the standard fonts can't show, which were written as `?`
+    pub replaced_chars: Vec<char>,
+}
+
+impl RenderedPdf {
+    /// Describes the replaced characters, if there were any.
+    pub fn warning(&self) -> Option<String> {
+        if self.replaced_chars.is_empty() {
+            return None;
+        }
+
+        let mut listed: String = self.replaced_chars.iter().take(MAX_LISTED_CHARS).collect();
+        if self.replaced_chars.len() > MAX_LISTED_CHARS {
+            listed.push('…');
+        }
+        Some(format!(
+            "{} characters can't be shown by the PDF fonts and were replaced with '?': {}",
+            self.replaced_chars.len(),
+            listed
+        ))
+    }
+}
+
+/// Exports a Markdown file to PDF.
+///
+/// # Arguments
+///
+/// * `path` - The Markdown file to export
+/// * `options` - Page setup
+///
+/// # Returns
+///
+/// * `Result<RenderedPdf, ExportError>` - The PDF file or an error
+pub fn export_pdf<P: AsRef<Path>>(
+    path: P,
+    options: &PdfOptions,
+) -> Result<RenderedPdf, ExportError> {
+    let path = path.as_ref();
+    let markdown = loader::load_markdown_file(path)?;
+    let base_dir = path.parent().unwrap_or_else(|| Path::new("."));
+
+    Ok(render_pdf(&markdown, base_dir, options))
+}
+
+/// Renders Markdown to a paginated PDF.
+///
+/// The document is parsed into the same AST used for HTML rendering and laid
+/// out directly, so no browser is needed. Headings become PDF bookmarks and
+/// `#anchor` links jump to them. Only images inside `base_dir` are embedded.
+///
+/// # Arguments
+///
+/// * `markdown` - The Markdown source text
+/// * `base_dir` - Directory that relative image paths are resolved against
+/// * `options` - Page setup
+///
+/// # Returns
+///
+/// * `RenderedPdf` - The PDF file and the characters its fonts couldn't show
+pub fn render_pdf(markdown: &str, base_dir: &Path, options: &PdfOptions) -> RenderedPdf {
+    let arena = Arena::new();
+    let markdown = admonitions::convert_mkdocs_admonitions(markdown);
+    let root = parse_document(&arena, &markdown, &parser::render_options());
+
+    let mut layout = Layout::new(options, base_dir);
+    layout.blocks(root, &Context::default());
+    layout.finish()
+}
+
+/// Where a link points.
+#[derive(Debug, Clone, PartialEq)]
+enum LinkTarget {
+    /// An external URL
+    Uri(String),
+    /// A heading in this document
+    Anchor(String),
+}
+
+/// A run of inline text in one font.
+#[derive(Debug, Clone)]
+struct Piece {
+    text: String,
+    font: Font,
+    link: Option<LinkTarget>,
+}
+
+/// A piece of text placed on a line, at its final font size.
+#[derive(Debug, Clone)]
+struct Run {
+    text: String,
+    font: Font,
+    size: f32,
+    link: Option<LinkTarget>,
+}
+
+/// Inline formatting in effect while collecting text.
+#[derive(Debug, Clone, Default)]
+struct InlineStyle {
+    bold: bool,
+    italic: bool,
+    link: Option<LinkTarget>,
+}
+
+/// Block nesting in effect while laying out.
+#[derive(Debug, Clone, Default)]
+struct Context {
+    indent: f32,
+    quote_bars: Vec<f32>,
+    tight: bool,
+    bold: bool,
+}
+
+impl Context {
+    fn color(&self) -> Rgb {
+        if self.quote_bars.is_empty() {
+            TEXT_COLOR
+        } else {
+            QUOTE_COLOR
+        }
+    }
+}
+
+/// How a line of text is drawn.
+struct LineStyle {
+    size: f32,
+    line_height: f32,
+    color: Rgb,
+    inset: f32,
+    background: Option<Rgb>,
+}
+
+/// An image decoded for embedding.
+struct Image {
+    data: Vec<u8>,
+    filter: Filter,
+    width: u32,
+    height: u32,
+    components: u8,
+    alpha: Option<Vec<u8>>,
+}
+
+/// The content and links of one page.
+struct Page {
+    content: Content,
+    links: Vec<(Rect, LinkTarget)>,
+    images: Vec<usize>,
+}
+
+impl Page {
+    fn new() -> Self {
+        Self {
+            content: Content::new(),
+            links: Vec::new(),
+            images: Vec::new(),
+        }
+    }
+}
+
+/// A heading and where it was placed.
+struct PlacedHeading {
+    item: TocItem,
+    page: usize,
+    top: f32,
+}
+
+/// Lays out blocks top to bottom, starting new pages as needed.
+struct Layout {
+    page_width: f32,
+    page_height: f32,
+    margin: f32,
+    base_dir: PathBuf,
+    pages: Vec<Page>,
+    y: f32,
+    headings: Vec<PlacedHeading>,
+    anchors: HashMap<String, (usize, f32)>,
+    images: Vec<Image>,
+    image_indices: HashMap<PathBuf, Option<usize>>,
+    pending_marker: Option<(String, Font)>,
+    replaced_chars: BTreeSet<char>,
+}
+
+impl Layout {
+    fn new(options: &PdfOptions, base_dir: &Path) -> Self {
+        let (page_width, page_height) = options.page_size.dimensions();
+        // Keep at least a third of the page for content
+        let margin = (options.margin_mm as f32 * POINTS_PER_MM).min(page_width / 3.0);
+
+        Self {
+            page_width,
+            page_height,
+            margin,
+            base_dir: base_dir.to_path_buf(),
+            pages: vec![Page::new()],
+            y: page_height - margin,
+            headings: Vec::new(),
+            anchors: HashMap::new(),
+            images: Vec::new(),
+            image_indices: HashMap::new(),
+            pending_marker: None,
+            replaced_chars: BTreeSet::new(),
+        }
+    }
+
+    fn top(&self) -> f32 {
+        self.page_height - self.margin
+    }
+
+    fn left(&self, context: &Context) -> f32 {
+        self.margin + context.indent
+    }
+
+    fn width(&self, context: &Context) -> f32 {
+        self.page_width - 2.0 * self.margin - context.indent
+    }
+
+    fn page(&mut self) -> &mut Page {
+        self.pages.last_mut().expect("layout always has a page")
+    }
+
+    /// Starts a new page unless there is room for `height` on this one.
+    fn ensure_space(&mut self, height: f32) {
+        if self.y - height < self.margin && self.y < self.top() {
+            self.pages.push(Page::new());
+            self.y = self.top();
+        }
+    }
+
+    /// Adds vertical space, unless at the top of a page.
+    fn space(&mut self, height: f32) {
+        if self.y < self.top() {
+            self.y -= height;
+        }
+    }
+
+    fn blocks<'a>(&mut self, node: &'a AstNode<'a>, context: &Context) {
+        for child in node.children() {
+            self.block(child, context);
+        }
+    }
+
+    fn block<'a>(&mut self, node: &'a AstNode<'a>, context: &Context) {
+        let value = node.data.borrow().value.clone();
+
+        match value {
+            NodeValue::Heading(heading) => self.heading(node, heading.level, context),
+            NodeValue::Paragraph => self.paragraph(node, context),
+            NodeValue::CodeBlock(code) => self.code_block(&code.literal, context),
+            NodeValue::List(list) => self.list(node, &list, context),
+            NodeValue::BlockQuote | NodeValue::MultilineBlockQuote(_) => {
+                let mut quote = context.clone();
+                quote.quote_bars.push(self.left(context) + 2.0);
+                quote.indent += QUOTE_INDENT;
+                self.blocks(node, &quote);
+            }
+            NodeValue::ThematicBreak => self.rule(context),
+            NodeValue::Table(_) => self.table(node, context),
+            NodeValue::FootnoteDefinition(definition) => {
+                let mut footnote = context.clone();
+                footnote.indent += LIST_INDENT;
+                self.pending_marker = Some((format!("[{}]", definition.name), Font::Regular));
+                self.blocks(node, &footnote);
+                self.pending_marker = None;
+            }
+            NodeValue::DescriptionTerm => {
+                let mut term = context.clone();
+                term.bold = true;
+                term.tight = true;
+                self.blocks(node, &term);
+            }
+            NodeValue::DescriptionDetails => {
+                let mut details = context.clone();
+                details.indent += LIST_INDENT;
+                self.blocks(node, &details);
+            }
+            NodeValue::HtmlBlock(_) | NodeValue::FrontMatter(_) => {}
+            _ => self.blocks(node, context),
+        }
+    }
+
+    fn heading<'a>(&mut self, node: &'a AstNode<'a>, level: u8, context: &Context) {
+        let size = HEADING_SIZES[(level.clamp(1, 6) - 1) as usize];
+        let line_height = size * 1.25;
+
+        self.space(size * 0.6);
+        // Keep the heading on the same page as the first line that follows it
+        self.ensure_space(line_height + BODY_SIZE * LINE_SPACING);
+
+        if let Some(item) = toc::heading_item(node) {
+            let page = self.pages.len() - 1;
+            self.anchors
+                .entry(item.id.clone())
+                .or_insert((page, self.y));
+            self.headings.push(PlacedHeading {
+                item,
+                page,
+                top: self.y,
+            });
+        }
+
+        let pieces = collect_pieces(
+            node,
+            &InlineStyle {
+                bold: true,
+                ..InlineStyle::default()
+            },
+        );
+        let style = LineStyle {
+            size,
+            line_height,
+            color: context.color(),
+            inset: 0.0,
+            background: None,
+        };
+        self.text(&pieces, &style, context);
+
+        if level <= 2 {
+            let (left, right, y) = (
+                self.left(context),
+                self.left(context) + self.width(context),
+                self.y - 3.0,
+            );
+            self.stroke_line(left, y, right, y, RULE_COLOR);
+            self.y -= 6.0;
+        }
+        self.y -= size * 0.4;
+    }
+
+    fn paragraph<'a>(&mut self, node: &'a AstNode<'a>, context: &Context) {
+        let mut pieces = Vec::new();
+        let mut images = Vec::new();
+        let inline = InlineStyle {
+            bold: context.bold,
+            ..InlineStyle::default()
+        };
+        collect_inlines(node, &inline, &mut pieces, &mut images);
+
+        let style = LineStyle {
+            size: BODY_SIZE,
+            line_height: BODY_SIZE * LINE_SPACING,
+            color: context.color(),
+            inset: 0.0,
+            background: None,
+        };
+        self.text(&pieces, &style, context);
+
+        // Images are placed as blocks below the text they appear in
+        for (url, alt) in images {
+            self.image(&url, &alt, context);
+        }
+
+        self.y -= if context.tight { 2.0 } else { BODY_SIZE * 0.6 };
+    }
+
+    fn text(&mut self, pieces: &[Piece], style: &LineStyle, context: &Context) {
+        let width = self.width(context) - 2.0 * style.inset;
+        for line in wrap(pieces, style.size, width) {
+            self.line(&line, style, context);
+        }
+    }
+
+    /// Draws one line of text at the current position and moves below it.
+    fn line(&mut self, runs: &[Run], style: &LineStyle, context: &Context) {
+        self.ensure_space(style.line_height);
+
+        let top = self.y;
+        let left = self.left(context);
+        let width = self.width(context);
+        let baseline = baseline(top, style.line_height, style.size);
+
+        if let Some(color) = style.background {
+            self.fill_rect(
+                left,
+                top - style.line_height,
+                width,
+                style.line_height,
+                color,
+            );
+        }
+        for &x in &context.quote_bars {
+            self.fill_rect(
+                x,
+                top - style.line_height,
+                2.5,
+                style.line_height,
+                RULE_COLOR,
+            );
+        }
+        if !runs.is_empty() {
+            if let Some((marker, font)) = self.pending_marker.take() {
+                let marker_width = font.text_width(&marker, BODY_SIZE);
+                let marker_run = Run {
+                    text: marker,
+                    font,
+                    size: BODY_SIZE,
+                    link: None,
+                };
+                self.draw_runs(
+                    &[marker_run],
+                    left - marker_width - 5.0,
+                    baseline,
+                    style.color,
+                );
+            }
+        }
+
+        self.draw_runs(runs, left + style.inset, baseline, style.color);
+        self.y -= style.line_height;
+    }
+
+    fn draw_runs(&mut self, runs: &[Run], x: f32, baseline: f32, color: Rgb) {
+        if runs.is_empty() {
+            return;
+        }
+
+        self.replaced_chars.extend(
+            runs.iter()
+                .flat_map(|run| run.text.chars())
+                .filter(|&c| !fonts::can_encode(c)),
+        );
+
+        let page = self.page();
+        let mut x = x;
+        page.content.begin_text();
+        for run in runs {
+            let (r, g, b) = if run.link.is_some() {
+                LINK_COLOR
+            } else {
+                color
+            };
+            let width = run.font.text_width(&run.text, run.size);
+
+            page.content
+                .set_fill_rgb(r, g, b)
+                .set_font(Name(run.font.resource_name()), run.size)
+                .set_text_matrix([1.0, 0.0, 0.0, 1.0, x, baseline])
+                .show(Str(&fonts::encode(&run.text)));
+
+            if let Some(link) = &run.link {
+                let rect = Rect::new(
+                    x,
+                    baseline - run.size * 0.25,
+                    x + width,
+                    baseline + run.size * 0.9,
+                );
+                page.links.push((rect, link.clone()));
+            }
+            x += width;
+        }
+        page.content.end_text();
+    }
+
+    fn fill_rect(&mut self, x: f32, y: f32, width: f32, height: f32, (r, g, b): Rgb) {
+        self.page()
+            .content
+            .set_fill_rgb(r, g, b)
+            .rect(x, y, width, height)
+            .fill_nonzero();
+    }
+
+    fn stroke_line(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, (r, g, b): Rgb) {
+        self.page()
+            .content
+            .set_stroke_rgb(r, g, b)
+            .set_line_width(0.75)
+            .move_to(x1, y1)
+            .line_to(x2, y2)
+            .stroke();
+    }
+
+    fn code_block(&mut self, literal: &str, context: &Context) {
+        let padding = 6.0;
+        let style = LineStyle {
+            size: CODE_SIZE,
+            line_height: CODE_SIZE * 1.45,
+            color: TEXT_COLOR,
+            inset: padding,
+            background: Some(SHADE_COLOR),
+        };
+        let spacer = LineStyle {
+            line_height: padding,
+            ..style
+        };
+
+        let text_width = self.width(context) - 2.0 * padding;
+        let max_chars = ((text_width / Font::Mono.text_width("0", CODE_SIZE)) as usize).max(1);
+
+        self.line(&[], &spacer, context);
+        for source_line in literal.trim_end_matches('\n').split('\n') {
+            let source_line = source_line.trim_end_matches('\r').replace('\t', "    ");
+            let chars: Vec<char> = source_line.chars().collect();
+            let chunks: Vec<String> = if chars.is_empty() {
+                vec![String::new()]
+            } else {
+                chars
+                    .chunks(max_chars)
+                    .map(|chunk| chunk.iter().collect())
+                    .collect()
+            };
+
+            for chunk in chunks {
+                let run = Run {
+                    text: chunk,
+                    font: Font::Mono,
+                    size: CODE_SIZE,
+                    link: None,
+                };
+                self.line(&[run], &style, context);
+            }
+        }
+        self.line(&[], &spacer, context);
+        self.y -= BODY_SIZE * 0.6;
+    }
+
+    fn list<'a>(&mut self, node: &'a AstNode<'a>, list: &NodeList, context: &Context) {
+        let mut item_context = context.clone();
+        item_context.indent += LIST_INDENT;
+        item_context.tight = list.tight;
+
+        let delimiter = match list.delimiter {
+            ListDelimType::Period => '.',
+            ListDelimType::Paren => ')',
+        };
+
+        for (index, item) in node.children().enumerate() {
+            let marker = match &item.data.borrow().value {
+                NodeValue::TaskItem(Some(_)) => ("[x]".to_string(), Font::Mono),
+                NodeValue::TaskItem(None) => ("[ ]".to_string(), Font::Mono),
+                _ => match list.list_type {
+                    ListType::Bullet => ("•".to_string(), Font::Regular),
+                    ListType::Ordered => (
+                        format!("{}{}", list.start + index, delimiter),
+                        Font::Regular,
+                    ),
+                },
+            };
+
+            self.pending_marker = Some(marker);
+            self.blocks(item, &item_context);
+            self.pending_marker = None;
+        }
+
+        if list.tight {
+            self.y -= BODY_SIZE * 0.5;
+        }
+    }
+
+    fn rule(&mut self, context: &Context) {
+        self.ensure_space(16.0);
+        self.y -= 6.0;
+        let (left, right, y) = (
+            self.left(context),
+            self.left(context) + self.width(context),
+            self.y,
+        );
+        self.stroke_line(left, y, right, y, RULE_COLOR);
+        self.y -= 10.0;
+    }
+
+    fn table<'a>(&mut self, node: &'a AstNode<'a>, context: &Context) {
+        let rows: Vec<(bool, Vec<Vec<Piece>>)> = node
+            .children()
+            .map(|row| {
+                let header = matches!(row.data.borrow().value, NodeValue::TableRow(true));
+                let style = InlineStyle {
+                    bold: header,
+                    ..InlineStyle::default()
+                };
+                let cells = row
+                    .children()
+                    .map(|cell| collect_pieces(cell, &style))
+                    .collect();
+                (header, cells)
+            })
+            .collect();
+
+        let columns = rows.iter().map(|(_, cells)| cells.len()).max().unwrap_or(0);
+        if columns == 0 {
+            return;
+        }
+
+        let size = BODY_SIZE * 0.9;
+        let line_height = size * 1.35;
+        let padding = 4.0;
+        let left = self.left(context);
+        let width = self.width(context);
+        let column_width = width / columns as f32;
+        let color = context.color();
+
+        for (header, cells) in rows {
+            let wrapped: Vec<Vec<Vec<Run>>> = cells
+                .iter()
+                .map(|cell| wrap(cell, size, column_width - 2.0 * padding))
+                .collect();
+            let lines = wrapped.iter().map(Vec::len).max().unwrap_or(0).max(1);
+            let row_height = lines as f32 * line_height + 2.0 * padding;
+
+            self.ensure_space(row_height);
+            let top = self.y;
+
+            if header {
+                self.fill_rect(left, top - row_height, width, row_height, SHADE_COLOR);
+            }
+            for &x in &context.quote_bars {
+                self.fill_rect(x, top - row_height, 2.5, row_height, RULE_COLOR);
+            }
+            for (column, cell_lines) in wrapped.iter().enumerate() {
+                let x = left + column as f32 * column_width + padding;
+                for (index, runs) in cell_lines.iter().enumerate() {
+                    let line_top = top - padding - index as f32 * line_height;
+                    self.draw_runs(runs, x, baseline(line_top, line_height, size), color);
+                }
+            }
+            self.stroke_line(
+                left,
+                top - row_height,
+                left + width,
+                top - row_height,
+                RULE_COLOR,
+            );
+            self.y = top - row_height;
+        }
+
+        self.y -= BODY_SIZE * 0.6;
+    }
+
+    fn image(&mut self, url: &str, alt: &str, context: &Context) {
+        let index = if links::is_external(url) || url.starts_with("data:") {
+            None
+        } else {
+            let (path, _) = links::split_local_url(url);
+            self.load_image(&path)
+        };
+
+        let Some(index) = index else {
+            // Images that can't be embedded are replaced by their description
+            let label = if alt.is_empty() { url } else { alt };
+            let piece = Piece {
+                text: format!("[Image: {}]", label),
+                font: Font::Italic,
+                link: None,
+            };
+            let style = LineStyle {
+                size: BODY_SIZE,
+                line_height: BODY_SIZE * LINE_SPACING,
+                color: QUOTE_COLOR,
+                inset: 0.0,
+                background: None,
+            };
+            self.text(&[piece], &style, context);
+            return;
+        };
+
+        let image = &self.images[index];
+        let natural_width = image.width as f32 * POINTS_PER_PIXEL;
+        let natural_height = image.height as f32 * POINTS_PER_PIXEL;
+        let max_height = (self.top() - self.margin) * 0.9;
+        let scale = (self.width(context) / natural_width)
+            .min(max_height / natural_height)
+            .min(1.0);
+        let (width, height) = (natural_width * scale, natural_height * scale);
+
+        self.ensure_space(height + 6.0);
+        self.pending_marker = None;
+        let (x, y) = (self.left(context), self.y - height);
+
+        let page = self.page();
+        if !page.images.contains(&index) {
+            page.images.push(index);
+        }
+        page.content
+            .save_state()
+            .transform([width, 0.0, 0.0, height, x, y])
+            .x_object(Name(image_name(index).as_bytes()))
+            .restore_state();
+
+        self.y -= height + 6.0;
+    }
+
+    /// Loads an image for embedding, returning its index.
+    ///
+    /// Images outside the document's folder aren't loaded, even through
+    /// absolute paths, `..` or symlinks.
+    fn load_image(&mut self, target: &str) -> Option<usize> {
+        let path = super::contained_file(&self.base_dir, &self.base_dir.join(target))?;
+        if let Some(index) = self.image_indices.get(&path) {
+            return *index;
+        }
+
+        let bytes = fs::read(&path).ok();
+        let index = bytes.and_then(|bytes| decode_image(&bytes)).map(|image| {
+            self.images.push(image);
+            self.images.len() - 1
+        });
+        self.image_indices.insert(path, index);
+        index
+    }
+
+    /// Writes the laid out pages into a PDF file.
+    fn finish(self) -> RenderedPdf {
+        let mut next_id = Ref::new(1);
+        let catalog_id = next_id.bump();
+        let page_tree_id = next_id.bump();
+        let info_id = next_id.bump();
+        let outline_id = next_id.bump();
+        let font_ids: Vec<Ref> = Font::ALL.iter().map(|_| next_id.bump()).collect();
+        let page_ids: Vec<Ref> = self.pages.iter().map(|_| next_id.bump()).collect();
+
+        let mut pdf = Pdf::new();
+
+        for (font, &id) in Font::ALL.iter().zip(&font_ids) {
+            pdf.type1_font(id)
+                .base_font(Name(font.base_font()))
+                .encoding_predefined(Name(b"WinAnsiEncoding"));
+        }
+
+        let mut image_ids = Vec::new();
+        for image in &self.images {
+            let id = next_id.bump();
+            let mask_id = image.alpha.as_ref().map(|_| next_id.bump());
+            image_ids.push(id);
+
+            let mut xobject = pdf.image_xobject(id, &image.data);
+            xobject.filter(image.filter);
+            xobject.width(image.width as i32);
+            xobject.height(image.height as i32);
+            match image.components {
+                1 => xobject.color_space().device_gray(),
+                4 => xobject.color_space().device_cmyk(),
+                _ => xobject.color_space().device_rgb(),
+            }
+            xobject.bits_per_component(8);
+            if let Some(mask_id) = mask_id {
+                xobject.s_mask(mask_id);
+            }
+            xobject.finish();
+
+            if let (Some(mask_id), Some(alpha)) = (mask_id, &image.alpha) {
+                let mut mask = pdf.image_xobject(mask_id, alpha);
+                mask.filter(Filter::FlateDecode);
+                mask.width(image.width as i32);
+                mask.height(image.height as i32);
+                mask.color_space().device_gray();
+                mask.bits_per_component(8);
+            }
+        }
+
+        for (index, page) in self.pages.into_iter().enumerate() {
+            let content_id = next_id.bump();
+            // Links to headings that don't exist have nowhere to go
+            let links: Vec<&(Rect, LinkTarget)> = page
+                .links
+                .iter()
+                .filter(|(_, target)| match target {
+                    LinkTarget::Anchor(anchor) => self.anchors.contains_key(anchor),
+                    LinkTarget::Uri(_) => true,
+                })
+                .collect();
+            let annotation_ids: Vec<Ref> = links.iter().map(|_| next_id.bump()).collect();
+
+            for (&(rect, target), &id) in links.iter().zip(&annotation_ids) {
+                let mut annotation = pdf.indirect(id).start::<Annotation>();
+                annotation
+                    .subtype(AnnotationType::Link)
+                    .rect(*rect)
+                    .border(0.0, 0.0, 0.0, None);
+                match target {
+                    LinkTarget::Uri(uri) => {
+                        annotation
+                            .action()
+                            .action_type(ActionType::Uri)
+                            .uri(Str(uri.as_bytes()));
+                    }
+                    LinkTarget::Anchor(anchor) => {
+                        let (target_page, top) = self.anchors[anchor];
+                        annotation
+                            .action()
+                            .action_type(ActionType::GoTo)
+                            .destination()
+                            .page(page_ids[target_page])
+                            .xyz(self.margin, top, None);
+                    }
+                }
+            }
+
+            let mut pdf_page = pdf.page(page_ids[index]);
+            pdf_page
+                .media_box(Rect::new(0.0, 0.0, self.page_width, self.page_height))
+                .parent(page_tree_id)
+                .contents(content_id);
+            if !annotation_ids.is_empty() {
+                pdf_page
+                    .insert(Name(b"Annots"))
+                    .array()
+                    .items(annotation_ids.iter().copied());
+            }
+
+            let mut resources = pdf_page.resources();
+            let mut font_dict = resources.fonts();
+            for (font, &id) in Font::ALL.iter().zip(&font_ids) {
+                font_dict.pair(Name(font.resource_name()), id);
+            }
+            font_dict.finish();
+            if !page.images.is_empty() {
+                let mut xobjects = resources.x_objects();
+                for &image in &page.images {
+                    xobjects.pair(Name(image_name(image).as_bytes()), image_ids[image]);
+                }
+            }
+            resources.finish();
+            pdf_page.finish();
+
+            pdf.stream(content_id, &page.content.finish());
+        }
+
+        pdf.pages(page_tree_id)
+            .kids(page_ids.iter().copied())
+            .count(page_ids.len() as i32);
+
+        let has_outline = !self.headings.is_empty();
+        if has_outline {
+            write_outline(
+                &mut pdf,
+                outline_id,
+                &mut next_id,
+                &self.headings,
+                &page_ids,
+                self.margin,
+            );
+        }
+
+        let mut catalog = pdf.catalog(catalog_id);
+        catalog.pages(page_tree_id);
+        if has_outline {
+            catalog
+                .outlines(outline_id)
+                .page_mode(PageMode::UseOutlines);
+        }
+        catalog.finish();
+
+        let mut info = pdf.document_info(info_id);
+        info.creator(TextStr("mdview"));
+        if let Some(title) = self.headings.iter().find(|heading| heading.item.level == 1) {
+            info.title(TextStr(&title.item.text));
+        }
+        info.finish();
+
+        RenderedPdf {
+            data: pdf.finish(),
+            replaced_chars: self.replaced_chars.into_iter().collect(),
+        }
+    }
+}
+
+/// Returns the baseline of a line of text whose box starts at `top`.
+fn baseline(top: f32, line_height: f32, size: f32) -> f32 {
+    top - (line_height - 0.93 * size) / 2.0 - 0.72 * size
+}
+
+/// Returns the resource name of an embedded image.
+fn image_name(index: usize) -> String {
+    format!("Im{}", index + 1)
+}
+
+/// Writes the document outline (bookmarks) from the placed headings.
+fn write_outline(
+    pdf: &mut Pdf,
+    outline_id: Ref,
+    next_id: &mut Ref,
+    headings: &[PlacedHeading],
+    page_ids: &[Ref],
+    left: f32,
+) {
+    let ids: Vec<Ref> = headings.iter().map(|_| next_id.bump()).collect();
+
+    // The parent of a heading is the closest earlier heading with a lower level
+    let parents: Vec<Option<usize>> = (0..headings.len())
+        .map(|index| {
+            (0..index)
+                .rev()
+                .find(|&candidate| headings[candidate].item.level < headings[index].item.level)
+        })
+        .collect();
+    let children = |parent: Option<usize>| -> Vec<usize> {
+        (0..headings.len())
+            .filter(|&index| parents[index] == parent)
+            .collect()
+    };
+    let descendants = |index: usize| -> i32 {
+        (index + 1..headings.len())
+            .take_while(|&later| headings[later].item.level > headings[index].item.level)
+            .count() as i32
+    };
+
+    let top_level = children(None);
+    pdf.outline(outline_id)
+        .first(ids[top_level[0]])
+        .last(ids[*top_level.last().unwrap()])
+        .count(headings.len() as i32);
+
+    for (index, heading) in headings.iter().enumerate() {
+        let siblings = children(parents[index]);
+        let position = siblings
+            .iter()
+            .position(|&sibling| sibling == index)
+            .unwrap();
+        let own_children = children(Some(index));
+
+        let mut item = pdf.outline_item(ids[index]);
+        item.title(TextStr(&heading.item.text));
+        item.parent(
+            parents[index]
+                .map(|parent| ids[parent])
+                .unwrap_or(outline_id),
+        );
+        if position > 0 {
+            item.prev(ids[siblings[position - 1]]);
+        }
+        if let Some(&next) = siblings.get(position + 1) {
+            item.next(ids[next]);
+        }
+        if let (Some(&first), Some(&last)) = (own_children.first(), own_children.last()) {
+            item.first(ids[first]);
+            item.last(ids[last]);
+            item.count(descendants(index));
+        }
+        item.dest()
+            .page(page_ids[heading.page])
+            .xyz(left, heading.top, None);
+    }
+}
+
+/// Collects the inline content of a node, ignoring images.
+fn collect_pieces<'a>(node: &'a AstNode<'a>, style: &InlineStyle) -> Vec<Piece> {
+    let mut pieces = Vec::new();
+    let mut images = Vec::new();
+    collect_inlines(node, style, &mut pieces, &mut images);
+    pieces
+}
+
+/// Collects inline text of a node's children with their formatting.
+///
+/// Images are collected separately as `(url, alt text)` pairs.
+fn collect_inlines<'a>(
+    node: &'a AstNode<'a>,
+    style: &InlineStyle,
+    pieces: &mut Vec<Piece>,
+    images: &mut Vec<(String, String)>,
+) {
+    let text_font = Font::styled(style.bold, style.italic);
+    let piece = |text: String, font: Font| Piece {
+        text,
+        font,
+        link: style.link.clone(),
+    };
+
+    for child in node.children() {
+        let value = child.data.borrow().value.clone();
+        match value {
+            NodeValue::Text(text) => pieces.push(piece(text, text_font)),
+            NodeValue::Code(code) => pieces.push(piece(code.literal, Font::Mono)),
+            NodeValue::SoftBreak => pieces.push(piece(" ".to_string(), text_font)),
+            NodeValue::LineBreak => pieces.push(piece("\n".to_string(), text_font)),
+            NodeValue::FootnoteReference(reference) => {
+                pieces.push(piece(format!("[{}]", reference.name), text_font))
+            }
+            NodeValue::HtmlInline(html) => {
+                if html.to_lowercase().starts_with("<br") {
+                    pieces.push(piece("\n".to_string(), text_font));
+                }
+            }
+            NodeValue::Emph => {
+                let inner = InlineStyle {
+                    italic: true,
+                    ..style.clone()
+                };
+                collect_inlines(child, &inner, pieces, images);
+            }
+            NodeValue::Strong => {
+                let inner = InlineStyle {
+                    bold: true,
+                    ..style.clone()
+                };
+                collect_inlines(child, &inner, pieces, images);
+            }
+            NodeValue::Link(link) => {
+                let target = if let Some(anchor) = link.url.strip_prefix('#') {
+                    Some(LinkTarget::Anchor(links::split_local_url(anchor).0))
+                } else if links::is_external(&link.url) || link.url.starts_with("mailto:") {
+                    Some(LinkTarget::Uri(link.url.clone()))
+                } else {
+                    // Links to other local files can't be followed from a PDF
+                    None
+                };
+                let inner = InlineStyle {
+                    link: target.or_else(|| style.link.clone()),
+                    ..style.clone()
+                };
+                collect_inlines(child, &inner, pieces, images);
+            }
+            NodeValue::Image(link) => {
+                let alt = toc::extract_text(child);
+                images.push((link.url.clone(), alt));
+            }
+            _ => collect_inlines(child, style, pieces, images),
+        }
+    }
+}
+
+/// Breaks inline pieces into lines that fit within `width`.
+fn wrap(pieces: &[Piece], size: f32, width: f32) -> Vec<Vec<Run>> {
+    let mut lines = Vec::new();
+    let mut line: Vec<Run> = Vec::new();
+    let mut line_width = 0.0;
+
+    for piece in pieces {
+        let piece_size = if piece.font == Font::Mono {
+            size * 0.9
+        } else {
+            size
+        };
+
+        for (index, segment) in piece.text.split('\n').enumerate() {
+            if index > 0 {
+                end_line(&mut lines, &mut line);
+                line_width = 0.0;
+            }
+
+            for word in split_words(segment) {
+                let is_space = word.starts_with(' ');
+                if is_space && line.is_empty() {
+                    continue;
+                }
+
+                let word_width = piece.font.text_width(word, piece_size);
+                if !is_space && line_width + word_width > width && !line.is_empty() {
+                    end_line(&mut lines, &mut line);
+                    line_width = 0.0;
+                }
+
+                if is_space || word_width <= width {
+                    push_run(&mut line, word, piece, piece_size);
+                    line_width += word_width;
+                    continue;
+                }
+
+                // Words wider than a whole line are broken between characters
+                let mut chunk = String::new();
+                for c in word.chars() {
+                    let char_width = piece
+                        .font
+                        .text_width(c.encode_utf8(&mut [0; 4]), piece_size);
+                    if line_width + char_width > width && !(line.is_empty() && chunk.is_empty()) {
+                        push_run(&mut line, &chunk, piece, piece_size);
+                        end_line(&mut lines, &mut line);
+                        chunk.clear();
+                        line_width = 0.0;
+                    }
+                    chunk.push(c);
+                    line_width += char_width;
+                }
+                push_run(&mut line, &chunk, piece, piece_size);
+            }
+        }
+    }
+
+    if !line.is_empty() {
+        end_line(&mut lines, &mut line);
+    }
+    lines
+}
+
+/// Finishes a line, dropping trailing spaces.
+fn end_line(lines: &mut Vec<Vec<Run>>, line: &mut Vec<Run>) {
+    while let Some(last) = line.last_mut() {
+        let trimmed = last.text.trim_end_matches(' ').len();
+        last.text.truncate(trimmed);
+        if !last.text.is_empty() {
+            break;
+        }
+        line.pop();
+    }
+    lines.push(std::mem::take(line));
+}
+
+/// Appends text to a line, merging it with the previous run if the formatting matches.
+fn push_run(line: &mut Vec<Run>, text: &str, piece: &Piece, size: f32) {
+    if text.is_empty() {
+        return;
+    }
+    if let Some(last) = line.last_mut() {
+        if last.font == piece.font && last.size == size && last.link == piece.link {
+            last.text.push_str(text);
+            return;
+        }
+    }
+    line.push(Run {
+        text: text.to_string(),
+        font: piece.font,
+        size,
+        link: piece.link.clone(),
+    });
+}
+
+/// Splits text into alternating words and runs of spaces.
+fn split_words(text: &str) -> Vec<&str> {
+    let mut words = Vec::new();
+    let mut start = 0;
+    let mut in_space = None;
+
+    for (index, c) in text.char_indices() {
+        let is_space = c == ' ';
+        if in_space.is_some_and(|space| space != is_space) {
+            words.push(&text[start..index]);
+            start = index;
+        }
+        in_space = Some(is_space);
+    }
+    if start < text.len() {
+        words.push(&text[start..]);
+    }
+    words
+}
+
+/// Decodes a JPEG or PNG image for embedding.
+fn decode_image(bytes: &[u8]) -> Option<Image> {
+    if bytes.starts_with(&[0xFF, 0xD8]) {
+        let (width, height, components) = jpeg_info(bytes)?;
+        return Some(Image {
+            data: bytes.to_vec(),
+            filter: Filter::DctDecode,
+            width,
+            height,
+            components,
+            alpha: None,
+        });
+    }
+
+    if bytes.starts_with(b"\x89PNG") {
+        return decode_png(bytes);
+    }
+
+    None
+}
+
+/// Reads the dimensions and component count from a JPEG's frame header.
+fn jpeg_info(bytes: &[u8]) -> Option<(u32, u32, u8)> {
+    let mut i = 2;
+    while i + 9 < bytes.len() {
+        if bytes[i] != 0xFF {
+            return None;
+        }
+        let marker = bytes[i + 1];
+        match marker {
+            0xFF => i += 1,
+            0x01 | 0xD0..=0xD8 => i += 2,
+            0xC0..=0xCF if !matches!(marker, 0xC4 | 0xC8 | 0xCC) => {
+                let height = u16::from_be_bytes([bytes[i + 5], bytes[i + 6]]) as u32;
+                let width = u16::from_be_bytes([bytes[i + 7], bytes[i + 8]]) as u32;
+                return Some((width, height, bytes[i + 9]));
+            }
+            _ => {
+                let length = u16::from_be_bytes([bytes[i + 2], bytes[i + 3]]) as usize;
+                i += 2 + length;
+            }
+        }
+    }
+    None
+}
+
+/// Decodes a PNG into raw samples, splitting off any alpha channel.
+fn decode_png(bytes: &[u8]) -> Option<Image> {
+    let mut decoder = png::Decoder::new(Cursor::new(bytes));
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info().ok()?;
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buffer).ok()?;
+    let samples = &buffer[..info.buffer_size()];
+
+    let (components, has_alpha) = match info.color_type {
+        png::ColorType::Grayscale => (1, false),
+        png::ColorType::GrayscaleAlpha => (1, true),
+        png::ColorType::Rgb => (3, false),
+        png::ColorType::Rgba => (3, true),
+        png::ColorType::Indexed => return None,
+    };
+
+    let (color, alpha) = if has_alpha {
+        let stride = components + 1;
+        let color: Vec<u8> = samples
+            .chunks_exact(stride)
+            .flat_map(|pixel| pixel[..components].iter().copied())
+            .collect();
+        let alpha: Vec<u8> = samples
+            .chunks_exact(stride)
+            .map(|pixel| pixel[components])
+            .collect();
+        (color, Some(alpha))
+    } else {
+        (samples.to_vec(), None)
+    };
+
+    let compress = |data: &[u8]| miniz_oxide::deflate::compress_to_vec_zlib(data, 6);
+    Some(Image {
+        data: compress(&color),
+        filter: Filter::FlateDecode,
+        width: info.width,
+        height: info.height,
+        components: components as u8,
+        alpha: alpha.map(|alpha| compress(&alpha)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+        haystack
+            .windows(needle.len())
+            .any(|window| window == needle)
+    }
+
+    fn count(haystack: &[u8], needle: &[u8]) -> usize {
+        haystack
+            .windows(needle.len())
+            .filter(|window| *window == needle)
+            .count()
+    }
+
+    #[test]
+    fn test_render_pdf_structure() {
+        let markdown = "# Spec\n\nSee [the API](#api) and [docs](https://example.com).\n\n## API\n\n```\nfn main() {}\n```\n";
+        let rendered = render_pdf(markdown, Path::new("."), &PdfOptions::default());
+        let pdf = rendered.data.as_slice();
+
+        assert!(pdf.starts_with(b"%PDF-"));
+        // Headings become bookmarks
+        assert!(contains(pdf, b"/Outlines"));
+        assert!(contains(pdf, b"(Spec)"));
+        assert!(contains(pdf, b"(API)"));
+        // One internal and one external link
+        assert!(contains(pdf, b"/S /GoTo"));
+        assert!(contains(pdf, b"/URI (https://example.com)"));
+        // Code is set in the monospace font
+        assert!(contains(pdf, b"/Courier"));
+        assert!(contains(pdf, b"/Title (Spec)"));
+        assert!(rendered.warning().is_none());
+    }
+
+    #[test]
+    fn test_render_pdf_reports_replaced_chars() {
+        let rendered = render_pdf(
+            "# 日本語\n\nCafé – ok ✓\n",
+            Path::new("."),
+            &PdfOptions::default(),
+        );
+
+        assert_eq!(rendered.replaced_chars, vec!['✓', '日', '本', '語']);
+        let warning = rendered.warning().unwrap();
+        assert!(warning.starts_with("4 characters can't be shown"));
+        assert!(warning.ends_with(": ✓日本語"));
+    }
+
+    #[test]
+    fn test_render_pdf_paginates() {
+        let markdown: String = (1..=200).map(|i| format!("Paragraph {}\n\n", i)).collect();
+
+        let a4 = render_pdf(&markdown, Path::new("."), &PdfOptions::default()).data;
+        let legal = render_pdf(
+            &markdown,
+            Path::new("."),
+            &PdfOptions {
+                page_size: PageSize::Legal,
+                margin_mm: 10,
+            },
+        )
+        .data;
+
+        assert!(count(&a4, b"/MediaBox") > 1, "expected several pages");
+        assert!(count(&legal, b"/MediaBox") < count(&a4, b"/MediaBox"));
+        assert!(contains(&legal, b"/MediaBox [0 0 612 1008]"));
+    }
+
+    #[test]
+    fn test_render_pdf_embeds_local_images() {
+        let temp_dir = tempdir().unwrap();
+        let mut png_bytes = Vec::new();
+        {
+            let mut encoder = png::Encoder::new(&mut png_bytes, 2, 2);
+            encoder.set_color(png::ColorType::Rgba);
+            encoder.set_depth(png::BitDepth::Eight);
+            let mut writer = encoder.write_header().unwrap();
+            writer.write_image_data(&[255; 16]).unwrap();
+        }
+        let docs = temp_dir.path().join("docs");
+        fs::create_dir(&docs).unwrap();
+        fs::write(docs.join("dot.png"), &png_bytes).unwrap();
+        fs::write(temp_dir.path().join("outside.png"), &png_bytes).unwrap();
+        let outside = temp_dir.path().join("outside.png");
+
+        let markdown = format!(
+            "![Dot](dot.png)\n\n![Missing](missing.png)\n\n![Up](../outside.png)\n\n![Absolute]({})",
+            outside.display()
+        );
+        let pdf = render_pdf(&markdown, &docs, &PdfOptions::default()).data;
+
+        assert!(contains(&pdf, b"/Subtype /Image"));
+        assert!(contains(&pdf, b"/SMask"));
+        assert!(contains(&pdf, b"/Im1 Do"));
+        assert!(contains(&pdf, b"[Image: Missing]"));
+        // Images outside the document's folder are never embedded
+        assert!(!contains(&pdf, b"/Im2"));
+        assert!(contains(&pdf, b"[Image: Up]"));
+        assert!(contains(&pdf, b"[Image: Absolute]"));
+    }
+
+    #[test]
+    fn test_wrap() {
+        let pieces = vec![Piece {
+            text: "one two three".to_string(),
+            font: Font::Mono,
+            link: None,
+        }];
+        // Monospace at 10pt with 0.9 scaling: 5.4pt per character
+        let lines = wrap(&pieces, 10.0, 5.4 * 8.0);
+
+        let texts: Vec<&str> = lines.iter().map(|line| line[0].text.as_str()).collect();
+        assert_eq!(texts, vec!["one two", "three"]);
+    }
+
+    #[test]
+    fn test_page_size_from_str() {
+        assert_eq!("Letter".parse::<PageSize>().unwrap(), PageSize::Letter);
+        assert!("b5".parse::<PageSize>().is_err());
+    }
+}
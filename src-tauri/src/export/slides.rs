@@ -0,0 +1,160 @@
+use super::markup::{escape_xml, image_media_type, to_xhtml};
+use super::ExportError;
+use crate::md::parser::{RenderConfig, RenderContext};
+use crate::md::slides::{split_slides, SlideSplit};
+use crate::md::trust::TrustLevel;
+use crate::md::{frontmatter, links, loader};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use std::fs;
+use std::path::Path;
+
+/// Page template of an exported deck, with `{{lang}}`, `{{title}}` and `{{slides}}` placeholders.
+const DECK_TEMPLATE: &str = include_str!("deck.html");
+
+/// Exports a document as a standalone HTML slide deck.
+///
+/// The deck is a single file: styles, navigation script and local images
+/// from the document's folder are all embedded, so it can be opened in any
+/// browser. Arrow keys move between slides, `n` shows speaker notes and `f`
+/// toggles full screen.
+///
+/// # Arguments
+///
+/// * `path` - The Markdown file to export
+/// * `config` - The rendering configuration
+/// * `trust` - The document's trust level; restricted documents lose their raw HTML
+/// * `split` - Where to start new slides
+///
+/// # Returns
+///
+/// * `Result<String, ExportError>` - The HTML of the deck or an error
+pub fn export_slides<P: AsRef<Path>>(
+    path: P,
+    config: &RenderConfig,
+    trust: TrustLevel,
+    split: SlideSplit,
+) -> Result<String, ExportError> {
+    let path = path.as_ref();
+    let markdown = loader::load_markdown_file(path)?;
+    let mut context = RenderContext::for_document_with_trust(path, config, trust);
+    context.config = context.config.for_export();
+    let slides = split_slides(&markdown, &context, split);
+    if slides.is_empty() {
+        return Err(ExportError::NoDocuments(path.display().to_string()));
+    }
+
+    let front_matter = frontmatter::parse(&markdown).unwrap_or_default();
+    let title = front_matter
+        .title
+        .or_else(|| slides.iter().find_map(|slide| slide.title.clone()))
+        .unwrap_or_else(|| {
+            path.file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_default()
+        });
+    let base_dir = path.parent().unwrap_or_else(|| Path::new("."));
+
+    let mut sections = String::new();
+    for (index, slide) in slides.iter().enumerate() {
+        sections.push_str(&format!(
+            "<section class=\"slide\" id=\"slide-{}\">\n{}",
+            index + 1,
+            embed_images(&slide.html, base_dir)
+        ));
+        if let Some(notes) = &slide.notes {
+            sections.push_str(&format!(
+                "<aside class=\"notes\">\n{}</aside>\n",
+                embed_images(notes, base_dir)
+            ));
+        }
+        sections.push_str("</section>\n");
+    }
+
+    Ok(DECK_TEMPLATE
+        .replace(
+            "{{lang}}",
+            &escape_xml(front_matter.language.as_deref().unwrap_or("en")),
+        )
+        .replace("{{title}}", &escape_xml(&title))
+        .replace("{{slides}}", &sections))
+}
+
+/// Replaces local image sources with data URLs so the deck is self-contained.
+///
+/// Only images inside `base_dir` are embedded; see `contained_file`.
+fn embed_images(html: &str, base_dir: &Path) -> String {
+    to_xhtml(html, |tag, attribute, value| {
+        if (tag, attribute) != ("img", "src") || links::is_external(value) {
+            return Some(value.to_string());
+        }
+
+        let (target, _) = links::split_local_url(&value.replace("&amp;", "&"));
+        let image_path = super::contained_file(base_dir, &base_dir.join(target));
+        let data_url = image_path.and_then(|image_path| {
+            let media_type = image_media_type(&image_path)?;
+            let data = fs::read(&image_path).ok()?;
+            Some(format!(
+                "data:{};base64,{}",
+                media_type,
+                BASE64.encode(data)
+            ))
+        });
+        Some(data_url.unwrap_or_else(|| value.to_string()))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_export_slides() {
+        let temp_dir = tempdir().unwrap();
+        let folder = temp_dir.path().join("talk");
+        fs::create_dir(&folder).unwrap();
+        let path = folder.join("talk.md");
+        fs::write(
+            &path,
+            "# Kick-off & Goals\n\n![Logo](logo.png) ![Up](../secret.png) <kbd>K</kbd>\n\nNote: Keep it short.\n\n---\n\n## Plan\n",
+        )
+        .unwrap();
+        fs::write(folder.join("logo.png"), b"png").unwrap();
+        fs::write(temp_dir.path().join("secret.png"), b"secret").unwrap();
+
+        let export =
+            |trust| export_slides(&path, &RenderConfig::default(), trust, SlideSplit::Rule);
+        let deck = export(TrustLevel::Trusted).unwrap();
+
+        assert!(deck.starts_with("<!DOCTYPE html>"));
+        assert!(deck.contains("<title>Kick-off &amp; Goals</title>"));
+        assert!(deck.contains("<section class=\"slide\" id=\"slide-1\">"));
+        assert!(deck.contains("<section class=\"slide\" id=\"slide-2\">"));
+        assert!(deck.contains("src=\"data:image/png;base64,cG5n\""));
+        // Images outside the document's folder are never embedded
+        assert!(deck.contains("src=\"../secret.png\""));
+        assert!(deck.contains("<kbd>K</kbd>"));
+        assert!(deck.contains("<aside class=\"notes\">\n<p>Keep it short.</p>"));
+        assert!(!deck.contains("{{"));
+
+        // Restricted documents lose their raw HTML, as when they are shown
+        let restricted = export(TrustLevel::Restricted).unwrap();
+        assert!(!restricted.contains("<kbd>"));
+    }
+
+    #[test]
+    fn test_export_empty_document() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("empty.md");
+        fs::write(&path, "\n---\n").unwrap();
+
+        let result = export_slides(
+            &path,
+            &RenderConfig::default(),
+            TrustLevel::Trusted,
+            SlideSplit::Rule,
+        );
+        assert!(matches!(result, Err(ExportError::NoDocuments(_))));
+    }
+}
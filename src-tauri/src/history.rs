@@ -1,6 +1,7 @@
+use crate::md::archive::{self, ArchivePath};
 use serde::{Deserialize, Serialize};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 /// Maximum number of files to keep in history
 const MAX_HISTORY_SIZE: usize = 20;
@@ -61,24 +62,27 @@ impl FileHistory {
     }
 
     /// Validates the history by removing files that no longer exist.
+    /// Files inside an archive (`bundle.zip!/docs/index.md`) are kept while
+    /// the archive still contains them.
     /// Adjusts the current_index to maintain relative position.
     pub fn validate(&mut self) {
         let original_len = self.files.len();
         let current_idx = self.current_index;
+        let exists: Vec<bool> = self.files.iter().map(|path| file_exists(path)).collect();
 
         // Count how many files before current position are removed
         let mut removed_before = 0;
         if current_idx >= 0 {
             let current_pos = current_idx as usize;
-            for i in 0..current_pos.min(original_len) {
-                if !PathBuf::from(&self.files[i]).exists() {
-                    removed_before += 1;
-                }
-            }
+            removed_before = exists[..current_pos.min(original_len)]
+                .iter()
+                .filter(|&&exists| !exists)
+                .count();
         }
 
         // Remove invalid files
-        self.files.retain(|path| PathBuf::from(path).exists());
+        let mut exists = exists.into_iter();
+        self.files.retain(|_| exists.next().unwrap_or(false));
 
         // Adjust current_index
         if self.files.is_empty() {
@@ -219,6 +223,14 @@ impl Default for FileHistory {
     }
 }
 
+/// Returns true if a file in the history still exists, on disk or in its archive.
+fn file_exists(path: &str) -> bool {
+    match ArchivePath::parse(path) {
+        Some(archived) => archive::contains(&archived),
+        None => Path::new(path).exists(),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -304,6 +316,33 @@ mod tests {
         assert_eq!(history.current_index, 0);
     }
 
+    #[test]
+    fn test_validate_archive_paths() {
+        use std::io::Write;
+
+        let temp_dir = tempdir().unwrap();
+        let archive = temp_dir.path().join("bundle.zip");
+        let mut zip = zip::ZipWriter::new(File::create(&archive).unwrap());
+        zip.start_file("docs/index.md", zip::write::SimpleFileOptions::default())
+            .unwrap();
+        zip.write_all(b"# Docs").unwrap();
+        zip.finish().unwrap();
+        let entry = |name: &str| format!("{}!/{}", archive.display(), name);
+
+        let mut history = FileHistory::new();
+        history.add(entry("docs/index.md"));
+        history.add(entry("docs/removed.md"));
+        history.validate();
+
+        // The path is kept exactly as added
+        assert_eq!(history.files, vec![entry("docs/index.md")]);
+        assert_eq!(history.current_index, 0);
+
+        fs::remove_file(&archive).unwrap();
+        history.validate();
+        assert!(history.files.is_empty());
+    }
+
     #[test]
     fn test_save_and_load() {
         let temp_dir = tempdir().unwrap();
@@ -1,25 +1,123 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use clap::Parser;
+use clap::{Parser, Subcommand};
+use export::pdf::{PageSize, PdfOptions};
+use md::slides::SlideSplit;
 
 mod app;
+mod cli;
 mod commands;
+mod editor;
+mod export;
 mod history;
 mod md;
 mod menu;
+mod path_policy;
+mod render_cache;
+mod settings;
 mod state;
+mod system_theme;
+mod themes;
 
 /// A lightweight cross-platform Markdown viewer
 #[derive(Parser, Debug)]
 #[command(name = "mdview")]
 #[command(version, about, long_about = None)]
+#[command(args_conflicts_with_subcommands = true)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// Path to the Markdown file to open
     #[arg(value_name = "FILE")]
     file: Option<String>,
 }
 
+/// Command-line subcommands that run without opening a window
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Check local links and anchors, exiting non-zero if any are broken
+    Check {
+        /// Markdown files or directories to check
+        #[arg(value_name = "PATH", required = true)]
+        paths: Vec<String>,
+    },
+    /// Print word count, reading time and other document statistics as JSON
+    Stats {
+        /// Markdown file to analyze
+        #[arg(value_name = "FILE")]
+        path: String,
+    },
+    /// Export a document to another format without opening a window
+    Export {
+        /// Markdown file to export, or a folder of them for EPUB
+        #[arg(value_name = "PATH")]
+        path: String,
+
+        /// Output format
+        #[arg(long, value_enum, default_value = "pdf")]
+        format: cli::ExportFormat,
+
+        /// Output file; defaults to PATH with the format's extension
+        #[arg(short, long, value_name = "OUTPUT")]
+        output: Option<String>,
+
+        /// PDF paper size: a4, a5, letter or legal
+        #[arg(long, default_value = "a4")]
+        page_size: PageSize,
+
+        /// PDF page margin in millimetres
+        #[arg(long, value_name = "MM", default_value_t = 20)]
+        margin: u32,
+
+        /// Where slide decks start new slides: rule (at `---`), h1 or h2
+        #[arg(long, default_value = "rule")]
+        slide_split: SlideSplit,
+    },
+}
+
 fn main() {
+    #[cfg(windows)]
+    attach_console();
     let args = Args::parse();
-    app::run(args.file);
+
+    match args.command {
+        Some(Command::Check { paths }) => std::process::exit(cli::check(&paths)),
+        Some(Command::Stats { path }) => std::process::exit(cli::stats(&path)),
+        Some(Command::Export {
+            path,
+            format,
+            output,
+            page_size,
+            margin,
+            slide_split,
+        }) => {
+            let options = cli::ExportOptions {
+                pdf: PdfOptions {
+                    page_size,
+                    margin_mm: margin,
+                },
+                slide_split,
+            };
+            std::process::exit(cli::export(&path, format, output.as_deref(), &options))
+        }
+        None => app::run(args.file),
+    }
+}
+
+/// Attaches to the console of the process that started the app, if any.
+///
+/// Release builds use the Windows GUI subsystem, so without this the output
+/// of subcommands, `--help` and argument errors would be lost. Only done
+/// when arguments are given; a plain launch from Explorer has no console.
+#[cfg(windows)]
+fn attach_console() {
+    use windows_sys::Win32::System::Console::{AttachConsole, ATTACH_PARENT_PROCESS};
+
+    if std::env::args_os().len() > 1 {
+        // Fails harmlessly when there is no parent console
+        unsafe {
+            AttachConsole(ATTACH_PARENT_PROCESS);
+        }
+    }
 }
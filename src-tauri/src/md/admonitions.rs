@@ -0,0 +1,418 @@
+use super::parser::{escape_html, HtmlFragments};
+use comrak::arena_tree::Node;
+use comrak::nodes::{Ast, AstNode, LineColumn, NodeValue};
+use comrak::Arena;
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+
+/// How an alert/admonition kind is displayed.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct AdmonitionStyle {
+    /// Default title shown when the block doesn't give one
+    pub title: String,
+    /// Icon shown before the title (text or emoji)
+    pub icon: String,
+    /// Built-in kind whose styling this kind reuses (e.g. `"warning"`)
+    pub like: Option<String>,
+}
+
+/// Built-in kinds: GitHub's five alerts plus the common MkDocs admonition types.
+const BUILTIN_KINDS: &[(&str, &str, &str)] = &[
+    ("note", "Note", "ℹ️"),
+    ("tip", "Tip", "💡"),
+    ("important", "Important", "❗"),
+    ("warning", "Warning", "⚠️"),
+    ("caution", "Caution", "🛑"),
+    ("abstract", "Abstract", "📋"),
+    ("info", "Info", "ℹ️"),
+    ("success", "Success", "✅"),
+    ("question", "Question", "❓"),
+    ("failure", "Failure", "❌"),
+    ("danger", "Danger", "⚡"),
+    ("bug", "Bug", "🐞"),
+    ("example", "Example", "📝"),
+    ("quote", "Quote", "💬"),
+];
+
+/// Icon used for kinds that are neither built in nor configured.
+const DEFAULT_ICON: &str = "📌";
+
+/// Looks up the style for a kind, preferring configured kinds over built-in ones.
+///
+/// Unknown kinds get a generic style titled after the kind itself.
+pub fn style_for(kind: &str, configured: &BTreeMap<String, AdmonitionStyle>) -> AdmonitionStyle {
+    let kind = kind.to_lowercase();
+    let builtin =
+        BUILTIN_KINDS
+            .iter()
+            .find(|(name, _, _)| *name == kind)
+            .map(|(_, title, icon)| AdmonitionStyle {
+                title: title.to_string(),
+                icon: icon.to_string(),
+                like: None,
+            });
+
+    match (configured.get(&kind), builtin) {
+        (Some(style), builtin) => {
+            let fallback = builtin.unwrap_or_else(|| generic_style(&kind));
+            AdmonitionStyle {
+                title: non_empty(&style.title).unwrap_or(fallback.title),
+                icon: non_empty(&style.icon).unwrap_or(fallback.icon),
+                like: style.like.as_ref().map(|like| like.to_lowercase()),
+            }
+        }
+        (None, Some(builtin)) => builtin,
+        (None, None) => generic_style(&kind),
+    }
+}
+
+/// Returns the string if it is not empty.
+fn non_empty(value: &str) -> Option<String> {
+    (!value.is_empty()).then(|| value.to_string())
+}
+
+/// Style for kinds without any configuration.
+fn generic_style(kind: &str) -> AdmonitionStyle {
+    let mut chars = kind.chars();
+    let title = match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    };
+
+    AdmonitionStyle {
+        title,
+        icon: DEFAULT_ICON.to_string(),
+        like: None,
+    }
+}
+
+/// Rewrites MkDocs `!!! kind "Title"` admonitions as GitHub-style alert blockquotes.
+///
+/// The admonition body is the following block of lines indented by four more
+/// spaces (or a tab). Every line is converted in place, so line numbers in the
+/// output match the source. Fenced code blocks are left untouched.
+pub fn convert_mkdocs_admonitions(markdown: &str) -> String {
+    if !markdown.contains("!!!") {
+        return markdown.to_string();
+    }
+
+    let lines: Vec<&str> = markdown.split('\n').collect();
+    let mut output = Vec::with_capacity(lines.len());
+    let mut fence: Option<String> = None;
+    let mut i = 0;
+
+    while i < lines.len() {
+        let line = lines[i];
+        let trimmed = line.trim_start();
+
+        // Track fenced code blocks so their contents are never rewritten
+        if let Some(marker) = &fence {
+            if trimmed.starts_with(marker.as_str()) {
+                fence = None;
+            }
+            output.push(line.to_string());
+            i += 1;
+            continue;
+        }
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            fence = Some(trimmed[..3].to_string());
+            output.push(line.to_string());
+            i += 1;
+            continue;
+        }
+
+        let Some((kind, title)) = parse_mkdocs_header(trimmed) else {
+            output.push(line.to_string());
+            i += 1;
+            continue;
+        };
+
+        let indent = &line[..line.len() - trimmed.len()];
+        output.push(
+            format!("{}> [!{}] {}", indent, kind, title)
+                .trim_end()
+                .to_string(),
+        );
+        i += 1;
+
+        // Collect the indented body, leaving trailing blank lines outside the block
+        let body_start = i;
+        let mut body_end = i;
+        while i < lines.len() {
+            let body_line = lines[i];
+            if body_line.trim().is_empty() {
+                i += 1;
+                continue;
+            }
+            if strip_body_indent(body_line, indent).is_none() {
+                break;
+            }
+            i += 1;
+            body_end = i;
+        }
+
+        for body_line in &lines[body_start..body_end] {
+            match strip_body_indent(body_line, indent) {
+                Some(content) => output.push(format!("{}> {}", indent, content)),
+                None => output.push(format!("{}>", indent)),
+            }
+        }
+        i = body_end;
+    }
+
+    output.join("\n")
+}
+
+/// Parses `!!! kind "Title"`, returning the kind and title.
+fn parse_mkdocs_header(line: &str) -> Option<(String, String)> {
+    let rest = line.strip_prefix("!!!")?;
+    if !rest.starts_with([' ', '\t']) {
+        return None;
+    }
+    let rest = rest.trim();
+
+    let (kind, title) = match rest.split_once(char::is_whitespace) {
+        Some((kind, title)) => (kind, title.trim()),
+        None => (rest, ""),
+    };
+
+    let valid_kind = !kind.is_empty()
+        && kind
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '-' || c == '_');
+    if !valid_kind {
+        return None;
+    }
+
+    let title = title
+        .strip_prefix('"')
+        .and_then(|t| t.strip_suffix('"'))
+        .unwrap_or(title);
+
+    Some((kind.to_string(), title.to_string()))
+}
+
+/// Removes the admonition body indentation, or returns None if the line isn't indented enough.
+fn strip_body_indent<'l>(line: &'l str, indent: &str) -> Option<&'l str> {
+    let rest = line.strip_prefix(indent)?;
+    rest.strip_prefix("    ")
+        .or_else(|| rest.strip_prefix('\t'))
+}
+
+/// Parses a GitHub alert marker such as `[!NOTE]` or `[!warning] Custom title`.
+fn parse_alert_marker(text: &str) -> Option<(String, String)> {
+    let rest = text.strip_prefix("[!")?;
+    let (kind, title) = rest.split_once(']')?;
+
+    let valid_kind = !kind.is_empty()
+        && kind
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '-' || c == '_');
+    if !valid_kind {
+        return None;
+    }
+
+    Some((kind.to_lowercase(), title.trim().to_string()))
+}
+
+/// Converts alert blockquotes in a parsed document into admonition blocks.
+///
+/// A blockquote whose first line is `[!KIND]` (optionally followed by a title)
+/// becomes a `<div class="admonition admonition-KIND">` with a title row. The
+/// blockquote's content is kept in the AST and rendered by comrak as usual.
+pub(crate) fn process<'a>(
+    arena: &'a Arena<AstNode<'a>>,
+    root: &'a AstNode<'a>,
+    configured: &BTreeMap<String, AdmonitionStyle>,
+    fragments: &mut HtmlFragments,
+) {
+    let blockquotes: Vec<_> = root
+        .descendants()
+        .filter(|node| matches!(node.data.borrow().value, NodeValue::BlockQuote))
+        .collect();
+
+    for blockquote in blockquotes {
+        let Some(paragraph) = blockquote.first_child() else {
+            continue;
+        };
+        if !matches!(paragraph.data.borrow().value, NodeValue::Paragraph) {
+            continue;
+        }
+        let Some(marker) = paragraph.first_child() else {
+            continue;
+        };
+        let parsed = match &marker.data.borrow().value {
+            NodeValue::Text(text) => parse_alert_marker(text),
+            _ => None,
+        };
+        let Some((kind, title)) = parsed else {
+            continue;
+        };
+
+        // Drop the marker line from the content
+        if let Some(next) = marker.next_sibling() {
+            if matches!(
+                next.data.borrow().value,
+                NodeValue::SoftBreak | NodeValue::LineBreak
+            ) {
+                next.detach();
+            }
+        }
+        marker.detach();
+        if paragraph.first_child().is_none() {
+            paragraph.detach();
+        }
+
+        let style = style_for(&kind, configured);
+        let title = if title.is_empty() {
+            style.title.clone()
+        } else {
+            title
+        };
+        let mut classes = format!("admonition admonition-{}", kind);
+        if let Some(like) = &style.like {
+            classes.push_str(&format!(" admonition-{}", like));
+        }
+
+        let open = format!(
+            "<div class=\"{}\" role=\"note\"><p class=\"admonition-title\"><span class=\"admonition-icon\" aria-hidden=\"true\">{}</span>{}</p>",
+            escape_html(&classes),
+            escape_html(&style.icon),
+            escape_html(&title)
+        );
+        let start = blockquote.data.borrow().sourcepos.start;
+
+        blockquote.insert_before(placeholder_paragraph(arena, fragments.insert(open), start));
+        for child in blockquote.children().collect::<Vec<_>>() {
+            blockquote.insert_before(child);
+        }
+        blockquote.insert_before(placeholder_paragraph(
+            arena,
+            fragments.insert("</div>".to_string()),
+            start,
+        ));
+        blockquote.detach();
+    }
+}
+
+/// Allocates a paragraph containing only a fragment placeholder.
+pub(super) fn placeholder_paragraph<'a>(
+    arena: &'a Arena<AstNode<'a>>,
+    placeholder: String,
+    start: LineColumn,
+) -> &'a AstNode<'a> {
+    let paragraph = arena.alloc(Node::new(RefCell::new(Ast::new(
+        NodeValue::Paragraph,
+        start,
+    ))));
+    let text = arena.alloc(Node::new(RefCell::new(Ast::new(
+        NodeValue::Text(placeholder),
+        start,
+    ))));
+    paragraph.append(text);
+    paragraph
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::md::parser::{markdown_to_html, render_markdown, RenderConfig, RenderContext};
+    use crate::md::sanitize::{SanitizeConfig, SanitizePreset};
+
+    #[test]
+    fn test_github_alert() {
+        let html = markdown_to_html("> [!NOTE]\n> Useful *info*.");
+
+        assert!(html.contains("<div class=\"admonition admonition-note\""));
+        assert!(html.contains("<p class=\"admonition-title\">"));
+        assert!(html.contains(">Note</p>"));
+        assert!(html.contains("<p>Useful <em>info</em>.</p>"));
+        assert!(!html.contains("[!NOTE]"));
+        assert!(!html.contains("<blockquote>"));
+    }
+
+    #[test]
+    fn test_github_alert_custom_title() {
+        let html = markdown_to_html("> [!warning] Mind the gap\n> Careful.");
+
+        assert!(html.contains("admonition-warning"));
+        assert!(html.contains(">Mind the gap</p>"));
+    }
+
+    #[test]
+    fn test_plain_blockquote_unchanged() {
+        let html = markdown_to_html("> Just a quote\n> [!NOTE] later");
+
+        assert!(html.contains("<blockquote>"));
+        assert!(!html.contains("admonition"));
+    }
+
+    #[test]
+    fn test_mkdocs_admonition() {
+        let html = markdown_to_html(
+            "!!! tip \"Pro tip\"\n    First line.\n\n    Second paragraph.\n\nAfter.",
+        );
+
+        assert!(html.contains("admonition-tip"));
+        assert!(html.contains(">Pro tip</p>"));
+        assert!(html.contains("<p>First line.</p>"));
+        assert!(html.contains("<p>Second paragraph.</p>"));
+        assert!(html.contains("</div>\n<p>After.</p>"));
+    }
+
+    #[test]
+    fn test_convert_mkdocs_preserves_lines_and_fences() {
+        let markdown = "```\n!!! note\n    code\n```\n!!! danger\n    body\n\ntext";
+        let converted = convert_mkdocs_admonitions(markdown);
+
+        assert_eq!(converted.lines().count(), markdown.lines().count());
+        assert!(converted.starts_with("```\n!!! note\n    code\n```\n"));
+        assert!(converted.contains("> [!danger]\n> body\n\ntext"));
+    }
+
+    #[test]
+    fn test_configured_kind() {
+        let mut configured = BTreeMap::new();
+        configured.insert(
+            "todo".to_string(),
+            AdmonitionStyle {
+                title: "To do".to_string(),
+                icon: "☑️".to_string(),
+                like: Some("Tip".to_string()),
+            },
+        );
+
+        let style = style_for("TODO", &configured);
+        assert_eq!(style.title, "To do");
+        assert_eq!(style.like.as_deref(), Some("tip"));
+
+        let generic = style_for("custom-kind", &BTreeMap::new());
+        assert_eq!(generic.title, "Custom-kind");
+        assert_eq!(generic.icon, DEFAULT_ICON);
+    }
+
+    #[test]
+    fn test_title_is_escaped() {
+        let html = markdown_to_html("> [!note] a < b & c\n> body");
+        assert!(html.contains(">a &lt; b &amp; c</p>"));
+
+        // Raw HTML in a title is never rendered as markup by the strict preset
+        let markdown = "> [!note] <img src=x onerror=alert(1)>\n> body";
+        let strict = RenderContext::default().with_config(RenderConfig {
+            sanitize: SanitizeConfig {
+                preset: SanitizePreset::Strict,
+                ..SanitizeConfig::default()
+            },
+            ..RenderConfig::default()
+        });
+        let html = render_markdown(markdown, &strict);
+        assert!(!html.contains("<img"));
+
+        // The GitHub preset keeps the image but drops its event handler
+        let html = markdown_to_html(markdown);
+        assert!(html.contains("<p><img src=\"x\">\nbody</p>"));
+        assert!(!html.contains("onerror"));
+    }
+}
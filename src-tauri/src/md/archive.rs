@@ -0,0 +1,628 @@
+use super::{links, remote, workspace};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+use std::path::{Path, PathBuf};
+
+/// Separates the archive from the entry in a virtual path, as in `bundle.zip!/docs/index.md`.
+pub const ARCHIVE_SEPARATOR: &str = "!/";
+
+/// File extensions of archives that can be opened as a workspace (`.tar.gz` too).
+pub const ARCHIVE_EXTENSIONS: &[&str] = &["zip", "tar", "tgz"];
+
+/// Entries opened when an archive itself is opened, in order of preference.
+const INDEX_NAMES: &[&str] = &["index.md", "readme.md"];
+
+/// Images larger than this are left out of documents rather than inlined.
+const MAX_IMAGE_SIZE: u64 = 10 * 1024 * 1024;
+
+/// Media types of images that can be inlined, by file extension.
+const IMAGE_MEDIA_TYPES: &[(&str, &str)] = &[
+    ("png", "image/png"),
+    ("jpg", "image/jpeg"),
+    ("jpeg", "image/jpeg"),
+    ("gif", "image/gif"),
+    ("svg", "image/svg+xml"),
+    ("webp", "image/webp"),
+];
+
+/// Errors that can occur when reading an archive.
+#[derive(Debug, thiserror::Error)]
+pub enum ArchiveError {
+    /// The archive file could not be read
+    #[error(transparent)]
+    Io(#[from] io::Error),
+
+    /// The archive has no file with this name
+    #[error("Not found in archive: {0}")]
+    NotFound(String),
+
+    /// The entry is larger than the caller allowed
+    #[error("Archive entry is too large: {size} bytes, the limit is {limit}")]
+    TooLarge { size: u64, limit: u64 },
+
+    /// The archive isn't a valid zip or tar file
+    #[error("Corrupt archive: {0}")]
+    Corrupt(String),
+}
+
+/// Archive formats, recognised by file name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Zip,
+    Tar,
+    TarGz,
+}
+
+impl Format {
+    fn of(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_str()?.to_ascii_lowercase();
+        if name.ends_with(".zip") {
+            Some(Self::Zip)
+        } else if name.ends_with(".tar") {
+            Some(Self::Tar)
+        } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Some(Self::TarGz)
+        } else {
+            None
+        }
+    }
+}
+
+/// Returns true if the path names a zip or tar archive.
+pub fn is_archive_path<P: AsRef<Path>>(path: P) -> bool {
+    Format::of(path.as_ref()).is_some()
+}
+
+/// A file inside an archive, written as `bundle.zip!/docs/index.md`.
+///
+/// The entry is always normalized: relative to the archive root, separated
+/// by `/`, without `.` or `..` segments.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ArchivePath {
+    /// The archive file on disk
+    pub archive: PathBuf,
+    /// The path of the file within the archive
+    pub entry: String,
+}
+
+impl ArchivePath {
+    /// Creates a path to an entry, or None if the entry would lie outside the archive.
+    pub fn new<P: Into<PathBuf>>(archive: P, entry: &str) -> Option<Self> {
+        Some(Self {
+            archive: archive.into(),
+            entry: normalize_entry(entry)?,
+        })
+    }
+
+    /// Parses a virtual path.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - A path such as `/downloads/bundle.zip!/docs/index.md`
+    ///
+    /// # Returns
+    ///
+    /// * `Option<ArchivePath>` - The archive and entry, or None for ordinary paths
+    pub fn parse(path: &str) -> Option<Self> {
+        let mut offset = 0;
+        while let Some(found) = path[offset..].find(ARCHIVE_SEPARATOR) {
+            let split = offset + found;
+            let archive = Path::new(&path[..split]);
+            if is_archive_path(archive) {
+                return Self::new(archive, &path[split + ARCHIVE_SEPARATOR.len()..]);
+            }
+            offset = split + ARCHIVE_SEPARATOR.len();
+        }
+        None
+    }
+
+    /// Parses a virtual path given as a `Path`.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        path.to_str().and_then(Self::parse)
+    }
+
+    /// Resolves a link target against this entry.
+    ///
+    /// Relative targets are resolved against the entry's folder and targets
+    /// starting with `/` against the archive root.
+    ///
+    /// # Arguments
+    ///
+    /// * `target` - The decoded link target, without anchor
+    ///
+    /// # Returns
+    ///
+    /// * `Option<ArchivePath>` - The target, or None if it lies outside the archive
+    pub fn join(&self, target: &str) -> Option<Self> {
+        let entry = match (target.strip_prefix('/'), self.entry.rsplit_once('/')) {
+            (Some(rooted), _) => rooted.to_string(),
+            (None, Some((dir, _))) => format!("{}/{}", dir, target),
+            (None, None) => target.to_string(),
+        };
+        Self::new(&self.archive, &entry)
+    }
+
+    /// Returns the archive root as a virtual path prefix, e.g. `bundle.zip!`.
+    pub fn root(&self) -> String {
+        format!("{}!", self.archive.display())
+    }
+}
+
+impl fmt::Display for ArchivePath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}{}{}",
+            self.archive.display(),
+            ARCHIVE_SEPARATOR,
+            self.entry
+        )
+    }
+}
+
+/// Normalizes an entry name, returning None if it escapes the archive root.
+fn normalize_entry(entry: &str) -> Option<String> {
+    let mut parts: Vec<&str> = Vec::new();
+    for part in entry.split(['/', '\\']) {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop()?;
+            }
+            part => parts.push(part),
+        }
+    }
+    (!parts.is_empty()).then(|| parts.join("/"))
+}
+
+/// Lists the files in an archive.
+///
+/// # Arguments
+///
+/// * `archive` - The archive file
+///
+/// # Returns
+///
+/// * `Result<Vec<String>, ArchiveError>` - Normalized entry names, sorted
+pub fn list_files<P: AsRef<Path>>(archive: P) -> Result<Vec<String>, ArchiveError> {
+    let mut files = Vec::new();
+    visit_files(archive.as_ref(), |name, _, _| {
+        files.push(name.to_string());
+        Ok(true)
+    })?;
+    files.sort();
+    files.dedup();
+    Ok(files)
+}
+
+/// Lists the Markdown files in an archive, like `workspace::find_markdown_files`
+/// does for folders.
+///
+/// Hidden files and folders (names starting with `.`) are skipped.
+///
+/// # Arguments
+///
+/// * `archive` - The archive file
+///
+/// # Returns
+///
+/// * `Result<Vec<ArchivePath>, ArchiveError>` - The Markdown entries, sorted
+pub fn find_markdown_files<P: AsRef<Path>>(archive: P) -> Result<Vec<ArchivePath>, ArchiveError> {
+    let archive = archive.as_ref();
+    Ok(list_files(archive)?
+        .into_iter()
+        .filter(|name| !name.split('/').any(|part| part.starts_with('.')))
+        .filter(|name| workspace::is_markdown_path(name))
+        .map(|entry| ArchivePath {
+            archive: archive.to_path_buf(),
+            entry,
+        })
+        .collect())
+}
+
+/// Picks the document shown when an archive itself is opened.
+///
+/// The shallowest `index.md` or `README.md` wins; otherwise the first
+/// Markdown file.
+///
+/// # Arguments
+///
+/// * `archive` - The archive file
+///
+/// # Returns
+///
+/// * `Result<ArchivePath, ArchiveError>` - The entry to open, or `NotFound` if
+///   the archive contains no Markdown
+pub fn index_file<P: AsRef<Path>>(archive: P) -> Result<ArchivePath, ArchiveError> {
+    let archive = archive.as_ref();
+    let files = find_markdown_files(archive)?;
+    let rank = |path: &ArchivePath| {
+        let name = path.entry.rsplit('/').next().unwrap_or_default();
+        let index = INDEX_NAMES
+            .iter()
+            .position(|index| index.eq_ignore_ascii_case(name))?;
+        Some((path.entry.matches('/').count(), index))
+    };
+
+    files
+        .iter()
+        .filter_map(|path| Some((rank(path)?, path)))
+        .min_by_key(|(rank, _)| *rank)
+        .map(|(_, path)| path)
+        .or(files.first())
+        .cloned()
+        .ok_or_else(|| ArchiveError::NotFound(archive.display().to_string()))
+}
+
+/// Returns true if the archive contains the file.
+pub fn contains(path: &ArchivePath) -> bool {
+    let mut found = false;
+    let result = visit_files(&path.archive, |name, _, _| {
+        found = name == path.entry;
+        Ok(!found)
+    });
+    result.is_ok() && found
+}
+
+/// Reads a file from an archive.
+///
+/// # Arguments
+///
+/// * `path` - The archive and entry
+/// * `limit` - The largest size allowed
+///
+/// # Returns
+///
+/// * `Result<Vec<u8>, ArchiveError>` - The file contents, or `TooLarge` if
+///   the entry is larger than `limit`
+pub fn read_file(path: &ArchivePath, limit: u64) -> Result<Vec<u8>, ArchiveError> {
+    let mut data = None;
+    visit_files(&path.archive, |name, size, reader| {
+        if name != path.entry {
+            return Ok(true);
+        }
+        data = Some(read_limited(reader, size, limit)?);
+        Ok(false)
+    })?;
+    data.ok_or_else(|| ArchiveError::NotFound(path.to_string()))
+}
+
+/// Replaces the sources of images stored in the archive with `data:` URLs.
+///
+/// The webview can't load files from inside an archive, so relative image
+/// sources of a document in an archive are resolved against it and the
+/// images are read in a single pass over the archive. Images that are
+/// missing, too large or of an unknown type are left unchanged.
+///
+/// # Arguments
+///
+/// * `html` - Rendered (and sanitized) HTML
+/// * `document` - The document the HTML was rendered from
+///
+/// # Returns
+///
+/// * `String` - The HTML with archived images inlined
+pub fn inline_images(html: &str, document: &ArchivePath) -> String {
+    let image_entry = |src: &str| {
+        if src.is_empty() || links::is_external(src) {
+            return None;
+        }
+        let (target, _) = links::split_local_url(src);
+        let image = document.join(&target)?;
+        media_type(&image.entry)?;
+        Some(image.entry)
+    };
+
+    let mut wanted = HashSet::new();
+    remote::rewrite_img_tags(html, |attributes| {
+        if let Some(entry) = attribute(attributes, "src").and_then(|src| image_entry(&src)) {
+            wanted.insert(entry);
+        }
+        None
+    });
+    if wanted.is_empty() {
+        return html.to_string();
+    }
+
+    let mut images = HashMap::new();
+    let result = visit_files(&document.archive, |name, size, reader| {
+        if wanted.contains(name) && size <= MAX_IMAGE_SIZE {
+            if let Ok(data) = read_limited(reader, size, MAX_IMAGE_SIZE) {
+                images.insert(name.to_string(), data);
+            }
+        }
+        Ok(images.len() < wanted.len())
+    });
+    if let Err(e) = result {
+        eprintln!(
+            "Failed to read images from {}: {}",
+            document.archive.display(),
+            e
+        );
+    }
+
+    remote::rewrite_img_tags(html, |attributes| {
+        let entry = attribute(attributes, "src").and_then(|src| image_entry(&src))?;
+        let data = images.get(&entry)?;
+        let src = format!(
+            "data:{};base64,{}",
+            media_type(&entry)?,
+            BASE64.encode(data)
+        );
+        Some(
+            attributes
+                .iter()
+                .filter(|(name, _)| !name.eq_ignore_ascii_case("srcset"))
+                .map(|(name, value)| match value {
+                    _ if name.eq_ignore_ascii_case("src") => (name.clone(), Some(src.clone())),
+                    value => (name.clone(), value.clone()),
+                })
+                .collect(),
+        )
+    })
+}
+
+/// Returns the decoded value of an attribute.
+fn attribute(attributes: &[(String, Option<String>)], name: &str) -> Option<String> {
+    attributes
+        .iter()
+        .find(|(attribute, _)| attribute.eq_ignore_ascii_case(name))
+        .and_then(|(_, value)| value.as_deref())
+        .map(|value| value.replace("&amp;", "&"))
+}
+
+/// Returns the media type of an image entry.
+fn media_type(entry: &str) -> Option<&'static str> {
+    let (_, extension) = entry.rsplit_once('.')?;
+    IMAGE_MEDIA_TYPES
+        .iter()
+        .find(|(known, _)| known.eq_ignore_ascii_case(extension))
+        .map(|(_, media_type)| *media_type)
+}
+
+/// Reads an entry, failing if it turns out larger than `limit`.
+///
+/// The declared size isn't trusted, so the read itself is limited too.
+fn read_limited(reader: &mut dyn Read, size: u64, limit: u64) -> Result<Vec<u8>, ArchiveError> {
+    if size > limit {
+        return Err(ArchiveError::TooLarge { size, limit });
+    }
+    let mut data = Vec::with_capacity(size as usize);
+    reader
+        .take(limit.saturating_add(1))
+        .read_to_end(&mut data)?;
+    if data.len() as u64 > limit {
+        return Err(ArchiveError::TooLarge {
+            size: data.len() as u64,
+            limit,
+        });
+    }
+    Ok(data)
+}
+
+/// Calls `visit` with the name, declared size and content of each file in an
+/// archive, until it returns false.
+///
+/// Folders, links and entries whose names would lie outside the archive are skipped.
+fn visit_files<F>(archive: &Path, mut visit: F) -> Result<(), ArchiveError>
+where
+    F: FnMut(&str, u64, &mut dyn Read) -> Result<bool, ArchiveError>,
+{
+    let format =
+        Format::of(archive).ok_or_else(|| ArchiveError::NotFound(archive.display().to_string()))?;
+    let file = BufReader::new(File::open(archive)?);
+
+    match format {
+        Format::Zip => {
+            let corrupt = |e: zip::result::ZipError| ArchiveError::Corrupt(e.to_string());
+            let mut zip = zip::ZipArchive::new(file).map_err(corrupt)?;
+            for index in 0..zip.len() {
+                let mut entry = zip.by_index(index).map_err(corrupt)?;
+                if !entry.is_file() {
+                    continue;
+                }
+                let Some(name) = normalize_entry(entry.name()) else {
+                    continue;
+                };
+                let size = entry.size();
+                if !visit(&name, size, &mut entry)? {
+                    break;
+                }
+            }
+            Ok(())
+        }
+        Format::Tar => visit_tar(tar::Archive::new(file), visit),
+        Format::TarGz => visit_tar(
+            tar::Archive::new(flate2::read::MultiGzDecoder::new(file)),
+            visit,
+        ),
+    }
+}
+
+/// Visits the files of a tar archive, see `visit_files`.
+fn visit_tar<R, F>(mut archive: tar::Archive<R>, mut visit: F) -> Result<(), ArchiveError>
+where
+    R: Read,
+    F: FnMut(&str, u64, &mut dyn Read) -> Result<bool, ArchiveError>,
+{
+    let corrupt = |e: io::Error| ArchiveError::Corrupt(e.to_string());
+    for entry in archive.entries().map_err(corrupt)? {
+        let mut entry = entry.map_err(corrupt)?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let path = entry.path().map_err(corrupt)?;
+        let Some(name) = normalize_entry(&path.to_string_lossy()) else {
+            continue;
+        };
+        let size = entry.size();
+        if !visit(&name, size, &mut entry)? {
+            break;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::tempdir;
+
+    const FILES: &[(&str, &[u8])] = &[
+        (
+            "docs/index.md",
+            b"# Docs\n\n[Guide](guide.md)\n\n![Logo](../img/logo.png)\n",
+        ),
+        ("docs/guide.md", b"# Guide\n"),
+        ("img/logo.png", b"\x89PNG"),
+        (".hidden/notes.md", b"# Hidden\n"),
+    ];
+
+    /// Writes the test files into a zip archive.
+    fn write_zip(path: &Path, files: &[(&str, &[u8])]) {
+        let mut zip = zip::ZipWriter::new(File::create(path).unwrap());
+        let options = zip::write::SimpleFileOptions::default();
+        zip.add_directory("docs/", options).unwrap();
+        for (name, data) in files {
+            zip.start_file(*name, options).unwrap();
+            zip.write_all(data).unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    /// Writes the test files into a gzip-compressed tar archive.
+    fn write_tar_gz(path: &Path, files: &[(&str, &[u8])]) {
+        let encoder = flate2::write::GzEncoder::new(
+            File::create(path).unwrap(),
+            flate2::Compression::default(),
+        );
+        let mut tar = tar::Builder::new(encoder);
+        for (name, data) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            tar.append_data(&mut header, format!("./{}", name), *data)
+                .unwrap();
+        }
+        tar.into_inner().unwrap().finish().unwrap();
+    }
+
+    #[test]
+    fn test_parse_archive_path() {
+        let path = ArchivePath::parse("/downloads/bundle.zip!/docs/./index.md").unwrap();
+        assert_eq!(path.archive, PathBuf::from("/downloads/bundle.zip"));
+        assert_eq!(path.entry, "docs/index.md");
+        assert_eq!(path.to_string(), "/downloads/bundle.zip!/docs/index.md");
+        assert_eq!(path.root(), "/downloads/bundle.zip!");
+
+        assert!(ArchivePath::parse("/notes/wow!/bundle.tar.gz!/a.md").is_some());
+        assert!(ArchivePath::parse("/notes/README.md").is_none());
+        assert!(ArchivePath::parse("/notes/wow!/README.md").is_none());
+        assert!(ArchivePath::parse("/bundle.zip!/../escape.md").is_none());
+    }
+
+    #[test]
+    fn test_join() {
+        let path = ArchivePath::parse("/bundle.zip!/docs/index.md").unwrap();
+        assert_eq!(path.join("guide.md").unwrap().entry, "docs/guide.md");
+        assert_eq!(path.join("../img/logo.png").unwrap().entry, "img/logo.png");
+        assert_eq!(path.join("/README.md").unwrap().entry, "README.md");
+        assert!(path.join("../../outside.md").is_none());
+    }
+
+    #[test]
+    fn test_read_zip_and_tar_gz() {
+        let temp_dir = tempdir().unwrap();
+        let zip_path = temp_dir.path().join("bundle.zip");
+        let tar_path = temp_dir.path().join("bundle.tar.gz");
+        write_zip(&zip_path, FILES);
+        write_tar_gz(&tar_path, FILES);
+
+        for archive in [&zip_path, &tar_path] {
+            let guide = ArchivePath::new(archive, "docs/guide.md").unwrap();
+            assert_eq!(read_file(&guide, 1024).unwrap(), b"# Guide\n");
+            assert!(contains(&guide));
+
+            let missing = ArchivePath::new(archive, "docs/missing.md").unwrap();
+            assert!(matches!(
+                read_file(&missing, 1024),
+                Err(ArchiveError::NotFound(_))
+            ));
+            assert!(!contains(&missing));
+            assert!(matches!(
+                read_file(&guide, 4),
+                Err(ArchiveError::TooLarge { size: 8, limit: 4 })
+            ));
+
+            let entries: Vec<String> = find_markdown_files(archive)
+                .unwrap()
+                .into_iter()
+                .map(|path| path.entry)
+                .collect();
+            assert_eq!(entries, ["docs/guide.md", "docs/index.md"]);
+            assert_eq!(index_file(archive).unwrap().entry, "docs/index.md");
+        }
+    }
+
+    #[test]
+    fn test_index_file_preference() {
+        let temp_dir = tempdir().unwrap();
+        let archive = temp_dir.path().join("bundle.zip");
+        write_zip(
+            &archive,
+            &[
+                ("a/index.md", b"# A"),
+                ("README.md", b"# Readme"),
+                ("b/readme.md", b"# B"),
+            ],
+        );
+        assert_eq!(index_file(&archive).unwrap().entry, "README.md");
+
+        write_zip(&archive, &[("b.md", b"# B"), ("a.md", b"# A")]);
+        assert_eq!(index_file(&archive).unwrap().entry, "a.md");
+
+        write_zip(&archive, &[("image.png", b"png")]);
+        assert!(matches!(
+            index_file(&archive),
+            Err(ArchiveError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_corrupt_archive() {
+        let temp_dir = tempdir().unwrap();
+        let archive = temp_dir.path().join("bundle.zip");
+        std::fs::write(&archive, b"not a zip file").unwrap();
+
+        assert!(matches!(
+            list_files(&archive),
+            Err(ArchiveError::Corrupt(_))
+        ));
+        assert!(matches!(
+            list_files(temp_dir.path().join("missing.zip")),
+            Err(ArchiveError::Io(_))
+        ));
+    }
+
+    #[test]
+    fn test_inline_images() {
+        let temp_dir = tempdir().unwrap();
+        let archive = temp_dir.path().join("bundle.zip");
+        write_zip(&archive, FILES);
+        let document = ArchivePath::new(&archive, "docs/index.md").unwrap();
+
+        let html = "<p><img src=\"../img/logo.png\" alt=\"Logo\" srcset=\"x.png 2x\"> \
+                    <img src=\"missing.png\"> <img src=\"https://example.com/a.png\"></p>";
+        assert_eq!(
+            inline_images(html, &document),
+            "<p><img src=\"data:image/png;base64,iVBORw==\" alt=\"Logo\"> \
+             <img src=\"missing.png\"> <img src=\"https://example.com/a.png\"></p>"
+        );
+    }
+}
@@ -0,0 +1,365 @@
+use super::parser::RenderedBlock;
+use super::remote::tag_length;
+use super::MarkdownDocument;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+/// Rendered documents larger than this are sent to the webview in chunks.
+pub const CHUNKED_THRESHOLD: usize = 1024 * 1024;
+
+/// Approximate size of one chunk's HTML.
+const CHUNK_SIZE: usize = 32 * 1024;
+
+/// Approximate size of the HTML sent with the document, about a screenful.
+const INITIAL_SIZE: usize = 64 * 1024;
+
+/// Where a top-level block (see `parser::render_blocks`) ends in a document's HTML.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BlockSpan {
+    /// Offset just past the block's HTML
+    pub end: usize,
+    /// Hash of the block's Markdown source
+    pub source_hash: u64,
+}
+
+/// A run of consecutive top-level blocks of a rendered document.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Chunk {
+    /// Identifier derived from the chunk's content, so it survives edits elsewhere in the document
+    pub id: String,
+    /// Position of the chunk in the document
+    pub index: usize,
+    /// The chunk's HTML
+    pub html: String,
+}
+
+/// What the frontend needs to lay out a chunk before loading it.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ChunkInfo {
+    /// Identifier of the chunk
+    pub id: String,
+    /// Length of the chunk's HTML, for estimating its height
+    pub size: usize,
+    /// Element ids in the chunk, so headings can be found before the chunk is loaded
+    pub anchors: Vec<String>,
+}
+
+/// Outline of a document sent in chunks.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ChunkedContent {
+    /// Every chunk of the document, in order
+    pub chunks: Vec<ChunkInfo>,
+    /// The first chunks with their HTML, shown right away
+    pub initial: Vec<Chunk>,
+}
+
+/// Splits a large document's HTML into chunks for sending to the webview.
+///
+/// # Arguments
+///
+/// * `document` - The rendered document
+///
+/// # Returns
+///
+/// * `Vec<Chunk>` - The chunks, or none if the document is below `CHUNKED_THRESHOLD`
+pub fn chunk_document(document: &MarkdownDocument) -> Vec<Chunk> {
+    if document.html_content.len() < CHUNKED_THRESHOLD {
+        return Vec::new();
+    }
+    split_chunks(&document.html_content, &document.blocks, CHUNK_SIZE)
+}
+
+/// Returns where each block ends once the blocks are joined.
+///
+/// # Arguments
+///
+/// * `blocks` - Rendered blocks of a document
+///
+/// # Returns
+///
+/// * `Vec<BlockSpan>` - The end and source hash of each block
+pub fn block_spans(blocks: &[RenderedBlock]) -> Vec<BlockSpan> {
+    let mut end = 0;
+    blocks
+        .iter()
+        .map(|block| {
+            end += block.html.len();
+            BlockSpan {
+                end,
+                source_hash: block.source_hash,
+            }
+        })
+        .collect()
+}
+
+/// Returns the copy of a document that is sent to the webview.
+///
+/// A chunked document is sent without `html_content`; `chunked` lists its
+/// chunks and carries the first ones, and the rest are served by `get_chunks`.
+///
+/// # Arguments
+///
+/// * `document` - The rendered document
+/// * `chunks` - The document's chunks from `chunk_document`
+///
+/// # Returns
+///
+/// * `MarkdownDocument` - The document to send
+pub fn for_webview(document: &MarkdownDocument, chunks: &[Chunk]) -> MarkdownDocument {
+    let mut view = document.clone();
+    view.blocks = Vec::new();
+    if chunks.is_empty() {
+        return view;
+    }
+
+    let mut initial_size = 0;
+    let initial = chunks
+        .iter()
+        .take_while(|chunk| {
+            let take = initial_size < INITIAL_SIZE;
+            initial_size += chunk.html.len();
+            take
+        })
+        .cloned()
+        .collect();
+    view.html_content = String::new();
+    view.chunked = Some(ChunkedContent {
+        chunks: chunks.iter().map(ChunkInfo::from).collect(),
+        initial,
+    });
+    view
+}
+
+impl From<&Chunk> for ChunkInfo {
+    fn from(chunk: &Chunk) -> Self {
+        Self {
+            id: chunk.id.clone(),
+            size: chunk.html.len(),
+            anchors: anchors(&chunk.html),
+        }
+    }
+}
+
+/// Groups the top-level blocks of a document into chunks.
+///
+/// A block is never split, so a single block larger than `chunk_size`
+/// becomes a chunk of its own. A chunk's id is derived from the source of
+/// its blocks; without blocks the HTML is one block.
+///
+/// # Arguments
+///
+/// * `html` - Rendered HTML of the document
+/// * `blocks` - Where the document's blocks end in `html`
+/// * `chunk_size` - Size at which a chunk is closed
+///
+/// # Returns
+///
+/// * `Vec<Chunk>` - The chunks, in document order
+pub fn split_chunks(html: &str, blocks: &[BlockSpan], chunk_size: usize) -> Vec<Chunk> {
+    let mut chunks = Vec::new();
+    let mut seen = HashMap::new();
+    let mut start = 0;
+    let mut hasher = DefaultHasher::new();
+
+    for block in blocks {
+        block.source_hash.hash(&mut hasher);
+        if block.end - start >= chunk_size {
+            push_chunk(
+                &mut chunks,
+                &mut seen,
+                &html[start..block.end],
+                hasher.finish(),
+            );
+            start = block.end;
+            hasher = DefaultHasher::new();
+        }
+    }
+    if start < html.len() {
+        if blocks.is_empty() {
+            html.hash(&mut hasher);
+        }
+        push_chunk(&mut chunks, &mut seen, &html[start..], hasher.finish());
+    }
+    chunks
+}
+
+/// Adds a chunk, numbering repeated content so ids stay unique.
+fn push_chunk(chunks: &mut Vec<Chunk>, seen: &mut HashMap<u64, usize>, html: &str, hash: u64) {
+    let count = seen.entry(hash).or_insert(0);
+    let id = match *count {
+        0 => format!("chunk-{:016x}", hash),
+        n => format!("chunk-{:016x}-{}", hash, n),
+    };
+    *count += 1;
+
+    chunks.push(Chunk {
+        id,
+        index: chunks.len(),
+        html: html.to_string(),
+    });
+}
+
+/// Collects the values of `id` attributes in HTML.
+fn anchors(html: &str) -> Vec<String> {
+    let mut anchors = Vec::new();
+    let mut offset = 0;
+
+    while let Some(found) = html[offset..].find('<') {
+        let start = offset + found;
+        let Some(length) = tag_length(&html[start..]) else {
+            break;
+        };
+        let tag = &html[start..start + length];
+        offset = start + length;
+
+        if let Some(value) = tag.split_once(" id=\"").map(|(_, rest)| rest) {
+            if let Some((id, _)) = value.split_once('"') {
+                anchors.push(id.to_string());
+            }
+        }
+    }
+    anchors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::md::parser::{render_blocks, render_markdown, RenderConfig, RenderContext};
+    use crate::md::sanitize::{SanitizeConfig, SanitizePreset};
+
+    fn split(markdown: &str, context: &RenderContext, chunk_size: usize) -> Vec<Chunk> {
+        let blocks = render_blocks(markdown, context);
+        let html: String = blocks.iter().map(|block| block.html.as_str()).collect();
+        assert_eq!(html, render_markdown(markdown, context));
+        split_chunks(&html, &block_spans(&blocks), chunk_size)
+    }
+
+    fn html(chunks: &[Chunk]) -> Vec<&str> {
+        chunks.iter().map(|chunk| chunk.html.as_str()).collect()
+    }
+
+    #[test]
+    fn test_split_at_top_level_nodes() {
+        let markdown = "# Title\n\nOne *two*  \nthree\n\n---\n\n- a\n\n  b\n";
+
+        // A size of 1 closes a chunk after every block
+        let chunks = split(markdown, &RenderContext::default(), 1);
+        assert_eq!(
+            html(&chunks),
+            vec![
+                "<h1><a href=\"#title\" aria-hidden=\"true\" class=\"anchor\" id=\"title\"></a>Title</h1>\n",
+                "<p>One <em>two</em><br>\nthree</p>\n",
+                "<hr>\n",
+                "<ul>\n<li>\n<p>a</p>\n<p>b</p>\n</li>\n</ul>\n",
+            ]
+        );
+        assert_eq!(chunks[3].index, 3);
+        assert_eq!(anchors(&chunks[0].html), vec!["title"]);
+        assert!(anchors(&chunks[1].html).is_empty());
+
+        // A large size keeps everything together
+        assert_eq!(split(markdown, &RenderContext::default(), 1024).len(), 1);
+    }
+
+    #[test]
+    fn test_open_elements_and_footnotes_stay_together() {
+        let context = RenderContext::default().with_config(RenderConfig {
+            sanitize: SanitizeConfig {
+                preset: SanitizePreset::Github,
+                ..SanitizeConfig::default()
+            },
+            ..RenderConfig::default()
+        });
+        let markdown = "<details>\n<summary>More</summary>\n\nHidden *text*\n\n</details>\n\n\
+            > [!NOTE]\n> Careful\n\nA[^1] and B[^2]\n\n[^1]: One\n[^2]: Two\n";
+        let chunks = split(markdown, &context, 1);
+
+        assert_eq!(chunks.len(), 4);
+        assert!(chunks[0].html.starts_with("<details>"));
+        assert!(chunks[0].html.trim_end().ends_with("</details>"));
+        assert!(chunks[1]
+            .html
+            .starts_with("<div class=\"admonition admonition-note\""));
+        assert!(chunks[2].html.starts_with("<p>A<sup"));
+        assert_eq!(chunks[3].html.matches("<li id=\"fn-").count(), 2);
+    }
+
+    #[test]
+    fn test_stable_unique_ids() {
+        let context = RenderContext::default();
+        let chunks = split("same\n\nsame\n\nother\n", &context, 1);
+        assert_ne!(chunks[0].id, chunks[1].id);
+        assert!(chunks[1].id.starts_with(&chunks[0].id));
+
+        // Editing a later block doesn't change earlier ids
+        let edited = split("same\n\nsame\n\nchanged\n", &context, 1);
+        assert_eq!(chunks[0].id, edited[0].id);
+        assert_eq!(chunks[1].id, edited[1].id);
+        assert_ne!(chunks[2].id, edited[2].id);
+
+        // Ids come from the source, so a different rendering keeps them
+        let unrendered = split_chunks(
+            "<p>same</p>\n",
+            &[BlockSpan {
+                end: 12,
+                source_hash: render_blocks("same\n", &context)[0].source_hash,
+            }],
+            1,
+        );
+        assert_eq!(unrendered[0].id, chunks[0].id);
+    }
+
+    #[test]
+    fn test_chunk_document() {
+        let small = MarkdownDocument::new(
+            "small.md".to_string(),
+            String::new(),
+            "<p>small</p>".to_string(),
+            vec![],
+        );
+        assert!(chunk_document(&small).is_empty());
+        let view = for_webview(&small, &[]);
+        assert_eq!(view.html_content, "<p>small</p>");
+        assert!(view.chunked.is_none());
+
+        let paragraph = format!("{}\n\n", "x".repeat(1000));
+        let markdown = paragraph.repeat(CHUNKED_THRESHOLD / paragraph.len() + 1);
+        let blocks = render_blocks(&markdown, &RenderContext::default());
+        let html: String = blocks.iter().map(|block| block.html.as_str()).collect();
+        let large = MarkdownDocument {
+            blocks: block_spans(&blocks),
+            ..MarkdownDocument::new("large.md".to_string(), markdown, html.clone(), vec![])
+        };
+        let chunks = chunk_document(&large);
+        assert!(chunks.len() > 1);
+        assert_eq!(chunks.concat_html(), html);
+        assert!(chunks.iter().all(|chunk| chunk.html.len() < 2 * CHUNK_SIZE));
+
+        // A document without blocks is one chunk
+        let unsplit =
+            MarkdownDocument::new("x.md".to_string(), String::new(), html.clone(), vec![]);
+        assert_eq!(chunk_document(&unsplit).len(), 1);
+
+        let view = for_webview(&large, &chunks);
+        assert!(view.html_content.is_empty());
+        assert!(view.blocks.is_empty());
+        let chunked = view.chunked.unwrap();
+        assert_eq!(chunked.chunks.len(), chunks.len());
+        assert!(!chunked.initial.is_empty());
+        assert!(chunked.initial.len() < chunks.len());
+        assert_eq!(chunked.initial[..], chunks[..chunked.initial.len()]);
+    }
+
+    trait ConcatHtml {
+        fn concat_html(&self) -> String;
+    }
+
+    impl ConcatHtml for Vec<Chunk> {
+        fn concat_html(&self) -> String {
+            self.iter().map(|chunk| chunk.html.as_str()).collect()
+        }
+    }
+}
@@ -0,0 +1,276 @@
+use comrak::Options;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// A named set of Markdown syntax extensions.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum Profile {
+    /// Strict CommonMark without any extensions
+    CommonMark,
+    /// GitHub Flavored Markdown: tables, strikethrough, autolinks, task lists
+    /// and footnotes, plus wikilinks and description lists
+    #[default]
+    Gfm,
+    /// GFM plus superscript, subscript, multiline block quotes and smart
+    /// punctuation
+    Extended,
+}
+
+impl Profile {
+    /// Returns the extensions enabled by the profile.
+    pub fn extensions(self) -> Extensions {
+        let gfm = Extensions {
+            strikethrough: true,
+            tagfilter: true,
+            table: true,
+            autolink: true,
+            tasklist: true,
+            footnotes: true,
+            // Rendered before profiles existed, so documents may rely on them
+            description_lists: true,
+            wikilinks: true,
+            ..Extensions::default()
+        };
+
+        match self {
+            Profile::CommonMark => Extensions::default(),
+            Profile::Gfm => gfm,
+            Profile::Extended => Extensions {
+                superscript: true,
+                subscript: true,
+                multiline_block_quotes: true,
+                smart: true,
+                ..gfm
+            },
+        }
+    }
+}
+
+impl FromStr for Profile {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "commonmark" => Ok(Profile::CommonMark),
+            "gfm" => Ok(Profile::Gfm),
+            "extended" => Ok(Profile::Extended),
+            _ => Err(format!(
+                "unknown profile '{}' (expected commonmark, gfm or extended)",
+                value
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Profile::CommonMark => "commonmark",
+            Profile::Gfm => "gfm",
+            Profile::Extended => "extended",
+        })
+    }
+}
+
+/// The set of enabled syntax extensions.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Extensions {
+    /// `~~strikethrough~~`
+    pub strikethrough: bool,
+    /// Escapes GitHub's disallowed raw HTML tags such as `<script>`
+    pub tagfilter: bool,
+    /// Pipe tables
+    pub table: bool,
+    /// Bare URLs and `www.` links
+    pub autolink: bool,
+    /// `- [ ]` task list items
+    pub tasklist: bool,
+    /// `[^1]` footnotes
+    pub footnotes: bool,
+    /// Term / `: definition` description lists
+    pub description_lists: bool,
+    /// `^superscript^`
+    pub superscript: bool,
+    /// `~subscript~`
+    pub subscript: bool,
+    /// Block quotes fenced by `>>>`
+    pub multiline_block_quotes: bool,
+    /// `[[Page|alias]]` wikilinks
+    pub wikilinks: bool,
+    /// Curly quotes, en and em dashes and ellipses
+    pub smart: bool,
+    /// Renders every line break as `<br>`
+    pub hardbreaks: bool,
+}
+
+/// Extension settings: a profile plus overrides for single extensions.
+///
+/// Every field is optional so that configuration layers can be merged;
+/// unset extensions fall back to the profile.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(default)]
+pub struct ExtensionConfig {
+    /// The base profile; defaults to GFM
+    pub profile: Option<Profile>,
+    // Overrides for single extensions; see `Extensions` for what each one does
+    pub strikethrough: Option<bool>,
+    pub tagfilter: Option<bool>,
+    pub table: Option<bool>,
+    pub autolink: Option<bool>,
+    pub tasklist: Option<bool>,
+    pub footnotes: Option<bool>,
+    pub description_lists: Option<bool>,
+    pub superscript: Option<bool>,
+    pub subscript: Option<bool>,
+    pub multiline_block_quotes: Option<bool>,
+    pub wikilinks: Option<bool>,
+    pub smart: Option<bool>,
+    pub hardbreaks: Option<bool>,
+}
+
+impl ExtensionConfig {
+    /// Layers `other` on top of this configuration; values set in `other` win.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - The higher-precedence configuration
+    ///
+    /// # Returns
+    ///
+    /// * `ExtensionConfig` - The merged configuration
+    pub fn merge(&self, other: &ExtensionConfig) -> ExtensionConfig {
+        ExtensionConfig {
+            profile: other.profile.or(self.profile),
+            strikethrough: other.strikethrough.or(self.strikethrough),
+            tagfilter: other.tagfilter.or(self.tagfilter),
+            table: other.table.or(self.table),
+            autolink: other.autolink.or(self.autolink),
+            tasklist: other.tasklist.or(self.tasklist),
+            footnotes: other.footnotes.or(self.footnotes),
+            description_lists: other.description_lists.or(self.description_lists),
+            superscript: other.superscript.or(self.superscript),
+            subscript: other.subscript.or(self.subscript),
+            multiline_block_quotes: other.multiline_block_quotes.or(self.multiline_block_quotes),
+            wikilinks: other.wikilinks.or(self.wikilinks),
+            smart: other.smart.or(self.smart),
+            hardbreaks: other.hardbreaks.or(self.hardbreaks),
+        }
+    }
+
+    /// Returns the active profile.
+    pub fn profile(&self) -> Profile {
+        self.profile.unwrap_or_default()
+    }
+
+    /// Resolves the enabled extensions from the profile and the overrides.
+    pub fn extensions(&self) -> Extensions {
+        let base = self.profile().extensions();
+        Extensions {
+            strikethrough: self.strikethrough.unwrap_or(base.strikethrough),
+            tagfilter: self.tagfilter.unwrap_or(base.tagfilter),
+            table: self.table.unwrap_or(base.table),
+            autolink: self.autolink.unwrap_or(base.autolink),
+            tasklist: self.tasklist.unwrap_or(base.tasklist),
+            footnotes: self.footnotes.unwrap_or(base.footnotes),
+            description_lists: self.description_lists.unwrap_or(base.description_lists),
+            superscript: self.superscript.unwrap_or(base.superscript),
+            subscript: self.subscript.unwrap_or(base.subscript),
+            multiline_block_quotes: self
+                .multiline_block_quotes
+                .unwrap_or(base.multiline_block_quotes),
+            wikilinks: self.wikilinks.unwrap_or(base.wikilinks),
+            smart: self.smart.unwrap_or(base.smart),
+            hardbreaks: self.hardbreaks.unwrap_or(base.hardbreaks),
+        }
+    }
+
+    /// Builds the comrak options for the resolved extensions.
+    ///
+    /// Heading IDs are always generated since the TOC and anchors rely on
+    /// them, and raw HTML is left to the sanitizer.
+    ///
+    /// # Returns
+    ///
+    /// * `Options` - The comrak options
+    pub fn options(&self) -> Options {
+        let extensions = self.extensions();
+        let mut options = Options::default();
+
+        options.extension.strikethrough = extensions.strikethrough;
+        options.extension.tagfilter = extensions.tagfilter;
+        options.extension.table = extensions.table;
+        options.extension.autolink = extensions.autolink;
+        options.extension.tasklist = extensions.tasklist;
+        options.extension.footnotes = extensions.footnotes;
+        options.extension.description_lists = extensions.description_lists;
+        options.extension.superscript = extensions.superscript;
+        options.extension.subscript = extensions.subscript;
+        options.extension.multiline_block_quotes = extensions.multiline_block_quotes;
+        options.extension.wikilinks_title_after_pipe = extensions.wikilinks;
+        options.extension.header_ids = Some(String::new());
+        options.parse.smart = extensions.smart;
+        options.render.hardbreaks = extensions.hardbreaks;
+
+        options.render.unsafe_ = false; // Safe mode - raw HTML is only kept for sanitizing
+        options.render.escape = false; // Don't double-escape
+
+        options
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profiles() {
+        let commonmark = Profile::CommonMark.extensions();
+        assert!(!commonmark.table && !commonmark.wikilinks);
+
+        let gfm = Profile::Gfm.extensions();
+        assert!(gfm.table && gfm.footnotes && gfm.wikilinks && gfm.description_lists);
+        assert!(!gfm.smart && !gfm.superscript && !gfm.hardbreaks);
+
+        let extended = Profile::Extended.extensions();
+        assert!(extended.table && extended.description_lists && extended.smart);
+        assert!(!extended.hardbreaks);
+
+        assert_eq!("CommonMark".parse::<Profile>(), Ok(Profile::CommonMark));
+        assert!("markdown".parse::<Profile>().is_err());
+    }
+
+    #[test]
+    fn test_overrides_and_merge() {
+        let user: ExtensionConfig = serde_json::from_str(
+            r#"{ "profile": "extended", "smart": false, "hardbreaks": true }"#,
+        )
+        .unwrap();
+        let project = ExtensionConfig {
+            hardbreaks: Some(false),
+            table: Some(false),
+            ..ExtensionConfig::default()
+        };
+
+        let merged = user.merge(&project);
+        assert_eq!(merged.profile(), Profile::Extended);
+        let extensions = merged.extensions();
+        assert!(!extensions.smart);
+        assert!(!extensions.hardbreaks);
+        assert!(!extensions.table);
+        assert!(extensions.superscript);
+
+        let options = merged.options();
+        assert!(options.extension.superscript);
+        assert!(!options.parse.smart);
+        assert_eq!(options.extension.header_ids.as_deref(), Some(""));
+    }
+
+    #[test]
+    fn test_default_profile() {
+        let config = ExtensionConfig::default();
+        assert_eq!(config.profile(), Profile::Gfm);
+        assert_eq!(config.extensions(), Profile::Gfm.extensions());
+    }
+}
@@ -0,0 +1,184 @@
+use std::borrow::Cow;
+
+/// Metadata from a document's YAML front matter.
+///
+/// Only simple `key: value` pairs and lists of scalars are understood, which
+/// covers the fields mdview uses.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FrontMatter {
+    /// The `title` field
+    pub title: Option<String>,
+    /// The `author` or `authors` field; lists are joined with commas
+    pub author: Option<String>,
+    /// The `lang` or `language` field
+    pub language: Option<String>,
+}
+
+/// Parses the front matter at the start of a document.
+///
+/// # Arguments
+///
+/// * `markdown` - The Markdown source text
+///
+/// # Returns
+///
+/// * `Option<FrontMatter>` - The metadata, or None if the document has no front matter
+pub fn parse(markdown: &str) -> Option<FrontMatter> {
+    let (lines, _) = split(markdown)?;
+    let mut front_matter = FrontMatter::default();
+    let mut current_key: Option<&str> = None;
+    let mut list: Vec<String> = Vec::new();
+
+    for line in lines {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+
+        if let Some(item) = trimmed.strip_prefix("- ") {
+            if current_key.is_some() && line.starts_with([' ', '\t', '-']) {
+                list.push(unquote(item));
+            }
+            continue;
+        }
+        if line.starts_with([' ', '\t']) {
+            continue;
+        }
+
+        finish_list(current_key, &mut list, &mut front_matter);
+        current_key = None;
+
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        if value.is_empty() {
+            current_key = Some(key.trim());
+        } else if let Some(items) = value.strip_prefix('[').and_then(|v| v.strip_suffix(']')) {
+            let items: Vec<String> = items.split(',').map(unquote).collect();
+            set_field(&mut front_matter, key.trim(), items.join(", "));
+        } else {
+            set_field(&mut front_matter, key.trim(), unquote(value));
+        }
+    }
+    finish_list(current_key, &mut list, &mut front_matter);
+
+    Some(front_matter)
+}
+
+/// Blanks out the front matter so it isn't rendered as Markdown.
+///
+/// The front matter lines are replaced by empty lines, so line numbers in
+/// the rest of the document stay the same.
+///
+/// # Arguments
+///
+/// * `markdown` - The Markdown source text
+///
+/// # Returns
+///
+/// * `Cow<str>` - The text without front matter, borrowed if there was none
+pub fn blank(markdown: &str) -> Cow<'_, str> {
+    match split(markdown) {
+        Some((lines, rest)) => {
+            // The opening and closing delimiters are blanked too
+            let mut blanked = "\n".repeat(lines.len() + 2);
+            blanked.push_str(rest);
+            Cow::Owned(blanked)
+        }
+        None => Cow::Borrowed(markdown),
+    }
+}
+
+/// Splits off the front matter block, returning its inner lines and the rest of the text.
+///
+/// Front matter starts with a `---` line at the very start of the document
+/// and ends at the next `---` or `...` line.
+fn split(markdown: &str) -> Option<(Vec<&str>, &str)> {
+    let mut lines = markdown.split_inclusive('\n');
+    if lines.next()?.trim_end() != "---" {
+        return None;
+    }
+
+    let mut inner = Vec::new();
+    let mut consumed = markdown.find('\n')? + 1;
+    for line in lines {
+        consumed += line.len();
+        let content = line.trim_end_matches(['\r', '\n']);
+        if content == "---" || content == "..." {
+            return Some((inner, &markdown[consumed..]));
+        }
+        inner.push(content);
+    }
+
+    None
+}
+
+/// Stores a list collected under a key with an empty value.
+fn finish_list(key: Option<&str>, list: &mut Vec<String>, front_matter: &mut FrontMatter) {
+    if let Some(key) = key {
+        if !list.is_empty() {
+            set_field(front_matter, key, list.join(", "));
+        }
+    }
+    list.clear();
+}
+
+/// Stores a field mdview understands, ignoring all others.
+fn set_field(front_matter: &mut FrontMatter, key: &str, value: String) {
+    if value.is_empty() {
+        return;
+    }
+    match key {
+        "title" => front_matter.title = Some(value),
+        "author" | "authors" => front_matter.author = Some(value),
+        "lang" | "language" => front_matter.language = Some(value),
+        _ => {}
+    }
+}
+
+/// Removes surrounding quotes from a YAML scalar.
+fn unquote(value: &str) -> String {
+    let value = value.trim();
+    for quote in ['"', '\''] {
+        if let Some(inner) = value
+            .strip_prefix(quote)
+            .and_then(|v| v.strip_suffix(quote))
+        {
+            return inner.to_string();
+        }
+    }
+    value.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let markdown = "---\ntitle: \"User Guide\"\nauthor:\n  - Ada\n  - Grace\nlang: fr\ntags: [a, b]\n---\n# Intro\n";
+        let front_matter = parse(markdown).unwrap();
+
+        assert_eq!(front_matter.title.as_deref(), Some("User Guide"));
+        assert_eq!(front_matter.author.as_deref(), Some("Ada, Grace"));
+        assert_eq!(front_matter.language.as_deref(), Some("fr"));
+
+        let inline = parse("---\nauthors: ['Ada', Grace]\n---\n").unwrap();
+        assert_eq!(inline.author.as_deref(), Some("Ada, Grace"));
+    }
+
+    #[test]
+    fn test_parse_without_front_matter() {
+        assert_eq!(parse("# Title\n\n---\n"), None);
+        assert_eq!(parse("---\ntitle: never closed\n"), None);
+    }
+
+    #[test]
+    fn test_blank_preserves_lines() {
+        let markdown = "---\r\ntitle: X\r\n---\r\n# Heading\r\n";
+
+        assert_eq!(blank(markdown), "\n\n\n# Heading\r\n");
+        assert_eq!(blank("# No front matter\n"), "# No front matter\n");
+    }
+}
@@ -0,0 +1,302 @@
+use super::extensions::ExtensionConfig;
+use super::links::{self, LinkKind};
+use super::{loader, project, workspace, TocItem};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// A link from another document to the queried document.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Backlink {
+    /// The path of the referencing document
+    pub source: String,
+    /// The link text
+    pub text: String,
+    /// The 1-based line number of the link in the referencing document
+    pub line: usize,
+    /// The nearest heading above the link in the referencing document
+    pub heading: Option<TocItem>,
+    /// The anchor in the target document, if the link points at a section
+    pub anchor: Option<String>,
+}
+
+/// An outgoing link from a document to another Markdown file.
+#[derive(Debug, Clone)]
+struct Edge {
+    /// The normalized target path
+    target: PathBuf,
+    /// The backlink as seen from the target
+    backlink: Backlink,
+}
+
+/// The outgoing links of one document and the modification time they were read at.
+#[derive(Debug, Clone)]
+struct FileEntry {
+    modified: Option<SystemTime>,
+    edges: Vec<Edge>,
+}
+
+/// Graph of links between the Markdown files of a workspace.
+///
+/// The graph stores each document's outgoing links. Calling `refresh` rescans
+/// only files that were added, modified or removed since the previous scan,
+/// so the graph can be kept up to date cheaply as files change. Documents are
+/// parsed with the user's extension settings and, in trusted folders, their
+/// project's.
+#[derive(Debug, Clone)]
+pub struct LinkGraph {
+    root: PathBuf,
+    extensions: ExtensionConfig,
+    trusted_folders: Vec<PathBuf>,
+    files: HashMap<PathBuf, FileEntry>,
+}
+
+impl LinkGraph {
+    /// Builds the link graph for all Markdown files below a directory.
+    ///
+    /// # Arguments
+    ///
+    /// * `root` - The workspace root directory
+    /// * `extensions` - The user's extension settings
+    /// * `trusted_folders` - Folders whose project configuration is honored
+    ///
+    /// # Returns
+    ///
+    /// * `LinkGraph` - The fully scanned graph
+    pub fn build<P: AsRef<Path>>(
+        root: P,
+        extensions: ExtensionConfig,
+        trusted_folders: Vec<PathBuf>,
+    ) -> Self {
+        let root = root.as_ref();
+        let mut graph = Self {
+            root: root.canonicalize().unwrap_or_else(|_| root.to_path_buf()),
+            extensions,
+            trusted_folders,
+            files: HashMap::new(),
+        };
+        graph.refresh();
+        graph
+    }
+
+    /// Returns the workspace root this graph covers.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Returns the user extension settings documents are parsed with.
+    pub fn extensions(&self) -> &ExtensionConfig {
+        &self.extensions
+    }
+
+    /// Returns the folders whose project configuration is honored.
+    pub fn trusted_folders(&self) -> &[PathBuf] {
+        &self.trusted_folders
+    }
+
+    /// Rescans files that were added, modified or removed since the last scan.
+    ///
+    /// # Returns
+    ///
+    /// * `usize` - The number of files that were rescanned or dropped
+    pub fn refresh(&mut self) -> usize {
+        let current: Vec<PathBuf> = project::find_markdown_files(&self.root)
+            .iter()
+            .map(|path| graph_key(path))
+            .collect();
+        let current_set: HashSet<&PathBuf> = current.iter().collect();
+
+        let before = self.files.len();
+        self.files.retain(|path, _| current_set.contains(path));
+        let mut changed = before - self.files.len();
+
+        for path in &current {
+            let modified = modified_time(path);
+            let stale = match self.files.get(path) {
+                Some(entry) => modified.is_none() || entry.modified != modified,
+                None => true,
+            };
+
+            if stale {
+                self.update_file(path);
+                changed += 1;
+            }
+        }
+
+        changed
+    }
+
+    /// Rescans a single document, replacing its outgoing links.
+    ///
+    /// The document is dropped from the graph if it can no longer be loaded.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The document to rescan
+    pub fn update_file<P: AsRef<Path>>(&mut self, path: P) {
+        let path = graph_key(path.as_ref());
+
+        let markdown = match loader::load_markdown_file(&path) {
+            Ok(markdown) => markdown,
+            Err(_) => {
+                self.files.remove(&path);
+                return;
+            }
+        };
+
+        let base_dir = path.parent().unwrap_or_else(|| Path::new(""));
+        let source = path.display().to_string();
+
+        let extensions =
+            project::document_extensions(&path, &self.extensions, &self.trusted_folders);
+        let edges = links::scan_document(&markdown, &extensions)
+            .links
+            .into_iter()
+            .filter(|link| matches!(link.kind, LinkKind::File | LinkKind::FileAnchor))
+            .filter_map(|link| {
+                let (target, anchor) = links::split_local_url(&link.url);
+                let target = workspace::normalize_path(base_dir.join(target));
+                if !workspace::is_markdown_path(&target) {
+                    return None;
+                }
+
+                Some(Edge {
+                    target,
+                    backlink: Backlink {
+                        source: source.clone(),
+                        text: link.text,
+                        line: link.line,
+                        heading: link.heading,
+                        anchor: anchor.filter(|a| !a.is_empty()),
+                    },
+                })
+            })
+            .collect();
+
+        self.files.insert(
+            path.clone(),
+            FileEntry {
+                modified: modified_time(&path),
+                edges,
+            },
+        );
+    }
+
+    /// Returns all links to a document from other documents in the workspace.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The target document
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<Backlink>` - Backlinks ordered by source path and line
+    pub fn backlinks<P: AsRef<Path>>(&self, path: P) -> Vec<Backlink> {
+        let target = graph_key(path.as_ref());
+
+        let mut backlinks: Vec<Backlink> = self
+            .files
+            .iter()
+            .filter(|(source, _)| **source != target)
+            .flat_map(|(_, entry)| entry.edges.iter())
+            .filter(|edge| edge.target == target)
+            .map(|edge| edge.backlink.clone())
+            .collect();
+
+        backlinks.sort_by(|a, b| a.source.cmp(&b.source).then(a.line.cmp(&b.line)));
+        backlinks
+    }
+}
+
+/// Maps a path to the key used in the graph.
+fn graph_key(path: &Path) -> PathBuf {
+    path.canonicalize()
+        .unwrap_or_else(|_| workspace::normalize_path(path))
+}
+
+/// Returns the modification time of a file, if available.
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tempfile::tempdir;
+
+    fn write_later(path: &Path, contents: &str) {
+        // Make sure the modification time changes even on coarse-grained filesystems
+        let previous = modified_time(path);
+        fs::write(path, contents).unwrap();
+        while modified_time(path) == previous {
+            std::thread::sleep(Duration::from_millis(10));
+            fs::write(path, contents).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_backlinks() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path().canonicalize().unwrap();
+        fs::create_dir_all(root.join("docs")).unwrap();
+        fs::write(root.join("design.md"), "# Design\n\n## API\n").unwrap();
+        fs::write(
+            root.join("README.md"),
+            "# Readme\n\n## Links\n\nSee [the design](design.md#api).\n",
+        )
+        .unwrap();
+        fs::write(
+            root.join("docs/notes.md"),
+            "[Design doc](../design.md)\n[self](notes.md)\n",
+        )
+        .unwrap();
+
+        let graph = LinkGraph::build(&root, ExtensionConfig::default(), Vec::new());
+        let backlinks = graph.backlinks(root.join("design.md"));
+
+        assert_eq!(backlinks.len(), 2);
+        assert_eq!(
+            backlinks[0].source,
+            root.join("README.md").display().to_string()
+        );
+        assert_eq!(backlinks[0].text, "the design");
+        assert_eq!(backlinks[0].heading.as_ref().unwrap().text, "Links");
+        assert_eq!(backlinks[0].anchor.as_deref(), Some("api"));
+        assert_eq!(backlinks[1].text, "Design doc");
+        assert_eq!(backlinks[1].heading, None);
+
+        // Self-links are not backlinks
+        assert!(graph.backlinks(root.join("docs/notes.md")).is_empty());
+    }
+
+    #[test]
+    fn test_refresh_is_incremental() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path().canonicalize().unwrap();
+        fs::write(root.join("a.md"), "[b](b.md)\n").unwrap();
+        fs::write(root.join("b.md"), "# B\n").unwrap();
+        fs::write(root.join("c.md"), "# C\n").unwrap();
+
+        let mut graph = LinkGraph::build(&root, ExtensionConfig::default(), Vec::new());
+        assert_eq!(graph.backlinks(root.join("b.md")).len(), 1);
+        assert_eq!(graph.refresh(), 0);
+
+        // Modified file is rescanned
+        write_later(&root.join("c.md"), "[b again](b.md)\n");
+        assert_eq!(graph.refresh(), 1);
+        assert_eq!(graph.backlinks(root.join("b.md")).len(), 2);
+
+        // Removed file drops its links
+        fs::remove_file(root.join("a.md")).unwrap();
+        assert_eq!(graph.refresh(), 1);
+        assert_eq!(graph.backlinks(root.join("b.md")).len(), 1);
+
+        // New file is picked up
+        fs::write(root.join("d.md"), "[b](./b.md)\n").unwrap();
+        assert_eq!(graph.refresh(), 1);
+        assert_eq!(graph.backlinks(root.join("b.md")).len(), 2);
+    }
+}
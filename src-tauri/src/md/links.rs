@@ -0,0 +1,719 @@
+use super::archive::{self, ArchivePath};
+use super::extensions::ExtensionConfig;
+use super::loader::{self, MdLoadError};
+use super::project::{self, ProjectConfig};
+use super::{toc, workspace, TocItem};
+use comrak::nodes::{AstNode, NodeValue};
+use comrak::{parse_document, Arena};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+/// The kind of target a link points at.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LinkKind {
+    /// An anchor within the same document (`#section`)
+    Anchor,
+    /// A relative or absolute local file (`other.md`)
+    File,
+    /// A local file plus an anchor (`other.md#section`)
+    FileAnchor,
+    /// A URL with a scheme or a `www.` host (`https://...`, `mailto:...`)
+    External,
+    /// An image source
+    Image,
+}
+
+/// A link found in a Markdown document.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Link {
+    /// The classified link kind
+    pub kind: LinkKind,
+    /// The link destination as written in the source
+    pub url: String,
+    /// The link text (or alt text for images)
+    pub text: String,
+    /// The 1-based line number of the link in the source
+    pub line: usize,
+    /// The nearest heading above the link, if any
+    pub heading: Option<TocItem>,
+}
+
+/// The headings and links of a document, collected in a single AST walk.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DocumentScan {
+    /// The table of contents
+    pub toc: Vec<TocItem>,
+    /// All links and images in source order
+    pub links: Vec<Link>,
+}
+
+/// The result of checking a single link.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LinkStatus {
+    /// The target exists (and the anchor, if any, was found)
+    Ok,
+    /// The link was not checked (external URLs and remote images)
+    Unchecked,
+    /// The target file does not exist
+    MissingFile,
+    /// The target file exists but has no heading with the anchor ID
+    MissingAnchor,
+}
+
+impl LinkStatus {
+    /// Returns true if the link is broken.
+    pub fn is_broken(&self) -> bool {
+        matches!(self, LinkStatus::MissingFile | LinkStatus::MissingAnchor)
+    }
+}
+
+impl std::fmt::Display for LinkStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let text = match self {
+            LinkStatus::Ok => "ok",
+            LinkStatus::Unchecked => "unchecked",
+            LinkStatus::MissingFile => "missing file",
+            LinkStatus::MissingAnchor => "missing anchor",
+        };
+        f.write_str(text)
+    }
+}
+
+/// A link together with its check result.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CheckedLink {
+    #[serde(flatten)]
+    pub link: Link,
+    pub status: LinkStatus,
+}
+
+/// Link check results for one document.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct LinkReport {
+    /// The path of the checked document
+    pub path: String,
+    /// All links in the document, in source order
+    pub links: Vec<CheckedLink>,
+}
+
+impl LinkReport {
+    /// Returns the broken links in the report.
+    pub fn broken(&self) -> impl Iterator<Item = &CheckedLink> {
+        self.links.iter().filter(|l| l.status.is_broken())
+    }
+
+    /// Returns true if any link in the report is broken.
+    pub fn has_broken(&self) -> bool {
+        self.broken().next().is_some()
+    }
+}
+
+/// Extracts and classifies all links and images from Markdown text, parsed
+/// with the default extension profile.
+///
+/// # Arguments
+///
+/// * `markdown` - The Markdown source text
+///
+/// # Returns
+///
+/// * `Vec<Link>` - The links in source order
+pub fn extract_links(markdown: &str) -> Vec<Link> {
+    scan_document(markdown, &ExtensionConfig::default()).links
+}
+
+/// Extracts the table of contents and all links from Markdown text.
+///
+/// Each link records the nearest heading above it, which is what the
+/// backlinks panel shows as the link's context.
+///
+/// # Arguments
+///
+/// * `markdown` - The Markdown source text
+/// * `extensions` - The extension settings the document is rendered with
+///
+/// # Returns
+///
+/// * `DocumentScan` - The headings and links of the document
+pub fn scan_document(markdown: &str, extensions: &ExtensionConfig) -> DocumentScan {
+    let arena = Arena::new();
+    let options = extensions.options();
+
+    let root = parse_document(&arena, markdown, &options);
+    let mut scan = DocumentScan::default();
+    let mut current_heading = None;
+
+    scan_node(root, &mut scan, &mut current_heading);
+
+    scan
+}
+
+/// Recursively traverses the AST in document order, collecting headings and links.
+fn scan_node<'a>(
+    node: &'a AstNode<'a>,
+    scan: &mut DocumentScan,
+    current_heading: &mut Option<TocItem>,
+) {
+    if let Some(item) = toc::heading_item(node) {
+        *current_heading = Some(item.clone());
+        scan.toc.push(item);
+    }
+
+    let link = match &node.data.borrow().value {
+        NodeValue::Link(link) => Some((classify(&link.url, false), link.url.clone())),
+        NodeValue::Image(link) => Some((classify(&link.url, true), link.url.clone())),
+        _ => None,
+    };
+
+    if let Some((kind, url)) = link {
+        scan.links.push(Link {
+            kind,
+            url,
+            text: toc::extract_text(node),
+            line: node.data.borrow().sourcepos.start.line,
+            heading: current_heading.clone(),
+        });
+    }
+
+    for child in node.children() {
+        scan_node(child, scan, current_heading);
+    }
+}
+
+/// Classifies a link destination.
+///
+/// # Arguments
+///
+/// * `url` - The link destination
+/// * `is_image` - Whether the link is an image source
+///
+/// # Returns
+///
+/// * `LinkKind` - The kind of target
+pub fn classify(url: &str, is_image: bool) -> LinkKind {
+    if is_image {
+        LinkKind::Image
+    } else if is_external(url) {
+        LinkKind::External
+    } else if url.starts_with('#') {
+        LinkKind::Anchor
+    } else if url.contains('#') {
+        LinkKind::FileAnchor
+    } else {
+        LinkKind::File
+    }
+}
+
+/// Returns true if the URL points outside the local filesystem.
+///
+/// A scheme must be at least two characters so Windows drive letters
+/// (`C:\docs\file.md`) are treated as local paths.
+pub fn is_external(url: &str) -> bool {
+    if url.starts_with("www.") || url.starts_with("//") {
+        return true;
+    }
+
+    match url.find(':') {
+        Some(idx) if idx >= 2 => {
+            let scheme = &url[..idx];
+            scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+                && scheme
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+        }
+        _ => false,
+    }
+}
+
+/// Splits a local link into its decoded path and optional decoded anchor.
+///
+/// Query strings are dropped.
+pub fn split_local_url(url: &str) -> (String, Option<String>) {
+    let (path, anchor) = match url.split_once('#') {
+        Some((path, anchor)) => (path, Some(anchor)),
+        None => (url, None),
+    };
+    let path = path.split('?').next().unwrap_or_default();
+
+    (percent_decode(path), anchor.map(percent_decode))
+}
+
+/// Decodes `%XX` escapes in a URL component.
+fn percent_decode(input: &str) -> String {
+    let bytes = input.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok();
+            if let Some(byte) = hex.and_then(|h| u8::from_str_radix(h, 16).ok()) {
+                decoded.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Checks links against the filesystem, caching the TOC IDs of target files.
+///
+/// Documents are parsed with the user's extension settings and their
+/// project's, as they are rendered. Links in documents inside an archive
+/// are checked against the archive's files. Reuse one checker when checking
+/// many documents so each target file is parsed, and each archive listed,
+/// only once.
+#[derive(Debug, Default)]
+pub struct LinkChecker {
+    /// The user's extension settings
+    extensions: ExtensionConfig,
+    /// Folders whose project configuration is honored
+    trusted_folders: Vec<PathBuf>,
+    /// Heading IDs per Markdown file, or None if the file couldn't be loaded
+    anchors: HashMap<PathBuf, Option<HashSet<String>>>,
+    /// Files in each archive linked into, empty if it couldn't be read
+    archive_files: HashMap<PathBuf, HashSet<String>>,
+}
+
+impl LinkChecker {
+    /// Creates a new link checker with an empty cache.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parses documents with the given user extension settings instead of the defaults.
+    pub fn with_extensions(mut self, extensions: ExtensionConfig) -> Self {
+        self.extensions = extensions;
+        self
+    }
+
+    /// Honors the project configuration (extensions and `root`) of documents
+    /// in these folders; other documents' project files are ignored.
+    pub fn with_trusted_folders(mut self, trusted_folders: Vec<PathBuf>) -> Self {
+        self.trusted_folders = trusted_folders;
+        self
+    }
+
+    /// Loads a Markdown file and checks all of its links.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The document to check
+    ///
+    /// # Returns
+    ///
+    /// * `Result<LinkReport, MdLoadError>` - The report, or an error if the document can't be loaded
+    pub fn check_file<P: AsRef<Path>>(&mut self, path: P) -> Result<LinkReport, MdLoadError> {
+        let markdown = loader::load_markdown_file(&path)?;
+        Ok(self.check_markdown(path.as_ref(), &markdown))
+    }
+
+    /// Checks a single Markdown file, or every Markdown file below a directory
+    /// or in an archive.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - A Markdown file, a workspace directory or an archive
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<Result<LinkReport, MdLoadError>>` - One result per document checked
+    pub fn check_path<P: AsRef<Path>>(&mut self, path: P) -> Vec<Result<LinkReport, MdLoadError>> {
+        let path = path.as_ref();
+
+        if path.is_dir() {
+            project::find_markdown_files(path)
+                .iter()
+                .map(|file| self.check_file(file))
+                .collect()
+        } else if archive::is_archive_path(path) && path.is_file() {
+            match archive::find_markdown_files(path) {
+                Ok(files) => files
+                    .iter()
+                    .map(|file| self.check_file(file.to_string()))
+                    .collect(),
+                Err(e) => vec![Err(MdLoadError::Decompression {
+                    path: path.display().to_string(),
+                    message: e.to_string(),
+                })],
+            }
+        } else {
+            vec![self.check_file(path)]
+        }
+    }
+
+    /// Checks all links of an already loaded document.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The document path, used to resolve relative links
+    /// * `markdown` - The document's Markdown source
+    ///
+    /// # Returns
+    ///
+    /// * `LinkReport` - The check results
+    pub fn check_markdown(&mut self, path: &Path, markdown: &str) -> LinkReport {
+        let project = ProjectConfig::discover_trusted(path, &self.trusted_folders);
+        let extensions = match &project {
+            Some(project) => self.extensions.merge(&project.extensions),
+            None => self.extensions,
+        };
+        let scan = scan_document(markdown, &extensions);
+        let own_ids: HashSet<String> = scan.toc.into_iter().map(|item| item.id).collect();
+        let base_dir = path.parent().unwrap_or_else(|| Path::new(""));
+        let link_root = match ArchivePath::from_path(path) {
+            Some(archived) => Some(PathBuf::from(archived.root())),
+            None => project.and_then(|project| project.root_path()),
+        };
+
+        let links = scan
+            .links
+            .into_iter()
+            .map(|link| {
+                let status = self.check_link(&link, base_dir, link_root.as_deref(), &own_ids);
+                CheckedLink { link, status }
+            })
+            .collect();
+
+        LinkReport {
+            path: path.display().to_string(),
+            links,
+        }
+    }
+
+    /// Checks a single link relative to its document's directory, or to
+    /// the project's link root for links starting with `/`.
+    fn check_link(
+        &mut self,
+        link: &Link,
+        base_dir: &Path,
+        link_root: Option<&Path>,
+        own_ids: &HashSet<String>,
+    ) -> LinkStatus {
+        if link.kind == LinkKind::External || is_external(&link.url) {
+            return LinkStatus::Unchecked;
+        }
+
+        let (target, anchor) = split_local_url(&link.url);
+
+        if target.is_empty() {
+            return match anchor {
+                Some(anchor) if !anchor.is_empty() && !own_ids.contains(&anchor) => {
+                    LinkStatus::MissingAnchor
+                }
+                _ => LinkStatus::Ok,
+            };
+        }
+
+        let target_path = match (target.strip_prefix('/'), link_root) {
+            (Some(rooted), Some(root)) => root.join(rooted),
+            _ => base_dir.join(&target),
+        };
+        if !self.exists(&target_path) {
+            return LinkStatus::MissingFile;
+        }
+
+        match anchor {
+            Some(anchor) if !anchor.is_empty() && workspace::is_markdown_path(&target_path) => {
+                match self.anchors_for(&target_path) {
+                    Some(ids) if ids.contains(&anchor) => LinkStatus::Ok,
+                    _ => LinkStatus::MissingAnchor,
+                }
+            }
+            _ => LinkStatus::Ok,
+        }
+    }
+
+    /// Returns true if a link target exists, on disk or in an archive.
+    fn exists(&mut self, path: &Path) -> bool {
+        let Some(archived) = ArchivePath::from_path(path) else {
+            return path.exists();
+        };
+
+        self.archive_files
+            .entry(archived.archive.clone())
+            .or_insert_with(|| {
+                archive::list_files(&archived.archive)
+                    .map(|files| files.into_iter().collect())
+                    .unwrap_or_default()
+            })
+            .contains(&archived.entry)
+    }
+
+    /// Returns the cached heading IDs of a Markdown file, loading it on first use.
+    fn anchors_for(&mut self, path: &Path) -> Option<&HashSet<String>> {
+        let key = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+
+        self.anchors
+            .entry(key)
+            .or_insert_with(|| {
+                loader::load_markdown_file(path).ok().map(|markdown| {
+                    let extensions =
+                        project::document_extensions(path, &self.extensions, &self.trusted_folders);
+                    scan_document(&markdown, &extensions)
+                        .toc
+                        .into_iter()
+                        .map(|item| item.id)
+                        .collect()
+                })
+            })
+            .as_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_classify() {
+        assert_eq!(classify("#intro", false), LinkKind::Anchor);
+        assert_eq!(classify("other.md", false), LinkKind::File);
+        assert_eq!(
+            classify("../docs/other.md#setup", false),
+            LinkKind::FileAnchor
+        );
+        assert_eq!(classify("https://example.com", false), LinkKind::External);
+        assert_eq!(classify("mailto:me@example.com", false), LinkKind::External);
+        assert_eq!(classify("www.example.com", false), LinkKind::External);
+        assert_eq!(classify("C:\\docs\\file.md", false), LinkKind::File);
+        assert_eq!(classify("img/logo.png", true), LinkKind::Image);
+    }
+
+    #[test]
+    fn test_extract_links() {
+        let markdown =
+            "# Title\n\nSee [the *guide*](guide.md) and ![logo](logo.png).\n\n[top](#title)";
+        let links = extract_links(markdown);
+
+        assert_eq!(links.len(), 3);
+        assert_eq!(links[0].kind, LinkKind::File);
+        assert_eq!(links[0].text, "the guide");
+        assert_eq!(links[0].line, 3);
+        assert_eq!(links[1].kind, LinkKind::Image);
+        assert_eq!(links[1].text, "logo");
+        assert_eq!(links[2].kind, LinkKind::Anchor);
+        assert_eq!(links[2].line, 5);
+    }
+
+    #[test]
+    fn test_scan_document_records_nearest_heading() {
+        let markdown = "[intro](a.md)\n\n# One\n\n[first](b.md)\n\n## Two\n\n[second](c.md)";
+        let scan = scan_document(markdown, &ExtensionConfig::default());
+
+        assert_eq!(scan.toc.len(), 2);
+        assert_eq!(scan.links[0].heading, None);
+        assert_eq!(scan.links[1].heading.as_ref().unwrap().id, "one");
+        assert_eq!(scan.links[2].heading.as_ref().unwrap().text, "Two");
+    }
+
+    #[test]
+    fn test_split_local_url_decodes() {
+        assert_eq!(
+            split_local_url("my%20notes.md?raw=1#part%20two"),
+            ("my notes.md".to_string(), Some("part two".to_string()))
+        );
+        assert_eq!(split_local_url("a.md"), ("a.md".to_string(), None));
+        assert_eq!(split_local_url("100%"), ("100%".to_string(), None));
+    }
+
+    #[test]
+    fn test_check_markdown() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir_all(root.join("img")).unwrap();
+        fs::write(root.join("img/logo.png"), b"png").unwrap();
+        fs::write(root.join("guide.md"), "# Guide\n\n## Setup\n").unwrap();
+
+        let markdown = "\
+# Index
+
+[ok anchor](#index)
+[bad anchor](#nope)
+[ok file](guide.md)
+[missing file](missing.md)
+[ok file anchor](guide.md#setup)
+[bad file anchor](guide.md#teardown)
+[external](https://example.com)
+![ok image](img/logo.png)
+![missing image](img/none.png)
+";
+        let doc = root.join("index.md");
+        fs::write(&doc, markdown).unwrap();
+
+        let report = LinkChecker::new().check_file(&doc).unwrap();
+        let statuses: Vec<LinkStatus> = report.links.iter().map(|l| l.status).collect();
+
+        assert_eq!(
+            statuses,
+            vec![
+                LinkStatus::Ok,
+                LinkStatus::MissingAnchor,
+                LinkStatus::Ok,
+                LinkStatus::MissingFile,
+                LinkStatus::Ok,
+                LinkStatus::MissingAnchor,
+                LinkStatus::Unchecked,
+                LinkStatus::Ok,
+                LinkStatus::MissingFile,
+            ]
+        );
+        assert!(report.has_broken());
+        assert_eq!(report.broken().count(), 4);
+    }
+
+    #[test]
+    fn test_check_path_workspace() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir_all(root.join("docs")).unwrap();
+        fs::write(root.join("README.md"), "[guide](docs/guide.md)\n").unwrap();
+        fs::write(
+            root.join("docs/guide.md"),
+            "[back](../README.md)\n[gone](old.md)\n",
+        )
+        .unwrap();
+
+        let reports: Vec<LinkReport> = LinkChecker::new()
+            .check_path(root)
+            .into_iter()
+            .map(|r| r.unwrap())
+            .collect();
+
+        assert_eq!(reports.len(), 2);
+        assert!(!reports[0].has_broken());
+        assert_eq!(reports[1].broken().count(), 1);
+        assert_eq!(reports[1].broken().next().unwrap().link.url, "old.md");
+    }
+
+    #[test]
+    fn test_project_root_and_ignore() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir_all(root.join("site/docs")).unwrap();
+        fs::create_dir_all(root.join("site/drafts")).unwrap();
+        fs::write(
+            root.join(".mdview.toml"),
+            "root = \"site\"\nignore = [\"drafts\"]\n",
+        )
+        .unwrap();
+        fs::write(root.join("site/docs/guide.md"), "# Guide\n").unwrap();
+        fs::write(
+            root.join("site/index.md"),
+            "[guide](/docs/guide.md#guide)\n[missing](/docs/missing.md)\n",
+        )
+        .unwrap();
+        fs::write(root.join("site/drafts/wip.md"), "[broken](nowhere.md)\n").unwrap();
+
+        let reports: Vec<LinkReport> = LinkChecker::new()
+            .with_trusted_folders(vec![root.to_path_buf()])
+            .check_path(root)
+            .into_iter()
+            .map(|r| r.unwrap())
+            .collect();
+
+        assert_eq!(reports.len(), 2);
+        let index = reports
+            .iter()
+            .find(|r| r.path.ends_with("index.md"))
+            .unwrap();
+        let broken: Vec<_> = index.broken().map(|l| l.link.url.as_str()).collect();
+        assert_eq!(broken, vec!["/docs/missing.md"]);
+    }
+
+    #[test]
+    fn test_check_markdown_no_broken_links() {
+        let temp_dir = tempdir().unwrap();
+        let doc = temp_dir.path().join("index.md");
+        fs::write(&doc, "# Index\n\n[self](index.md#index)\n").unwrap();
+
+        let report = LinkChecker::new().check_file(&doc).unwrap();
+
+        assert!(!report.has_broken());
+    }
+
+    #[test]
+    fn test_check_uses_document_extensions() {
+        use crate::md::extensions::Profile;
+
+        let temp_dir = tempdir().unwrap();
+        let doc = temp_dir.path().join("index.md");
+        fs::write(&doc, "See www.example.com\n").unwrap();
+        let commonmark = ExtensionConfig {
+            profile: Some(Profile::CommonMark),
+            ..ExtensionConfig::default()
+        };
+
+        let report = LinkChecker::new().check_file(&doc).unwrap();
+        assert_eq!(report.links.len(), 1);
+        let report = LinkChecker::new()
+            .with_extensions(commonmark)
+            .check_file(&doc)
+            .unwrap();
+        assert!(report.links.is_empty());
+
+        // The project's profile wins over the user's once its folder is trusted
+        fs::write(
+            temp_dir.path().join(".mdview.toml"),
+            "[extensions]\nprofile = \"gfm\"\n",
+        )
+        .unwrap();
+        let report = LinkChecker::new()
+            .with_extensions(commonmark)
+            .check_file(&doc)
+            .unwrap();
+        assert!(report.links.is_empty());
+        let report = LinkChecker::new()
+            .with_extensions(commonmark)
+            .with_trusted_folders(vec![temp_dir.path().to_path_buf()])
+            .check_file(&doc)
+            .unwrap();
+        assert_eq!(report.links.len(), 1);
+    }
+
+    #[test]
+    fn test_check_archive() {
+        use std::io::Write;
+
+        let temp_dir = tempdir().unwrap();
+        let archive = temp_dir.path().join("bundle.zip");
+        let mut zip = zip::ZipWriter::new(fs::File::create(&archive).unwrap());
+        let options = zip::write::SimpleFileOptions::default();
+        for (name, content) in [
+            (
+                "docs/index.md",
+                "[guide](guide.md#setup)\n[root](/README.md)\n[gone](gone.md)\n[bad](guide.md#nope)\n",
+            ),
+            ("docs/guide.md", "# Setup\n"),
+            ("README.md", "![logo](img/logo.png)\n"),
+        ] {
+            zip.start_file(name, options).unwrap();
+            zip.write_all(content.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap();
+
+        let reports: Vec<LinkReport> = LinkChecker::new()
+            .check_path(&archive)
+            .into_iter()
+            .map(|r| r.unwrap())
+            .collect();
+
+        assert_eq!(reports.len(), 3);
+        let broken = |name: &str| -> Vec<String> {
+            let report = reports.iter().find(|r| r.path.ends_with(name)).unwrap();
+            report.broken().map(|l| l.link.url.clone()).collect()
+        };
+        assert_eq!(broken("!/docs/index.md"), ["gone.md", "guide.md#nope"]);
+        assert!(broken("!/docs/guide.md").is_empty());
+        assert_eq!(broken("!/README.md"), ["img/logo.png"]);
+    }
+}
@@ -1,13 +1,118 @@
+use super::archive::{self, ArchiveError, ArchivePath};
+use serde::{Deserialize, Serialize};
 use std::fs;
-use std::io;
-use std::path::Path;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+/// Files of at least this many bytes report progress while loading.
+pub const PROGRESS_THRESHOLD: u64 = 1024 * 1024;
+
+/// Size of the chunks large files are read in.
+const CHUNK_SIZE: usize = 256 * 1024;
+
+/// Files larger than this need confirmation before they are loaded, unless the settings say otherwise.
+pub const DEFAULT_MAX_FILE_SIZE: u64 = 50 * 1024 * 1024;
+
+/// Number of leading bytes checked for control characters.
+const BINARY_SAMPLE_SIZE: usize = 8 * 1024;
+
+/// Share of control characters in the sample above which a file is treated as binary.
+const MAX_CONTROL_RATIO: f64 = 0.1;
+
+/// Compressed files may expand to at most this many times their size...
+const MAX_COMPRESSION_RATIO: u64 = 100;
+
+/// ...unless they stay below this size, so small files with repetitive content still open.
+const MIN_BOMB_SIZE: u64 = 16 * 1024 * 1024;
+
+/// Compression formats recognised by their magic bytes.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum Compression {
+    Gzip,
+    Zstd,
+    Bzip2,
+    Xz,
+}
+
+impl Compression {
+    /// Detects the compression format from the start of a file.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - The first bytes of the file (at least 10 for bzip2)
+    ///
+    /// # Returns
+    ///
+    /// * `Option<Compression>` - The format, or None for uncompressed content
+    pub fn detect(bytes: &[u8]) -> Option<Self> {
+        if bytes.starts_with(&[0x1f, 0x8b]) {
+            Some(Self::Gzip)
+        } else if bytes.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+            Some(Self::Zstd)
+        } else if bytes.starts_with(&[0xfd, b'7', b'z', b'X', b'Z', 0x00]) {
+            Some(Self::Xz)
+        } else if is_bzip2(bytes) {
+            Some(Self::Bzip2)
+        } else {
+            None
+        }
+    }
+}
+
+/// A loaded file's content and how it was stored.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoadedFile {
+    /// The (decompressed) Markdown content
+    pub content: String,
+    /// The compression the file was stored with, if any
+    pub compression: Option<Compression>,
+}
+
+/// Limits applied when loading files, stored with the application settings.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct LoadLimits {
+    /// Largest file in bytes that loads without confirmation; 0 turns the limit off
+    pub max_file_size: u64,
+}
+
+impl LoadLimits {
+    /// Limits that let a file of any size load, e.g. once the user has confirmed it.
+    pub fn unlimited() -> Self {
+        Self { max_file_size: 0 }
+    }
+}
+
+impl Default for LoadLimits {
+    fn default() -> Self {
+        Self {
+            max_file_size: DEFAULT_MAX_FILE_SIZE,
+        }
+    }
+}
 
 /// Errors that can occur during Markdown file loading.
+///
+/// Errors about the file itself carry its canonical path, or the path as
+/// given when it can't be resolved.
 #[derive(Debug, thiserror::Error)]
 pub enum MdLoadError {
     /// File not found
     #[error("File not found: {0}")]
-    FileNotFound(String),
+    NotFound(String),
+    
+    /// The file or a folder on the way to it can't be read
+    #[error("Permission denied: {0}")]
+    PermissionDenied(String),
+    
+    /// Path is a directory
+    #[error("Is a directory, not a file: {0}")]
+    IsDirectory(String),
+    
+    /// Path is a symbolic link whose target doesn't exist
+    #[error("Broken symbolic link: {0}")]
+    BrokenSymlink(String),
     
     /// IO error during file reading
     #[error("IO error: {0}")]
@@ -16,9 +121,109 @@ pub enum MdLoadError {
     /// Invalid UTF-8 encoding
     #[error("Invalid UTF-8 encoding in file: {0}")]
     InvalidUtf8(String),
+    
+    /// The load was superseded by a newer one
+    #[error("Loading was cancelled: {0}")]
+    Cancelled(String),
+    
+    /// File is larger than the configured limit
+    #[error("File is too large: {path} is {} MB, the limit is {} MB", megabytes(*size), megabytes(*limit))]
+    TooLarge {
+        path: String,
+        size: u64,
+        limit: u64,
+    },
+    
+    /// File contains NUL bytes or mostly control characters
+    #[error("File appears to be binary: {0}")]
+    BinaryFile(String),
+    
+    /// Path is a FIFO, socket or device rather than a regular file
+    #[error("Not a regular file: {0}")]
+    NotAFile(String),
+    
+    /// Compressed file expands beyond the configured limit
+    #[error("File is too large: {path} expands to more than {} MB", megabytes(*limit))]
+    TooLargeDecompressed {
+        path: String,
+        limit: u64,
+    },
+    
+    /// Compressed file expands far more than Markdown plausibly does
+    #[error("Compressed file expands to more than {}x its size: {0}", MAX_COMPRESSION_RATIO)]
+    DecompressionBomb(String),
+    
+    /// Compressed file is corrupt or truncated
+    #[error("Failed to decompress {path}: {message}")]
+    Decompression {
+        path: String,
+        message: String,
+    },
 }
 
-/// Loads a Markdown file from the filesystem.
+/// Kind of a load failure, sent to the frontend with command errors.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LoadErrorKind {
+    NotFound,
+    PermissionDenied,
+    IsDirectory,
+    BrokenSymlink,
+    Io,
+    InvalidUtf8,
+    Cancelled,
+    TooLarge,
+    BinaryFile,
+    NotAFile,
+    DecompressionBomb,
+    Decompression,
+}
+
+impl MdLoadError {
+    /// Returns the kind of failure.
+    pub fn kind(&self) -> LoadErrorKind {
+        match self {
+            Self::NotFound(_) => LoadErrorKind::NotFound,
+            Self::PermissionDenied(_) => LoadErrorKind::PermissionDenied,
+            Self::IsDirectory(_) => LoadErrorKind::IsDirectory,
+            Self::BrokenSymlink(_) => LoadErrorKind::BrokenSymlink,
+            Self::IoError(_) => LoadErrorKind::Io,
+            Self::InvalidUtf8(_) => LoadErrorKind::InvalidUtf8,
+            Self::Cancelled(_) => LoadErrorKind::Cancelled,
+            Self::TooLarge { .. } | Self::TooLargeDecompressed { .. } => LoadErrorKind::TooLarge,
+            Self::BinaryFile(_) => LoadErrorKind::BinaryFile,
+            Self::NotAFile(_) => LoadErrorKind::NotAFile,
+            Self::DecompressionBomb(_) => LoadErrorKind::DecompressionBomb,
+            Self::Decompression { .. } => LoadErrorKind::Decompression,
+        }
+    }
+    
+    /// Returns the path of the file the error is about, if it names one.
+    pub fn path(&self) -> Option<&str> {
+        match self {
+            Self::NotFound(path)
+            | Self::PermissionDenied(path)
+            | Self::IsDirectory(path)
+            | Self::BrokenSymlink(path)
+            | Self::InvalidUtf8(path)
+            | Self::Cancelled(path)
+            | Self::TooLarge { path, .. }
+            | Self::BinaryFile(path)
+            | Self::NotAFile(path)
+            | Self::TooLargeDecompressed { path, .. }
+            | Self::DecompressionBomb(path)
+            | Self::Decompression { path, .. } => Some(path),
+            Self::IoError(_) => None,
+        }
+    }
+}
+
+/// Formats a byte count in megabytes for error messages.
+fn megabytes(bytes: u64) -> String {
+    format!("{:.1}", bytes as f64 / (1024.0 * 1024.0))
+}
+
+/// Loads a Markdown file from the filesystem, within the default `LoadLimits`.
 /// 
 /// # Arguments
 /// 
@@ -37,22 +242,325 @@ pub enum MdLoadError {
 /// # Ok::<(), Box<dyn std::error::Error>>(())
 /// ```
 pub fn load_markdown_file<P: AsRef<Path>>(path: P) -> Result<String, MdLoadError> {
+    load_markdown_file_with_progress(path, &LoadLimits::default(), |_, _| true)
+        .map(|loaded| loaded.content)
+}
+
+/// Loads a Markdown file, reporting progress for large files.
+///
+/// The file is opened once and everything else is checked on the open
+/// handle, so it can't be swapped between the checks and the read. Only
+/// regular files within the size limit are read, and content that looks
+/// binary is refused. Files compressed with gzip, zstd, bzip2 or xz are
+/// recognised by their content and decompressed; the limit then applies to
+/// the decompressed size too, and output beyond `MAX_COMPRESSION_RATIO`
+/// times the file size is refused. Paths into a zip or tar archive (as in
+/// `bundle.zip!/docs/index.md`) read that file from the archive, without
+/// progress reports. Files of at least `PROGRESS_THRESHOLD` bytes are
+/// read in chunks. After each chunk `progress` is called with the bytes read
+/// so far and the file size; returning false stops loading with
+/// `MdLoadError::Cancelled`.
+///
+/// # Arguments
+///
+/// * `path` - The file path to load
+/// * `limits` - The size limit to apply
+/// * `progress` - Called after each chunk of a large file; returns whether to continue
+///
+/// # Returns
+///
+/// * `Result<LoadedFile, MdLoadError>` - The file contents as a UTF-8 string or an error
+pub fn load_markdown_file_with_progress<P, F>(
+    path: P,
+    limits: &LoadLimits,
+    mut progress: F,
+) -> Result<LoadedFile, MdLoadError>
+where
+    P: AsRef<Path>,
+    F: FnMut(u64, u64) -> bool,
+{
     let path_ref = path.as_ref();
+    if let Some(archived) = ArchivePath::from_path(path_ref) {
+        return load_archived_file(&archived, limits);
+    }
     
-    // Check if file exists
-    if !path_ref.exists() {
-        return Err(MdLoadError::FileNotFound(
-            path_ref.display().to_string()
-        ));
+    let mut file = open_file(path_ref).map_err(|e| open_error(path_ref, e))?;
+    let canonical = canonical_path(path_ref).display().to_string();
+    
+    let metadata = file.metadata()?;
+    if metadata.is_dir() {
+        return Err(MdLoadError::IsDirectory(canonical));
+    }
+    if !metadata.is_file() {
+        return Err(MdLoadError::NotAFile(canonical));
+    }
+    let total = metadata.len();
+    if limits.max_file_size > 0 && total > limits.max_file_size {
+        return Err(MdLoadError::TooLarge {
+            path: canonical,
+            size: total,
+            limit: limits.max_file_size,
+        });
     }
     
     // Read file contents
-    let bytes = fs::read(path_ref)?;
+    let mut bytes = Vec::with_capacity(total as usize);
+    if total < PROGRESS_THRESHOLD {
+        file.read_to_end(&mut bytes)?;
+    } else {
+        let mut chunk = vec![0; CHUNK_SIZE];
+        loop {
+            let read = match file.read(&mut chunk) {
+                Ok(0) => break,
+                Ok(read) => read,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e.into()),
+            };
+            bytes.extend_from_slice(&chunk[..read]);
+            // Stop early rather than reading all of a large binary file
+            let first = bytes.len() == read;
+            if first && Compression::detect(&bytes).is_none() && looks_binary(&bytes) {
+                return Err(MdLoadError::BinaryFile(canonical));
+            }
+            if !progress(bytes.len() as u64, total) {
+                return Err(MdLoadError::Cancelled(path_ref.display().to_string()));
+            }
+        }
+    }
+    
+    decode(bytes, limits, canonical)
+}
+
+/// Loads a Markdown file from inside a zip or tar archive.
+///
+/// The archive file is checked like a Markdown file would be. The size of
+/// the entry counts against the limit, and entries expanding beyond
+/// `MAX_COMPRESSION_RATIO` times the archive's size are refused.
+fn load_archived_file(path: &ArchivePath, limits: &LoadLimits) -> Result<LoadedFile, MdLoadError> {
+    let file = open_file(&path.archive).map_err(|e| open_error(&path.archive, e))?;
+    let metadata = file.metadata()?;
+    let archive = canonical_path(&path.archive);
+    if metadata.is_dir() {
+        return Err(MdLoadError::IsDirectory(archive.display().to_string()));
+    }
+    if !metadata.is_file() {
+        return Err(MdLoadError::NotAFile(archive.display().to_string()));
+    }
+    drop(file);
+    
+    let path = ArchivePath {
+        archive,
+        entry: path.entry.clone(),
+    };
+    let display = path.to_string();
+    let bomb_limit = metadata
+        .len()
+        .saturating_mul(MAX_COMPRESSION_RATIO)
+        .max(MIN_BOMB_SIZE);
+    let limit = match limits.max_file_size {
+        0 => bomb_limit,
+        max_file_size => max_file_size.min(bomb_limit),
+    };
+    
+    let bytes = archive::read_file(&path, limit).map_err(|e| match e {
+        ArchiveError::Io(e) => MdLoadError::IoError(e),
+        ArchiveError::NotFound(_) => MdLoadError::NotFound(display.clone()),
+        ArchiveError::TooLarge { size, .. }
+            if limits.max_file_size > 0 && size > limits.max_file_size =>
+        {
+            MdLoadError::TooLarge {
+                path: display.clone(),
+                size,
+                limit: limits.max_file_size,
+            }
+        }
+        ArchiveError::TooLarge { .. } => MdLoadError::DecompressionBomb(display.clone()),
+        ArchiveError::Corrupt(message) => MdLoadError::Decompression {
+            path: display.clone(),
+            message,
+        },
+    })?;
+    decode(bytes, limits, display)
+}
+
+/// Decompresses file content if needed and checks that it is UTF-8 text.
+fn decode(bytes: Vec<u8>, limits: &LoadLimits, canonical: String) -> Result<LoadedFile, MdLoadError> {
+    // Compressed files are recognised by their content, not their name
+    let compression = Compression::detect(&bytes);
+    let bytes = match compression {
+        Some(compression) => decompress(compression, &bytes, limits.max_file_size, &canonical)?,
+        None => bytes,
+    };
+    
+    if looks_binary(&bytes) {
+        return Err(MdLoadError::BinaryFile(canonical));
+    }
     
     // Validate UTF-8 encoding
-    String::from_utf8(bytes).map_err(|_| {
-        MdLoadError::InvalidUtf8(path_ref.display().to_string())
-    })
+    let content = String::from_utf8(bytes).map_err(|_| {
+        MdLoadError::InvalidUtf8(canonical)
+    })?;
+    Ok(LoadedFile { content, compression })
+}
+
+/// Decompresses a file's content.
+///
+/// # Arguments
+///
+/// * `compression` - The format of the data
+/// * `data` - The compressed data
+/// * `limit` - The largest decompressed size allowed, 0 for no limit
+/// * `path` - Canonical path of the file, for errors
+///
+/// # Returns
+///
+/// * `Result<Vec<u8>, MdLoadError>` - The decompressed data or an error
+fn decompress(
+    compression: Compression,
+    data: &[u8],
+    limit: u64,
+    path: &str,
+) -> Result<Vec<u8>, MdLoadError> {
+    let bomb_limit = (data.len() as u64)
+        .saturating_mul(MAX_COMPRESSION_RATIO)
+        .max(MIN_BOMB_SIZE);
+    let mut output = LimitedWriter {
+        buffer: Vec::new(),
+        limit: if limit > 0 { limit.min(bomb_limit) } else { bomb_limit },
+        exceeded: false,
+    };
+    
+    let mut input = data;
+    let result = match compression {
+        Compression::Gzip => {
+            io::copy(&mut flate2::read::MultiGzDecoder::new(input), &mut output).map(drop)
+        }
+        Compression::Bzip2 => {
+            io::copy(&mut bzip2::read::MultiBzDecoder::new(input), &mut output).map(drop)
+        }
+        Compression::Zstd => ruzstd::decoding::StreamingDecoder::new(input)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))
+            .and_then(|mut decoder| io::copy(&mut decoder, &mut output))
+            .map(drop),
+        Compression::Xz => lzma_rs::xz_decompress(&mut input, &mut output)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string())),
+    };
+    
+    if output.exceeded {
+        return Err(if limit > 0 && limit <= bomb_limit {
+            MdLoadError::TooLargeDecompressed {
+                path: path.to_string(),
+                limit,
+            }
+        } else {
+            MdLoadError::DecompressionBomb(path.to_string())
+        });
+    }
+    result.map_err(|e| MdLoadError::Decompression {
+        path: path.to_string(),
+        message: e.to_string(),
+    })?;
+    Ok(output.buffer)
+}
+
+/// Collects decompressed output, failing once it grows past a limit.
+struct LimitedWriter {
+    buffer: Vec<u8>,
+    limit: u64,
+    exceeded: bool,
+}
+
+impl io::Write for LimitedWriter {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        if self.buffer.len() as u64 + data.len() as u64 > self.limit {
+            self.exceeded = true;
+            return Err(io::Error::other("decompressed size limit exceeded"));
+        }
+        self.buffer.extend_from_slice(data);
+        Ok(data.len())
+    }
+    
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Returns true if data starts with a bzip2 stream header.
+///
+/// The `BZh` signature alone could start a text file, so the block size
+/// digit and the magic number of the first block (or of the end of an empty
+/// stream) must follow.
+fn is_bzip2(bytes: &[u8]) -> bool {
+    const BLOCK_MAGIC: [u8; 6] = [0x31, 0x41, 0x59, 0x26, 0x53, 0x59];
+    const END_MAGIC: [u8; 6] = [0x17, 0x72, 0x45, 0x38, 0x50, 0x90];
+    
+    bytes.len() >= 10
+        && bytes.starts_with(b"BZh")
+        && (b'1'..=b'9').contains(&bytes[3])
+        && (bytes[4..10] == BLOCK_MAGIC || bytes[4..10] == END_MAGIC)
+}
+
+/// Opens a file for reading.
+///
+/// On Unix the file is opened non-blocking, so opening a FIFO returns
+/// instead of waiting for a writer; reads from regular files are unaffected.
+fn open_file(path: &Path) -> io::Result<fs::File> {
+    let mut options = fs::OpenOptions::new();
+    options.read(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.custom_flags(libc::O_NONBLOCK);
+    }
+    options.open(path)
+}
+
+/// Classifies a failure to open a file.
+fn open_error(path: &Path, error: io::Error) -> MdLoadError {
+    let canonical = canonical_path(path).display().to_string();
+    match error.kind() {
+        // A link that exists itself points nowhere
+        io::ErrorKind::NotFound if path.symlink_metadata().is_ok() => {
+            MdLoadError::BrokenSymlink(canonical)
+        }
+        io::ErrorKind::NotFound => MdLoadError::NotFound(canonical),
+        // Windows refuses to open directories as files
+        io::ErrorKind::PermissionDenied if path.is_dir() => MdLoadError::IsDirectory(canonical),
+        io::ErrorKind::PermissionDenied => MdLoadError::PermissionDenied(canonical),
+        io::ErrorKind::IsADirectory => MdLoadError::IsDirectory(canonical),
+        _ => MdLoadError::IoError(error),
+    }
+}
+
+/// Resolves a path for error messages; for a path that doesn't resolve, the
+/// folder it is in is resolved instead.
+fn canonical_path(path: &Path) -> PathBuf {
+    fs::canonicalize(path)
+        .ok()
+        .or_else(|| {
+            let parent = fs::canonicalize(path.parent()?).ok()?;
+            Some(parent.join(path.file_name()?))
+        })
+        .unwrap_or_else(|| path.to_path_buf())
+}
+
+/// Returns true if content contains NUL bytes or its start is mostly control characters.
+fn looks_binary(bytes: &[u8]) -> bool {
+    if bytes.contains(&0) {
+        return true;
+    }
+    
+    let sample = &bytes[..bytes.len().min(BINARY_SAMPLE_SIZE)];
+    if sample.is_empty() {
+        return false;
+    }
+    let control = sample
+        .iter()
+        .filter(|&&byte| {
+            (byte < 0x20 && !matches!(byte, b'\t' | b'\n' | b'\r' | 0x0c)) || byte == 0x7f
+        })
+        .count();
+    control as f64 / sample.len() as f64 > MAX_CONTROL_RATIO
 }
 
 #[cfg(test)]
@@ -81,8 +589,8 @@ mod tests {
         assert!(result.is_err());
         
         match result {
-            Err(MdLoadError::FileNotFound(_)) => {},
-            _ => panic!("Expected FileNotFound error"),
+            Err(MdLoadError::NotFound(path)) => assert_eq!(path, "/nonexistent/file.md"),
+            _ => panic!("Expected NotFound error"),
         }
     }
 
@@ -109,4 +617,274 @@ mod tests {
         assert!(result.is_ok());
         assert_eq!(result.unwrap(), "");
     }
+
+    #[test]
+    fn test_load_with_progress() {
+        let mut temp_file = NamedTempFile::new().unwrap();
+        let line = "Some text for a large document.\n";
+        let content = line.repeat(PROGRESS_THRESHOLD as usize / line.len() + 1);
+        temp_file.write_all(content.as_bytes()).unwrap();
+        
+        let mut reports = Vec::new();
+        let loaded = load_markdown_file_with_progress(temp_file.path(), &LoadLimits::default(), |read, total| {
+            reports.push((read, total));
+            true
+        }).unwrap();
+        assert_eq!(loaded.content, content);
+        assert_eq!(loaded.compression, None);
+        assert!(reports.len() > 1);
+        assert_eq!(reports.last(), Some(&(content.len() as u64, content.len() as u64)));
+        
+        // Returning false cancels after the first chunk
+        let mut calls = 0;
+        let result = load_markdown_file_with_progress(temp_file.path(), &LoadLimits::default(), |_, _| {
+            calls += 1;
+            false
+        });
+        assert!(matches!(result, Err(MdLoadError::Cancelled(_))));
+        assert_eq!(calls, 1);
+        
+        // Small files don't report progress
+        let mut small_file = NamedTempFile::new().unwrap();
+        write!(small_file, "# Small").unwrap();
+        let loaded = load_markdown_file_with_progress(small_file.path(), &LoadLimits::default(), |_, _| {
+            panic!("Small files shouldn't report progress")
+        }).unwrap();
+        assert_eq!(loaded.content, "# Small");
+    }
+    
+    #[test]
+    fn test_load_over_size_limit() {
+        let mut temp_file = NamedTempFile::new().unwrap();
+        write!(temp_file, "{}", "# Heading\n".repeat(100)).unwrap();
+        
+        let limits = LoadLimits { max_file_size: 100 };
+        let result = load_markdown_file_with_progress(temp_file.path(), &limits, |_, _| true);
+        match result {
+            Err(MdLoadError::TooLarge { size, limit, .. }) => {
+                assert_eq!(size, 1000);
+                assert_eq!(limit, 100);
+            }
+            _ => panic!("Expected TooLarge error"),
+        }
+        
+        // Once confirmed, the file loads without a limit
+        let loaded = load_markdown_file_with_progress(temp_file.path(), &LoadLimits::unlimited(), |_, _| true);
+        assert!(loaded.is_ok());
+    }
+    
+    #[test]
+    fn test_load_binary_file() {
+        // NUL bytes are enough, even in otherwise valid UTF-8
+        let mut temp_file = NamedTempFile::new().unwrap();
+        temp_file.write_all(b"# Title\n\0\0\0\0text").unwrap();
+        assert!(matches!(load_markdown_file(temp_file.path()), Err(MdLoadError::BinaryFile(_))));
+        
+        // So is a high share of control characters
+        let mut temp_file = NamedTempFile::new().unwrap();
+        temp_file.write_all(&b"ab\x01\x02\x03\x1b".repeat(100)).unwrap();
+        assert!(matches!(load_markdown_file(temp_file.path()), Err(MdLoadError::BinaryFile(_))));
+        
+        // Tabs, line breaks, form feeds and the odd escape sequence are fine
+        let mut temp_file = NamedTempFile::new().unwrap();
+        write!(temp_file, "# Log\n\n\tindented\r\n\x0c\n\x1b[1mbold\x1b[0m {}\n", "text ".repeat(20)).unwrap();
+        assert!(load_markdown_file(temp_file.path()).is_ok());
+    }
+    
+    #[test]
+    fn test_load_directory_is_refused() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let result = load_markdown_file(temp_dir.path());
+        assert!(matches!(result, Err(MdLoadError::IsDirectory(_))));
+        assert_eq!(result.unwrap_err().kind(), LoadErrorKind::IsDirectory);
+    }
+    
+    #[test]
+    fn test_errors_carry_canonical_path() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let canonical_dir = fs::canonicalize(temp_dir.path()).unwrap();
+        fs::create_dir(temp_dir.path().join("sub")).unwrap();
+        
+        let missing = temp_dir.path().join("sub/../missing.md");
+        let error = load_markdown_file(&missing).unwrap_err();
+        assert_eq!(error.kind(), LoadErrorKind::NotFound);
+        assert_eq!(error.path(), Some(canonical_dir.join("missing.md").to_str().unwrap()));
+        
+        let binary = temp_dir.path().join("sub/../binary.md");
+        fs::write(&binary, b"\0\0\0").unwrap();
+        let error = load_markdown_file(&binary).unwrap_err();
+        assert_eq!(error.path(), Some(canonical_dir.join("binary.md").to_str().unwrap()));
+    }
+    
+    #[cfg(unix)]
+    #[test]
+    fn test_load_broken_symlink() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let link = temp_dir.path().join("link.md");
+        std::os::unix::fs::symlink(temp_dir.path().join("gone.md"), &link).unwrap();
+        
+        assert!(matches!(load_markdown_file(&link), Err(MdLoadError::BrokenSymlink(_))));
+    }
+    
+    #[cfg(unix)]
+    #[test]
+    fn test_load_permission_denied() {
+        use std::os::unix::fs::PermissionsExt;
+        
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("secret.md");
+        fs::write(&path, "# Secret").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o000)).unwrap();
+        
+        // Root can read the file regardless
+        if fs::File::open(&path).is_ok() {
+            return;
+        }
+        assert!(matches!(load_markdown_file(&path), Err(MdLoadError::PermissionDenied(_))));
+    }
+    
+    #[cfg(unix)]
+    #[test]
+    fn test_load_fifo_is_refused() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let fifo = temp_dir.path().join("notes.md");
+        let status = std::process::Command::new("mkfifo").arg(&fifo).status().unwrap();
+        assert!(status.success());
+        
+        // Returns instead of blocking on open
+        assert!(matches!(load_markdown_file(&fifo), Err(MdLoadError::NotAFile(_))));
+    }
+    
+    /// Compresses data in each supported format.
+    ///
+    /// The xz encoder of `lzma-rs` only emits literals, so its output is no
+    /// smaller than the input; tests that rely on compression use `compress_shrinking`.
+    fn compress_all(data: &[u8]) -> Vec<(Compression, Vec<u8>)> {
+        let mut gzip = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        gzip.write_all(data).unwrap();
+        let mut bzip2 = bzip2::write::BzEncoder::new(Vec::new(), bzip2::Compression::default());
+        bzip2.write_all(data).unwrap();
+        let mut xz = Vec::new();
+        lzma_rs::xz_compress(&mut &data[..], &mut xz).unwrap();
+        let zstd = ruzstd::encoding::compress_to_vec(data, ruzstd::encoding::CompressionLevel::Fastest);
+        
+        vec![
+            (Compression::Gzip, gzip.finish().unwrap()),
+            (Compression::Bzip2, bzip2.finish().unwrap()),
+            (Compression::Xz, xz),
+            (Compression::Zstd, zstd),
+        ]
+    }
+    
+    /// Compresses data in each format whose test encoder actually shrinks it.
+    fn compress_shrinking(data: &[u8]) -> Vec<(Compression, Vec<u8>)> {
+        compress_all(data)
+            .into_iter()
+            .filter(|(compression, _)| *compression != Compression::Xz)
+            .collect()
+    }
+    
+    #[test]
+    fn test_load_compressed_files() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let content = "# Release Notes\n\n- Fixed a bug\n".repeat(20);
+        
+        for (compression, data) in compress_all(content.as_bytes()) {
+            assert_eq!(Compression::detect(&data), Some(compression));
+            
+            let path = temp_dir.path().join(format!("notes-{:?}.md.gz", compression));
+            fs::write(&path, &data).unwrap();
+            let loaded = load_markdown_file_with_progress(&path, &LoadLimits::default(), |_, _| true).unwrap();
+            assert_eq!(loaded.content, content, "{:?}", compression);
+            assert_eq!(loaded.compression, Some(compression));
+        }
+        
+        // Text that happens to start with a signature isn't mistaken for bzip2
+        assert_eq!(Compression::detect(b"BZh9 is not a header"), None);
+    }
+    
+    #[test]
+    fn test_compressed_size_limits() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let content = "# Notes\n\nSome text that compresses well.\n".repeat(200);
+        
+        for (compression, data) in compress_shrinking(content.as_bytes()) {
+            let path = temp_dir.path().join(format!("notes-{:?}.md.zst", compression));
+            fs::write(&path, &data).unwrap();
+            
+            // The limit applies to the decompressed size
+            let limits = LoadLimits { max_file_size: content.len() as u64 - 1 };
+            let result = load_markdown_file_with_progress(&path, &limits, |_, _| true);
+            assert!(matches!(result, Err(MdLoadError::TooLargeDecompressed { .. })), "{:?}", compression);
+            
+            let limits = LoadLimits { max_file_size: content.len() as u64 };
+            assert!(load_markdown_file_with_progress(&path, &limits, |_, _| true).is_ok());
+        }
+    }
+    
+    #[test]
+    fn test_decompression_bomb() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let bomb = vec![b'a'; MIN_BOMB_SIZE as usize + 1];
+        
+        for (compression, data) in compress_shrinking(&bomb) {
+            assert!((data.len() as u64) * MAX_COMPRESSION_RATIO < bomb.len() as u64);
+            let path = temp_dir.path().join(format!("bomb-{:?}.md", compression));
+            fs::write(&path, &data).unwrap();
+            
+            // Not even confirming the size lets it through
+            let result = load_markdown_file_with_progress(&path, &LoadLimits::unlimited(), |_, _| true);
+            assert!(matches!(result, Err(MdLoadError::DecompressionBomb(_))), "{:?}", compression);
+        }
+    }
+    
+    #[test]
+    fn test_load_from_archive() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let archive = temp_dir.path().canonicalize().unwrap().join("bundle.zip");
+        let mut zip = zip::ZipWriter::new(fs::File::create(&archive).unwrap());
+        let options = zip::write::SimpleFileOptions::default();
+        zip.start_file("docs/index.md", options).unwrap();
+        zip.write_all(b"# Bundle\n").unwrap();
+        zip.start_file("docs/logo.md", options).unwrap();
+        zip.write_all(b"\x89PNG\0\0").unwrap();
+        zip.finish().unwrap();
+        let path = |entry: &str| format!("{}!/{}", archive.display(), entry);
+        
+        assert_eq!(load_markdown_file(path("docs/index.md")).unwrap(), "# Bundle\n");
+        assert_eq!(load_markdown_file(path("docs/../docs/index.md")).unwrap(), "# Bundle\n");
+        
+        let missing = load_markdown_file(path("docs/missing.md")).unwrap_err();
+        assert!(matches!(&missing, MdLoadError::NotFound(p) if *p == path("docs/missing.md")));
+        assert!(matches!(
+            load_markdown_file(path("docs/logo.md")),
+            Err(MdLoadError::BinaryFile(_))
+        ));
+        let limits = LoadLimits { max_file_size: 4 };
+        assert!(matches!(
+            load_markdown_file_with_progress(path("docs/index.md"), &limits, |_, _| true),
+            Err(MdLoadError::TooLarge { size: 9, limit: 4, .. })
+        ));
+        
+        fs::write(&archive, "not a zip").unwrap();
+        assert!(matches!(
+            load_markdown_file(path("docs/index.md")),
+            Err(MdLoadError::Decompression { .. })
+        ));
+        fs::remove_file(&archive).unwrap();
+        assert!(matches!(
+            load_markdown_file(path("docs/index.md")),
+            Err(MdLoadError::NotFound(_))
+        ));
+    }
+    
+    #[test]
+    fn test_corrupt_compressed_file() {
+        let mut temp_file = NamedTempFile::new().unwrap();
+        temp_file.write_all(&[0x1f, 0x8b, 0x08, 0x00, 0x01, 0x02]).unwrap();
+        
+        let error = load_markdown_file(temp_file.path()).unwrap_err();
+        assert!(matches!(error, MdLoadError::Decompression { .. }));
+        assert_eq!(error.kind(), LoadErrorKind::Decompression);
+    }
 }
\ No newline at end of file
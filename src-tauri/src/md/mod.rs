@@ -1,6 +1,22 @@
+pub mod admonitions;
+pub mod archive;
+pub mod chunks;
+pub mod extensions;
+pub mod frontmatter;
+pub mod graph;
+pub mod links;
 pub mod loader;
 pub mod parser;
+pub mod project;
+pub mod remote;
+pub mod sanitize;
+pub mod slides;
+pub mod stats;
+pub mod tasks;
 pub mod toc;
+pub mod trust;
+pub mod wikilinks;
+pub mod workspace;
 
 use serde::{Deserialize, Serialize};
 
@@ -15,6 +31,34 @@ pub struct MarkdownDocument {
     pub html_content: String,
     /// The extracted table of contents
     pub toc: Vec<TocItem>,
+    /// Word count, reading time and content counts
+    #[serde(default)]
+    pub stats: stats::DocumentStats,
+    /// The extension profile the document was rendered with
+    #[serde(default)]
+    pub profile: extensions::Profile,
+    /// Contents of the project stylesheet from `.mdview.toml`, if any
+    #[serde(default)]
+    pub custom_css: Option<String>,
+    /// Directory that links starting with `/` resolve against, if the project sets one
+    /// or the document is inside an archive
+    #[serde(default)]
+    pub link_root: Option<String>,
+    /// Whether the document was rendered in trusted or restricted mode
+    #[serde(default)]
+    pub trust: trust::TrustLevel,
+    /// How the file is compressed, if it is; `path` stays the compressed file
+    #[serde(default)]
+    pub compression: Option<loader::Compression>,
+    /// The archive the document was read from, if any; `path` is then `archive!/entry`
+    #[serde(default)]
+    pub archive: Option<String>,
+    /// Where the top-level blocks of a document large enough to be chunked end
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub blocks: Vec<chunks::BlockSpan>,
+    /// Chunks of a very large document, sent in place of `html_content`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chunked: Option<chunks::ChunkedContent>,
 }
 
 impl MarkdownDocument {
@@ -25,6 +69,15 @@ impl MarkdownDocument {
             raw_content,
             html_content,
             toc,
+            stats: stats::DocumentStats::default(),
+            profile: extensions::Profile::default(),
+            custom_css: None,
+            link_root: None,
+            trust: trust::TrustLevel::default(),
+            compression: None,
+            archive: None,
+            blocks: Vec::new(),
+            chunked: None,
         }
     }
 
@@ -35,10 +88,19 @@ impl MarkdownDocument {
             raw_content: String::new(),
             html_content: String::new(),
             toc: Vec::new(),
+            stats: stats::DocumentStats::default(),
+            profile: extensions::Profile::default(),
+            custom_css: None,
+            link_root: None,
+            trust: trust::TrustLevel::default(),
+            compression: None,
+            archive: None,
+            blocks: Vec::new(),
+            chunked: None,
         }
     }
 
-    /// Loads and parses a Markdown file from the filesystem.
+    /// Loads and parses a Markdown file from the filesystem, in restricted mode.
     /// 
     /// # Arguments
     /// 
@@ -58,12 +120,121 @@ impl MarkdownDocument {
     /// # Ok::<(), Box<dyn std::error::Error>>(())
     /// ```
     pub fn from_file<P: AsRef<std::path::Path>>(path: P) -> Result<Self, loader::MdLoadError> {
+        Self::from_file_with_config(path, &parser::RenderConfig::default())
+    }
+
+    /// Loads and parses a Markdown file using the given rendering configuration.
+    ///
+    /// The document is restricted; trusted rendering is opted into with
+    /// `from_file_with_trust`.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The file path to load
+    /// * `config` - The user's rendering configuration
+    ///
+    /// # Returns
+    ///
+    /// * `Result<MarkdownDocument, loader::MdLoadError>` - The parsed document or an error
+    pub fn from_file_with_config<P: AsRef<std::path::Path>>(
+        path: P,
+        config: &parser::RenderConfig,
+    ) -> Result<Self, loader::MdLoadError> {
+        Self::from_file_with_trust(path, config, trust::TrustLevel::Restricted)
+    }
+
+    /// Loads and parses a Markdown file at a given trust level.
+    ///
+    /// Restricted documents are rendered with the restricted configuration
+    /// (see `RenderConfig::restricted`) and without their project
+    /// configuration, whose stylesheet could load remote resources.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The file path to load
+    /// * `config` - The user's rendering configuration
+    /// * `trust` - Whether the document is trusted
+    ///
+    /// # Returns
+    ///
+    /// * `Result<MarkdownDocument, loader::MdLoadError>` - The parsed document or an error
+    pub fn from_file_with_trust<P: AsRef<std::path::Path>>(
+        path: P,
+        config: &parser::RenderConfig,
+        trust: trust::TrustLevel,
+    ) -> Result<Self, loader::MdLoadError> {
+        let limits = loader::LoadLimits::default();
+        let loaded = loader::load_markdown_file_with_progress(&path, &limits, |_, _| true)?;
+        Ok(Self {
+            compression: loaded.compression,
+            ..Self::from_content_with_trust(path, loaded.content, config, trust)
+        })
+    }
+
+    /// Renders Markdown content already read from a file at a given trust level.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The file the content was read from
+    /// * `raw_content` - The Markdown content
+    /// * `config` - The user's rendering configuration
+    /// * `trust` - Whether the document is trusted
+    ///
+    /// # Returns
+    ///
+    /// * `MarkdownDocument` - The parsed document
+    pub fn from_content_with_trust<P: AsRef<std::path::Path>>(
+        path: P,
+        raw_content: String,
+        config: &parser::RenderConfig,
+        trust: trust::TrustLevel,
+    ) -> Self {
         let path_str = path.as_ref().display().to_string();
-        let raw_content = loader::load_markdown_file(&path)?;
-        let html_content = parser::markdown_to_html(&raw_content);
-        let toc = toc::extract_toc(&raw_content);
+        let context = parser::RenderContext::for_document_with_trust(&path, config, trust);
+        let blocks = parser::render_blocks(&raw_content, &context);
+        let html_content: String = blocks.iter().map(|block| block.html.as_str()).collect();
+        // Only a document that will be chunked keeps its blocks
+        let blocks = if html_content.len() >= chunks::CHUNKED_THRESHOLD {
+            chunks::block_spans(&blocks)
+        } else {
+            Vec::new()
+        };
+        let extensions = context.extensions();
+        let (toc, stats) = stats::analyze(&raw_content, &extensions.options());
+        let project = context.project.as_ref();
+        let custom_css = project
+            .and_then(project::ProjectConfig::css_path)
+            .and_then(|css| match std::fs::read_to_string(&css) {
+                // Stylesheet resources follow the remote image settings
+                Ok(css) => Some(remote::block_remote_css(
+                    &css,
+                    &context.config.remote_images,
+                    Some(path.as_ref()),
+                )),
+                Err(e) => {
+                    eprintln!("Failed to read stylesheet {}: {}", css.display(), e);
+                    None
+                }
+            });
+        let archived = archive::ArchivePath::from_path(path.as_ref());
+        // Links starting with `/` stay inside the archive
+        let link_root = match &archived {
+            Some(archived) => Some(archived.root()),
+            None => project
+                .and_then(project::ProjectConfig::root_path)
+                .map(|root| root.display().to_string()),
+        };
         
-        Ok(Self::new(path_str, raw_content, html_content, toc))
+        Self {
+            stats,
+            profile: extensions.profile(),
+            custom_css,
+            link_root,
+            trust,
+            archive: archived.map(|archived| archived.archive.display().to_string()),
+            blocks,
+            ..Self::new(path_str, raw_content, html_content, toc)
+        }
     }
 }
 
@@ -78,6 +249,9 @@ pub struct TocItem {
     pub id: String,
     /// The line number in the original Markdown (optional)
     pub line_number: Option<usize>,
+    /// Counts for the section under this heading, including its subsections
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stats: Option<stats::SectionStats>,
 }
 
 impl TocItem {
@@ -88,6 +262,7 @@ impl TocItem {
             text,
             id,
             line_number: None,
+            stats: None,
         }
     }
 
@@ -98,6 +273,7 @@ impl TocItem {
             text,
             id,
             line_number: Some(line_number),
+            stats: None,
         }
     }
 }
@@ -1,4 +1,141 @@
-use comrak::{markdown_to_html as comrak_md_to_html, Options};
+use super::admonitions::{self, AdmonitionStyle};
+use super::archive::{self, ArchivePath};
+use super::extensions::ExtensionConfig;
+use super::project::ProjectConfig;
+use super::remote::{self, tag_length, RemoteImagePolicy};
+use super::sanitize::{SanitizeConfig, SanitizePreset};
+use super::trust::TrustLevel;
+use super::{wikilinks, workspace};
+use comrak::nodes::{AstNode, NodeValue};
+use comrak::{format_html, parse_document, Arena, Options};
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::BTreeMap;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+
+/// User-configurable rendering options, stored with the application settings.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct RenderConfig {
+    /// Custom or overridden alert/admonition kinds, keyed by kind name
+    pub admonitions: BTreeMap<String, AdmonitionStyle>,
+    /// How raw HTML in documents is sanitized
+    pub sanitize: SanitizeConfig,
+    /// Tags task list items with their source line so they can be toggled
+    pub interactive_tasks: bool,
+    /// Syntax extension profile and per-extension overrides
+    pub extensions: ExtensionConfig,
+    /// Which remote images may be loaded; others are replaced with placeholders
+    pub remote_images: RemoteImagePolicy,
+}
+
+impl RenderConfig {
+    /// Returns the configuration for exporting to a file.
+    ///
+    /// Exports never load remote content themselves, so remote images keep
+    /// their URLs and the reader of the exported file decides.
+    pub fn for_export(&self) -> RenderConfig {
+        let mut config = self.clone();
+        config.remote_images.allow_all = true;
+        config
+    }
+
+    /// Returns the configuration for documents in restricted mode.
+    ///
+    /// Raw HTML is dropped, every remote image stays blocked and task
+    /// checkboxes aren't tagged for toggling.
+    pub fn restricted(&self) -> RenderConfig {
+        RenderConfig {
+            sanitize: SanitizeConfig {
+                preset: SanitizePreset::Strict,
+                ..self.sanitize.clone()
+            },
+            interactive_tasks: false,
+            remote_images: RemoteImagePolicy::default(),
+            ..self.clone()
+        }
+    }
+}
+
+/// Context for rendering a document from a particular location.
+///
+/// Features such as wikilinks resolve targets relative to the document and
+/// its workspace; without a context they are rendered as unresolved.
+#[derive(Debug, Clone, Default)]
+pub struct RenderContext {
+    /// Path of the document being rendered
+    pub document_path: Option<PathBuf>,
+    /// Root directory searched when resolving wikilinks; the project's `root` if configured
+    pub workspace_root: Option<PathBuf>,
+    /// User rendering configuration
+    pub config: RenderConfig,
+    /// Project configuration found above the document, which overrides the user's
+    pub project: Option<ProjectConfig>,
+}
+
+impl RenderContext {
+    /// Creates a context for a document, locating its project configuration
+    /// and workspace root.
+    pub fn for_document<P: AsRef<Path>>(path: P) -> Self {
+        let path = path.as_ref();
+        let project = ProjectConfig::discover(path);
+        let workspace_root = project
+            .as_ref()
+            .and_then(ProjectConfig::root_path)
+            .unwrap_or_else(|| workspace::find_root(path));
+        Self {
+            document_path: Some(path.to_path_buf()),
+            workspace_root: Some(workspace_root),
+            config: RenderConfig::default(),
+            project,
+        }
+    }
+
+    /// Creates a context for a document at a given trust level.
+    ///
+    /// A restricted document gets the restricted configuration (see
+    /// `RenderConfig::restricted`) and its project configuration is ignored,
+    /// including the project's `root` and extensions.
+    pub fn for_document_with_trust<P: AsRef<Path>>(
+        path: P,
+        config: &RenderConfig,
+        trust: TrustLevel,
+    ) -> Self {
+        match trust {
+            TrustLevel::Trusted => Self::for_document(path).with_config(config.clone()),
+            TrustLevel::Restricted => {
+                let path = path.as_ref();
+                Self {
+                    document_path: Some(path.to_path_buf()),
+                    workspace_root: Some(workspace::find_root(path)),
+                    config: config.restricted(),
+                    project: None,
+                }
+            }
+        }
+    }
+
+    /// Replaces the rendering configuration.
+    pub fn with_config(mut self, config: RenderConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Returns the effective extension settings, with the project's
+    /// `.mdview.toml` layered over the user's settings.
+    pub fn extensions(&self) -> ExtensionConfig {
+        match &self.project {
+            Some(project) => self.config.extensions.merge(&project.extensions),
+            None => self.config.extensions,
+        }
+    }
+
+    /// Returns the directory containing the document, if known.
+    pub fn document_dir(&self) -> Option<&Path> {
+        self.document_path.as_deref().and_then(Path::parent)
+    }
+}
 
 /// Converts Markdown text to HTML using comrak.
 /// 
@@ -12,8 +149,8 @@ use comrak::{markdown_to_html as comrak_md_to_html, Options};
 /// 
 /// # Features
 /// 
-/// - Enables CommonMark extensions (tables, strikethrough, task lists)
-/// - Uses safe mode to prevent XSS attacks
+/// - Enables the extensions of the default GFM profile (tables, strikethrough, task lists)
+/// - Sanitizes raw HTML through an allowlist to prevent XSS attacks
 /// - Generates heading IDs for anchor links
 /// 
 /// # Examples
@@ -25,29 +162,368 @@ use comrak::{markdown_to_html as comrak_md_to_html, Options};
 /// assert!(html.contains("<h1>"));
 /// ```
 pub fn markdown_to_html(markdown: &str) -> String {
-    let mut options = Options::default();
-    
-    // Enable extensions
-    options.extension.strikethrough = true;
-    options.extension.tagfilter = true;
-    options.extension.table = true;
-    options.extension.autolink = true;
-    options.extension.tasklist = true;
-    options.extension.superscript = false;
-    options.extension.header_ids = Some(String::new()); // Enable heading IDs
-    options.extension.footnotes = true;
-    options.extension.description_lists = true;
-    
-    // Render options
-    options.render.unsafe_ = false; // Safe mode - prevent XSS
-    options.render.escape = false;  // Don't double-escape
-    
-    comrak_md_to_html(markdown, &options)
+    render_markdown(markdown, &RenderContext::default())
+}
+
+/// Converts Markdown text to HTML, resolving links relative to a document.
+///
+/// Images of a document inside an archive are read from the archive and
+/// inlined, since the webview can't load them from there.
+///
+/// # Arguments
+///
+/// * `markdown` - The Markdown source text
+/// * `context` - Where the document lives
+///
+/// # Returns
+///
+/// * `String` - The rendered HTML
+pub fn render_markdown(markdown: &str, context: &RenderContext) -> String {
+    let mut embed_stack = embed_stack(context);
+    let (html, _) = render_tree(markdown, context, &mut embed_stack, false);
+    inline_archive_images(html, context)
+}
+
+/// A run of top-level nodes of a rendered document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenderedBlock {
+    /// The nodes' HTML
+    pub html: String,
+    /// Hash of the nodes' Markdown source
+    pub source_hash: u64,
+}
+
+/// Converts Markdown text to HTML, split between the top-level nodes of the AST.
+///
+/// Each node is its own block, except that raw HTML which leaves elements
+/// open keeps the following nodes in its block, and the footnote definitions
+/// (which comrak renders as one section) form a single block. The blocks
+/// joined together are the output of `render_markdown`.
+///
+/// # Arguments
+///
+/// * `markdown` - The Markdown source text
+/// * `context` - Where the document lives
+///
+/// # Returns
+///
+/// * `Vec<RenderedBlock>` - The blocks, in document order
+pub fn render_blocks(markdown: &str, context: &RenderContext) -> Vec<RenderedBlock> {
+    let mut embed_stack = embed_stack(context);
+    let (html, source_hashes) = render_tree(markdown, context, &mut embed_stack, true);
+    let html = inline_archive_images(html, context);
+
+    let pieces: Vec<&str> = html
+        .split(BLOCK_BOUNDARY)
+        .enumerate()
+        // The boundary paragraph's line break stays behind after the boundary
+        .map(|(index, piece)| match index {
+            0 => piece,
+            _ => piece.strip_prefix('\n').unwrap_or(piece),
+        })
+        .collect();
+    if pieces.len() != source_hashes.len() {
+        // Only if the sanitizer moved a boundary; the document stays whole
+        let mut hasher = DefaultHasher::new();
+        source_hashes.hash(&mut hasher);
+        return vec![RenderedBlock {
+            html: pieces.concat(),
+            source_hash: hasher.finish(),
+        }];
+    }
+    pieces
+        .into_iter()
+        .zip(source_hashes)
+        .map(|(html, source_hash)| RenderedBlock {
+            html: html.to_string(),
+            source_hash,
+        })
+        .collect()
+}
+
+/// Starts the chain of embedded notes with the document itself.
+fn embed_stack(context: &RenderContext) -> Vec<PathBuf> {
+    context
+        .document_path
+        .iter()
+        .map(|path| path.canonicalize().unwrap_or_else(|_| path.clone()))
+        .collect()
+}
+
+/// Inlines the images of a document inside an archive.
+fn inline_archive_images(html: String, context: &RenderContext) -> String {
+    match context.document_path.as_deref().and_then(ArchivePath::from_path) {
+        Some(document) => archive::inline_images(&html, &document),
+        None => html,
+    }
+}
+
+/// Renders Markdown, tracking the chain of embedded notes to stop cycles.
+pub(crate) fn render_nested(
+    markdown: &str,
+    context: &RenderContext,
+    embed_stack: &mut Vec<PathBuf>,
+) -> String {
+    render_tree(markdown, context, embed_stack, false).0
+}
+
+/// Renders Markdown, optionally marking the boundaries between top-level blocks.
+///
+/// # Returns
+///
+/// * `(String, Vec<u64>)` - The HTML, and with `mark_blocks` the source hash
+///   of each block between `BLOCK_BOUNDARY` markers
+fn render_tree(
+    markdown: &str,
+    context: &RenderContext,
+    embed_stack: &mut Vec<PathBuf>,
+    mark_blocks: bool,
+) -> (String, Vec<u64>) {
+    let arena = Arena::new();
+    let mut options = context.extensions().options();
+    options.render.unsafe_ = context.config.sanitize.allows_raw_html();
+    options.render.sourcepos = context.config.interactive_tasks;
+    let markdown =
+        admonitions::convert_mkdocs_admonitions(&HtmlFragments::escape_markers(markdown));
+    let root = parse_document(&arena, &markdown, &options);
+    let mut fragments = HtmlFragments::default();
+
+    // Marked before admonitions turn one node into several
+    let source_hashes = if mark_blocks {
+        mark_block_boundaries(&arena, root, &markdown, options.render.unsafe_, &mut fragments)
+    } else {
+        Vec::new()
+    };
+    wikilinks::process(root, context, &mut fragments, embed_stack);
+    admonitions::process(&arena, root, &context.config.admonitions, &mut fragments);
+
+    let mut html = Vec::new();
+    format_html(root, &options, &mut html).expect("writing to a Vec cannot fail");
+
+    // Fragments are generated by mdview, so they are added after sanitizing
+    let html = context.config.sanitize.clean(&String::from_utf8_lossy(&html));
+    let html = remote::block_remote_images(
+        &fragments.apply(html),
+        &context.config.remote_images,
+        context.document_path.as_deref(),
+    );
+    (html, source_hashes)
+}
+
+/// Puts a `BLOCK_BOUNDARY` fragment between the top-level blocks of a document.
+///
+/// # Arguments
+///
+/// * `arena` - The arena the document was parsed into
+/// * `root` - The document node
+/// * `markdown` - The source the document was parsed from
+/// * `raw_html` - Whether raw HTML is rendered, so it can leave elements open
+/// * `fragments` - Fragments of the document
+///
+/// # Returns
+///
+/// * `Vec<u64>` - Hash of each block's source lines, in order
+fn mark_block_boundaries<'a>(
+    arena: &'a Arena<AstNode<'a>>,
+    root: &'a AstNode<'a>,
+    markdown: &str,
+    raw_html: bool,
+    fragments: &mut HtmlFragments,
+) -> Vec<u64> {
+    let lines: Vec<&str> = markdown.split_inclusive('\n').collect();
+    let nodes: Vec<&AstNode> = root.children().collect();
+    let mut source_hashes = Vec::new();
+    let mut hasher = DefaultHasher::new();
+    let mut open_elements = 0;
+
+    for (index, node) in nodes.iter().enumerate() {
+        let sourcepos = node.data.borrow().sourcepos;
+        let source = lines.get(sourcepos.start.line.saturating_sub(1)..sourcepos.end.line);
+        source.unwrap_or_default().hash(&mut hasher);
+        if raw_html {
+            open_elements = open_raw_elements(node, open_elements);
+        }
+
+        let next = nodes.get(index + 1);
+        let ends_block = match next {
+            Some(next) => open_elements == 0 && !(is_footnote(node) && is_footnote(next)),
+            None => true,
+        };
+        if !ends_block {
+            continue;
+        }
+        source_hashes.push(hasher.finish());
+        hasher = DefaultHasher::new();
+        if next.is_some() {
+            let boundary = fragments.insert(BLOCK_BOUNDARY.to_string());
+            node.insert_after(admonitions::placeholder_paragraph(
+                arena,
+                boundary,
+                sourcepos.end,
+            ));
+        }
+    }
+    source_hashes
+}
+
+/// Returns true for footnote definitions, which comrak renders in one section.
+fn is_footnote<'a>(node: &'a AstNode<'a>) -> bool {
+    matches!(node.data.borrow().value, NodeValue::FootnoteDefinition(_))
+}
+
+/// Elements without an end tag.
+const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track",
+    "wbr",
+];
+
+/// Counts the elements left open after the raw HTML in a node.
+///
+/// # Arguments
+///
+/// * `node` - A top-level node
+/// * `open` - Elements left open before the node
+///
+/// # Returns
+///
+/// * `usize` - Elements left open after the node
+fn open_raw_elements<'a>(node: &'a AstNode<'a>, mut open: usize) -> usize {
+    for descendant in node.descendants() {
+        let value = &descendant.data.borrow().value;
+        let html = match value {
+            NodeValue::HtmlBlock(block) => &block.literal,
+            NodeValue::HtmlInline(literal) => literal,
+            _ => continue,
+        };
+
+        let mut offset = 0;
+        while let Some(found) = html[offset..].find('<') {
+            let start = offset + found;
+            let Some(length) = tag_length(&html[start..]) else {
+                break;
+            };
+            let tag = &html[start..start + length];
+            offset = start + length;
+
+            if tag.starts_with("</") {
+                open = open.saturating_sub(1);
+            } else if tag[1..].starts_with(|c: char| c.is_ascii_alphabetic()) && !is_void(tag) {
+                open += 1;
+            }
+        }
+    }
+    open
+}
+
+/// Returns true for start tags of void elements and self-closing tags.
+fn is_void(tag: &str) -> bool {
+    let name: String = tag[1..]
+        .chars()
+        .take_while(|c| c.is_ascii_alphanumeric())
+        .collect::<String>()
+        .to_ascii_lowercase();
+    tag.ends_with("/>") || VOID_ELEMENTS.contains(&name.as_str())
+}
+
+/// Builds the comrak options used for rendering with the default profile.
+///
+/// Only for walks that have no document settings; link checks, backlinks
+/// and slides use the document's effective extensions instead.
+pub fn render_options() -> Options {
+    ExtensionConfig::default().options()
+}
+
+/// Start and end markers of a fragment placeholder (Unicode private use characters).
+const FRAGMENT_START: char = '\u{E000}';
+const FRAGMENT_END: char = '\u{E001}';
+
+/// Marks the boundaries between top-level blocks in the output of `render_tree`.
+const BLOCK_BOUNDARY: char = '\u{E002}';
+
+/// HTML generated by mdview itself and spliced into comrak's output.
+///
+/// Safe mode drops raw HTML nodes, so features that need custom markup put a
+/// placeholder into the AST as text and the HTML is substituted after formatting.
+#[derive(Debug, Default)]
+pub(crate) struct HtmlFragments {
+    fragments: Vec<String>,
+}
+
+impl HtmlFragments {
+    /// Replaces placeholder markers that occur in the document itself, so
+    /// they can't be mistaken for placeholders.
+    ///
+    /// U+FFFD has the same UTF-8 length as the markers, so source positions
+    /// are unchanged.
+    pub(crate) fn escape_markers(markdown: &str) -> std::borrow::Cow<'_, str> {
+        let markers = [FRAGMENT_START, FRAGMENT_END, BLOCK_BOUNDARY];
+        if markdown.contains(markers) {
+            markdown
+                .replace(markers, "\u{FFFD}")
+                .into()
+        } else {
+            markdown.into()
+        }
+    }
+
+    /// Stores a fragment and returns the placeholder text to put in the AST.
+    pub(crate) fn insert(&mut self, html: String) -> String {
+        self.fragments.push(html);
+        format!("{}{}{}", FRAGMENT_START, self.fragments.len() - 1, FRAGMENT_END)
+    }
+
+    /// Replaces all placeholders in the rendered HTML with their fragments.
+    ///
+    /// A placeholder that makes up a whole paragraph replaces the paragraph,
+    /// so block-level fragments aren't nested inside `<p>`.
+    pub(crate) fn apply(&self, mut html: String) -> String {
+        for (index, fragment) in self.fragments.iter().enumerate() {
+            let placeholder = format!("{}{}{}", FRAGMENT_START, index, FRAGMENT_END);
+            let Some(start) = html.find(&placeholder) else {
+                continue;
+            };
+            let end = start + placeholder.len();
+
+            // The paragraph may carry attributes such as `data-sourcepos`
+            let paragraph_start = html[..start]
+                .rfind("<p")
+                .filter(|&open| {
+                    let tag = &html[open..start];
+                    (tag == "<p>" || tag.starts_with("<p "))
+                        && tag.ends_with('>')
+                        && !tag[1..].contains('<')
+                })
+                .filter(|_| html[end..].starts_with("</p>"));
+
+            match paragraph_start {
+                Some(open) => html.replace_range(open..end + "</p>".len(), fragment),
+                None => html.replace_range(start..end, fragment),
+            }
+        }
+        html
+    }
+}
+
+/// Escapes text for inclusion in HTML content or attribute values.
+pub(crate) fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::md::sanitize::SanitizePreset;
 
     #[test]
     fn test_markdown_to_html_basic() {
@@ -124,4 +600,118 @@ mod tests {
         // Should generate an ID attribute for the heading
         assert!(html.contains("id="));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_interactive_tasks_have_source_lines() {
+        let markdown = "# Runbook\n\n- [ ] Tag\n- [x] Build\n\n> [!NOTE]\n> Careful";
+
+        for sanitize in [SanitizePreset::Strict, SanitizePreset::Github] {
+            let context = RenderContext::default().with_config(RenderConfig {
+                interactive_tasks: true,
+                sanitize: SanitizeConfig {
+                    preset: sanitize,
+                    ..SanitizeConfig::default()
+                },
+                ..RenderConfig::default()
+            });
+            let html = render_markdown(markdown, &context);
+
+            assert!(html.contains("<li data-sourcepos=\"3:1-3:9\"><input type=\"checkbox\""));
+            assert!(html.contains("<li data-sourcepos=\"4:1-5:0\">"));
+            // Fragment paragraphs are still replaced whole
+            assert!(html.contains("<div class=\"admonition admonition-note\""));
+            assert!(!html.contains('\u{E000}'));
+        }
+
+        let html = markdown_to_html(markdown);
+        assert!(!html.contains("data-sourcepos"));
+    }
+
+    #[test]
+    fn test_literal_placeholder_markers() {
+        let markdown = "> [!NOTE]\n> Careful\n\nText \u{E000}0\u{E001} here\n";
+        let html = markdown_to_html(markdown);
+
+        assert_eq!(html.matches("class=\"admonition admonition-note\"").count(), 1);
+        assert!(html.contains("<p>Text \u{FFFD}0\u{FFFD} here</p>"));
+        assert!(!html.contains(['\u{E000}', '\u{E001}']));
+
+        let blocks = render_blocks("One \u{E002} two\n\nThree\n", &RenderContext::default());
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[0].html, "<p>One \u{FFFD} two</p>\n");
+    }
+
+    #[test]
+    fn test_extension_profiles() {
+        use crate::md::extensions::{ExtensionConfig, Profile};
+        use std::fs;
+
+        let markdown = "| a |\n|---|\n| b |\n\n\"Quote\" -- x^2^\n";
+        let render = |extensions: ExtensionConfig| {
+            let context = RenderContext::default().with_config(RenderConfig {
+                extensions,
+                ..RenderConfig::default()
+            });
+            render_markdown(markdown, &context)
+        };
+
+        let gfm = render(ExtensionConfig::default());
+        assert!(gfm.contains("<table>"));
+        // Description lists were always rendered, so the default profile keeps them
+        assert!(markdown_to_html("Term\n\n: Definition\n").contains("<dl>"));
+        assert!(gfm.contains("\"Quote\" -- x^2^"));
+
+        let extended = render(ExtensionConfig {
+            profile: Some(Profile::Extended),
+            ..ExtensionConfig::default()
+        });
+        assert!(extended.contains("“Quote” – x<sup>2</sup>"));
+
+        let commonmark = render(ExtensionConfig {
+            profile: Some(Profile::CommonMark),
+            ..ExtensionConfig::default()
+        });
+        assert!(!commonmark.contains("<table>"));
+
+        // A project file overrides the user's settings
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("doc.md");
+        fs::write(&path, markdown).unwrap();
+        fs::write(
+            temp_dir.path().join(".mdview.toml"),
+            "[extensions]\nprofile = \"commonmark\"\n",
+        )
+        .unwrap();
+        let context = RenderContext::for_document(&path).with_config(RenderConfig {
+            extensions: ExtensionConfig {
+                profile: Some(Profile::Extended),
+                table: Some(true),
+                ..ExtensionConfig::default()
+            },
+            ..RenderConfig::default()
+        });
+        assert_eq!(context.extensions().profile(), Profile::CommonMark);
+        let html = render_markdown(markdown, &context);
+        assert!(html.contains("<table>"));
+        assert!(!html.contains("<sup>"));
+
+        let document = crate::md::MarkdownDocument::from_file_with_trust(
+            &path,
+            &RenderConfig::default(),
+            TrustLevel::Trusted,
+        )
+        .unwrap();
+        assert_eq!(document.profile, Profile::CommonMark);
+
+        // Until the folder is trusted the project file is ignored
+        let document = crate::md::MarkdownDocument::from_file(&path).unwrap();
+        assert_eq!(document.profile, Profile::Gfm);
+        let context = RenderContext::for_document_with_trust(
+            &path,
+            &RenderConfig::default(),
+            TrustLevel::Restricted,
+        );
+        assert!(context.project.is_none());
+        assert_eq!(context.extensions().profile(), Profile::Gfm);
+    }
+}
@@ -0,0 +1,658 @@
+use super::archive::ArchivePath;
+use super::extensions::ExtensionConfig;
+use super::trust::{self, TrustLevel};
+use super::workspace;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Names of project configuration files, in order of preference within a directory.
+pub const PROJECT_FILE_NAMES: &[&str] = &[".mdview.toml", "mdview.json"];
+
+/// Project-local settings read from a `.mdview.toml` or `mdview.json` file.
+///
+/// Values set here take precedence over the user's settings for every
+/// document below the directory containing the file. Relative paths are
+/// resolved against that directory.
+#[derive(Debug, Clone, Default, Deserialize, PartialEq, Eq, Hash)]
+#[serde(default)]
+pub struct ProjectConfig {
+    /// Path of the file the configuration was read from
+    #[serde(skip)]
+    pub path: PathBuf,
+    /// Syntax extension profile and overrides
+    pub extensions: ExtensionConfig,
+    /// Stylesheet applied on top of the built-in styles; must be inside the project directory
+    pub css: Option<PathBuf>,
+    /// Directory that links starting with `/` resolve against; also bounds wikilink lookups.
+    /// Must be the project directory or below it
+    pub root: Option<PathBuf>,
+    /// Gitignore-style patterns for files and folders left out of workspace scans
+    pub ignore: Vec<String>,
+    /// Name of the theme to use for documents in the project
+    pub theme: Option<String>,
+    /// Directory with additional theme stylesheets
+    pub themes: Option<PathBuf>,
+}
+
+impl ProjectConfig {
+    /// Finds the project configuration that applies to a document.
+    ///
+    /// Walks up from the document's directory to the repository root (the
+    /// nearest directory containing `.git`) or the filesystem root, and
+    /// returns the first configuration file found. A file that can't be read
+    /// or parsed is reported and skipped, so a typo doesn't stop documents
+    /// from rendering. Documents inside an archive have no project
+    /// configuration.
+    ///
+    /// # Arguments
+    ///
+    /// * `document` - Path of the Markdown document
+    ///
+    /// # Returns
+    ///
+    /// * `Option<ProjectConfig>` - The nearest project configuration, if any
+    pub fn discover<P: AsRef<Path>>(document: P) -> Option<ProjectConfig> {
+        let document = document.as_ref();
+        if ArchivePath::from_path(document).is_some() {
+            return None;
+        }
+        Self::discover_from_dir(document.parent()?)
+    }
+
+    /// Finds the project configuration for a document in a trusted folder.
+    ///
+    /// A project file changes how documents render and which folder links
+    /// resolve against, so it is ignored until the user trusts its folder.
+    ///
+    /// # Arguments
+    ///
+    /// * `document` - Path of the Markdown document
+    /// * `trusted_folders` - Folders the user has trusted
+    ///
+    /// # Returns
+    ///
+    /// * `Option<ProjectConfig>` - The nearest project configuration, if any and trusted
+    pub fn discover_trusted<P: AsRef<Path>>(
+        document: P,
+        trusted_folders: &[PathBuf],
+    ) -> Option<ProjectConfig> {
+        let document = document.as_ref();
+        match trust::trust_level(document, trusted_folders) {
+            TrustLevel::Trusted => Self::discover(document),
+            TrustLevel::Restricted => None,
+        }
+    }
+
+    /// Finds the project configuration that applies to a directory and the files below it.
+    ///
+    /// # Arguments
+    ///
+    /// * `dir` - The directory to start searching from
+    ///
+    /// # Returns
+    ///
+    /// * `Option<ProjectConfig>` - The nearest project configuration, if any
+    pub fn discover_from_dir<P: AsRef<Path>>(dir: P) -> Option<ProjectConfig> {
+        let dir = workspace::canonical_path(dir.as_ref());
+
+        for ancestor in dir.ancestors() {
+            for name in PROJECT_FILE_NAMES {
+                let path = ancestor.join(name);
+                if !path.is_file() {
+                    continue;
+                }
+                match Self::load(&path) {
+                    Ok(config) => return Some(config),
+                    Err(e) => eprintln!("Failed to load {}: {}", path.display(), e),
+                }
+            }
+            if ancestor.join(".git").exists() {
+                break;
+            }
+        }
+        None
+    }
+
+    /// Reads a project configuration file.
+    fn load(path: &Path) -> Result<ProjectConfig, String> {
+        let contents = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let mut config: ProjectConfig = if path.extension().is_some_and(|ext| ext == "json") {
+            serde_json::from_str(&contents).map_err(|e| e.to_string())?
+        } else {
+            toml::from_str(&contents).map_err(|e| e.to_string())?
+        };
+        config.path = path.to_path_buf();
+        Ok(config)
+    }
+
+    /// Returns the directory containing the configuration file.
+    pub fn dir(&self) -> &Path {
+        self.path.parent().unwrap_or_else(|| Path::new(""))
+    }
+
+    /// Returns the configured stylesheet path, resolved against the project directory.
+    ///
+    /// A stylesheet outside the project directory, through an absolute
+    /// path, `..` or a symlink, is ignored.
+    pub fn css_path(&self) -> Option<PathBuf> {
+        self.css.as_ref().and_then(|css| self.contained("css", css))
+    }
+
+    /// Returns the configured root for `/` links, resolved against the project directory.
+    ///
+    /// A root outside the project directory is ignored, so a project file
+    /// can't widen workspace scans beyond its own folder.
+    pub fn root_path(&self) -> Option<PathBuf> {
+        self.root
+            .as_ref()
+            .and_then(|root| self.contained("root", root))
+    }
+
+    /// Resolves a configured path against the project directory, or returns
+    /// None (and reports it) if it points outside that directory.
+    fn contained(&self, key: &str, path: &Path) -> Option<PathBuf> {
+        let dir = workspace::canonical_path(self.dir());
+        let resolved = workspace::canonical_path(dir.join(path));
+        if resolved.starts_with(&dir) {
+            return Some(resolved);
+        }
+        eprintln!(
+            "Ignoring {} in {}: {} is outside the project folder",
+            key,
+            self.path.display(),
+            resolved.display()
+        );
+        None
+    }
+
+    /// Returns the configured themes directory, resolved against the project directory.
+    ///
+    /// A themes directory outside the project directory is ignored, like
+    /// the stylesheet.
+    pub fn themes_path(&self) -> Option<PathBuf> {
+        self.themes
+            .as_ref()
+            .and_then(|themes| self.contained("themes", themes))
+    }
+
+    /// Returns true if a path matches one of the `ignore` patterns.
+    ///
+    /// Patterns without a `/` match a file or folder name anywhere below the
+    /// project directory (`node_modules`, `*.draft.md`); patterns with a `/`
+    /// match the path relative to it (`docs/archive`, `drafts/**`). A
+    /// trailing `/` is ignored, and `**` matches across folders.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The file or folder to test
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - True if the path, or a folder containing it, is ignored
+    pub fn is_ignored<P: AsRef<Path>>(&self, path: P) -> bool {
+        if self.ignore.is_empty() {
+            return false;
+        }
+        let path = workspace::canonical_path(path.as_ref());
+        let relative = match path.strip_prefix(self.dir()) {
+            Ok(relative) => relative,
+            Err(_) => return false,
+        };
+        let components: Vec<String> = relative
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy().into_owned())
+            .collect();
+
+        self.ignore.iter().any(|pattern| {
+            let pattern = pattern.trim().trim_start_matches('/').trim_end_matches('/');
+            if pattern.is_empty() {
+                return false;
+            }
+            if pattern.contains('/') {
+                (1..=components.len())
+                    .any(|count| glob_match(pattern, &components[..count].join("/")))
+            } else {
+                components.iter().any(|name| glob_match(pattern, name))
+            }
+        })
+    }
+}
+
+/// Finds the Markdown files below a directory, leaving out the paths ignored
+/// by the project configuration that applies to it.
+///
+/// # Arguments
+///
+/// * `dir` - The directory to search
+///
+/// # Returns
+///
+/// * `Vec<PathBuf>` - Paths of the Markdown files found, sorted
+pub fn find_markdown_files<P: AsRef<Path>>(dir: P) -> Vec<PathBuf> {
+    let dir = dir.as_ref();
+    let mut files = workspace::find_markdown_files(dir);
+    if let Some(project) = ProjectConfig::discover_from_dir(dir) {
+        files.retain(|file| !project.is_ignored(file));
+    }
+    files
+}
+
+/// Returns the extension settings a document is rendered with: the user's
+/// settings with the nearest project configuration layered on top, if the
+/// document is trusted.
+///
+/// # Arguments
+///
+/// * `document` - Path to a Markdown document
+/// * `user` - The user's extension settings
+/// * `trusted_folders` - Folders the user has trusted
+///
+/// # Returns
+///
+/// * `ExtensionConfig` - The effective extension settings
+pub fn document_extensions<P: AsRef<Path>>(
+    document: P,
+    user: &ExtensionConfig,
+    trusted_folders: &[PathBuf],
+) -> ExtensionConfig {
+    match ProjectConfig::discover_trusted(document, trusted_folders) {
+        Some(project) => user.merge(&project.extensions),
+        None => *user,
+    }
+}
+
+/// Returns the workspace root of a document: the project's `root` if one is
+/// configured and the document is trusted, otherwise the nearest `.git` or
+/// `.obsidian` folder.
+///
+/// # Arguments
+///
+/// * `document` - Path to a Markdown document
+/// * `trusted_folders` - Folders the user has trusted
+///
+/// # Returns
+///
+/// * `PathBuf` - The workspace root directory
+pub fn workspace_root<P: AsRef<Path>>(document: P, trusted_folders: &[PathBuf]) -> PathBuf {
+    let document = document.as_ref();
+    ProjectConfig::discover_trusted(document, trusted_folders)
+        .and_then(|project| project.root_path())
+        .unwrap_or_else(|| workspace::find_root(document))
+}
+
+/// Where an effective configuration value came from.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ConfigSource {
+    /// mdview's built-in default
+    Default,
+    /// The user's `settings.json`
+    Settings,
+    /// A project configuration file
+    Project { path: String },
+}
+
+/// One effective configuration value and its origin.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ConfigEntry {
+    /// Dotted key, e.g. `extensions.table`
+    pub key: String,
+    /// The value in effect
+    pub value: Value,
+    /// Where the value came from
+    pub source: ConfigSource,
+}
+
+/// Lists the effective configuration for a document and where each value came from.
+///
+/// Precedence, from lowest to highest: built-in defaults, the user's
+/// settings, then the nearest project configuration file.
+///
+/// # Arguments
+///
+/// * `user` - Extension settings from the user's `settings.json`
+/// * `user_theme` - Theme chosen by the user's `settings.json`, if any
+/// * `default_theme` - Built-in theme matching the system's light or dark mode
+/// * `project` - The project configuration that applies to the document, if any
+///
+/// # Returns
+///
+/// * `Vec<ConfigEntry>` - One entry per setting
+pub fn explain(
+    user: &ExtensionConfig,
+    user_theme: Option<&str>,
+    default_theme: &str,
+    project: Option<&ProjectConfig>,
+) -> Vec<ConfigEntry> {
+    let project_source = || ConfigSource::Project {
+        path: project
+            .map(|project| project.path.display().to_string())
+            .unwrap_or_default(),
+    };
+    let project_extensions = project
+        .map(|project| project.extensions)
+        .unwrap_or_default();
+    let effective = user.merge(&project_extensions);
+
+    let user_values = to_map(user);
+    let project_values = to_map(&project_extensions);
+    let resolved = to_map(&effective.extensions());
+    let source_of = |key: &str| {
+        if !project_values[key].is_null() {
+            project_source()
+        } else if !user_values[key].is_null() {
+            ConfigSource::Settings
+        } else {
+            ConfigSource::Default
+        }
+    };
+
+    let mut entries = vec![ConfigEntry {
+        key: "extensions.profile".to_string(),
+        value: serde_json::to_value(effective.profile()).unwrap_or_default(),
+        source: source_of("profile"),
+    }];
+    entries.extend(resolved.into_iter().map(|(key, value)| ConfigEntry {
+        key: format!("extensions.{}", key),
+        value,
+        source: source_of(&key),
+    }));
+
+    let project_entry = |key: &str, value: Option<Value>, default: Value| match value {
+        Some(value) => ConfigEntry {
+            key: key.to_string(),
+            value,
+            source: project_source(),
+        },
+        None => ConfigEntry {
+            key: key.to_string(),
+            value: default,
+            source: ConfigSource::Default,
+        },
+    };
+    let path_value =
+        |path: Option<PathBuf>| path.map(|path| Value::from(path.display().to_string()));
+    entries.push(project_entry(
+        "css",
+        path_value(project.and_then(ProjectConfig::css_path)),
+        Value::Null,
+    ));
+    entries.push(project_entry(
+        "root",
+        path_value(project.and_then(ProjectConfig::root_path)),
+        Value::Null,
+    ));
+    entries.push(project_entry(
+        "ignore",
+        project
+            .filter(|project| !project.ignore.is_empty())
+            .map(|project| Value::from(project.ignore.clone())),
+        Value::Array(Vec::new()),
+    ));
+    entries.push(project_entry(
+        "themes",
+        path_value(project.and_then(ProjectConfig::themes_path)),
+        Value::Null,
+    ));
+    entries.push(match user_theme {
+        Some(theme) if project.and_then(|project| project.theme.as_ref()).is_none() => {
+            ConfigEntry {
+                key: "theme".to_string(),
+                value: Value::from(theme),
+                source: ConfigSource::Settings,
+            }
+        }
+        _ => project_entry(
+            "theme",
+            project
+                .and_then(|project| project.theme.clone())
+                .map(Value::from),
+            Value::from(default_theme),
+        ),
+    });
+
+    entries
+}
+
+/// Serializes a struct into its JSON fields.
+fn to_map<T: Serialize>(value: &T) -> serde_json::Map<String, Value> {
+    match serde_json::to_value(value) {
+        Ok(Value::Object(map)) => map,
+        _ => serde_json::Map::new(),
+    }
+}
+
+/// Matches text against a glob pattern with `*`, `**` and `?` wildcards.
+///
+/// `*` and `?` don't match `/`; `**` matches any number of characters including `/`.
+fn glob_match(pattern: &str, text: &str) -> bool {
+    fn matches(pattern: &[u8], text: &[u8]) -> bool {
+        match pattern {
+            [] => text.is_empty(),
+            [b'*', b'*', rest @ ..] => {
+                let rest = rest.strip_prefix(b"/").unwrap_or(rest);
+                (0..=text.len()).any(|skip| matches(rest, &text[skip..]))
+            }
+            [b'*', rest @ ..] => (0..=text.len())
+                .take_while(|&skip| skip == 0 || text[skip - 1] != b'/')
+                .any(|skip| matches(rest, &text[skip..])),
+            [b'?', rest @ ..] => !text.is_empty() && text[0] != b'/' && matches(rest, &text[1..]),
+            [first, rest @ ..] => text.first() == Some(first) && matches(rest, &text[1..]),
+        }
+    }
+    matches(pattern.as_bytes(), text.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::md::extensions::Profile;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_discover_walks_up() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path().canonicalize().unwrap();
+        let nested = root.join("docs/guide");
+        fs::create_dir_all(&nested).unwrap();
+        fs::write(
+            root.join(".mdview.toml"),
+            "css = \"brand.css\"\n\n[extensions]\nprofile = \"commonmark\"\ntable = true\n",
+        )
+        .unwrap();
+
+        let config = ProjectConfig::discover(nested.join("intro.md")).unwrap();
+        assert_eq!(config.path, root.join(".mdview.toml"));
+        assert_eq!(config.extensions.profile, Some(Profile::CommonMark));
+        assert_eq!(config.extensions.table, Some(true));
+        assert_eq!(config.css_path(), Some(root.join("brand.css")));
+
+        // The nearest file wins
+        fs::write(
+            nested.join("mdview.json"),
+            r#"{ "extensions": { "smart": true }, "root": ".", "theme": "high-contrast" }"#,
+        )
+        .unwrap();
+        let config = ProjectConfig::discover(nested.join("intro.md")).unwrap();
+        assert_eq!(config.extensions.profile, None);
+        assert_eq!(config.extensions.smart, Some(true));
+        assert_eq!(config.root_path(), Some(nested.clone()));
+        assert_eq!(config.theme.as_deref(), Some("high-contrast"));
+        assert_eq!(
+            workspace_root(nested.join("intro.md"), std::slice::from_ref(&root)),
+            nested
+        );
+    }
+
+    #[test]
+    fn test_untrusted_project_is_ignored() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path().canonicalize().unwrap();
+        fs::create_dir_all(root.join(".git")).unwrap();
+        fs::create_dir_all(root.join("site")).unwrap();
+        fs::write(
+            root.join(".mdview.toml"),
+            "root = \"site\"\n[extensions]\nprofile = \"commonmark\"\n",
+        )
+        .unwrap();
+        let doc = root.join("site/index.md");
+        let user = ExtensionConfig::default();
+
+        assert_eq!(ProjectConfig::discover_trusted(&doc, &[]), None);
+        assert_eq!(document_extensions(&doc, &user, &[]), user);
+        assert_eq!(workspace_root(&doc, &[]), root);
+
+        let trusted = [root.clone()];
+        assert!(ProjectConfig::discover_trusted(&doc, &trusted).is_some());
+        assert_eq!(
+            document_extensions(&doc, &user, &trusted).profile,
+            Some(Profile::CommonMark)
+        );
+        assert_eq!(workspace_root(&doc, &trusted), root.join("site"));
+    }
+
+    #[test]
+    fn test_paths_stay_inside_project() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path().canonicalize().unwrap();
+        fs::create_dir_all(root.join("project/site")).unwrap();
+        fs::write(root.join("outside.css"), "body {}").unwrap();
+        let project = |css: &str, root_dir: &str| ProjectConfig {
+            path: root.join("project/.mdview.toml"),
+            css: Some(PathBuf::from(css)),
+            root: Some(PathBuf::from(root_dir)),
+            themes: Some(PathBuf::from(root_dir).join("themes")),
+            ..ProjectConfig::default()
+        };
+
+        let config = project("site/brand.css", "site");
+        assert_eq!(config.css_path(), Some(root.join("project/site/brand.css")));
+        assert_eq!(config.root_path(), Some(root.join("project/site")));
+        assert_eq!(config.themes_path(), Some(root.join("project/site/themes")));
+
+        let config = project("../outside.css", "..");
+        assert_eq!(config.css_path(), None);
+        assert_eq!(config.root_path(), None);
+        assert_eq!(config.themes_path(), None);
+
+        let outside = root.join("outside.css").display().to_string();
+        let config = project(&outside, "/");
+        assert_eq!(config.css_path(), None);
+        assert_eq!(config.root_path(), None);
+        assert_eq!(config.themes_path(), None);
+    }
+
+    #[test]
+    fn test_discover_stops_at_git_root() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir_all(root.join("repo/.git")).unwrap();
+        fs::write(root.join(".mdview.toml"), "theme = \"dark\"\n").unwrap();
+
+        assert_eq!(ProjectConfig::discover(root.join("repo/README.md")), None);
+        assert!(ProjectConfig::discover(root.join("notes.md")).is_some());
+    }
+
+    #[test]
+    fn test_invalid_file_is_skipped() {
+        let temp_dir = tempdir().unwrap();
+        fs::write(
+            temp_dir.path().join(".mdview.toml"),
+            "[extensions]\nprofile = \"markdown\"\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            ProjectConfig::discover(temp_dir.path().join("notes.md")),
+            None
+        );
+    }
+
+    #[test]
+    fn test_ignored_paths() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path();
+        for dir in ["docs/archive", "node_modules/pkg", "drafts/2024"] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        for file in [
+            "README.md",
+            "docs/guide.md",
+            "docs/guide.draft.md",
+            "docs/archive/old.md",
+            "node_modules/pkg/readme.md",
+            "drafts/2024/idea.md",
+        ] {
+            fs::write(root.join(file), "# Doc\n").unwrap();
+        }
+        fs::write(
+            root.join(".mdview.toml"),
+            "ignore = [\"node_modules\", \"*.draft.md\", \"docs/archive/\", \"drafts/**\"]\n",
+        )
+        .unwrap();
+
+        let files: Vec<_> = find_markdown_files(root)
+            .into_iter()
+            .map(|file| file.strip_prefix(root).unwrap().to_path_buf())
+            .collect();
+        assert_eq!(
+            files,
+            vec![PathBuf::from("README.md"), PathBuf::from("docs/guide.md")]
+        );
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("*.md", "notes.md"));
+        assert!(!glob_match("*.md", "docs/notes.md"));
+        assert!(glob_match("docs/**/*.md", "docs/a/b/notes.md"));
+        assert!(glob_match("docs/**", "docs/a"));
+        assert!(glob_match("v?", "v1"));
+        assert!(!glob_match("v?", "v10"));
+    }
+
+    #[test]
+    fn test_explain() {
+        let user = ExtensionConfig {
+            profile: Some(Profile::Extended),
+            smart: Some(false),
+            ..ExtensionConfig::default()
+        };
+        let project = ProjectConfig {
+            path: PathBuf::from("/repo/.mdview.toml"),
+            extensions: ExtensionConfig {
+                smart: Some(true),
+                ..ExtensionConfig::default()
+            },
+            theme: Some("brand".to_string()),
+            ..ProjectConfig::default()
+        };
+
+        let entries = explain(&user, Some("dark"), "light", Some(&project));
+        let entry = |key: &str| entries.iter().find(|entry| entry.key == key).unwrap();
+        let from_project = ConfigSource::Project {
+            path: "/repo/.mdview.toml".to_string(),
+        };
+
+        assert_eq!(entry("extensions.profile").value, "extended");
+        assert_eq!(entry("extensions.profile").source, ConfigSource::Settings);
+        assert_eq!(entry("extensions.smart").value, true);
+        assert_eq!(entry("extensions.smart").source, from_project);
+        assert_eq!(entry("extensions.table").source, ConfigSource::Default);
+        assert_eq!(entry("theme").value, "brand");
+        assert_eq!(entry("theme").source, from_project);
+        assert_eq!(entry("css").source, ConfigSource::Default);
+
+        let entries = explain(&user, Some("dark"), "light", None);
+        let theme = entries.iter().find(|entry| entry.key == "theme").unwrap();
+        assert_eq!(theme.value, "dark");
+        assert_eq!(theme.source, ConfigSource::Settings);
+
+        let defaults = explain(&ExtensionConfig::default(), None, "light", None);
+        assert!(defaults
+            .iter()
+            .all(|entry| entry.source == ConfigSource::Default));
+    }
+}
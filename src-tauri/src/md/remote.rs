@@ -0,0 +1,706 @@
+use super::workspace;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Image shown in place of a blocked remote image.
+///
+/// An inline SVG, so showing it doesn't make a request either.
+const PLACEHOLDER_SRC: &str = "data:image/svg+xml,%3Csvg xmlns='http://www.w3.org/2000/svg' width='48' height='36' viewBox='0 0 48 36'%3E%3Crect x='1' y='1' width='46' height='34' rx='3' fill='%23f6f8fa' stroke='%23999' stroke-dasharray='4 3'/%3E%3Cpath d='M10 28l9-10 7 7 5-5 7 8z' fill='%23bbb'/%3E%3Ccircle cx='33' cy='11' r='3' fill='%23bbb'/%3E%3C/svg%3E";
+
+/// Class added to blocked images so the frontend can find and style them.
+pub const BLOCKED_CLASS: &str = "remote-image-blocked";
+
+/// Attribute holding the original URL of a blocked image.
+pub const REMOTE_SRC_ATTRIBUTE: &str = "data-remote-src";
+
+/// Which remote images may be loaded, stored with the rendering settings.
+///
+/// Remote images are blocked by default, because loading them tells the
+/// server that (and from where) the document was opened.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct RemoteImagePolicy {
+    /// Load every remote image without asking
+    pub allow_all: bool,
+    /// Hosts whose images are loaded, including their subdomains
+    pub allowed_domains: Vec<String>,
+    /// Documents whose remote images are all loaded
+    pub allowed_documents: Vec<PathBuf>,
+}
+
+impl RemoteImagePolicy {
+    /// Returns true if a remote image may be loaded in a document.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The image URL
+    /// * `document` - Path of the document showing the image, if known
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - True if the image may be loaded
+    pub fn allows(&self, url: &str, document: Option<&Path>) -> bool {
+        if self.allow_all || document.is_some_and(|document| self.allows_document(document)) {
+            return true;
+        }
+        let Some(host) = host(url) else {
+            return false;
+        };
+        self.allowed_domains.iter().any(|domain| {
+            let domain = domain.trim_start_matches('.').to_ascii_lowercase();
+            host == domain
+                || host
+                    .strip_suffix(domain.as_str())
+                    .is_some_and(|prefix| prefix.ends_with('.'))
+        })
+    }
+
+    /// Returns true if all remote images in a document may be loaded.
+    pub fn allows_document(&self, document: &Path) -> bool {
+        let document = workspace::canonical_path(document);
+        self.allowed_documents
+            .iter()
+            .any(|allowed| workspace::canonical_path(allowed) == document)
+    }
+
+    /// Allows remote images from a host and its subdomains.
+    pub fn allow_domain(&mut self, domain: &str) {
+        let domain = domain.trim().trim_start_matches('.').to_ascii_lowercase();
+        if !domain.is_empty() && !self.allowed_domains.contains(&domain) {
+            self.allowed_domains.push(domain);
+        }
+    }
+
+    /// Allows all remote images in a document.
+    pub fn allow_document(&mut self, document: &Path) {
+        if !self.allows_document(document) {
+            self.allowed_documents
+                .push(workspace::canonical_path(document));
+        }
+    }
+}
+
+/// Returns true if a URL points at a remote server (`http:`, `https:` or
+/// protocol-relative `//`).
+pub fn is_remote(url: &str) -> bool {
+    let url = url.trim_start();
+    let lower = url.get(..8).unwrap_or(url).to_ascii_lowercase();
+    lower.starts_with("http://") || lower.starts_with("https://") || lower.starts_with("//")
+}
+
+/// Returns the lowercase host name of a remote URL.
+///
+/// # Arguments
+///
+/// * `url` - A remote URL
+///
+/// # Returns
+///
+/// * `Option<String>` - The host without user info or port, or None for local URLs
+pub fn host(url: &str) -> Option<String> {
+    if !is_remote(url) {
+        return None;
+    }
+    let (_, rest) = url.trim_start().split_once("//")?;
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    let authority = authority
+        .rsplit_once('@')
+        .map_or(authority, |(_, host)| host);
+    let host = if authority.starts_with('[') {
+        // IPv6 literal, e.g. [::1]:8080
+        authority
+            .split_once(']')
+            .map_or(authority, |(host, _)| host)
+    } else {
+        authority.split(':').next().unwrap_or_default()
+    };
+    let host = host.trim_start_matches('[').to_ascii_lowercase();
+    (!host.is_empty()).then_some(host)
+}
+
+/// Replaces remote images that the policy doesn't allow with placeholders.
+///
+/// The original URL moves to a `data-remote-src` attribute, which browsers
+/// don't load, and `srcset` is dropped. Runs on the final HTML, so images
+/// from raw HTML and embedded notes are covered too.
+///
+/// # Arguments
+///
+/// * `html` - Rendered (and sanitized) HTML
+/// * `policy` - Which remote images may load
+/// * `document` - Path of the document being rendered, if known
+///
+/// # Returns
+///
+/// * `String` - The HTML with blocked images replaced
+pub fn block_remote_images(
+    html: &str,
+    policy: &RemoteImagePolicy,
+    document: Option<&Path>,
+) -> String {
+    if policy.allow_all || !html.contains("<img") {
+        return html.to_string();
+    }
+
+    replace_img_tags(html, |tag| rewrite_img(tag, policy, document))
+}
+
+/// Removes the remote resources the policy doesn't allow from a stylesheet.
+///
+/// Blocked `url()` values become `url()` and blocked quoted URLs, as used
+/// by `@import` and `image-set()`, become `""`, so nothing is fetched.
+/// CSS escapes are decoded before a URL is checked, and any URL with a
+/// scheme other than `data:` counts as remote.
+///
+/// # Arguments
+///
+/// * `css` - The stylesheet
+/// * `policy` - Which remote images may load
+/// * `document` - Path of the document the stylesheet applies to, if known
+///
+/// # Returns
+///
+/// * `String` - The stylesheet with blocked resources removed
+pub fn block_remote_css(css: &str, policy: &RemoteImagePolicy, document: Option<&Path>) -> String {
+    if policy.allow_all {
+        return css.to_string();
+    }
+    let blocked = |url: &str| is_external_css_url(url) && !policy.allows(url, document);
+
+    let chars: Vec<char> = css.chars().collect();
+    let mut output = String::with_capacity(css.len());
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c == '/' && chars.get(i + 1) == Some(&'*') {
+            let end = (i + 2..chars.len().saturating_sub(1))
+                .find(|&j| chars[j] == '*' && chars[j + 1] == '/')
+                .map_or(chars.len(), |j| j + 2);
+            output.extend(&chars[i..end]);
+            i = end;
+        } else if c == '"' || c == '\'' {
+            let (value, end) = read_css_string(&chars, i);
+            if blocked(&value) {
+                output.push_str("\"\"");
+            } else {
+                output.extend(&chars[i..end]);
+            }
+            i = end;
+        } else if is_css_name_char(c) || c == '\\' {
+            let (name, end) = read_css_name(&chars, i);
+            output.extend(&chars[i..end]);
+            i = end;
+            if name.eq_ignore_ascii_case("url") && chars.get(i) == Some(&'(') {
+                output.push('(');
+                i += 1;
+                let start = i;
+                while chars.get(i).is_some_and(|c| c.is_whitespace()) {
+                    i += 1;
+                }
+                if matches!(chars.get(i), Some('"' | '\'')) {
+                    // Quoted, checked as a string on the next iteration
+                    output.extend(&chars[start..i]);
+                    continue;
+                }
+                let (value, end) = read_css_url(&chars, i);
+                if blocked(&value) {
+                    output.push(')');
+                } else {
+                    output.extend(&chars[start..end]);
+                }
+                i = end;
+            }
+        } else {
+            output.push(c);
+            i += 1;
+        }
+    }
+    output
+}
+
+/// Returns true if a URL from a stylesheet may point outside the app.
+///
+/// Stricter than [`is_remote`]: browsers drop tabs and newlines from URLs
+/// and treat `\\` like `/`, so those are normalized first.
+fn is_external_css_url(url: &str) -> bool {
+    let url: String = url
+        .chars()
+        .filter(|c| !matches!(c, '\t' | '\n' | '\r'))
+        .collect::<String>()
+        .trim_start_matches(|c: char| c <= ' ')
+        .replace('\\', "/")
+        .to_ascii_lowercase();
+    if url.starts_with("//") {
+        return true;
+    }
+    match url.split_once(':') {
+        Some((scheme, _)) => {
+            let is_scheme = !scheme.is_empty()
+                && scheme
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'));
+            is_scheme && scheme != "data"
+        }
+        None => false,
+    }
+}
+
+fn is_css_name_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '-' | '_') || !c.is_ascii()
+}
+
+/// Reads a CSS escape after the backslash at `start`, returning the decoded
+/// character and the index after the escape.
+fn read_css_escape(chars: &[char], start: usize) -> (Option<char>, usize) {
+    let mut i = start + 1;
+    let hex_start = i;
+    while i < chars.len() && i - hex_start < 6 && chars[i].is_ascii_hexdigit() {
+        i += 1;
+    }
+    if i > hex_start {
+        let digits: String = chars[hex_start..i].iter().collect();
+        if chars.get(i).is_some_and(|c| c.is_whitespace()) {
+            i += 1;
+        }
+        let decoded = u32::from_str_radix(&digits, 16)
+            .ok()
+            .and_then(char::from_u32)
+            .unwrap_or('\u{FFFD}');
+        return (Some(decoded), i);
+    }
+    match chars.get(i) {
+        // An escaped newline in a string is a line continuation
+        Some('\n') => (None, i + 1),
+        Some(&c) => (Some(c), i + 1),
+        None => (None, i),
+    }
+}
+
+/// Reads an identifier starting at `start`, returning it with escapes
+/// decoded and the index after it.
+fn read_css_name(chars: &[char], start: usize) -> (String, usize) {
+    let mut name = String::new();
+    let mut i = start;
+    while i < chars.len() {
+        if chars[i] == '\\' && chars.get(i + 1).is_some_and(|&c| c != '\n') {
+            let (decoded, end) = read_css_escape(chars, i);
+            name.extend(decoded);
+            i = end;
+        } else if is_css_name_char(chars[i]) {
+            name.push(chars[i]);
+            i += 1;
+        } else {
+            break;
+        }
+    }
+    if i == start {
+        // A lone backslash
+        return (String::from("\\"), i + 1);
+    }
+    (name, i)
+}
+
+/// Reads a quoted string starting at `start`, returning its decoded value
+/// and the index after the closing quote.
+fn read_css_string(chars: &[char], start: usize) -> (String, usize) {
+    let quote = chars[start];
+    let mut value = String::new();
+    let mut i = start + 1;
+    while i < chars.len() {
+        match chars[i] {
+            c if c == quote => return (value, i + 1),
+            // An unescaped newline ends the (invalid) string
+            '\n' => return (value, i),
+            '\\' => {
+                let (decoded, end) = read_css_escape(chars, i);
+                value.extend(decoded);
+                i = end;
+            }
+            c => {
+                value.push(c);
+                i += 1;
+            }
+        }
+    }
+    (value, i)
+}
+
+/// Reads an unquoted `url()` value starting at `start`, returning it with
+/// escapes decoded and the index after the closing parenthesis.
+fn read_css_url(chars: &[char], start: usize) -> (String, usize) {
+    let mut value = String::new();
+    let mut i = start;
+    while i < chars.len() {
+        match chars[i] {
+            ')' => return (value.trim_end().to_string(), i + 1),
+            '\\' => {
+                let (decoded, end) = read_css_escape(chars, i);
+                value.extend(decoded);
+                i = end;
+            }
+            c => {
+                value.push(c);
+                i += 1;
+            }
+        }
+    }
+    (value.trim_end().to_string(), i)
+}
+
+/// Rebuilds the `<img>` tags for which `rewrite` returns new attributes.
+///
+/// `rewrite` gets each tag's attributes as name/value pairs, with values
+/// still HTML-escaped; tags it returns None for are kept as they are.
+pub(super) fn rewrite_img_tags<F>(html: &str, mut rewrite: F) -> String
+where
+    F: FnMut(&[(String, Option<String>)]) -> Option<Vec<(String, Option<String>)>>,
+{
+    if !html.contains("<img") {
+        return html.to_string();
+    }
+
+    replace_img_tags(html, |tag| {
+        let Some(attributes) = rewrite(&parse_attributes(tag)) else {
+            return tag.to_string();
+        };
+        let mut rewritten = String::from("<img");
+        for (name, value) in &attributes {
+            match value {
+                Some(value) => {
+                    rewritten.push_str(&format!(" {}=\"{}\"", name, value.replace('"', "&quot;")))
+                }
+                None => rewritten.push_str(&format!(" {}", name)),
+            }
+        }
+        rewritten.push_str(if tag.ends_with("/>") { " />" } else { ">" });
+        rewritten
+    })
+}
+
+/// Replaces every `<img>` start tag with the result of `replace`.
+fn replace_img_tags<F>(html: &str, mut replace: F) -> String
+where
+    F: FnMut(&str) -> String,
+{
+    let mut output = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(start) = find_img_tag(rest) {
+        let Some(length) = tag_length(&rest[start..]) else {
+            break;
+        };
+        let end = start + length;
+        output.push_str(&rest[..start]);
+        output.push_str(&replace(&rest[start..end]));
+        rest = &rest[end..];
+    }
+    output.push_str(rest);
+    output
+}
+
+/// Finds the next `<img` start tag.
+fn find_img_tag(html: &str) -> Option<usize> {
+    let mut offset = 0;
+    while let Some(found) = html[offset..].find("<img") {
+        let start = offset + found;
+        match html[start + 4..].chars().next() {
+            Some(c) if c.is_ascii_whitespace() || c == '/' || c == '>' => return Some(start),
+            _ => offset = start + 4,
+        }
+    }
+    None
+}
+
+/// Returns the length of the tag at the start of `html`, skipping `>` inside quoted values.
+pub(super) fn tag_length(html: &str) -> Option<usize> {
+    let mut quote = None;
+    for (index, c) in html.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(open), _) if c == open => quote = None,
+            (None, '>') => return Some(index + 1),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Splits the attributes of a start tag into name/value pairs.
+fn parse_attributes(tag: &str) -> Vec<(String, Option<String>)> {
+    let inner = tag
+        .trim_start_matches("<img")
+        .trim_end_matches('>')
+        .trim_end_matches('/');
+    let mut attributes = Vec::new();
+    let mut chars = inner.chars().peekable();
+
+    loop {
+        while chars
+            .next_if(|c| c.is_ascii_whitespace() || *c == '/')
+            .is_some()
+        {}
+        let mut name = String::new();
+        while let Some(c) = chars.next_if(|c| !c.is_ascii_whitespace() && *c != '=') {
+            name.push(c);
+        }
+        if name.is_empty() {
+            break;
+        }
+        while chars.next_if(char::is_ascii_whitespace).is_some() {}
+        if chars.next_if_eq(&'=').is_none() {
+            attributes.push((name, None));
+            continue;
+        }
+        while chars.next_if(char::is_ascii_whitespace).is_some() {}
+        let mut value = String::new();
+        match chars.next_if(|c| *c == '"' || *c == '\'') {
+            Some(quote) => {
+                for c in chars.by_ref() {
+                    if c == quote {
+                        break;
+                    }
+                    value.push(c);
+                }
+            }
+            None => {
+                while let Some(c) = chars.next_if(|c| !c.is_ascii_whitespace()) {
+                    value.push(c);
+                }
+            }
+        }
+        attributes.push((name, Some(value)));
+    }
+
+    attributes
+}
+
+/// Rewrites one `<img>` tag if it loads a blocked remote image.
+fn rewrite_img(tag: &str, policy: &RemoteImagePolicy, document: Option<&Path>) -> String {
+    let attributes = parse_attributes(tag);
+    let value_of = |name: &str| {
+        attributes
+            .iter()
+            .find(|(attribute, _)| attribute.eq_ignore_ascii_case(name))
+            .and_then(|(_, value)| value.as_deref())
+            .map(|value| value.replace("&amp;", "&"))
+    };
+    let blocked = |url: &str| is_remote(url) && !policy.allows(url, document);
+
+    let blocked_src = value_of("src").is_some_and(|src| blocked(&src));
+    let blocked_srcset = value_of("srcset").is_some_and(|srcset| {
+        srcset
+            .split(',')
+            .filter_map(|candidate| candidate.split_whitespace().next())
+            .any(blocked)
+    });
+    if !blocked_src && !blocked_srcset {
+        return tag.to_string();
+    }
+
+    let mut rewritten = String::from("<img");
+    let mut has_class = false;
+    for (name, value) in &attributes {
+        let name_lower = name.to_ascii_lowercase();
+        // Values are re-quoted with double quotes
+        let value = value.as_deref().map(|value| value.replace('"', "&quot;"));
+        match (name_lower.as_str(), value.as_deref()) {
+            ("srcset", _) => continue,
+            ("src", Some(src)) if blocked_src => {
+                rewritten.push_str(&format!(
+                    " src=\"{}\" {}=\"{}\"",
+                    PLACEHOLDER_SRC, REMOTE_SRC_ATTRIBUTE, src
+                ));
+            }
+            ("class", Some(class)) => {
+                has_class = true;
+                rewritten.push_str(&format!(" class=\"{} {}\"", class, BLOCKED_CLASS));
+            }
+            (_, Some(value)) => rewritten.push_str(&format!(" {}=\"{}\"", name, value)),
+            (_, None) => rewritten.push_str(&format!(" {}", name)),
+        }
+    }
+    if !has_class {
+        rewritten.push_str(&format!(" class=\"{}\"", BLOCKED_CLASS));
+    }
+    rewritten.push_str(if tag.ends_with("/>") { " />" } else { ">" });
+    rewritten
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::md::parser::{render_markdown, RenderConfig, RenderContext};
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_host() {
+        assert_eq!(
+            host("https://Example.com/a.png").as_deref(),
+            Some("example.com")
+        );
+        assert_eq!(
+            host("//user@cdn.example.com:8080/x?y").as_deref(),
+            Some("cdn.example.com")
+        );
+        assert_eq!(host("http://[::1]:80/p.gif").as_deref(), Some("::1"));
+        assert_eq!(host("images/local.png"), None);
+        assert_eq!(host("data:image/png;base64,AAAA"), None);
+    }
+
+    #[test]
+    fn test_allowed_domains() {
+        let mut policy = RemoteImagePolicy::default();
+        assert!(!policy.allows("https://example.com/a.png", None));
+
+        policy.allow_domain("Example.com");
+        assert!(policy.allows("https://example.com/a.png", None));
+        assert!(policy.allows("https://img.example.com/a.png", None));
+        assert!(!policy.allows("https://badexample.com/a.png", None));
+        assert!(!policy.allows("https://example.com.evil.net/a.png", None));
+    }
+
+    #[test]
+    fn test_block_remote_images() {
+        let html = concat!(
+            "<p><img src=\"https://tracker.example/p.gif?id=1&amp;u=2\" alt=\"a > b\" /></p>",
+            "<p><img src=\"local.png\" alt=\"local\"></p>",
+            "<p><img class=\"wide\" src=\"images/x.png\" srcset=\"https://cdn.example/x2.png 2x\"></p>",
+        );
+        let blocked = block_remote_images(html, &RemoteImagePolicy::default(), None);
+
+        assert!(!blocked.contains(" src=\"https://"));
+        assert!(!blocked.contains("srcset"));
+        assert!(blocked.contains(
+            "data-remote-src=\"https://tracker.example/p.gif?id=1&amp;u=2\" alt=\"a > b\" class=\"remote-image-blocked\" />"
+        ));
+        assert!(blocked.contains("<img src=\"local.png\" alt=\"local\">"));
+        assert!(blocked.contains("class=\"wide remote-image-blocked\" src=\"images/x.png\">"));
+
+        let mut policy = RemoteImagePolicy::default();
+        policy.allow_domain("tracker.example");
+        let allowed = block_remote_images(html, &policy, None);
+        assert!(allowed.contains(" src=\"https://tracker.example/p.gif"));
+    }
+
+    #[test]
+    fn test_block_remote_css() {
+        let css = concat!(
+            "@import \"https://fonts.example/a.css\";\n",
+            "@import url(//fonts.example/b.css);\n",
+            "body { background: url( 'HTTPS://tracker.example/p.gif' ) }\n",
+            "h1 { background: \\75 rl(https://tracker.example/h.gif) }\n",
+            "h2 { background: url(ht\\74 p://tracker.example/e.gif) }\n",
+            "h3 { background: url(\"https:\\\\tracker.example/s.gif\") }\n",
+            ".logo { background: url(images/logo.png), url(\"data:image/png;base64,AA\") }\n",
+            "/* url(https://tracker.example/comment.gif) */\n",
+        );
+        let blocked = block_remote_css(css, &RemoteImagePolicy::default(), None);
+
+        assert!(!blocked.contains("fonts.example"));
+        assert!(!blocked.contains("tracker.example/p.gif"));
+        assert!(!blocked.contains("h.gif"));
+        assert!(!blocked.contains("e.gif"));
+        assert!(!blocked.contains("s.gif"));
+        assert!(blocked.contains("@import \"\";"));
+        assert!(blocked.contains("@import url();"));
+        assert!(blocked.contains("background: url( \"\" )"));
+        assert!(blocked.contains("url(images/logo.png), url(\"data:image/png;base64,AA\")"));
+        assert!(blocked.contains("/* url(https://tracker.example/comment.gif) */"));
+
+        let mut policy = RemoteImagePolicy::default();
+        policy.allow_domain("fonts.example");
+        let allowed = block_remote_css(css, &policy, None);
+        assert!(allowed.contains("@import \"https://fonts.example/a.css\";"));
+        assert!(allowed.contains("@import url(//fonts.example/b.css);"));
+        assert!(!allowed.contains("p.gif"));
+    }
+
+    /// Attributes of every `<img>` tag in the HTML, as name/value pairs.
+    fn img_attributes(html: &str) -> Vec<Vec<(String, Option<String>)>> {
+        let mut images = Vec::new();
+        replace_img_tags(html, |tag| {
+            images.push(parse_attributes(tag));
+            tag.to_string()
+        });
+        images
+    }
+
+    fn attribute<'a>(attributes: &'a [(String, Option<String>)], name: &str) -> Option<&'a str> {
+        attributes
+            .iter()
+            .find(|(attribute, _)| attribute == name)
+            .and_then(|(_, value)| value.as_deref())
+    }
+
+    #[test]
+    fn test_no_live_remote_src_until_allowed() {
+        let url = "https://tracker.example/pixel.gif";
+        let temp_dir = tempdir().unwrap();
+        let document = temp_dir.path().join("doc.md");
+        let markdown = format!(
+            "![pixel]({url})\n\n<img src=\"{url}\" srcset=\"{url} 2x\" width=\"1\" height=\"1\">\n",
+            url = url
+        );
+        fs::write(&document, &markdown).unwrap();
+
+        let mut config = RenderConfig::default();
+        let render = |config: &RenderConfig| {
+            let context = RenderContext::for_document(&document).with_config(config.clone());
+            render_markdown(&markdown, &context)
+        };
+
+        // Blocked: the only loadable source is the inline placeholder
+        let images = img_attributes(&render(&config));
+        assert_eq!(images.len(), 2);
+        for image in &images {
+            assert_eq!(attribute(image, "src"), Some(PLACEHOLDER_SRC));
+            assert_eq!(attribute(image, REMOTE_SRC_ATTRIBUTE), Some(url));
+            assert_eq!(attribute(image, "srcset"), None);
+            assert!(attribute(image, "class").is_some_and(|class| class.contains(BLOCKED_CLASS)));
+        }
+
+        // Allowed: the original source is back and nothing is left to restore
+        config.remote_images.allow_document(&document);
+        let images = img_attributes(&render(&config));
+        assert_eq!(images.len(), 2);
+        for image in &images {
+            assert_eq!(attribute(image, "src"), Some(url));
+            assert_eq!(attribute(image, REMOTE_SRC_ATTRIBUTE), None);
+            assert_eq!(attribute(image, "class"), None);
+        }
+    }
+
+    #[test]
+    fn test_csp_only_lets_images_reach_remote_hosts() {
+        let config: serde_json::Value =
+            serde_json::from_str(include_str!("../../tauri.conf.json")).unwrap();
+        let csp = config["app"]["security"]["csp"]
+            .as_str()
+            .expect("the webview must have a content security policy");
+        let directives: Vec<(&str, Vec<&str>)> = csp
+            .split(';')
+            .filter_map(|directive| {
+                let mut parts = directive.split_whitespace();
+                Some((parts.next()?, parts.collect()))
+            })
+            .collect();
+        assert!(directives.iter().any(|(name, _)| *name == "default-src"));
+
+        // Remote images are gated by the rendered HTML; everything else, such as
+        // stylesheet fonts or fetches, must not reach a remote host at all
+        let reaches_remote = |source: &str| {
+            source.contains('*')
+                || matches!(source, "http:" | "https:" | "ws:" | "wss:")
+                || (source.contains("://") && !source.ends_with(".localhost"))
+        };
+        for (name, sources) in &directives {
+            if *name == "img-src" {
+                assert!(sources.contains(&"data:"), "placeholders must load");
+                continue;
+            }
+            for source in sources {
+                assert!(!reaches_remote(source), "{} allows {}", name, source);
+            }
+        }
+    }
+}
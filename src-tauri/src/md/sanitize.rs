@@ -0,0 +1,301 @@
+use ammonia::Builder;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// Built-in sanitization allowlists.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SanitizePreset {
+    /// No raw HTML at all; it is dropped by the Markdown renderer
+    Strict,
+    /// Raw HTML is kept if it passes an allowlist based on GitHub's; unlike
+    /// GitHub, `class`, `id` and `name` are kept as written
+    #[default]
+    Github,
+}
+
+/// Configuration of the HTML sanitization stage.
+///
+/// The `allowed_*` lists extend the preset's allowlist. They have no effect
+/// with the strict preset, which never lets raw HTML through.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct SanitizeConfig {
+    /// The base allowlist
+    pub preset: SanitizePreset,
+    /// Additional tags to allow
+    pub allowed_tags: Vec<String>,
+    /// Additional attributes to allow on every tag
+    pub allowed_attributes: Vec<String>,
+    /// Additional URL schemes to allow in links and images
+    pub allowed_url_schemes: Vec<String>,
+}
+
+/// Tags allowed by the GitHub preset.
+///
+/// This follows GitHub's own sanitization filter, plus the tags comrak emits
+/// for extensions (footnote sections, task list checkboxes).
+#[rustfmt::skip]
+const GITHUB_TAGS: &[&str] = &[
+    "a", "abbr", "b", "bdo", "blockquote", "br", "caption", "cite", "code", "dd", "del",
+    "details", "dfn", "div", "dl", "dt", "em", "figcaption", "figure", "h1", "h2", "h3", "h4",
+    "h5", "h6", "hr", "i", "img", "input", "ins", "kbd", "li", "mark", "ol", "p", "pre", "q",
+    "rp", "rt", "ruby", "s", "samp", "section", "small", "span", "strike", "strong", "sub",
+    "summary", "sup", "table", "tbody", "td", "tfoot", "th", "thead", "time", "tr", "tt", "ul",
+    "var", "wbr",
+];
+
+/// Attributes allowed on every tag by the GitHub preset.
+///
+/// `class` and `id` are needed by the markup comrak generates (heading
+/// anchors, footnotes, code block languages). The sanitizer can't tell that
+/// markup from raw HTML, so unlike GitHub it doesn't strip `class` or prefix
+/// `id` and `name` with `user-content-`.
+#[rustfmt::skip]
+const GITHUB_GENERIC_ATTRIBUTES: &[&str] = &[
+    "abbr", "align", "alt", "aria-describedby", "aria-hidden", "aria-label", "aria-labelledby",
+    "axis", "border", "cellpadding", "cellspacing", "checked", "class", "clear", "colspan",
+    "datetime", "dir", "disabled", "headers", "height", "hspace", "id", "itemprop", "label",
+    "lang", "name", "nowrap", "open", "role", "rowspan", "scope", "span", "start", "summary",
+    "title", "type", "valign", "value", "vspace", "width",
+];
+
+/// Attributes allowed only on specific tags by the GitHub preset.
+const GITHUB_TAG_ATTRIBUTES: &[(&str, &[&str])] = &[
+    (
+        "a",
+        &[
+            "href",
+            "data-footnote-ref",
+            "data-footnote-backref",
+            "data-backref",
+        ],
+    ),
+    ("img", &["src", "longdesc"]),
+    ("li", &["data-sourcepos"]),
+    ("blockquote", &["cite"]),
+    ("del", &["cite"]),
+    ("ins", &["cite"]),
+    ("q", &["cite"]),
+    ("section", &["data-footnotes"]),
+];
+
+/// Tags removed together with their content.
+///
+/// These can never be allowed, even through `allowed_tags`.
+const CLEAN_CONTENT_TAGS: &[&str] = &["script", "style"];
+
+/// URL schemes allowed in links and images by the GitHub preset.
+///
+/// Relative URLs and fragments are always allowed.
+const GITHUB_URL_SCHEMES: &[&str] = &["http", "https", "mailto"];
+
+impl SanitizeConfig {
+    /// Returns true if the renderer should keep raw HTML for sanitizing.
+    pub fn allows_raw_html(&self) -> bool {
+        self.preset != SanitizePreset::Strict
+    }
+
+    /// Cleans rendered HTML through the configured allowlist.
+    ///
+    /// With the strict preset the HTML is returned unchanged, since the
+    /// renderer has already dropped all raw HTML.
+    ///
+    /// # Arguments
+    ///
+    /// * `html` - HTML produced by the Markdown renderer
+    ///
+    /// # Returns
+    ///
+    /// * `String` - The sanitized HTML
+    pub fn clean(&self, html: &str) -> String {
+        match self.preset {
+            SanitizePreset::Strict => html.to_string(),
+            SanitizePreset::Github => self.github_builder().clean(html).to_string(),
+        }
+    }
+
+    /// Builds the sanitizer for the GitHub preset plus configured additions.
+    ///
+    /// `CLEAN_CONTENT_TAGS` in `allowed_tags` are ignored, since ammonia
+    /// panics when a tag is both allowed and removed with its content.
+    fn github_builder(&self) -> Builder<'_> {
+        let tag_attributes: HashMap<&str, HashSet<&str>> = GITHUB_TAG_ATTRIBUTES
+            .iter()
+            .map(|(tag, attributes)| (*tag, attributes.iter().copied().collect()))
+            .collect();
+
+        let mut builder = Builder::default();
+        builder
+            .tags(GITHUB_TAGS.iter().copied().collect())
+            .add_tags(
+                self.allowed_tags
+                    .iter()
+                    .map(String::as_str)
+                    .filter(|tag| !CLEAN_CONTENT_TAGS.contains(&tag.to_ascii_lowercase().as_str())),
+            )
+            .clean_content_tags(CLEAN_CONTENT_TAGS.iter().copied().collect())
+            .generic_attributes(GITHUB_GENERIC_ATTRIBUTES.iter().copied().collect())
+            .add_generic_attributes(self.allowed_attributes.iter().map(String::as_str))
+            .tag_attributes(tag_attributes)
+            .url_schemes(GITHUB_URL_SCHEMES.iter().copied().collect())
+            .add_url_schemes(self.allowed_url_schemes.iter().map(String::as_str))
+            .link_rel(None);
+        builder
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::md::parser::{render_markdown, RenderConfig, RenderContext};
+
+    fn render(markdown: &str, preset: SanitizePreset) -> String {
+        let context = RenderContext::default().with_config(RenderConfig {
+            sanitize: SanitizeConfig {
+                preset,
+                ..SanitizeConfig::default()
+            },
+            ..RenderConfig::default()
+        });
+        render_markdown(markdown, &context)
+    }
+
+    /// Known XSS vectors; none may survive either preset.
+    const XSS_VECTORS: &[&str] = &[
+        "<script>alert(1)</script>",
+        "<img src=x onerror=alert(1)>",
+        "<a href=\"javascript:alert(1)\">x</a>",
+        "<a href=\"JaVaScRiPt:alert(1)\">x</a>",
+        "<a href=\"jav&#x09;ascript:alert(1)\">x</a>",
+        "[x](javascript:alert(1))",
+        "![x](javascript:alert(1))",
+        "<iframe src=\"https://evil.example\"></iframe>",
+        "<svg onload=alert(1)>",
+        "<svg><script>alert(1)</script></svg>",
+        "<math><mtext><table><mglyph><style><img src=x onerror=alert(1)>",
+        "<details open ontoggle=alert(1)>",
+        "<div style=\"background:url(javascript:alert(1))\">x</div>",
+        "<object data=\"data:text/html;base64,PHNjcmlwdD5hbGVydCgxKTwvc2NyaXB0Pg==\"></object>",
+        "<a href=\"data:text/html,<script>alert(1)</script>\">x</a>",
+        "<form action=\"javascript:alert(1)\"><button>x</button></form>",
+        "<meta http-equiv=\"refresh\" content=\"0;url=javascript:alert(1)\">",
+        "<base href=\"javascript:alert(1)//\">",
+        "<style>body { display: none }</style>",
+        "<noscript><p title=\"</noscript><img src=x onerror=alert(1)>\">",
+        "<img src=\"x\" onerror=\"alert(1)\"/>",
+        "<body onload=alert(1)>",
+        "<input autofocus onfocus=alert(1)>",
+        "<a href=\"vbscript:msgbox(1)\">x</a>",
+    ];
+
+    fn assert_safe(html: &str, vector: &str) {
+        let lower = html.to_lowercase();
+        for needle in [
+            "<script",
+            "javascript:",
+            "vbscript:",
+            "onerror",
+            "onload",
+            "ontoggle",
+            "onfocus",
+            "<iframe",
+            "<svg",
+            "<object",
+            "<form",
+            "<meta",
+            "<base",
+            "<style",
+            "style=",
+            "data:text/html",
+        ] {
+            // Escaped text (`&lt;script`) is harmless; only live markup matters
+            let live = lower.match_indices(needle).any(|(i, _)| {
+                let before = &lower[..i];
+                let in_tag = before.rfind('<') > before.rfind('>');
+                needle.starts_with('<') || in_tag
+            });
+            assert!(
+                !live,
+                "{:?} survived in {:?} from {:?}",
+                needle, html, vector
+            );
+        }
+    }
+
+    #[test]
+    fn test_xss_vectors_github() {
+        for vector in XSS_VECTORS {
+            assert_safe(&render(vector, SanitizePreset::Github), vector);
+        }
+    }
+
+    #[test]
+    fn test_xss_vectors_strict() {
+        for vector in XSS_VECTORS {
+            assert_safe(&render(vector, SanitizePreset::Strict), vector);
+        }
+    }
+
+    #[test]
+    fn test_github_keeps_allowed_html() {
+        let markdown = "<details><summary>More</summary>\n\nPress <kbd>Ctrl</kbd>+<kbd>C</kbd>\n\n</details>\n\nx<sup>2</sup><br>\n<img src=\"logo.png\" width=\"100\" alt=\"Logo\">";
+        let html = render(markdown, SanitizePreset::Github);
+
+        assert!(html.contains("<details>"));
+        assert!(html.contains("<summary>More</summary>"));
+        assert!(html.contains("<kbd>Ctrl</kbd>"));
+        assert!(html.contains("<sup>2</sup>"));
+        assert!(html.contains("<br>"));
+        assert!(html.contains("<img src=\"logo.png\" width=\"100\" alt=\"Logo\">"));
+    }
+
+    #[test]
+    fn test_strict_drops_raw_html() {
+        let html = render("Press <kbd>Ctrl</kbd>", SanitizePreset::Strict);
+
+        assert!(!html.contains("<kbd>"));
+    }
+
+    #[test]
+    fn test_github_keeps_rendered_markdown() {
+        let markdown = "# Title\n\n- [x] done\n\nText[^1] and [link](https://example.com).\n\n```rust\nfn main() {}\n```\n\n[^1]: Note";
+        let github = render(markdown, SanitizePreset::Github);
+        let strict = render(markdown, SanitizePreset::Strict);
+
+        assert!(github.contains("id=\"title\""));
+        assert!(github.contains("<input type=\"checkbox\""));
+        assert!(github.contains("class=\"language-rust\""));
+        assert!(github.contains("href=\"https://example.com\""));
+        assert!(github.contains("data-footnotes"));
+        assert!(github.contains("href=\"#fn-1\""));
+        assert_eq!(github.matches('<').count(), strict.matches('<').count());
+    }
+
+    #[test]
+    fn test_configured_additions() {
+        let config = SanitizeConfig {
+            allowed_tags: vec!["abbr".to_string(), "u".to_string()],
+            allowed_url_schemes: vec!["obsidian".to_string()],
+            ..SanitizeConfig::default()
+        };
+
+        let html = config.clean("<u>under</u> <a href=\"obsidian://open?vault=x\">v</a>");
+        assert!(html.contains("<u>under</u>"));
+        assert!(html.contains("href=\"obsidian://open?vault=x\""));
+
+        let default = SanitizeConfig::default().clean("<u>under</u>");
+        assert_eq!(default, "under");
+    }
+
+    #[test]
+    fn test_script_and_style_stay_removed() {
+        let config = SanitizeConfig {
+            allowed_tags: vec!["script".to_string(), "Style".to_string(), "u".to_string()],
+            ..SanitizeConfig::default()
+        };
+
+        let html = config.clean("<script>alert(1)</script><style>p {}</style><u>under</u>");
+        assert_eq!(html, "<u>under</u>");
+    }
+}
@@ -0,0 +1,222 @@
+use super::parser::{self, RenderConfig, RenderContext};
+use super::{admonitions, frontmatter, toc};
+use comrak::nodes::NodeValue;
+use comrak::{parse_document, Arena};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// Where a document is split into slides.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SlideSplit {
+    /// At thematic breaks (`---`), which are removed
+    #[default]
+    Rule,
+    /// Before every level 1 heading
+    H1,
+    /// Before every level 1 or 2 heading
+    H2,
+}
+
+impl FromStr for SlideSplit {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "rule" | "hr" => Ok(SlideSplit::Rule),
+            "h1" => Ok(SlideSplit::H1),
+            "h2" => Ok(SlideSplit::H2),
+            _ => Err(format!(
+                "unknown slide split '{}' (expected rule, h1 or h2)",
+                value
+            )),
+        }
+    }
+}
+
+/// A single slide of a presentation.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Slide {
+    /// The text of the first heading on the slide
+    pub title: Option<String>,
+    /// The rendered slide content
+    pub html: String,
+    /// The rendered speaker notes, from a paragraph starting with `Note:` to the end of the slide
+    pub notes: Option<String>,
+}
+
+/// Prefixes that start a slide's speaker notes.
+const NOTE_PREFIXES: &[&str] = &["Note:", "Notes:"];
+
+/// Splits a document into slides.
+///
+/// Split points are found in the AST, so `---` inside code blocks or used
+/// as a setext heading underline never starts a slide. Each slide is then
+/// rendered through the normal pipeline.
+///
+/// # Arguments
+///
+/// * `markdown` - The Markdown source text
+/// * `context` - Where the document lives
+/// * `split` - Where to start new slides
+///
+/// # Returns
+///
+/// * `Vec<Slide>` - The slides in order; empty slides are dropped
+pub fn split_slides(markdown: &str, context: &RenderContext, split: SlideSplit) -> Vec<Slide> {
+    // Slides are rendered from parts of the file, so their source lines can't be toggled
+    let context = RenderContext {
+        config: RenderConfig {
+            interactive_tasks: false,
+            ..context.config.clone()
+        },
+        ..context.clone()
+    };
+
+    // Front matter describes the whole deck, so no slide shows it
+    let source = frontmatter::blank(markdown);
+    let lines: Vec<&str> = source.split_inclusive('\n').collect();
+    let arena = Arena::new();
+    let prepared = admonitions::convert_mkdocs_admonitions(&source);
+    let root = parse_document(&arena, &prepared, &context.extensions().options());
+
+    // Slides as 1-based [start, end) line ranges
+    let mut ranges = Vec::new();
+    let mut start = 1;
+    let mut headings = Vec::new();
+    let mut note_lines = Vec::new();
+
+    for node in root.children() {
+        let data = node.data.borrow();
+        let (first, last) = (data.sourcepos.start.line, data.sourcepos.end.line);
+
+        match &data.value {
+            NodeValue::ThematicBreak if split == SlideSplit::Rule => {
+                ranges.push((start, first));
+                start = last + 1;
+            }
+            NodeValue::Heading(heading) => {
+                let splits = match split {
+                    SlideSplit::Rule => false,
+                    SlideSplit::H1 => heading.level == 1,
+                    SlideSplit::H2 => heading.level <= 2,
+                };
+                if splits {
+                    ranges.push((start, first));
+                    start = first;
+                }
+                headings.push((first, toc::extract_text(node)));
+            }
+            NodeValue::Paragraph => {
+                let text = lines.get(first - 1).map_or("", |line| line.trim_start());
+                if NOTE_PREFIXES.iter().any(|prefix| text.starts_with(prefix)) {
+                    note_lines.push(first);
+                }
+            }
+            _ => {}
+        }
+    }
+    ranges.push((start, lines.len() + 1));
+
+    ranges
+        .into_iter()
+        .filter_map(|(start, end)| {
+            let in_slide = |line: usize| line >= start && line < end;
+            let notes_start = note_lines
+                .iter()
+                .copied()
+                .find(|&line| in_slide(line))
+                .unwrap_or(end);
+
+            let content = join_lines(&lines, start, notes_start);
+            let notes = join_lines(&lines, notes_start, end);
+            let notes = strip_note_prefix(&notes);
+            if content.trim().is_empty() && notes.trim().is_empty() {
+                return None;
+            }
+
+            Some(Slide {
+                title: headings
+                    .iter()
+                    .find(|(line, _)| in_slide(*line) && *line < notes_start)
+                    .map(|(_, text)| text.clone()),
+                html: parser::render_markdown(&content, &context),
+                notes: (!notes.trim().is_empty()).then(|| parser::render_markdown(notes, &context)),
+            })
+        })
+        .collect()
+}
+
+/// Joins the 1-based lines in `[start, end)`.
+fn join_lines(lines: &[&str], start: usize, end: usize) -> String {
+    lines[(start - 1).min(lines.len())..(end - 1).min(lines.len())].concat()
+}
+
+/// Removes the `Note:` prefix from the start of the notes.
+fn strip_note_prefix(notes: &str) -> &str {
+    let trimmed = notes.trim_start();
+    NOTE_PREFIXES
+        .iter()
+        .find_map(|prefix| trimmed.strip_prefix(prefix))
+        .unwrap_or(notes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn split(markdown: &str, split: SlideSplit) -> Vec<Slide> {
+        split_slides(markdown, &RenderContext::default(), split)
+    }
+
+    #[test]
+    fn test_split_at_rules() {
+        let markdown =
+            "# Intro\n\nHello\n\n---\n\n## Agenda\n\n```\n---\n```\n\nSetext\n---\n\n***\n\nLast\n";
+        let slides = split(markdown, SlideSplit::Rule);
+
+        assert_eq!(slides.len(), 3);
+        assert_eq!(slides[0].title.as_deref(), Some("Intro"));
+        assert!(slides[0].html.contains("<p>Hello</p>"));
+        // A rule in a code block and a setext underline don't split
+        assert_eq!(slides[1].title.as_deref(), Some("Agenda"));
+        assert!(slides[1].html.contains("<pre><code>---"));
+        assert!(slides[1].html.contains("Setext</h2>"));
+        assert_eq!(slides[2].title, None);
+        assert!(!slides.iter().any(|slide| slide.html.contains("<hr")));
+    }
+
+    #[test]
+    fn test_split_at_headings() {
+        let markdown = "Preamble\n\n# One\n\n## Two\n\n### Three\n\n# Four\n";
+
+        let h1 = split(markdown, SlideSplit::H1);
+        let titles: Vec<_> = h1.iter().map(|slide| slide.title.as_deref()).collect();
+        assert_eq!(titles, vec![None, Some("One"), Some("Four")]);
+
+        let h2 = split(markdown, SlideSplit::H2);
+        let titles: Vec<_> = h2.iter().map(|slide| slide.title.as_deref()).collect();
+        assert_eq!(titles, vec![None, Some("One"), Some("Two"), Some("Four")]);
+        assert!(h2[2].html.contains("Three"));
+    }
+
+    #[test]
+    fn test_speaker_notes() {
+        let markdown = "---\ntitle: Talk\n---\n# Welcome\n\nNote: Thank the organisers.\n\n- mention the *sponsors*\n\n---\n\n# No notes\n";
+        let slides = split(markdown, SlideSplit::Rule);
+
+        assert_eq!(slides.len(), 2);
+        assert!(!slides[0].html.contains("organisers"));
+        assert!(!slides[0].html.contains("Talk"));
+        let notes = slides[0].notes.as_deref().unwrap();
+        assert!(notes.contains("<p>Thank the organisers.</p>"));
+        assert!(notes.contains("<em>sponsors</em>"));
+        assert_eq!(slides[1].notes, None);
+    }
+
+    #[test]
+    fn test_slide_split_from_str() {
+        assert_eq!("H2".parse::<SlideSplit>(), Ok(SlideSplit::H2));
+        assert!("h3".parse::<SlideSplit>().is_err());
+    }
+}
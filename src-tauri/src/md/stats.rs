@@ -0,0 +1,230 @@
+use super::{toc, TocItem};
+use comrak::nodes::{AstNode, NodeValue};
+use comrak::{parse_document, Arena, Options};
+use serde::{Deserialize, Serialize};
+
+/// Average reading speed used for reading time estimates.
+const WORDS_PER_MINUTE: usize = 200;
+
+/// Content counts for a document or one of its sections.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SectionStats {
+    /// Words of prose, headings and inline code; code blocks are not counted
+    pub words: usize,
+    /// Estimated reading time in whole minutes, rounded up
+    pub reading_minutes: usize,
+    /// Fenced and indented code blocks
+    pub code_blocks: usize,
+    /// Tables
+    pub tables: usize,
+    /// Images
+    pub images: usize,
+    /// Links, including autolinks and wikilinks
+    pub links: usize,
+}
+
+impl SectionStats {
+    fn add(&mut self, other: &SectionStats) {
+        self.words += other.words;
+        self.code_blocks += other.code_blocks;
+        self.tables += other.tables;
+        self.images += other.images;
+        self.links += other.links;
+        self.reading_minutes = self.words.div_ceil(WORDS_PER_MINUTE);
+    }
+}
+
+/// Statistics for a whole document.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct DocumentStats {
+    /// Counts for the whole document
+    #[serde(flatten)]
+    pub totals: SectionStats,
+    /// Number of headings at each level, from h1 to h6
+    pub headings: [usize; 6],
+}
+
+/// Extracts the table of contents and content statistics in a single AST walk.
+///
+/// Each TOC item gets the stats of its section, which runs until the next
+/// heading of the same or a higher level and so includes its subsections.
+///
+/// # Arguments
+///
+/// * `markdown` - The Markdown source text
+/// * `options` - The comrak options the document is rendered with
+///
+/// # Returns
+///
+/// * `(Vec<TocItem>, DocumentStats)` - The TOC items with section stats, and the document stats
+pub fn analyze(markdown: &str, options: &Options) -> (Vec<TocItem>, DocumentStats) {
+    let arena = Arena::new();
+    let root = parse_document(&arena, markdown, options);
+
+    let mut walker = Walker::default();
+    walker.walk(root);
+    walker.finish_section();
+
+    let mut stats = DocumentStats::default();
+    stats.totals.add(&walker.preamble);
+    for (item, _) in &walker.sections {
+        stats.headings[usize::from(item.level.clamp(1, 6)) - 1] += 1;
+    }
+
+    let mut items = Vec::with_capacity(walker.sections.len());
+    for (index, (item, own)) in walker.sections.iter().enumerate() {
+        stats.totals.add(own);
+
+        let mut section = *own;
+        section.reading_minutes = section.words.div_ceil(WORDS_PER_MINUTE);
+        for (nested, nested_stats) in &walker.sections[index + 1..] {
+            if nested.level <= item.level {
+                break;
+            }
+            section.add(nested_stats);
+        }
+
+        items.push(TocItem {
+            stats: Some(section),
+            ..item.clone()
+        });
+    }
+
+    (items, stats)
+}
+
+/// AST walk state: counts for the content before the first heading and for
+/// each heading's own content, excluding its subsections.
+#[derive(Default)]
+struct Walker {
+    preamble: SectionStats,
+    sections: Vec<(TocItem, SectionStats)>,
+    current: SectionStats,
+    text: String,
+}
+
+impl Walker {
+    fn walk<'a>(&mut self, node: &'a AstNode<'a>) {
+        if let Some(item) = toc::heading_item(node) {
+            self.finish_section();
+            self.sections.push((item, SectionStats::default()));
+        }
+
+        let is_block = {
+            let data = node.data.borrow();
+            match &data.value {
+                NodeValue::Text(text) => self.text.push_str(text),
+                NodeValue::Code(code) => self.text.push_str(&code.literal),
+                NodeValue::SoftBreak | NodeValue::LineBreak => self.text.push(' '),
+                NodeValue::CodeBlock(_) => {
+                    self.current.code_blocks += 1;
+                    return;
+                }
+                NodeValue::Image(_) => {
+                    // Alt text isn't read, so it doesn't count towards the words
+                    self.current.images += 1;
+                    return;
+                }
+                NodeValue::HtmlBlock(_) | NodeValue::HtmlInline(_) => return,
+                NodeValue::Table(_) => self.current.tables += 1,
+                NodeValue::Link(_) | NodeValue::WikiLink(_) => self.current.links += 1,
+                _ => {}
+            }
+            data.value.block()
+        };
+
+        // Separate blocks so words in adjacent paragraphs or cells don't run together
+        if is_block {
+            self.text.push(' ');
+        }
+        for child in node.children() {
+            self.walk(child);
+        }
+        if is_block {
+            self.text.push(' ');
+        }
+    }
+
+    /// Counts the words of the section being walked and resets it.
+    fn finish_section(&mut self) {
+        let mut stats = std::mem::take(&mut self.current);
+        stats.words = count_words(&std::mem::take(&mut self.text));
+        stats.reading_minutes = stats.words.div_ceil(WORDS_PER_MINUTE);
+
+        match self.sections.last_mut() {
+            Some((_, section)) => *section = stats,
+            None => self.preamble = stats,
+        }
+    }
+}
+
+/// Counts whitespace-separated words that contain a letter or digit.
+fn count_words(text: &str) -> usize {
+    text.split_whitespace()
+        .filter(|word| word.chars().any(char::is_alphanumeric))
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::md::parser::render_options;
+
+    fn analyze(markdown: &str) -> (Vec<TocItem>, DocumentStats) {
+        super::analyze(markdown, &render_options())
+    }
+
+    #[test]
+    fn test_document_stats() {
+        let markdown = "Intro **bold**text here.\n\n# Guide\n\nSee [the docs](https://example.com) and <https://example.org>.\n\n```rust\nfn main() {}\n```\n\n## Table\n\n| a | b |\n|---|---|\n| one | two |\n\n![alt words](image.png)\n\n## Code `inline`\n\n# Appendix\n\n- [[Other Note]]\n";
+        let (toc, stats) = analyze(markdown);
+
+        assert_eq!(stats.totals.code_blocks, 1);
+        assert_eq!(stats.totals.tables, 1);
+        assert_eq!(stats.totals.images, 1);
+        assert_eq!(stats.totals.links, 3);
+        assert_eq!(stats.headings, [2, 2, 0, 0, 0, 0]);
+        assert_eq!(stats.totals.reading_minutes, 1);
+
+        let titles: Vec<_> = toc.iter().map(|item| item.text.as_str()).collect();
+        assert_eq!(titles, vec!["Guide", "Table", "Code inline", "Appendix"]);
+
+        // Inline markup doesn't split words: "Intro boldtext here." is 3 words
+        assert_eq!(stats.totals.words, 19);
+
+        let guide = toc[0].stats.unwrap();
+        assert_eq!(guide.words, 13);
+        assert_eq!(guide.code_blocks, 1);
+        assert_eq!(guide.tables, 1);
+        assert_eq!(guide.links, 2);
+        // Table header and cells: "Table a b one two"
+        assert_eq!(toc[1].stats.unwrap().words, 5);
+        assert_eq!(toc[2].stats.unwrap().words, 2);
+        assert_eq!(toc[3].stats.unwrap().links, 1);
+    }
+
+    #[test]
+    fn test_toc_matches_extract_toc() {
+        let markdown = "# One\n\nText\n\n## Two\n\n### Three\n";
+        let (items, _) = analyze(markdown);
+        let plain: Vec<_> = items
+            .into_iter()
+            .map(|item| TocItem {
+                stats: None,
+                ..item
+            })
+            .collect();
+
+        assert_eq!(plain, toc::extract_toc(markdown));
+    }
+
+    #[test]
+    fn test_reading_time() {
+        let markdown = "word ".repeat(401);
+        let (_, stats) = analyze(&markdown);
+
+        assert_eq!(stats.totals.words, 401);
+        assert_eq!(stats.totals.reading_minutes, 3);
+        assert_eq!(analyze("").1, DocumentStats::default());
+    }
+}
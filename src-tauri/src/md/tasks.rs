@@ -0,0 +1,286 @@
+use super::admonitions;
+use super::archive::ArchivePath;
+use super::extensions::ExtensionConfig;
+use super::loader::{self, LoadLimits, MdLoadError};
+use super::parser::HtmlFragments;
+use comrak::nodes::NodeValue;
+use comrak::{parse_document, Arena};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Errors that can occur when toggling a task list item.
+#[derive(Debug, thiserror::Error)]
+pub enum TaskError {
+    /// The line does not start a task list item
+    #[error("Line {0} is not a task list item")]
+    NotATask(usize),
+
+    /// The file on disk differs from the content that was rendered
+    #[error("The file has changed on disk since it was loaded: {0}")]
+    FileChanged(String),
+
+    /// Writing back would replace the compressed file with plain text
+    #[error("Tasks can't be changed in compressed files: {0}")]
+    Compressed(String),
+
+    /// Files inside archives are read-only
+    #[error("Tasks can't be changed in files inside archives: {0}")]
+    Archived(String),
+
+    /// The file could not be read
+    #[error(transparent)]
+    Load(#[from] MdLoadError),
+
+    /// The file could not be written
+    #[error("Failed to write file: {0}")]
+    Write(#[from] io::Error),
+}
+
+/// Toggles a task list item in a file and writes the file back.
+///
+/// The file is only written if its content still matches what was loaded,
+/// so edits made elsewhere since then are never overwritten. Compressed
+/// files and files inside archives are never written.
+///
+/// # Arguments
+///
+/// * `path` - The Markdown file
+/// * `loaded_content` - The content the document was rendered from
+/// * `line` - The 1-based line number of the task item
+/// * `extensions` - The document's effective extension settings
+///
+/// # Returns
+///
+/// * `Result<(), TaskError>` - Ok if the file was updated
+pub fn toggle_task_in_file<P: AsRef<Path>>(
+    path: P,
+    loaded_content: &str,
+    line: usize,
+    extensions: ExtensionConfig,
+) -> Result<(), TaskError> {
+    let path = path.as_ref();
+    if ArchivePath::from_path(path).is_some() {
+        return Err(TaskError::Archived(path.display().to_string()));
+    }
+    let loaded =
+        loader::load_markdown_file_with_progress(path, &LoadLimits::unlimited(), |_, _| true)?;
+    if loaded.compression.is_some() {
+        return Err(TaskError::Compressed(path.display().to_string()));
+    }
+    let current = loaded.content;
+    if current != loaded_content {
+        return Err(TaskError::FileChanged(path.display().to_string()));
+    }
+
+    let toggled = toggle_task(&current, line, extensions)?;
+    fs::write(path, toggled)?;
+    Ok(())
+}
+
+/// Toggles the checkbox of the task list item starting on a line.
+///
+/// Only the `[ ]`/`[x]` marker on that line is changed; the rest of the
+/// document, including its line endings, is kept byte for byte.
+///
+/// # Arguments
+///
+/// * `markdown` - The Markdown source text
+/// * `line` - The 1-based line number of the task item
+/// * `extensions` - The document's effective extension settings
+///
+/// # Returns
+///
+/// * `Result<String, TaskError>` - The updated source or an error
+pub fn toggle_task(
+    markdown: &str,
+    line: usize,
+    extensions: ExtensionConfig,
+) -> Result<String, TaskError> {
+    if !is_task_item_line(markdown, line, extensions) {
+        return Err(TaskError::NotATask(line));
+    }
+
+    let (start, text) = markdown
+        .split_inclusive('\n')
+        .scan(0, |offset, text| {
+            let start = *offset;
+            *offset += text.len();
+            Some((start, text))
+        })
+        .nth(line - 1)
+        .ok_or(TaskError::NotATask(line))?;
+
+    let marker = find_checkbox(text).ok_or(TaskError::NotATask(line))?;
+    let position = start + marker + 1;
+    let replacement = if &markdown[position..position + 1] == " " {
+        "x"
+    } else {
+        " "
+    };
+
+    let mut toggled = markdown.to_string();
+    toggled.replace_range(position..position + 1, replacement);
+    Ok(toggled)
+}
+
+/// Returns true if a task list item starts on the line.
+///
+/// The document is parsed with its own extensions and the same preprocessing
+/// as for rendering, so only lines rendered as task items match; checkboxes
+/// inside code blocks and similar never do.
+fn is_task_item_line(markdown: &str, line: usize, extensions: ExtensionConfig) -> bool {
+    let arena = Arena::new();
+    let markdown =
+        admonitions::convert_mkdocs_admonitions(&HtmlFragments::escape_markers(markdown));
+    let root = parse_document(&arena, &markdown, &extensions.options());
+
+    root.descendants().any(|node| {
+        let data = node.data.borrow();
+        matches!(data.value, NodeValue::TaskItem(_)) && data.sourcepos.start.line == line
+    })
+}
+
+/// Finds the byte offset of the `[` of a task checkbox on a source line.
+///
+/// Skips indentation, blockquote markers and the list marker (`-`, `*`, `+`,
+/// `1.` or `1)`).
+fn find_checkbox(line: &str) -> Option<usize> {
+    let bytes = line.as_bytes();
+    let mut i = 0;
+
+    // Indentation and blockquote markers (including converted MkDocs admonitions)
+    while i < bytes.len() && matches!(bytes[i], b' ' | b'\t' | b'>') {
+        i += 1;
+    }
+
+    // List marker
+    match bytes.get(i)? {
+        b'-' | b'*' | b'+' => i += 1,
+        b'0'..=b'9' => {
+            while i < bytes.len() && bytes[i].is_ascii_digit() {
+                i += 1;
+            }
+            if !matches!(bytes.get(i)?, b'.' | b')') {
+                return None;
+            }
+            i += 1;
+        }
+        _ => return None,
+    }
+
+    while i < bytes.len() && matches!(bytes[i], b' ' | b'\t') {
+        i += 1;
+    }
+
+    let checkbox = bytes.get(i..i + 3)?;
+    matches!(checkbox, b"[ ]" | b"[x]" | b"[X]").then_some(i)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::md::extensions::Profile;
+    use tempfile::tempdir;
+
+    fn assert_not_a_task(result: Result<String, TaskError>, line: usize) {
+        assert!(matches!(result, Err(TaskError::NotATask(l)) if l == line));
+    }
+
+    #[test]
+    fn test_toggle_task() {
+        let markdown = "# Runbook\n\n- [ ] Tag release\n- [x] Build\r\n1. [X] Publish\n";
+
+        assert_eq!(
+            toggle_task(markdown, 3, ExtensionConfig::default()).unwrap(),
+            "# Runbook\n\n- [x] Tag release\n- [x] Build\r\n1. [X] Publish\n"
+        );
+        assert_eq!(
+            toggle_task(markdown, 4, ExtensionConfig::default()).unwrap(),
+            "# Runbook\n\n- [ ] Tag release\n- [ ] Build\r\n1. [X] Publish\n"
+        );
+        assert_eq!(
+            toggle_task(markdown, 5, ExtensionConfig::default()).unwrap(),
+            "# Runbook\n\n- [ ] Tag release\n- [x] Build\r\n1. [ ] Publish\n"
+        );
+    }
+
+    #[test]
+    fn test_toggle_nested_and_quoted() {
+        let markdown = "- [ ] Outer\n  - [ ] Inner\n\n> - [x] Quoted\n";
+
+        assert_eq!(
+            toggle_task(markdown, 2, ExtensionConfig::default()).unwrap(),
+            "- [ ] Outer\n  - [x] Inner\n\n> - [x] Quoted\n"
+        );
+        assert_eq!(
+            toggle_task(markdown, 4, ExtensionConfig::default()).unwrap(),
+            "- [ ] Outer\n  - [ ] Inner\n\n> - [ ] Quoted\n"
+        );
+    }
+
+    #[test]
+    fn test_toggle_rejects_non_tasks() {
+        let markdown = "- plain item\n\n```\n- [ ] in code\n```\n[ ] text\n";
+
+        assert_not_a_task(toggle_task(markdown, 1, ExtensionConfig::default()), 1);
+        assert_not_a_task(toggle_task(markdown, 4, ExtensionConfig::default()), 4);
+        assert_not_a_task(toggle_task(markdown, 6, ExtensionConfig::default()), 6);
+        assert_not_a_task(toggle_task(markdown, 0, ExtensionConfig::default()), 0);
+        assert_not_a_task(toggle_task(markdown, 99, ExtensionConfig::default()), 99);
+    }
+
+    #[test]
+    fn test_toggle_follows_document_extensions() {
+        let markdown = "- [ ] Deploy
+";
+        let commonmark = ExtensionConfig {
+            profile: Some(Profile::CommonMark),
+            ..ExtensionConfig::default()
+        };
+
+        // Without task lists the line renders as a plain list item
+        assert_not_a_task(toggle_task(markdown, 1, commonmark), 1);
+        assert_eq!(
+            toggle_task(markdown, 1, ExtensionConfig::default()).unwrap(),
+            "- [x] Deploy\n"
+        );
+    }
+
+    #[test]
+    fn test_toggle_task_in_file() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("runbook.md");
+        let loaded = "- [ ] Deploy\n";
+        fs::write(&path, loaded).unwrap();
+
+        toggle_task_in_file(&path, loaded, 1, ExtensionConfig::default()).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "- [x] Deploy\n");
+
+        // The file no longer matches what was loaded
+        let result = toggle_task_in_file(&path, loaded, 1, ExtensionConfig::default());
+        assert!(matches!(result, Err(TaskError::FileChanged(_))));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "- [x] Deploy\n");
+    }
+
+    #[test]
+    fn test_toggle_refuses_compressed_file() {
+        use std::io::Write;
+
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("runbook.md.gz");
+        let loaded = "- [ ] Deploy\n";
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(loaded.as_bytes()).unwrap();
+        let compressed = encoder.finish().unwrap();
+        fs::write(&path, &compressed).unwrap();
+
+        let result = toggle_task_in_file(&path, loaded, 1, ExtensionConfig::default());
+        assert!(matches!(result, Err(TaskError::Compressed(_))));
+        assert_eq!(fs::read(&path).unwrap(), compressed);
+
+        let archived = temp_dir.path().join("bundle.zip!/runbook.md");
+        let result = toggle_task_in_file(&archived, loaded, 1, ExtensionConfig::default());
+        assert!(matches!(result, Err(TaskError::Archived(_))));
+    }
+}
@@ -36,27 +36,9 @@ pub fn extract_toc(markdown: &str) -> Vec<TocItem> {
 
 /// Recursively traverses the AST to find heading nodes.
 fn extract_headings<'a>(node: &'a AstNode<'a>, toc_items: &mut Vec<TocItem>) {
-    match &node.data.borrow().value {
-        NodeValue::Heading(heading) => {
-            let level = heading.level as u8;
-            let text = extract_text(node);
-            let id = generate_id(&text);
-            
-            // Get line number if available
-            let line_number = node.data.borrow().sourcepos.start.line;
-            
-            if line_number > 0 {
-                toc_items.push(TocItem::with_line_number(
-                    level,
-                    text,
-                    id,
-                    line_number as usize,
-                ));
-            } else {
-                toc_items.push(TocItem::new(level, text, id));
-            }
-        }
-        _ => {
+    match heading_item(node) {
+        Some(item) => toc_items.push(item),
+        None => {
             // Recursively traverse children
             for child in node.children() {
                 extract_headings(child, toc_items);
@@ -65,8 +47,30 @@ fn extract_headings<'a>(node: &'a AstNode<'a>, toc_items: &mut Vec<TocItem>) {
     }
 }
 
+/// Builds the TOC item for a heading node.
+///
+/// Returns None if the node is not a heading. Shared with other AST walks
+/// (such as link extraction) that need to know which section they are in.
+pub(crate) fn heading_item<'a>(node: &'a AstNode<'a>) -> Option<TocItem> {
+    let level = match &node.data.borrow().value {
+        NodeValue::Heading(heading) => heading.level,
+        _ => return None,
+    };
+    let text = extract_text(node);
+    let id = generate_id(&text);
+    
+    // Get line number if available
+    let line_number = node.data.borrow().sourcepos.start.line;
+    
+    if line_number > 0 {
+        Some(TocItem::with_line_number(level, text, id, line_number))
+    } else {
+        Some(TocItem::new(level, text, id))
+    }
+}
+
 /// Extracts plain text from a heading node.
-fn extract_text<'a>(node: &'a AstNode<'a>) -> String {
+pub(crate) fn extract_text<'a>(node: &'a AstNode<'a>) -> String {
     let mut text = String::new();
     
     for child in node.children() {
@@ -94,7 +98,7 @@ fn collect_text<'a>(node: &'a AstNode<'a>, text: &mut String) {
 }
 
 /// Generates a URL-safe ID from heading text.
-fn generate_id(text: &str) -> String {
+pub(crate) fn generate_id(text: &str) -> String {
     let id = text
         .to_lowercase()
         .chars()
@@ -0,0 +1,125 @@
+use super::workspace;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// How much a document is trusted, modelled after editors' workspace trust.
+///
+/// Restricted documents render without raw HTML, remote images or project
+/// configuration, can't write task checkboxes back to disk, and external
+/// links ask for confirmation before opening. Documents are restricted
+/// unless trust is granted explicitly.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TrustLevel {
+    /// Everything the settings enable is allowed
+    Trusted,
+    /// The document is outside the trusted folders
+    #[default]
+    Restricted,
+}
+
+/// Returns the trust level of a document.
+///
+/// A document is trusted if it lies in one of the trusted folders (or a
+/// folder below one). Paths are canonicalized, so a symlink inside a trusted
+/// folder that points elsewhere isn't trusted.
+///
+/// # Arguments
+///
+/// * `document` - Path of the document
+/// * `trusted_folders` - Folders the user has trusted
+///
+/// # Returns
+///
+/// * `TrustLevel` - Trusted or restricted
+pub fn trust_level(document: &Path, trusted_folders: &[PathBuf]) -> TrustLevel {
+    let document = workspace::canonical_path(document);
+    let trusted = trusted_folders
+        .iter()
+        .any(|folder| document.starts_with(workspace::canonical_path(folder)));
+    if trusted {
+        TrustLevel::Trusted
+    } else {
+        TrustLevel::Restricted
+    }
+}
+
+/// Adds a folder to the trusted folders, skipping folders already covered.
+///
+/// # Arguments
+///
+/// * `trusted_folders` - The trusted folders to update
+/// * `folder` - The folder to trust
+///
+/// # Returns
+///
+/// * `bool` - True if the list changed
+pub fn trust_folder(trusted_folders: &mut Vec<PathBuf>, folder: &Path) -> bool {
+    let folder = workspace::canonical_path(folder);
+    if trusted_folders
+        .iter()
+        .any(|existing| folder.starts_with(workspace::canonical_path(existing)))
+    {
+        return false;
+    }
+    trusted_folders.push(folder);
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_trust_level() {
+        let temp_dir = tempdir().unwrap();
+        let trusted = temp_dir.path().join("work");
+        let other = temp_dir.path().join("downloads");
+        fs::create_dir_all(trusted.join("docs")).unwrap();
+        fs::create_dir_all(&other).unwrap();
+        fs::write(trusted.join("docs/a.md"), "# A").unwrap();
+        fs::write(other.join("b.md"), "# B").unwrap();
+
+        let mut folders = Vec::new();
+        assert_eq!(
+            trust_level(&trusted.join("docs/a.md"), &folders),
+            TrustLevel::Restricted
+        );
+
+        assert!(trust_folder(&mut folders, &trusted));
+        assert!(!trust_folder(&mut folders, &trusted.join("docs")));
+        assert_eq!(folders.len(), 1);
+        assert_eq!(
+            trust_level(&trusted.join("docs/a.md"), &folders),
+            TrustLevel::Trusted
+        );
+        assert_eq!(
+            trust_level(&other.join("b.md"), &folders),
+            TrustLevel::Restricted
+        );
+        assert_eq!(
+            trust_level(&trusted.join("../downloads/b.md"), &folders),
+            TrustLevel::Restricted
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlink_out_of_trusted_folder() {
+        let temp_dir = tempdir().unwrap();
+        let trusted = temp_dir.path().join("work");
+        let other = temp_dir.path().join("downloads");
+        fs::create_dir_all(&trusted).unwrap();
+        fs::create_dir_all(&other).unwrap();
+        fs::write(other.join("b.md"), "# B").unwrap();
+        std::os::unix::fs::symlink(other.join("b.md"), trusted.join("b.md")).unwrap();
+
+        let folders = vec![trusted.clone()];
+        assert_eq!(
+            trust_level(&trusted.join("b.md"), &folders),
+            TrustLevel::Restricted
+        );
+    }
+}
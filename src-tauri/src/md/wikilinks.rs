@@ -0,0 +1,479 @@
+use super::parser::{self, escape_html, HtmlFragments, RenderConfig, RenderContext};
+use super::{loader, project, toc, workspace};
+use comrak::nodes::{AstNode, NodeLink, NodeValue};
+use std::path::{Path, PathBuf};
+
+/// Maximum nesting depth for `![[note]]` transclusion.
+const MAX_EMBED_DEPTH: usize = 3;
+
+/// A parsed wikilink target such as `Page#Heading|alias`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WikiTarget {
+    /// The page name or path (empty for links within the same note)
+    pub page: String,
+    /// The heading within the page, if any
+    pub heading: Option<String>,
+    /// The display text after `|`, if any
+    pub alias: Option<String>,
+}
+
+impl WikiTarget {
+    /// Parses the text between `[[` and `]]`.
+    pub fn parse(inner: &str) -> Self {
+        let (target, alias) = match inner.split_once('|') {
+            Some((target, alias)) => (target, Some(alias.trim().to_string())),
+            None => (inner, None),
+        };
+        let (page, heading) = match target.split_once('#') {
+            Some((page, heading)) => (page, Some(heading.trim().to_string())),
+            None => (target, None),
+        };
+
+        Self {
+            page: page.trim().to_string(),
+            heading: heading.filter(|h| !h.is_empty()),
+            alias: alias.filter(|a| !a.is_empty()),
+        }
+    }
+
+    /// Returns the text shown for the link.
+    pub fn label(&self) -> String {
+        if let Some(alias) = &self.alias {
+            return alias.clone();
+        }
+        match (&self.heading, self.page.is_empty()) {
+            (Some(heading), true) => heading.clone(),
+            (Some(heading), false) => format!("{} > {}", self.page, heading),
+            (None, _) => self.page.clone(),
+        }
+    }
+}
+
+/// Index of the notes in a workspace, used to resolve wikilinks by name.
+#[derive(Debug, Clone, Default)]
+pub struct WikiIndex {
+    notes: Vec<PathBuf>,
+}
+
+impl WikiIndex {
+    /// Indexes all Markdown files below a workspace root.
+    pub fn build<P: AsRef<Path>>(root: P) -> Self {
+        Self {
+            notes: project::find_markdown_files(root),
+        }
+    }
+
+    /// Resolves a page name to a note.
+    ///
+    /// Names are matched case-insensitively against the file name without its
+    /// extension; names containing `/` must match the end of the note's path.
+    /// When several notes match, one in `from_dir` wins, then the shortest path.
+    ///
+    /// # Arguments
+    ///
+    /// * `page` - The page name from the wikilink
+    /// * `from_dir` - Directory of the linking document, if known
+    ///
+    /// # Returns
+    ///
+    /// * `Option<PathBuf>` - The matching note, if any
+    pub fn resolve(&self, page: &str, from_dir: Option<&Path>) -> Option<PathBuf> {
+        let wanted = strip_markdown_extension(page)
+            .replace('\\', "/")
+            .to_lowercase();
+        if wanted.is_empty() {
+            return None;
+        }
+
+        let mut matches: Vec<&PathBuf> = self
+            .notes
+            .iter()
+            .filter(|note| {
+                let stem = note.with_extension("");
+                let stem = stem.to_string_lossy().replace('\\', "/").to_lowercase();
+                stem == wanted || stem.ends_with(&format!("/{}", wanted))
+            })
+            .collect();
+
+        matches.sort_by_key(|note| {
+            let same_dir = from_dir.is_some_and(|dir| note.parent() == Some(dir));
+            (!same_dir, note.components().count(), note.to_path_buf())
+        });
+
+        matches.first().map(|note| note.to_path_buf())
+    }
+}
+
+/// Strips a Markdown extension from a page name, if present.
+fn strip_markdown_extension(page: &str) -> &str {
+    match page.rsplit_once('.') {
+        Some((stem, _)) if workspace::is_markdown_path(page) => stem,
+        _ => page,
+    }
+}
+
+/// Rewrites wikilinks and embeds in a parsed document.
+///
+/// Resolved `[[links]]` become ordinary relative links, unresolved ones are
+/// rendered as `wikilink-unresolved` spans, and `![[note]]` embeds are
+/// replaced with the rendered content of the note (or one of its sections).
+pub(crate) fn process<'a>(
+    root: &'a AstNode<'a>,
+    context: &RenderContext,
+    fragments: &mut HtmlFragments,
+    embed_stack: &mut Vec<PathBuf>,
+) {
+    // Only index the workspace when the document actually uses wikilinks
+    let mut index: Option<WikiIndex> = None;
+
+    for node in root.descendants().collect::<Vec<_>>() {
+        let value = node.data.borrow().value.clone();
+        match value {
+            NodeValue::WikiLink(link) => {
+                let mut target = WikiTarget::parse(&link.url);
+                let text = toc::extract_text(node);
+                if target.alias.is_none() && text != link.url {
+                    target.alias = Some(text);
+                }
+
+                let index = index.get_or_insert_with(|| build_index(context));
+                rewrite_link(node, &target, index, context, fragments);
+            }
+            NodeValue::Text(text) if text.contains("![[") => {
+                let index = index.get_or_insert_with(|| build_index(context));
+                let replaced = replace_embeds(&text, index, context, fragments, embed_stack);
+                node.data.borrow_mut().value = NodeValue::Text(replaced);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Builds the note index for the context's workspace.
+fn build_index(context: &RenderContext) -> WikiIndex {
+    context
+        .workspace_root
+        .as_ref()
+        .map(WikiIndex::build)
+        .unwrap_or_default()
+}
+
+/// Replaces a WikiLink node with a resolved link or an unresolved marker.
+fn rewrite_link<'a>(
+    node: &'a AstNode<'a>,
+    target: &WikiTarget,
+    index: &WikiIndex,
+    context: &RenderContext,
+    fragments: &mut HtmlFragments,
+) {
+    let anchor = target
+        .heading
+        .as_ref()
+        .map(|heading| format!("#{}", toc::generate_id(heading)))
+        .unwrap_or_default();
+
+    let url = if target.page.is_empty() {
+        Some(anchor)
+    } else {
+        index
+            .resolve(&target.page, context.document_dir())
+            .map(|note| format!("{}{}", relative_url(context.document_dir(), &note), anchor))
+    };
+
+    match url {
+        Some(url) => {
+            node.data.borrow_mut().value = NodeValue::Link(NodeLink {
+                url,
+                title: String::new(),
+            });
+        }
+        None => {
+            let html = format!(
+                "<a class=\"wikilink wikilink-unresolved\" title=\"{}\">{}</a>",
+                escape_html(&format!("No note named '{}'", target.page)),
+                escape_html(&target.label())
+            );
+
+            for child in node.children().collect::<Vec<_>>() {
+                child.detach();
+            }
+            node.data.borrow_mut().value = NodeValue::Text(fragments.insert(html));
+        }
+    }
+}
+
+/// Replaces every `![[embed]]` in a text node with a transclusion placeholder.
+fn replace_embeds(
+    text: &str,
+    index: &WikiIndex,
+    context: &RenderContext,
+    fragments: &mut HtmlFragments,
+    embed_stack: &mut Vec<PathBuf>,
+) -> String {
+    let mut result = String::new();
+    let mut rest = text;
+
+    while let Some(start) = rest.find("![[") {
+        let Some(len) = rest[start + 3..].find("]]") else {
+            break;
+        };
+
+        result.push_str(&rest[..start]);
+        let target = WikiTarget::parse(&rest[start + 3..start + 3 + len]);
+        let html = embed_html(&target, index, context, embed_stack);
+        result.push_str(&fragments.insert(html));
+
+        rest = &rest[start + 3 + len + 2..];
+    }
+
+    result.push_str(rest);
+    result
+}
+
+/// Renders the HTML for a single `![[embed]]`.
+fn embed_html(
+    target: &WikiTarget,
+    index: &WikiIndex,
+    context: &RenderContext,
+    embed_stack: &mut Vec<PathBuf>,
+) -> String {
+    let note = if target.page.is_empty() {
+        context.document_path.clone()
+    } else {
+        index.resolve(&target.page, context.document_dir())
+    };
+
+    let unresolved = |reason: &str| {
+        format!(
+            "<span class=\"wikilink wikilink-unresolved wikilink-embed-missing\" title=\"{}\">{}</span>",
+            escape_html(reason),
+            escape_html(&target.label())
+        )
+    };
+
+    let Some(note) = note else {
+        return unresolved(&format!("No note named '{}'", target.page));
+    };
+
+    let key = note.canonicalize().unwrap_or_else(|_| note.clone());
+    if embed_stack.contains(&key) || embed_stack.len() >= MAX_EMBED_DEPTH {
+        return unresolved("Embed skipped: the note embeds itself or is nested too deeply");
+    }
+
+    let markdown = match loader::load_markdown_file(&note) {
+        Ok(markdown) => markdown,
+        Err(e) => return unresolved(&e.to_string()),
+    };
+
+    let markdown = match &target.heading {
+        Some(heading) => match section(&markdown, &toc::generate_id(heading)) {
+            Some(section) => section,
+            None => return unresolved(&format!("No heading '{}' in '{}'", heading, target.page)),
+        },
+        None => markdown,
+    };
+
+    let embed_context = RenderContext {
+        document_path: Some(note.clone()),
+        workspace_root: context.workspace_root.clone(),
+        // Source lines of embedded notes don't belong to the embedding document
+        config: RenderConfig {
+            interactive_tasks: false,
+            ..context.config.clone()
+        },
+        // Embeds are rendered with the syntax of the document they appear in
+        project: context.project.clone(),
+    };
+
+    embed_stack.push(key);
+    let content = parser::render_nested(&markdown, &embed_context, embed_stack);
+    embed_stack.pop();
+
+    format!(
+        "<div class=\"wikilink-embed\" data-source=\"{}\"><div class=\"wikilink-embed-title\">{}</div>{}</div>",
+        escape_html(&relative_url(context.document_dir(), &note)),
+        escape_html(&target.label()),
+        content
+    )
+}
+
+/// Extracts the section starting at a heading, up to the next heading of the
+/// same or a higher level.
+fn section(markdown: &str, heading_id: &str) -> Option<String> {
+    let toc = toc::extract_toc(markdown);
+    let position = toc.iter().position(|item| item.id == heading_id)?;
+    let heading = &toc[position];
+    let start = heading.line_number?;
+
+    let end = toc[position + 1..]
+        .iter()
+        .find(|item| item.level <= heading.level)
+        .and_then(|item| item.line_number);
+
+    let lines: Vec<&str> = markdown.lines().collect();
+    let end = end.map(|line| line - 1).unwrap_or(lines.len());
+
+    Some(lines[start - 1..end.max(start - 1)].join("\n"))
+}
+
+/// Builds a URL for `target` relative to `from_dir`, escaping characters that
+/// have a meaning in URLs.
+fn relative_url(from_dir: Option<&Path>, target: &Path) -> String {
+    let relative = match from_dir {
+        Some(dir) => relative_path(dir, target),
+        None => target.to_path_buf(),
+    };
+
+    relative
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().into_owned())
+        .filter(|c| c != "/" && c != "\\")
+        .map(|c| {
+            c.replace('%', "%25")
+                .replace('#', "%23")
+                .replace('?', "%3F")
+                .replace(' ', "%20")
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Computes the path of `target` relative to the directory `from`.
+fn relative_path(from: &Path, target: &Path) -> PathBuf {
+    let from =
+        workspace::normalize_path(from.canonicalize().unwrap_or_else(|_| from.to_path_buf()));
+    let target = workspace::normalize_path(
+        target
+            .canonicalize()
+            .unwrap_or_else(|_| target.to_path_buf()),
+    );
+
+    let from_parts: Vec<_> = from.components().collect();
+    let target_parts: Vec<_> = target.components().collect();
+    let common = from_parts
+        .iter()
+        .zip(&target_parts)
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    if common == 0 {
+        return target;
+    }
+
+    let mut relative = PathBuf::new();
+    for _ in common..from_parts.len() {
+        relative.push("..");
+    }
+    for part in &target_parts[common..] {
+        relative.push(part);
+    }
+    relative
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    fn vault() -> (tempfile::TempDir, PathBuf) {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path().canonicalize().unwrap();
+        fs::create_dir_all(root.join(".obsidian")).unwrap();
+        fs::create_dir_all(root.join("notes/projects")).unwrap();
+        fs::write(
+            root.join("notes/Page Name.md"),
+            "# Page Name\n\nIntro.\n\n## Heading\n\nSection body.\n\n## Other\n\nOther body.\n",
+        )
+        .unwrap();
+        fs::write(root.join("notes/projects/Plan.md"), "# Plan\n\n![[Plan]]\n").unwrap();
+        (temp_dir, root)
+    }
+
+    #[test]
+    fn test_parse_target() {
+        assert_eq!(
+            WikiTarget::parse("Page#Heading|alias"),
+            WikiTarget {
+                page: "Page".to_string(),
+                heading: Some("Heading".to_string()),
+                alias: Some("alias".to_string()),
+            }
+        );
+        assert_eq!(WikiTarget::parse("Page").label(), "Page");
+        assert_eq!(WikiTarget::parse("#Heading").label(), "Heading");
+        assert_eq!(WikiTarget::parse("Page#Heading").label(), "Page > Heading");
+    }
+
+    #[test]
+    fn test_index_resolve() {
+        let (_temp_dir, root) = vault();
+        let index = WikiIndex::build(&root);
+
+        assert_eq!(
+            index.resolve("page name", None),
+            Some(root.join("notes/Page Name.md"))
+        );
+        assert_eq!(
+            index.resolve("projects/Plan.md", None),
+            Some(root.join("notes/projects/Plan.md"))
+        );
+        assert_eq!(index.resolve("Missing", None), None);
+    }
+
+    #[test]
+    fn test_render_resolved_and_unresolved_links() {
+        let (_temp_dir, root) = vault();
+        let doc = root.join("index.md");
+        let context = RenderContext::for_document(&doc);
+
+        let html = parser::render_markdown(
+            "See [[Page Name]], [[Page Name#Heading|the heading]] and [[Nowhere]].",
+            &context,
+        );
+
+        assert!(html.contains("<a href=\"notes/Page%20Name.md\">Page Name</a>"));
+        assert!(html.contains("<a href=\"notes/Page%20Name.md#heading\">the heading</a>"));
+        assert!(html.contains("wikilink-unresolved"));
+        assert!(html.contains(">Nowhere</a>"));
+    }
+
+    #[test]
+    fn test_render_embed_section() {
+        let (_temp_dir, root) = vault();
+        let context = RenderContext::for_document(root.join("index.md"));
+
+        let html = parser::render_markdown("![[Page Name#Heading]]", &context);
+
+        assert!(html.starts_with("<div class=\"wikilink-embed\""));
+        assert!(html.contains("Section body."));
+        assert!(!html.contains("Other body."));
+        assert!(!html.contains("<p><div"));
+    }
+
+    #[test]
+    fn test_render_embed_cycle_is_stopped() {
+        let (_temp_dir, root) = vault();
+        let plan = root.join("notes/projects/Plan.md");
+        let context = RenderContext::for_document(&plan);
+
+        let html = parser::render_markdown("![[Plan]]", &context);
+
+        assert!(html.contains("wikilink-embed-missing"));
+    }
+
+    #[test]
+    fn test_unresolved_label_is_escaped() {
+        let html = parser::markdown_to_html("[[<script>alert(1)</script>]]");
+
+        assert!(!html.contains("<script>"));
+    }
+
+    #[test]
+    fn test_relative_url() {
+        assert_eq!(
+            relative_url(Some(Path::new("/a/b")), Path::new("/a/c/My Note#1.md")),
+            "../c/My%20Note%231.md"
+        );
+    }
+}
@@ -0,0 +1,237 @@
+use super::archive::ArchivePath;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+/// File extensions recognised as Markdown documents.
+pub const MARKDOWN_EXTENSIONS: &[&str] = &["md", "markdown", "mdown", "mkd", "mdx"];
+
+/// Entries that mark the root directory of a workspace.
+const WORKSPACE_MARKERS: &[&str] = &[".git", ".obsidian"];
+
+/// Extensions of compressed files, which count as Markdown when the name
+/// underneath has a Markdown extension (e.g. `notes.md.gz`).
+pub const COMPRESSED_EXTENSIONS: &[&str] = &["gz", "zst", "bz2", "xz"];
+
+/// Returns true if the path has a Markdown file extension, optionally
+/// followed by a compression extension.
+pub fn is_markdown_path<P: AsRef<Path>>(path: P) -> bool {
+    let path = path.as_ref();
+    let compressed = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| {
+            COMPRESSED_EXTENSIONS
+                .iter()
+                .any(|compressed| compressed.eq_ignore_ascii_case(ext))
+        });
+    let path = if compressed {
+        Path::new(path.file_stem().unwrap_or_default())
+    } else {
+        path
+    };
+
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| {
+            MARKDOWN_EXTENSIONS
+                .iter()
+                .any(|md| md.eq_ignore_ascii_case(ext))
+        })
+        .unwrap_or(false)
+}
+
+/// Finds the workspace root for a document.
+///
+/// The root is the nearest ancestor directory containing a `.git` entry or an
+/// Obsidian vault's `.obsidian` folder, or the document's own directory if
+/// there is none. A document inside an archive has the archive itself as
+/// its workspace, written as `bundle.zip!`. The result is
+/// canonicalized when possible.
+///
+/// # Arguments
+///
+/// * `document` - Path to a Markdown document
+///
+/// # Returns
+///
+/// * `PathBuf` - The workspace root directory
+pub fn find_root<P: AsRef<Path>>(document: P) -> PathBuf {
+    let document = document.as_ref();
+    if let Some(mut archived) = ArchivePath::from_path(document) {
+        archived.archive = archived
+            .archive
+            .canonicalize()
+            .unwrap_or_else(|_| normalize_path(&archived.archive));
+        return PathBuf::from(archived.root());
+    }
+    let document = document
+        .canonicalize()
+        .unwrap_or_else(|_| normalize_path(document));
+    let dir = document.parent().unwrap_or_else(|| Path::new("/"));
+
+    dir.ancestors()
+        .find(|ancestor| {
+            WORKSPACE_MARKERS
+                .iter()
+                .any(|marker| ancestor.join(marker).exists())
+        })
+        .unwrap_or(dir)
+        .to_path_buf()
+}
+
+/// Lexically normalizes a path by resolving `.` and `..` components.
+///
+/// Unlike `canonicalize`, this works for paths that don't exist and does
+/// not resolve symlinks.
+pub fn normalize_path<P: AsRef<Path>>(path: P) -> PathBuf {
+    let mut normalized = PathBuf::new();
+
+    for component in path.as_ref().components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !normalized.pop() {
+                    normalized.push(component);
+                }
+            }
+            other => normalized.push(other),
+        }
+    }
+
+    normalized
+}
+
+/// Canonicalizes a path, falling back to lexical normalization if it can't
+/// be resolved, for example because it doesn't exist.
+pub fn canonical_path<P: AsRef<Path>>(path: P) -> PathBuf {
+    let path = path.as_ref();
+    path.canonicalize().unwrap_or_else(|_| normalize_path(path))
+}
+
+/// Recursively finds all Markdown files below a directory.
+///
+/// Hidden files and directories (names starting with `.`) are skipped.
+/// Unreadable directories are ignored. The result is sorted for stable output.
+///
+/// # Arguments
+///
+/// * `root` - The directory to search
+///
+/// # Returns
+///
+/// * `Vec<PathBuf>` - Paths of all Markdown files found
+pub fn find_markdown_files<P: AsRef<Path>>(root: P) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    collect_markdown_files(root.as_ref(), &mut files);
+    files.sort();
+    files
+}
+
+/// Recursively collects Markdown files into `files`.
+fn collect_markdown_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let hidden = entry.file_name().to_string_lossy().starts_with('.');
+        if hidden {
+            continue;
+        }
+
+        match entry.file_type() {
+            Ok(file_type) if file_type.is_dir() => collect_markdown_files(&path, files),
+            Ok(_) if path.is_file() && is_markdown_path(&path) => files.push(path),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_is_markdown_path() {
+        assert!(is_markdown_path("README.md"));
+        assert!(is_markdown_path("notes.MARKDOWN"));
+        assert!(is_markdown_path("docs/page.mdx"));
+        assert!(!is_markdown_path("image.png"));
+        assert!(!is_markdown_path("Makefile"));
+        assert!(is_markdown_path("CHANGELOG.md.gz"));
+        assert!(is_markdown_path("notes/2023.markdown.ZST"));
+        assert!(!is_markdown_path("release.tar.gz"));
+        assert!(!is_markdown_path("md.gz"));
+    }
+
+    #[test]
+    fn test_canonical_path() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path().canonicalize().unwrap();
+        fs::create_dir(root.join("docs")).unwrap();
+
+        assert_eq!(canonical_path(root.join("docs/./../docs")), root.join("docs"));
+        assert_eq!(
+            canonical_path(root.join("docs/../missing/./a.md")),
+            root.join("missing/a.md")
+        );
+    }
+
+    #[test]
+    fn test_normalize_path() {
+        assert_eq!(
+            normalize_path("/docs/guide/../api/./index.md"),
+            PathBuf::from("/docs/api/index.md")
+        );
+        assert_eq!(normalize_path("../a.md"), PathBuf::from("../a.md"));
+    }
+
+    #[test]
+    fn test_find_root() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path().canonicalize().unwrap();
+        fs::create_dir_all(root.join("repo/.git")).unwrap();
+        fs::create_dir_all(root.join("repo/docs")).unwrap();
+        fs::create_dir_all(root.join("loose")).unwrap();
+        File::create(root.join("repo/docs/guide.md")).unwrap();
+        File::create(root.join("loose/note.md")).unwrap();
+
+        assert_eq!(
+            find_root(root.join("repo/docs/guide.md")),
+            root.join("repo")
+        );
+        assert_eq!(find_root(root.join("loose/note.md")), root.join("loose"));
+        assert_eq!(
+            find_root(root.join("repo/docs/bundle.zip!/guide/index.md")),
+            root.join("repo/docs/bundle.zip!")
+        );
+    }
+
+    #[test]
+    fn test_find_markdown_files() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir_all(root.join("docs/nested")).unwrap();
+        fs::create_dir_all(root.join(".git")).unwrap();
+        File::create(root.join("README.md")).unwrap();
+        File::create(root.join("docs/guide.markdown")).unwrap();
+        File::create(root.join("docs/nested/deep.md")).unwrap();
+        File::create(root.join("docs/image.png")).unwrap();
+        File::create(root.join(".git/HEAD.md")).unwrap();
+
+        let files = find_markdown_files(root);
+
+        assert_eq!(
+            files,
+            vec![
+                root.join("README.md"),
+                root.join("docs/guide.markdown"),
+                root.join("docs/nested/deep.md"),
+            ]
+        );
+    }
+}
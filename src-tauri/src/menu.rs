@@ -1,13 +1,25 @@
+use crate::state::AppState;
+use crate::themes::{self, Theme, ThemeWatcher};
+use std::sync::Mutex;
 use tauri::{
-    menu::{MenuBuilder, MenuItemBuilder, SubmenuBuilder},
-    AppHandle, Emitter, Runtime,
+    menu::{MenuBuilder, MenuItemBuilder, PredefinedMenuItem, Submenu, SubmenuBuilder},
+    AppHandle, Emitter, Manager, Runtime,
 };
 
+/// Prefix of the menu item ids in the Theme submenu, followed by the theme id.
+const THEME_ITEM_PREFIX: &str = "theme:";
+
+/// Id of the View menu.
+const VIEW_MENU_ID: &str = "view";
+
+/// Id of the Theme submenu.
+const THEME_MENU_ID: &str = "theme";
+
 /// Creates and builds the native application menu.
 ///
 /// This function constructs a platform-native menu bar with the following structure:
-/// - macOS: App menu (About, Quit), File (Open), Edit (Copy, Search), View (Zoom controls)
-/// - Windows/Linux: File (Open, Quit), Edit (Copy, Search), View (Zoom controls), Help (About)
+/// - macOS: App menu (About, Quit), File (Open, Export PDF/EPUB/Slides), Edit (Copy, Search, External Editor), View (Zoom controls, Theme, Presentation, Configuration)
+/// - Windows/Linux: File (Open, Export PDF/EPUB/Slides, Quit), Edit (Copy, Search, External Editor), View (Zoom controls, Theme, Presentation, Configuration), Help (About)
 ///
 /// Menu actions emit events to the frontend for handling.
 pub fn build_menu<R: Runtime>(app: &AppHandle<R>) -> tauri::Result<tauri::menu::Menu<R>> {
@@ -15,6 +27,16 @@ pub fn build_menu<R: Runtime>(app: &AppHandle<R>) -> tauri::Result<tauri::menu::
         .accelerator("CmdOrCtrl+O")
         .build(app)?;
 
+    let export_pdf = MenuItemBuilder::with_id("export-pdf", "Export as PDF...")
+        .accelerator("CmdOrCtrl+Shift+E")
+        .build(app)?;
+
+    let export_epub =
+        MenuItemBuilder::with_id("export-epub", "Export Folder as EPUB...").build(app)?;
+
+    let export_slides =
+        MenuItemBuilder::with_id("export-slides", "Export as Slide Deck...").build(app)?;
+
     let prev_file = MenuItemBuilder::with_id("prev-file", "Previous File")
         .accelerator("CmdOrCtrl+Left")
         .build(app)?;
@@ -33,9 +55,12 @@ pub fn build_menu<R: Runtime>(app: &AppHandle<R>) -> tauri::Result<tauri::menu::
     let file_menu = {
         #[cfg(target_os = "macos")]
         {
-            // On macOS, File menu only has Open (Quit is in app menu)
+            // On macOS, File menu has no Quit (it is in the app menu)
             SubmenuBuilder::new(app, "File")
                 .item(&open)
+                .item(&export_pdf)
+                .item(&export_epub)
+                .item(&export_slides)
                 .separator()
                 .item(&prev_file)
                 .item(&next_file)
@@ -44,9 +69,12 @@ pub fn build_menu<R: Runtime>(app: &AppHandle<R>) -> tauri::Result<tauri::menu::
 
         #[cfg(not(target_os = "macos"))]
         {
-            // On Windows/Linux, File menu has Open and Quit
+            // On Windows/Linux, File menu also has Quit
             SubmenuBuilder::new(app, "File")
                 .item(&open)
+                .item(&export_pdf)
+                .item(&export_epub)
+                .item(&export_slides)
                 .separator()
                 .item(&prev_file)
                 .item(&next_file)
@@ -65,10 +93,16 @@ pub fn build_menu<R: Runtime>(app: &AppHandle<R>) -> tauri::Result<tauri::menu::
         .accelerator("CmdOrCtrl+F")
         .build(app)?;
 
+    let open_in_editor = MenuItemBuilder::with_id("open-in-editor", "Edit in External Editor")
+        .accelerator("CmdOrCtrl+E")
+        .build(app)?;
+
     let edit_menu = SubmenuBuilder::new(app, "Edit")
         .item(&copy)
         .separator()
         .item(&search)
+        .separator()
+        .item(&open_in_editor)
         .build()?;
 
     // View menu
@@ -84,11 +118,34 @@ pub fn build_menu<R: Runtime>(app: &AppHandle<R>) -> tauri::Result<tauri::menu::
         .accelerator("CmdOrCtrl+0")
         .build(app)?;
 
-    let view_menu = SubmenuBuilder::new(app, "View")
+    let start_presentation = MenuItemBuilder::with_id("start-presentation", "Start Presentation")
+        .accelerator("F5")
+        .build(app)?;
+
+    let show_config =
+        MenuItemBuilder::with_id("show-config", "Show Effective Configuration").build(app)?;
+
+    // Theme submenu, generated from the built-in and user themes until a
+    // document is loaded (see `refresh_theme_menu`)
+    let config_dir = app.path().app_config_dir().ok();
+    let theme_menu = SubmenuBuilder::with_id(app, THEME_MENU_ID, "Theme").build()?;
+    append_theme_items(
+        app,
+        &theme_menu,
+        &themes::list_themes(config_dir.as_deref(), None),
+    )?;
+
+    let view_menu = SubmenuBuilder::with_id(app, VIEW_MENU_ID, "View")
         .item(&zoom_in)
         .item(&zoom_out)
         .separator()
         .item(&zoom_reset)
+        .separator()
+        .item(&theme_menu)
+        .separator()
+        .item(&start_presentation)
+        .separator()
+        .item(&show_config)
         .build()?;
 
     // Build complete menu
@@ -127,6 +184,64 @@ pub fn build_menu<R: Runtime>(app: &AppHandle<R>) -> tauri::Result<tauri::menu::
     Ok(menu)
 }
 
+/// Rebuilds the Theme submenu with the themes available for the current document.
+///
+/// Called when a document is loaded and when a theme folder changes. The
+/// `ThemeWatcher`, if the app manages one, is pointed at the user themes
+/// folder and the current project's themes folder.
+///
+/// # Arguments
+///
+/// * `app` - The application handle
+pub fn refresh_theme_menu<R: Runtime>(app: &AppHandle<R>) -> tauri::Result<()> {
+    let config_dir = app.path().app_config_dir().ok();
+    let project = app.state::<AppState>().current_project();
+    if let Some(watcher) = app.try_state::<Mutex<ThemeWatcher>>() {
+        let dirs = themes::theme_dirs(config_dir.as_deref(), project.as_ref());
+        watcher.lock().unwrap().watch(&dirs);
+    }
+
+    let Some(view_menu) = app.menu().and_then(|menu| menu.get(VIEW_MENU_ID)) else {
+        return Ok(());
+    };
+    let Some(theme_menu) = view_menu
+        .as_submenu()
+        .and_then(|view_menu| view_menu.get(THEME_MENU_ID))
+    else {
+        return Ok(());
+    };
+    let Some(theme_menu) = theme_menu.as_submenu() else {
+        return Ok(());
+    };
+
+    for item in theme_menu.items()? {
+        theme_menu.remove(&item)?;
+    }
+    append_theme_items(
+        app,
+        theme_menu,
+        &themes::list_themes(config_dir.as_deref(), project.as_ref()),
+    )
+}
+
+/// Adds "Follow System" and an item per theme to the Theme submenu.
+fn append_theme_items<R: Runtime>(
+    app: &AppHandle<R>,
+    theme_menu: &Submenu<R>,
+    themes: &[Theme],
+) -> tauri::Result<()> {
+    let follow_system = MenuItemBuilder::with_id("theme-system", "Follow System").build(app)?;
+    theme_menu.append(&follow_system)?;
+    theme_menu.append(&PredefinedMenuItem::separator(app)?)?;
+    for theme in themes {
+        let item =
+            MenuItemBuilder::with_id(format!("{}{}", THEME_ITEM_PREFIX, theme.id), &theme.name)
+                .build(app)?;
+        theme_menu.append(&item)?;
+    }
+    Ok(())
+}
+
 /// Sets up menu event handlers.
 ///
 /// This function registers a handler for all menu events, emitting corresponding
@@ -142,6 +257,21 @@ pub fn setup_menu_handlers<R: Runtime>(app: &AppHandle<R>) {
                     eprintln!("Failed to emit menu-open event: {}", e);
                 }
             }
+            "export-pdf" => {
+                if let Err(e) = app.emit("menu-export-pdf", ()) {
+                    eprintln!("Failed to emit menu-export-pdf event: {}", e);
+                }
+            }
+            "export-epub" => {
+                if let Err(e) = app.emit("menu-export-epub", ()) {
+                    eprintln!("Failed to emit menu-export-epub event: {}", e);
+                }
+            }
+            "export-slides" => {
+                if let Err(e) = app.emit("menu-export-slides", ()) {
+                    eprintln!("Failed to emit menu-export-slides event: {}", e);
+                }
+            }
             "prev-file" => {
                 if let Err(e) = app.emit("menu-prev-file", ()) {
                     eprintln!("Failed to emit menu-prev-file event: {}", e);
@@ -165,6 +295,11 @@ pub fn setup_menu_handlers<R: Runtime>(app: &AppHandle<R>) {
                     eprintln!("Failed to emit menu-search event: {}", e);
                 }
             }
+            "open-in-editor" => {
+                if let Err(e) = app.emit("menu-open-in-editor", ()) {
+                    eprintln!("Failed to emit menu-open-in-editor event: {}", e);
+                }
+            }
             "zoom-in" => {
                 if let Err(e) = app.emit("menu-zoom-in", ()) {
                     eprintln!("Failed to emit menu-zoom-in event: {}", e);
@@ -180,13 +315,34 @@ pub fn setup_menu_handlers<R: Runtime>(app: &AppHandle<R>) {
                     eprintln!("Failed to emit menu-zoom-reset event: {}", e);
                 }
             }
+            "start-presentation" => {
+                if let Err(e) = app.emit("menu-start-presentation", ()) {
+                    eprintln!("Failed to emit menu-start-presentation event: {}", e);
+                }
+            }
+            "show-config" => {
+                if let Err(e) = app.emit("menu-show-config", ()) {
+                    eprintln!("Failed to emit menu-show-config event: {}", e);
+                }
+            }
+            "theme-system" => {
+                if let Err(e) = app.emit("menu-set-theme", None::<String>) {
+                    eprintln!("Failed to emit menu-set-theme event: {}", e);
+                }
+            }
             "about" => {
                 if let Err(e) = app.emit("menu-about", ()) {
                     eprintln!("Failed to emit menu-about event: {}", e);
                 }
             }
             _ => {
-                eprintln!("Unknown menu event: {}", event_id);
+                if let Some(theme_id) = event_id.strip_prefix(THEME_ITEM_PREFIX) {
+                    if let Err(e) = app.emit("menu-set-theme", theme_id) {
+                        eprintln!("Failed to emit menu-set-theme event: {}", e);
+                    }
+                } else {
+                    eprintln!("Unknown menu event: {}", event_id);
+                }
             }
         }
     });
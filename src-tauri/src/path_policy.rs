@@ -0,0 +1,407 @@
+use crate::md::archive::{self, ArchivePath};
+use crate::md::workspace;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Errors returned when a path may not be opened.
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub enum PathPolicyError {
+    /// The path doesn't exist or isn't a regular file
+    #[error("File not found: {0}")]
+    NotFound(String),
+
+    /// The path (after resolving symlinks) doesn't have a Markdown extension
+    #[error("Not a Markdown file: {0}")]
+    NotMarkdown(String),
+
+    /// The path is outside the approved folders and wasn't opened by the user
+    #[error("Access denied: {0} is outside the folders mdview is allowed to open")]
+    Denied(String),
+}
+
+/// Decides which files the webview may ask the backend to open.
+///
+/// Paths are canonicalized before checking, so `..` segments and symlinks
+/// can't lead outside an approved folder. A file is allowed if it is a
+/// Markdown file and either lies under an approved root or was explicitly
+/// opened by the user. Files inside an archive (`bundle.zip!/docs/index.md`)
+/// count as lying below the archive.
+///
+/// The policy locks itself, and only after paths have been resolved, so
+/// slow filesystems don't hold up other commands.
+#[derive(Debug, Default)]
+pub struct PathPolicy {
+    approved: Mutex<Approved>,
+}
+
+/// Folders and files approved so far.
+#[derive(Debug, Default)]
+struct Approved {
+    /// Canonical folders whose Markdown files may be opened
+    roots: Vec<PathBuf>,
+    /// Canonical files the user opened explicitly
+    files: HashSet<PathBuf>,
+}
+
+impl PathPolicy {
+    /// Creates a policy that allows the given folders.
+    ///
+    /// Folders that don't exist are reported and skipped.
+    ///
+    /// # Arguments
+    ///
+    /// * `roots` - Folders approved in the user's settings
+    pub fn new<I, P>(roots: I) -> Self
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<Path>,
+    {
+        let mut approved = Approved::default();
+        for root in roots {
+            let root = root.as_ref();
+            match root.canonicalize() {
+                Ok(root) if root.is_dir() => approved.roots.push(root),
+                _ => eprintln!("Ignoring allowed root {}: not a folder", root.display()),
+            }
+        }
+        Self {
+            approved: Mutex::new(approved),
+        }
+    }
+
+    /// Approves a file the user opened explicitly, e.g. through the open
+    /// dialog or the command line.
+    ///
+    /// The file's own folder is approved too, so links to neighbouring
+    /// documents keep working; the rest of its repository is not. For a file
+    /// inside an archive, or an archive itself, that is the archive.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The file the user chose
+    ///
+    /// # Returns
+    ///
+    /// * `Result<PathBuf, PathPolicyError>` - The canonical path, or an error if
+    ///   it isn't an existing Markdown file
+    pub fn approve_file<P: AsRef<Path>>(&self, path: P) -> Result<PathBuf, PathPolicyError> {
+        let canonical = canonical_markdown(path.as_ref())?;
+        let root = if ArchivePath::from_path(&canonical).is_some() {
+            workspace::find_root(&canonical)
+        } else {
+            canonical
+                .parent()
+                .map_or_else(|| canonical.clone(), Path::to_path_buf)
+        };
+
+        let mut approved = self.approved.lock().unwrap();
+        if !approved
+            .roots
+            .iter()
+            .any(|existing| root.starts_with(existing))
+        {
+            approved.roots.push(root);
+        }
+        approved.files.insert(canonical.clone());
+        Ok(canonical)
+    }
+
+    /// Checks whether a path requested by the webview may be opened.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The requested path
+    ///
+    /// # Returns
+    ///
+    /// * `Result<PathBuf, PathPolicyError>` - The canonical path to open, or the reason it was denied
+    pub fn check<P: AsRef<Path>>(&self, path: P) -> Result<PathBuf, PathPolicyError> {
+        let canonical = canonical_markdown(path.as_ref())?;
+
+        let approved = self.approved.lock().unwrap();
+        let allowed = approved.files.contains(&canonical)
+            || approved
+                .roots
+                .iter()
+                .any(|root| canonical.starts_with(root));
+        if allowed {
+            Ok(canonical)
+        } else {
+            Err(PathPolicyError::Denied(path.as_ref().display().to_string()))
+        }
+    }
+
+    /// Checks whether a folder requested by the webview may be scanned.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The requested folder
+    ///
+    /// # Returns
+    ///
+    /// * `Result<PathBuf, PathPolicyError>` - The canonical folder, or the reason it was denied
+    pub fn check_dir<P: AsRef<Path>>(&self, path: P) -> Result<PathBuf, PathPolicyError> {
+        let not_found = || PathPolicyError::NotFound(path.as_ref().display().to_string());
+        let canonical = path.as_ref().canonicalize().map_err(|_| not_found())?;
+        if !canonical.is_dir() {
+            return Err(not_found());
+        }
+
+        let approved = self.approved.lock().unwrap();
+        if approved
+            .roots
+            .iter()
+            .any(|root| canonical.starts_with(root))
+        {
+            Ok(canonical)
+        } else {
+            Err(PathPolicyError::Denied(path.as_ref().display().to_string()))
+        }
+    }
+}
+
+/// Resolves a path to an existing Markdown file, following symlinks.
+///
+/// An archive resolves to the document it opens at (see `archive::index_file`).
+///
+/// # Arguments
+///
+/// * `path` - The requested path
+///
+/// # Returns
+///
+/// * `Result<PathBuf, PathPolicyError>` - The canonical path, or an error if it
+///   isn't an existing Markdown file
+pub fn canonical_markdown(path: &Path) -> Result<PathBuf, PathPolicyError> {
+    if let Some(archived) = ArchivePath::from_path(path) {
+        return canonical_archived(path, archived);
+    }
+
+    let canonical = path
+        .canonicalize()
+        .map_err(|_| PathPolicyError::NotFound(path.display().to_string()))?;
+    if !canonical.is_file() {
+        return Err(PathPolicyError::NotFound(path.display().to_string()));
+    }
+    if archive::is_archive_path(&canonical) {
+        let index = archive::index_file(&canonical)
+            .map_err(|_| PathPolicyError::NotMarkdown(path.display().to_string()))?;
+        return Ok(PathBuf::from(index.to_string()));
+    }
+    // Check the resolved name so `notes.md -> id_rsa` is rejected
+    if !workspace::is_markdown_path(&canonical) {
+        return Err(PathPolicyError::NotMarkdown(path.display().to_string()));
+    }
+    Ok(canonical)
+}
+
+/// Resolves a path to a Markdown file inside an archive, which must exist.
+fn canonical_archived(path: &Path, archived: ArchivePath) -> Result<PathBuf, PathPolicyError> {
+    let not_found = || PathPolicyError::NotFound(path.display().to_string());
+    let archive = archived.archive.canonicalize().map_err(|_| not_found())?;
+    // As above, check the resolved name
+    if !archive.is_file() || !archive::is_archive_path(&archive) {
+        return Err(not_found());
+    }
+    if !workspace::is_markdown_path(&archived.entry) {
+        return Err(PathPolicyError::NotMarkdown(path.display().to_string()));
+    }
+
+    let archived = ArchivePath {
+        archive,
+        entry: archived.entry,
+    };
+    if !archive::contains(&archived) {
+        return Err(not_found());
+    }
+    Ok(PathBuf::from(archived.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_allowed_roots() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path().join("notes");
+        let outside = temp_dir.path().join("private");
+        fs::create_dir_all(root.join("sub")).unwrap();
+        fs::create_dir_all(&outside).unwrap();
+        fs::write(root.join("sub/a.md"), "# A").unwrap();
+        fs::write(root.join("todo.txt"), "text").unwrap();
+        fs::write(outside.join("secret.md"), "# Secret").unwrap();
+
+        let policy = PathPolicy::new([&root]);
+
+        assert!(policy.check(root.join("sub/a.md")).is_ok());
+        assert!(matches!(
+            policy.check(root.join("todo.txt")),
+            Err(PathPolicyError::NotMarkdown(_))
+        ));
+        assert!(matches!(
+            policy.check(root.join("missing.md")),
+            Err(PathPolicyError::NotFound(_))
+        ));
+        assert!(matches!(
+            policy.check(outside.join("secret.md")),
+            Err(PathPolicyError::Denied(_))
+        ));
+        assert!(matches!(
+            policy.check(root.join("sub/../../private/secret.md")),
+            Err(PathPolicyError::Denied(_))
+        ));
+    }
+
+    #[test]
+    fn test_explicitly_opened_file() {
+        let temp_dir = tempdir().unwrap();
+        let docs = temp_dir.path().join("docs");
+        let other = temp_dir.path().join("other");
+        fs::create_dir_all(&docs).unwrap();
+        fs::create_dir_all(&other).unwrap();
+        fs::write(docs.join("index.md"), "# Index").unwrap();
+        fs::write(docs.join("guide.md"), "# Guide").unwrap();
+        fs::write(other.join("notes.md"), "# Notes").unwrap();
+
+        let policy = PathPolicy::default();
+        assert!(policy.check(docs.join("index.md")).is_err());
+
+        let opened = policy.approve_file(docs.join("index.md")).unwrap();
+        assert_eq!(policy.check(docs.join("index.md")).unwrap(), opened);
+        assert!(policy.check(docs.join("guide.md")).is_ok());
+        assert!(policy.check(other.join("notes.md")).is_err());
+
+        assert!(matches!(
+            policy.approve_file(docs.join("missing.md")),
+            Err(PathPolicyError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_opened_file_approves_only_its_folder() {
+        let temp_dir = tempdir().unwrap();
+        let repo = temp_dir.path().join("repo");
+        fs::create_dir_all(repo.join(".git")).unwrap();
+        fs::create_dir_all(repo.join("docs/sub")).unwrap();
+        fs::create_dir_all(repo.join("private")).unwrap();
+        fs::write(repo.join("docs/index.md"), "# Index").unwrap();
+        fs::write(repo.join("docs/sub/page.md"), "# Page").unwrap();
+        fs::write(repo.join("README.md"), "# Readme").unwrap();
+        fs::write(repo.join("private/notes.md"), "# Notes").unwrap();
+
+        let policy = PathPolicy::default();
+        policy.approve_file(repo.join("docs/index.md")).unwrap();
+
+        assert!(policy.check(repo.join("docs/sub/page.md")).is_ok());
+        assert!(matches!(
+            policy.check(repo.join("README.md")),
+            Err(PathPolicyError::Denied(_))
+        ));
+        assert!(matches!(
+            policy.check(repo.join("private/notes.md")),
+            Err(PathPolicyError::Denied(_))
+        ));
+
+        assert!(policy.check_dir(repo.join("docs/sub")).is_ok());
+        assert!(matches!(
+            policy.check_dir(&repo),
+            Err(PathPolicyError::Denied(_))
+        ));
+        assert!(matches!(
+            policy.check_dir(repo.join("docs/../private")),
+            Err(PathPolicyError::Denied(_))
+        ));
+        assert!(matches!(
+            policy.check_dir(repo.join("docs/index.md")),
+            Err(PathPolicyError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_archives() {
+        use std::io::Write;
+
+        let temp_dir = tempdir().unwrap();
+        let downloads = temp_dir.path().canonicalize().unwrap().join("downloads");
+        fs::create_dir_all(&downloads).unwrap();
+        let archive = downloads.join("bundle.zip");
+        let mut zip = zip::ZipWriter::new(fs::File::create(&archive).unwrap());
+        for name in ["docs/index.md", "docs/guide.md", "docs/logo.png"] {
+            zip.start_file(name, zip::write::SimpleFileOptions::default())
+                .unwrap();
+            zip.write_all(b"# Docs").unwrap();
+        }
+        zip.finish().unwrap();
+        fs::write(downloads.join("notes.md"), "# Notes").unwrap();
+        let entry = |name: &str| PathBuf::from(format!("{}!/{}", archive.display(), name));
+
+        let policy = PathPolicy::default();
+        assert!(matches!(
+            policy.check(entry("docs/index.md")),
+            Err(PathPolicyError::Denied(_))
+        ));
+
+        // Opening the archive opens its index and approves the whole archive
+        let opened = policy.approve_file(downloads.join("./bundle.zip")).unwrap();
+        assert_eq!(opened, entry("docs/index.md"));
+        assert_eq!(
+            policy.check(entry("docs/../docs/guide.md")).unwrap(),
+            entry("docs/guide.md")
+        );
+        assert!(policy.check(downloads.join("notes.md")).is_err());
+        assert!(matches!(
+            policy.check(entry("docs/missing.md")),
+            Err(PathPolicyError::NotFound(_))
+        ));
+        assert!(matches!(
+            policy.check(entry("docs/logo.png")),
+            Err(PathPolicyError::NotMarkdown(_))
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlink_escapes() {
+        use std::os::unix::fs::symlink;
+
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path().join("notes");
+        let outside = temp_dir.path().join("private");
+        fs::create_dir_all(&root).unwrap();
+        fs::create_dir_all(&outside).unwrap();
+        fs::write(outside.join("secret.md"), "# Secret").unwrap();
+        fs::write(outside.join("id_rsa"), "key").unwrap();
+        fs::write(root.join("local-key"), "key").unwrap();
+
+        // A Markdown-named link to a file outside the root
+        symlink(outside.join("secret.md"), root.join("linked.md")).unwrap();
+        // A linked folder pointing outside the root
+        symlink(&outside, root.join("private")).unwrap();
+        // A Markdown-named link to a non-Markdown file, inside and outside
+        symlink(outside.join("id_rsa"), root.join("key.md")).unwrap();
+        symlink(root.join("local-key"), root.join("local-key.md")).unwrap();
+
+        let policy = PathPolicy::new([&root]);
+
+        assert!(matches!(
+            policy.check(root.join("linked.md")),
+            Err(PathPolicyError::Denied(_))
+        ));
+        assert!(matches!(
+            policy.check(root.join("private/secret.md")),
+            Err(PathPolicyError::Denied(_))
+        ));
+        assert!(matches!(
+            policy.check(root.join("key.md")),
+            Err(PathPolicyError::NotMarkdown(_))
+        ));
+        assert!(matches!(
+            policy.check(root.join("local-key.md")),
+            Err(PathPolicyError::NotMarkdown(_))
+        ));
+    }
+}
@@ -0,0 +1,481 @@
+use crate::md::parser::RenderConfig;
+use crate::md::project::ProjectConfig;
+use crate::md::trust::TrustLevel;
+use crate::md::MarkdownDocument;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Name of the render cache folder inside the application cache directory
+pub const CACHE_DIR_NAME: &str = "render-cache";
+
+/// Maximum number of documents kept in the on-disk cache
+const MAX_DISK_ENTRIES: usize = 256;
+
+/// Render cache options, stored with the application settings.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct CacheOptions {
+    /// Number of rendered documents kept in memory; 0 turns the cache off
+    pub memory_entries: usize,
+    /// Also keep rendered documents in the application cache directory
+    pub disk: bool,
+}
+
+impl Default for CacheOptions {
+    fn default() -> Self {
+        Self {
+            memory_entries: 32,
+            disk: false,
+        }
+    }
+}
+
+/// Identifies one rendering of one version of a file.
+///
+/// The modification time and size catch most edits; the content hash
+/// catches edits that keep both, and the profile hash changes whenever the
+/// settings, project configuration or trust level the document is rendered
+/// with change.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct CacheKey {
+    /// Canonical path of the document
+    pub path: PathBuf,
+    /// Modification time of the file
+    pub modified: Option<SystemTime>,
+    /// Size of the file in bytes
+    pub size: u64,
+    /// Hash of the file content
+    pub content_hash: u64,
+    /// Hash of the render profile, see `render_profile`
+    pub profile: u64,
+}
+
+impl CacheKey {
+    /// Creates the key for a file's current content.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path of the document
+    /// * `content` - The content read from the file
+    /// * `profile` - Hash of the render profile
+    ///
+    /// # Returns
+    ///
+    /// * `Option<CacheKey>` - The key, or None if the file can't be inspected
+    pub fn new(path: &Path, content: &str, profile: u64) -> Option<Self> {
+        let path = path.canonicalize().ok()?;
+        let metadata = fs::metadata(&path).ok()?;
+        Some(Self {
+            modified: metadata.modified().ok(),
+            size: metadata.len(),
+            content_hash: hash_of(content),
+            profile,
+            path,
+        })
+    }
+
+    /// Returns the name of the file the entry is stored in on disk.
+    fn file_name(&self) -> String {
+        format!("{:016x}.json", hash_of(self))
+    }
+}
+
+/// Hashes everything besides the document that affects its rendering.
+///
+/// # Arguments
+///
+/// * `config` - The user's rendering configuration
+/// * `trust` - The trust level of the document, which decides how `config` is applied
+/// * `project` - The project configuration that applies to the document
+///
+/// # Returns
+///
+/// * `u64` - The profile hash
+pub fn render_profile(
+    config: &RenderConfig,
+    trust: TrustLevel,
+    project: Option<&ProjectConfig>,
+) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    serde_json::to_string(config)
+        .unwrap_or_default()
+        .hash(&mut hasher);
+    trust.hash(&mut hasher);
+    project.hash(&mut hasher);
+    // The project stylesheet is embedded in the document
+    let css_modified = project
+        .and_then(ProjectConfig::css_path)
+        .and_then(|css| fs::metadata(css).ok())
+        .and_then(|metadata| metadata.modified().ok());
+    css_modified.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Result of looking a document up in the render cache's memory.
+#[derive(Debug)]
+pub enum CacheLookup {
+    /// The document was in memory
+    Hit(Box<MarkdownDocument>),
+    /// Not in memory, but it may be in this file of the on-disk cache; read it
+    /// with `read_disk` and report the result with `RenderCache::record_disk_read`
+    Disk(PathBuf),
+    /// The document isn't cached
+    Miss,
+}
+
+/// Hit and miss counters of the render cache.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CacheStats {
+    /// Lookups answered from memory or disk
+    pub hits: u64,
+    /// Lookups that required rendering the document
+    pub misses: u64,
+    /// Hits that were read from the on-disk cache
+    pub disk_hits: u64,
+    /// Documents currently held in memory
+    pub entries: usize,
+}
+
+/// A document stored in the on-disk cache, with the key it was rendered for.
+#[derive(Serialize, Deserialize)]
+struct DiskEntry {
+    key: CacheKey,
+    document: MarkdownDocument,
+}
+
+/// Least-recently-used cache of rendered documents.
+///
+/// Rendering resolves wikilinks and embeds against other files, which aren't
+/// part of the key; reloading after renaming a linked note may show the old
+/// result until the document itself changes.
+///
+/// The cache itself never touches the disk: lookups and inserts return the
+/// on-disk file to read or write, so callers can do that after releasing
+/// the lock the cache is kept under.
+#[derive(Debug, Default)]
+pub struct RenderCache {
+    /// Maximum number of documents kept in memory
+    capacity: usize,
+    /// Rendered documents by key
+    entries: HashMap<CacheKey, MarkdownDocument>,
+    /// Keys from least to most recently used
+    order: VecDeque<CacheKey>,
+    /// Folder of the on-disk cache, if enabled
+    disk_dir: Option<PathBuf>,
+    /// Hit and miss counters
+    stats: CacheStats,
+}
+
+impl RenderCache {
+    /// Creates an in-memory cache.
+    ///
+    /// # Arguments
+    ///
+    /// * `capacity` - Maximum number of documents kept in memory
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            ..Self::default()
+        }
+    }
+
+    /// Also stores rendered documents in a folder, so they survive restarts.
+    ///
+    /// # Arguments
+    ///
+    /// * `dir` - The folder for the on-disk cache
+    pub fn with_disk_dir(mut self, dir: PathBuf) -> Self {
+        self.disk_dir = Some(dir);
+        self
+    }
+
+    /// Looks up a rendered document in memory.
+    ///
+    /// Counts a hit, or a miss if there is no on-disk cache to try next.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key of the file version and render profile
+    ///
+    /// # Returns
+    ///
+    /// * `CacheLookup` - The cached document, the on-disk file to try, or a miss
+    pub fn get(&mut self, key: &CacheKey) -> CacheLookup {
+        if let Some(document) = self.entries.get(key).cloned() {
+            self.touch(key);
+            self.stats.hits += 1;
+            return CacheLookup::Hit(Box::new(document));
+        }
+
+        match self.disk_file(key) {
+            Some(file) => CacheLookup::Disk(file),
+            None => {
+                self.stats.misses += 1;
+                CacheLookup::Miss
+            }
+        }
+    }
+
+    /// Records the result of reading the on-disk file returned by `get`,
+    /// keeping a found document in memory.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key that was looked up
+    /// * `document` - The document read from disk, if it was there
+    pub fn record_disk_read(&mut self, key: &CacheKey, document: Option<MarkdownDocument>) {
+        match document {
+            Some(document) => {
+                self.insert_memory(key.clone(), document);
+                self.stats.hits += 1;
+                self.stats.disk_hits += 1;
+            }
+            None => self.stats.misses += 1,
+        }
+    }
+
+    /// Stores a rendered document in memory.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key of the file version and render profile
+    /// * `document` - The rendered document
+    ///
+    /// # Returns
+    ///
+    /// * `Option<PathBuf>` - The on-disk file to store the document in with
+    ///   `write_disk`, if the on-disk cache is enabled
+    pub fn insert(&mut self, key: CacheKey, document: MarkdownDocument) -> Option<PathBuf> {
+        let file = self.disk_file(&key);
+        self.insert_memory(key, document);
+        file
+    }
+
+    /// Returns the hit and miss counters.
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            entries: self.entries.len(),
+            ..self.stats
+        }
+    }
+
+    /// Adds a document to memory, evicting the least recently used ones.
+    fn insert_memory(&mut self, key: CacheKey, document: MarkdownDocument) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.entries.insert(key.clone(), document).is_some() {
+            self.touch(&key);
+            return;
+        }
+        self.order.push_back(key);
+        while self.order.len() > self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.entries.remove(&oldest);
+            }
+        }
+    }
+
+    /// Marks a key as the most recently used.
+    fn touch(&mut self, key: &CacheKey) {
+        if let Some(pos) = self.order.iter().position(|k| k == key) {
+            if let Some(key) = self.order.remove(pos) {
+                self.order.push_back(key);
+            }
+        }
+    }
+
+    /// Returns the on-disk file for a key, if the on-disk cache is enabled.
+    fn disk_file(&self, key: &CacheKey) -> Option<PathBuf> {
+        self.disk_dir.as_ref().map(|dir| dir.join(key.file_name()))
+    }
+}
+
+/// Reads a document from an on-disk cache file.
+///
+/// # Arguments
+///
+/// * `file` - The file returned by `RenderCache::get`
+/// * `key` - The key that was looked up
+///
+/// # Returns
+///
+/// * `Option<MarkdownDocument>` - The stored document, if present and stored for this key
+pub fn read_disk(file: &Path, key: &CacheKey) -> Option<MarkdownDocument> {
+    let contents = fs::read_to_string(file).ok()?;
+    let entry: DiskEntry = serde_json::from_str(&contents).ok()?;
+    // Guard against hash collisions between keys
+    (entry.key == *key).then_some(entry.document)
+}
+
+/// Writes a document to an on-disk cache file and prunes old entries.
+///
+/// # Arguments
+///
+/// * `file` - The file returned by `RenderCache::insert`
+/// * `key` - The key the document was rendered for
+/// * `document` - The rendered document
+///
+/// # Returns
+///
+/// * `Result<(), String>` - Ok once written, or a description of the failure
+pub fn write_disk(file: &Path, key: &CacheKey, document: &MarkdownDocument) -> Result<(), String> {
+    let dir = file.parent().unwrap_or_else(|| Path::new("."));
+    fs::create_dir_all(dir).map_err(|e| format!("Failed to create cache directory: {}", e))?;
+
+    let entry = DiskEntry {
+        key: key.clone(),
+        document: document.clone(),
+    };
+    let json = serde_json::to_string(&entry)
+        .map_err(|e| format!("Failed to serialize document: {}", e))?;
+    fs::write(file, json).map_err(|e| format!("Failed to write cache file: {}", e))?;
+
+    prune_dir(dir, MAX_DISK_ENTRIES);
+    Ok(())
+}
+
+/// Removes the oldest cache files until at most `limit` remain.
+fn prune_dir(dir: &Path, limit: usize) {
+    let Ok(read_dir) = fs::read_dir(dir) else {
+        return;
+    };
+    let mut files: Vec<(SystemTime, PathBuf)> = read_dir
+        .filter_map(Result::ok)
+        .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "json"))
+        .map(|entry| {
+            let modified = entry
+                .metadata()
+                .and_then(|metadata| metadata.modified())
+                .unwrap_or(SystemTime::UNIX_EPOCH);
+            (modified, entry.path())
+        })
+        .collect();
+    if files.len() <= limit {
+        return;
+    }
+
+    files.sort();
+    let excess = files.len() - limit;
+    for (_, path) in files.into_iter().take(excess) {
+        if let Err(e) = fs::remove_file(&path) {
+            eprintln!("Failed to remove cache file {}: {}", path.display(), e);
+        }
+    }
+}
+
+/// Hashes a value with the standard library's hasher.
+///
+/// The hash may change between Rust releases, which only makes old
+/// on-disk entries miss.
+fn hash_of<T: Hash + ?Sized>(value: &T) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn document(path: &Path, content: &str) -> MarkdownDocument {
+        MarkdownDocument::new(
+            path.display().to_string(),
+            content.to_string(),
+            format!("<p>{}</p>", content),
+            vec![],
+        )
+    }
+
+    #[test]
+    fn test_key_changes_with_content_and_profile() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("a.md");
+        fs::write(&path, "one").unwrap();
+
+        let key = CacheKey::new(&path, "one", 1).unwrap();
+        assert_eq!(key, CacheKey::new(&path, "one", 1).unwrap());
+        assert_ne!(key, CacheKey::new(&path, "two", 1).unwrap());
+        assert_ne!(key, CacheKey::new(&path, "one", 2).unwrap());
+        assert!(CacheKey::new(&temp_dir.path().join("missing.md"), "one", 1).is_none());
+
+        let config = RenderConfig::default();
+        assert_ne!(
+            render_profile(&config, TrustLevel::Trusted, None),
+            render_profile(&config, TrustLevel::Restricted, None)
+        );
+    }
+
+    #[test]
+    fn test_lru_eviction_and_stats() {
+        let temp_dir = tempdir().unwrap();
+        let mut keys = Vec::new();
+        for name in ["a", "b", "c"] {
+            let path = temp_dir.path().join(format!("{}.md", name));
+            fs::write(&path, name).unwrap();
+            keys.push((
+                CacheKey::new(&path, name, 0).unwrap(),
+                document(&path, name),
+            ));
+        }
+
+        let mut cache = RenderCache::new(2);
+        assert_eq!(cache.insert(keys[0].0.clone(), keys[0].1.clone()), None);
+        cache.insert(keys[1].0.clone(), keys[1].1.clone());
+        // Using "a" leaves "b" as the least recently used
+        assert!(matches!(cache.get(&keys[0].0), CacheLookup::Hit(_)));
+        cache.insert(keys[2].0.clone(), keys[2].1.clone());
+
+        assert!(matches!(cache.get(&keys[1].0), CacheLookup::Miss));
+        assert!(matches!(cache.get(&keys[0].0), CacheLookup::Hit(_)));
+        assert!(
+            matches!(cache.get(&keys[2].0), CacheLookup::Hit(document) if document.raw_content == "c")
+        );
+
+        let stats = cache.stats();
+        assert_eq!(stats.hits, 3);
+        assert_eq!(stats.misses, 1);
+        assert_eq!(stats.disk_hits, 0);
+        assert_eq!(stats.entries, 2);
+    }
+
+    #[test]
+    fn test_disk_cache() {
+        let temp_dir = tempdir().unwrap();
+        let cache_dir = temp_dir.path().join(CACHE_DIR_NAME);
+        let path = temp_dir.path().join("a.md");
+        fs::write(&path, "# A").unwrap();
+        let key = CacheKey::new(&path, "# A", 0).unwrap();
+
+        let mut cache = RenderCache::new(4).with_disk_dir(cache_dir.clone());
+        let file = cache.insert(key.clone(), document(&path, "# A")).unwrap();
+        write_disk(&file, &key, &document(&path, "# A")).unwrap();
+
+        // A new cache, as after a restart, points at the document on disk
+        let mut cache = RenderCache::new(4).with_disk_dir(cache_dir.clone());
+        let CacheLookup::Disk(file) = cache.get(&key) else {
+            panic!("expected the on-disk file");
+        };
+        let stored = read_disk(&file, &key);
+        assert_eq!(stored.as_ref().unwrap().raw_content, "# A");
+        cache.record_disk_read(&key, stored);
+        assert!(matches!(cache.get(&key), CacheLookup::Hit(_)));
+        assert_eq!(cache.stats().disk_hits, 1);
+        assert_eq!(cache.stats().hits, 2);
+
+        // Another key's file isn't mistaken for this one
+        let other = CacheKey::new(&path, "# A", 1).unwrap();
+        assert!(read_disk(&file, &other).is_none());
+        cache.record_disk_read(&other, None);
+        assert_eq!(cache.stats().misses, 1);
+
+        prune_dir(&cache_dir, 0);
+        assert_eq!(fs::read_dir(&cache_dir).unwrap().count(), 0);
+    }
+}
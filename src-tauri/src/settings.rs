@@ -0,0 +1,220 @@
+use crate::export::pdf::PdfOptions;
+use crate::md::loader::LoadLimits;
+use crate::md::parser::RenderConfig;
+use crate::md::slides::SlideSplit;
+use crate::render_cache::CacheOptions;
+use crate::themes::ThemePreference;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// User settings persisted in the application config directory.
+///
+/// Settings are stored as JSON in `settings.json`. Missing fields fall back
+/// to their defaults so older settings files keep loading after upgrades.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct Settings {
+    /// Command template used to open documents in an external editor,
+    /// e.g. `code -g {path}:{line}`. Falls back to `$VISUAL`/`$EDITOR` when unset.
+    pub editor_command: Option<String>,
+    /// Rendering options such as custom admonition kinds
+    pub render: RenderConfig,
+    /// Page setup for PDF export
+    pub pdf: PdfOptions,
+    /// Where presentations start new slides
+    pub slide_split: SlideSplit,
+    /// Folders whose Markdown files may be opened from links, in addition to
+    /// files opened through the dialog or command line
+    pub allowed_roots: Vec<PathBuf>,
+    /// Folders whose documents open in trusted mode; others open restricted
+    pub trusted_folders: Vec<PathBuf>,
+    /// Size of the render cache and whether it is kept on disk
+    pub cache: CacheOptions,
+    /// Largest file that opens without asking first
+    pub limits: LoadLimits,
+    /// Selected theme, or the themes to use in light and dark mode
+    #[serde(flatten)]
+    pub theme: ThemePreference,
+}
+
+impl Settings {
+    /// Creates settings with default values.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Loads settings from the config directory.
+    /// Returns default settings if the file doesn't exist or is corrupted.
+    ///
+    /// # Arguments
+    ///
+    /// * `config_dir` - The application config directory path
+    ///
+    /// # Returns
+    ///
+    /// * `Settings` - The loaded settings or the defaults
+    pub fn load(config_dir: &Path) -> Self {
+        let settings_path = config_dir.join("settings.json");
+
+        if !settings_path.exists() {
+            return Self::new();
+        }
+
+        match fs::read_to_string(&settings_path) {
+            Ok(contents) => match serde_json::from_str::<Settings>(&contents) {
+                Ok(settings) => settings,
+                Err(e) => {
+                    eprintln!("Failed to parse settings.json (corrupted): {}", e);
+                    Self::new()
+                }
+            },
+            Err(e) => {
+                eprintln!("Failed to read settings.json: {}", e);
+                Self::new()
+            }
+        }
+    }
+
+    /// Saves settings to the config directory.
+    /// Creates the config directory if it doesn't exist.
+    ///
+    /// # Arguments
+    ///
+    /// * `config_dir` - The application config directory path
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), String>` - Ok on success, or error message
+    pub fn save(&self, config_dir: &Path) -> Result<(), String> {
+        if !config_dir.exists() {
+            fs::create_dir_all(config_dir)
+                .map_err(|e| format!("Failed to create config directory: {}", e))?;
+        }
+
+        let settings_path = config_dir.join("settings.json");
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("Failed to serialize settings: {}", e))?;
+
+        fs::write(&settings_path, json)
+            .map_err(|e| format!("Failed to write settings file: {}", e))?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_load_missing_returns_default() {
+        let temp_dir = tempdir().unwrap();
+        let settings = Settings::load(temp_dir.path());
+
+        assert_eq!(settings, Settings::default());
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let temp_dir = tempdir().unwrap();
+        let config_dir = temp_dir.path().to_path_buf();
+
+        let settings = Settings {
+            editor_command: Some("code -g {path}:{line}".to_string()),
+            ..Settings::default()
+        };
+        settings.save(&config_dir).unwrap();
+
+        let loaded = Settings::load(&config_dir);
+        assert_eq!(loaded, settings);
+    }
+
+    #[test]
+    fn test_load_corrupted_returns_default() {
+        let temp_dir = tempdir().unwrap();
+        let config_dir = temp_dir.path().to_path_buf();
+        fs::write(config_dir.join("settings.json"), "{ not json").unwrap();
+
+        let settings = Settings::load(&config_dir);
+        assert_eq!(settings, Settings::default());
+    }
+
+    #[test]
+    fn test_load_ignores_missing_fields() {
+        let temp_dir = tempdir().unwrap();
+        let config_dir = temp_dir.path().to_path_buf();
+        fs::write(config_dir.join("settings.json"), "{}").unwrap();
+
+        let settings = Settings::load(&config_dir);
+        assert_eq!(settings.editor_command, None);
+    }
+
+    #[test]
+    fn test_load_custom_admonitions() {
+        let temp_dir = tempdir().unwrap();
+        let config_dir = temp_dir.path().to_path_buf();
+        fs::write(
+            config_dir.join("settings.json"),
+            r#"{ "render": { "admonitions": { "todo": { "title": "To do", "like": "tip" } } } }"#,
+        )
+        .unwrap();
+
+        let settings = Settings::load(&config_dir);
+        let todo = &settings.render.admonitions["todo"];
+        assert_eq!(todo.title, "To do");
+        assert_eq!(todo.icon, "");
+        assert_eq!(todo.like.as_deref(), Some("tip"));
+    }
+
+    #[test]
+    fn test_load_pdf_options() {
+        use crate::export::pdf::PageSize;
+
+        let temp_dir = tempdir().unwrap();
+        let config_dir = temp_dir.path().to_path_buf();
+        fs::write(
+            config_dir.join("settings.json"),
+            r#"{ "pdf": { "page_size": "letter" } }"#,
+        )
+        .unwrap();
+
+        let settings = Settings::load(&config_dir);
+        assert_eq!(settings.pdf.page_size, PageSize::Letter);
+        assert_eq!(settings.pdf.margin_mm, 20);
+    }
+
+    #[test]
+    fn test_load_slide_split() {
+        let temp_dir = tempdir().unwrap();
+        let config_dir = temp_dir.path().to_path_buf();
+        assert_eq!(Settings::load(&config_dir).slide_split, SlideSplit::Rule);
+
+        fs::write(
+            config_dir.join("settings.json"),
+            r#"{ "slide_split": "h2" }"#,
+        )
+        .unwrap();
+        assert_eq!(Settings::load(&config_dir).slide_split, SlideSplit::H2);
+    }
+
+    #[test]
+    fn test_load_theme_preference() {
+        let temp_dir = tempdir().unwrap();
+        let config_dir = temp_dir.path().to_path_buf();
+        fs::write(
+            config_dir.join("settings.json"),
+            r#"{ "theme": "sepia", "dark_theme": "high-contrast", "slide_split": "h1" }"#,
+        )
+        .unwrap();
+
+        let settings = Settings::load(&config_dir);
+        assert_eq!(settings.theme.theme.as_deref(), Some("sepia"));
+        assert_eq!(settings.theme.dark_theme.as_deref(), Some("high-contrast"));
+        assert_eq!(settings.slide_split, SlideSplit::H1);
+
+        settings.save(&config_dir).unwrap();
+        assert_eq!(Settings::load(&config_dir), settings);
+    }
+}
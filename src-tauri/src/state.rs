@@ -1,7 +1,47 @@
 use crate::history::FileHistory;
+use crate::md::chunks::{self, Chunk};
+use crate::md::graph::LinkGraph;
+use crate::md::loader::{self, LoadLimits, MdLoadError};
+use crate::md::project::ProjectConfig;
+use crate::md::remote;
+use crate::md::trust::{self, TrustLevel};
 use crate::md::MarkdownDocument;
+use crate::path_policy::PathPolicy;
+use crate::render_cache::{self, CacheKey, CacheLookup, CacheStats, RenderCache};
+use crate::settings::Settings;
+use crate::themes::{self, ActiveTheme, Appearance, ThemeError};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
 
+/// Identifies one document load; starting another load supersedes it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LoadTicket(u64);
+
+/// What a document load is busy with.
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LoadStage {
+    /// Reading the file from disk
+    Reading,
+    /// Parsing and rendering the Markdown
+    Rendering,
+}
+
+/// Progress of loading a large document, emitted as `load-progress`.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct LoadProgress {
+    /// Path of the document being loaded
+    pub path: String,
+    /// What the load is busy with
+    pub stage: LoadStage,
+    /// Bytes read so far
+    pub bytes_read: u64,
+    /// Size of the file in bytes
+    pub total_bytes: u64,
+}
+
 /// Application state shared across Tauri commands.
 ///
 /// This struct holds the current document and application settings.
@@ -9,10 +49,24 @@ use std::sync::{Arc, Mutex};
 pub struct AppState {
     /// The currently loaded Markdown document
     pub current_document: Mutex<Option<MarkdownDocument>>,
+    /// Chunks of the current document, if it is too large to send at once
+    pub document_chunks: Mutex<Vec<Chunk>>,
     /// The current zoom factor (1.0 = 100%)
     pub zoom_factor: Mutex<f64>,
     /// File history for navigation
     pub file_history: Arc<Mutex<FileHistory>>,
+    /// User settings loaded from the config directory
+    pub settings: Mutex<Settings>,
+    /// Link graph of the current workspace, built on first use
+    pub link_graph: Mutex<Option<LinkGraph>>,
+    /// Whether the OS is currently in light or dark mode
+    pub system_appearance: Mutex<Appearance>,
+    /// Which files the frontend may open
+    pub path_policy: PathPolicy,
+    /// Recently rendered documents
+    pub render_cache: Mutex<RenderCache>,
+    /// Number of document loads started, used to cancel superseded loads
+    load_generation: AtomicU64,
 }
 
 impl AppState {
@@ -21,17 +75,487 @@ impl AppState {
     /// # Arguments
     ///
     /// * `file_history` - Shared file history instance
-    pub fn new(file_history: Arc<Mutex<FileHistory>>) -> Self {
+    /// * `settings` - User settings
+    pub fn new(file_history: Arc<Mutex<FileHistory>>, settings: Settings) -> Self {
         Self {
             current_document: Mutex::new(None),
+            document_chunks: Mutex::new(Vec::new()),
             zoom_factor: Mutex::new(1.0),
             file_history,
+            path_policy: PathPolicy::new(&settings.allowed_roots),
+            render_cache: Mutex::new(RenderCache::new(settings.cache.memory_entries)),
+            settings: Mutex::new(settings),
+            link_graph: Mutex::new(None),
+            system_appearance: Mutex::new(Appearance::Light),
+            load_generation: AtomicU64::new(0),
+        }
+    }
+
+    /// Keeps rendered documents in the given cache directory too, if the
+    /// settings enable the on-disk cache.
+    ///
+    /// # Arguments
+    ///
+    /// * `cache_dir` - The application cache directory
+    pub fn with_cache_dir(self, cache_dir: &Path) -> Self {
+        let options = self.settings.lock().unwrap().cache;
+        if options.disk {
+            let cache = RenderCache::new(options.memory_entries)
+                .with_disk_dir(cache_dir.join(render_cache::CACHE_DIR_NAME));
+            *self.render_cache.lock().unwrap() = cache;
+        }
+        self
+    }
+
+    /// Starts a document load, superseding any load still in progress.
+    pub fn begin_load(&self) -> LoadTicket {
+        LoadTicket(self.load_generation.fetch_add(1, Ordering::SeqCst) + 1)
+    }
+
+    /// Returns true if a newer load has started since the ticket was issued.
+    pub fn is_superseded(&self, ticket: LoadTicket) -> bool {
+        self.load_generation.load(Ordering::SeqCst) != ticket.0
+    }
+
+    /// Loads a document using the rendering options from the user settings.
+    ///
+    /// Files over the size limit fail with `MdLoadError::TooLarge`, so the
+    /// caller can ask before loading them without a limit. Unchanged documents
+    /// rendered with the same settings come from the render cache. Large files
+    /// report progress while they are read and before they are rendered. The
+    /// load stops with `MdLoadError::Cancelled` once a newer load has started.
+    /// No lock is held while reading or rendering.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The file path to load
+    /// * `ticket` - The ticket from `begin_load`
+    /// * `limits` - The size limit to apply, usually `Settings::limits`
+    /// * `progress` - Called with the progress of large files
+    ///
+    /// # Returns
+    ///
+    /// * `Result<MarkdownDocument, MdLoadError>` - The parsed document or an error
+    pub fn load_document<F>(
+        &self,
+        path: &str,
+        ticket: LoadTicket,
+        limits: &LoadLimits,
+        mut progress: F,
+    ) -> Result<MarkdownDocument, MdLoadError>
+    where
+        F: FnMut(LoadProgress),
+    {
+        let (config, trusted_folders) = {
+            let settings = self.settings.lock().unwrap();
+            (settings.render.clone(), settings.trusted_folders.clone())
+        };
+        let trust = trust::trust_level(Path::new(path), &trusted_folders);
+        let cancelled = || MdLoadError::Cancelled(path.to_string());
+
+        let loaded =
+            loader::load_markdown_file_with_progress(path, limits, |bytes_read, total_bytes| {
+                progress(LoadProgress {
+                    path: path.to_string(),
+                    stage: LoadStage::Reading,
+                    bytes_read,
+                    total_bytes,
+                });
+                !self.is_superseded(ticket)
+            })?;
+        if self.is_superseded(ticket) {
+            return Err(cancelled());
+        }
+        let raw_content = loaded.content;
+
+        let project = ProjectConfig::discover_trusted(path, &trusted_folders);
+        let profile = render_cache::render_profile(&config, trust, project.as_ref());
+        let key = CacheKey::new(Path::new(path), &raw_content, profile);
+        if let Some(key) = &key {
+            if let Some(mut document) = self.cached_document(key) {
+                // The cached copy may have been opened through another path to the same file
+                document.path = path.to_string();
+                return Ok(document);
+            }
+        }
+
+        let size = raw_content.len() as u64;
+        if size >= loader::PROGRESS_THRESHOLD {
+            progress(LoadProgress {
+                path: path.to_string(),
+                stage: LoadStage::Rendering,
+                bytes_read: size,
+                total_bytes: size,
+            });
+        }
+        let document = MarkdownDocument {
+            compression: loaded.compression,
+            ..MarkdownDocument::from_content_with_trust(path, raw_content, &config, trust)
+        };
+        if let Some(key) = key {
+            // Write the on-disk copy after releasing the lock
+            let file = self
+                .render_cache
+                .lock()
+                .unwrap()
+                .insert(key.clone(), document.clone());
+            if let Some(file) = file {
+                if let Err(e) = render_cache::write_disk(&file, &key, &document) {
+                    eprintln!("Failed to write render cache: {}", e);
+                }
+            }
+        }
+        Ok(document)
+    }
+
+    /// Looks a document up in the render cache, reading the on-disk cache
+    /// without holding the lock.
+    fn cached_document(&self, key: &CacheKey) -> Option<MarkdownDocument> {
+        let lookup = self.render_cache.lock().unwrap().get(key);
+        match lookup {
+            CacheLookup::Hit(document) => Some(*document),
+            CacheLookup::Disk(file) => {
+                let document = render_cache::read_disk(&file, key);
+                self.render_cache
+                    .lock()
+                    .unwrap()
+                    .record_disk_read(key, document.clone());
+                document
+            }
+            CacheLookup::Miss => None,
+        }
+    }
+
+    /// Makes a loaded document current, unless a newer load has started.
+    ///
+    /// A very large document is split into chunks here, outside the lock.
+    ///
+    /// # Arguments
+    ///
+    /// * `document` - The loaded document
+    /// * `ticket` - The ticket the document was loaded with
+    ///
+    /// # Returns
+    ///
+    /// * `Result<MarkdownDocument, MdLoadError>` - The document to send to the webview, or
+    ///   `MdLoadError::Cancelled` if the load was superseded
+    pub fn set_current_document(
+        &self,
+        document: MarkdownDocument,
+        ticket: LoadTicket,
+    ) -> Result<MarkdownDocument, MdLoadError> {
+        let document_chunks = chunks::chunk_document(&document);
+        let view = chunks::for_webview(&document, &document_chunks);
+
+        let mut current = self.current_document.lock().unwrap();
+        // Checked under the lock, so a newer document is never replaced by an older one
+        if self.is_superseded(ticket) {
+            return Err(MdLoadError::Cancelled(document.path));
         }
+        *current = Some(document);
+        *self.document_chunks.lock().unwrap() = document_chunks;
+        Ok(view)
+    }
+
+    /// Returns the current document as it is sent to the webview, if one is loaded.
+    pub fn current_view(&self) -> Option<MarkdownDocument> {
+        let current = self.current_document.lock().unwrap();
+        let document_chunks = self.document_chunks.lock().unwrap();
+        current
+            .as_ref()
+            .map(|document| chunks::for_webview(document, &document_chunks))
+    }
+
+    /// Returns a range of the current document's chunks.
+    ///
+    /// The range is clipped to the chunks that exist.
+    ///
+    /// # Arguments
+    ///
+    /// * `start` - Index of the first chunk
+    /// * `end` - Index just past the last chunk
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<Chunk>` - The chunks in the range
+    pub fn chunks(&self, start: usize, end: usize) -> Vec<Chunk> {
+        let document_chunks = self.document_chunks.lock().unwrap();
+        let end = end.min(document_chunks.len());
+        document_chunks[start.min(end)..end].to_vec()
+    }
+
+    /// Returns the render cache's hit and miss counters.
+    pub fn render_cache_stats(&self) -> CacheStats {
+        self.render_cache.lock().unwrap().stats()
+    }
+
+    /// Returns the trust level of the current document, if one is loaded.
+    pub fn current_trust(&self) -> Option<TrustLevel> {
+        self.current_document
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|doc| doc.trust)
+    }
+
+    /// Finds the project configuration for the current document, if any.
+    ///
+    /// Project files come with the document's folder, so they are ignored
+    /// in restricted mode.
+    pub fn current_project(&self) -> Option<ProjectConfig> {
+        let path = self
+            .current_document
+            .lock()
+            .unwrap()
+            .as_ref()
+            .filter(|doc| doc.trust == TrustLevel::Trusted)
+            .map(|doc| doc.path.clone());
+        path.and_then(ProjectConfig::discover)
+    }
+
+    /// Resolves the theme in effect for the current document.
+    ///
+    /// Combines the project's theme (for trusted documents), the user's theme
+    /// settings and the system appearance. An unknown or unreadable theme
+    /// falls back to the built-in theme matching the system appearance.
+    ///
+    /// Remote resources in the stylesheet follow the current document's
+    /// remote image settings.
+    ///
+    /// # Arguments
+    ///
+    /// * `config_dir` - The application config directory, if known
+    ///
+    /// # Returns
+    ///
+    /// * `Result<ActiveTheme, ThemeError>` - The theme to apply
+    pub fn active_theme(&self, config_dir: Option<&Path>) -> Result<ActiveTheme, ThemeError> {
+        let project = self.current_project();
+        let document = self
+            .current_document
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|doc| (PathBuf::from(&doc.path), doc.trust));
+        let system = *self.system_appearance.lock().unwrap();
+        let (id, remote_images) = {
+            let settings = self.settings.lock().unwrap();
+            let remote_images = match document {
+                Some((_, TrustLevel::Restricted)) => settings.render.restricted().remote_images,
+                _ => settings.render.remote_images.clone(),
+            };
+            let id = themes::active_theme_id(&settings.theme, system, project.as_ref());
+            (id, remote_images)
+        };
+
+        let mut theme = themes::load_theme(&id, config_dir, project.as_ref()).or_else(|e| {
+            eprintln!("Failed to load theme: {}", e);
+            themes::load_theme(themes::default_theme(system), config_dir, project.as_ref())
+        })?;
+        let document = document.as_ref().map(|(path, _)| path.as_path());
+        theme.css = remote::block_remote_css(&theme.css, &remote_images, document);
+        Ok(theme)
     }
 }
 
 impl Default for AppState {
     fn default() -> Self {
-        Self::new(Arc::new(Mutex::new(FileHistory::new())))
+        Self::new(Arc::new(Mutex::new(FileHistory::new())), Settings::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_theme_follows_remote_image_settings() {
+        let temp_dir = tempdir().unwrap();
+        let themes_dir = temp_dir.path().join(themes::THEMES_DIR_NAME);
+        fs::create_dir(&themes_dir).unwrap();
+        fs::write(
+            themes_dir.join("tracked.css"),
+            "body { background: url(https://tracker.example/pixel.png); }",
+        )
+        .unwrap();
+
+        let state = AppState::default();
+        state.settings.lock().unwrap().theme.theme = Some("tracked".to_string());
+        let theme = state.active_theme(Some(temp_dir.path())).unwrap();
+        assert_eq!(theme.theme.id, "tracked");
+        assert!(!theme.css.contains("tracker.example"));
+
+        state
+            .settings
+            .lock()
+            .unwrap()
+            .render
+            .remote_images
+            .allow_all = true;
+        let theme = state.active_theme(Some(temp_dir.path())).unwrap();
+        assert!(theme.css.contains("url(https://tracker.example/pixel.png)"));
+    }
+
+    #[test]
+    fn test_superseded_load_is_cancelled() {
+        let temp_dir = tempdir().unwrap();
+        let first = temp_dir.path().join("first.md");
+        let second = temp_dir.path().join("second.md");
+        fs::write(&first, "# First").unwrap();
+        fs::write(&second, "# Second").unwrap();
+        let state = AppState::default();
+
+        let old_ticket = state.begin_load();
+        let new_ticket = state.begin_load();
+        assert!(state.is_superseded(old_ticket));
+
+        let result = state.load_document(
+            &first.to_string_lossy(),
+            old_ticket,
+            &LoadLimits::default(),
+            |_| {},
+        );
+        assert!(matches!(result, Err(MdLoadError::Cancelled(_))));
+
+        // A document loaded before the newer load started can't replace it
+        let racing = AppState::default();
+        let ticket = racing.begin_load();
+        let document = racing
+            .load_document(
+                &first.to_string_lossy(),
+                ticket,
+                &LoadLimits::default(),
+                |_| {},
+            )
+            .unwrap();
+        racing.begin_load();
+        assert!(matches!(
+            racing.set_current_document(document, ticket),
+            Err(MdLoadError::Cancelled(_))
+        ));
+        assert!(racing.current_document.lock().unwrap().is_none());
+
+        let document = state
+            .load_document(
+                &second.to_string_lossy(),
+                new_ticket,
+                &LoadLimits::default(),
+                |_| {},
+            )
+            .unwrap();
+        state.set_current_document(document, new_ticket).unwrap();
+        assert_eq!(
+            state
+                .current_document
+                .lock()
+                .unwrap()
+                .as_ref()
+                .unwrap()
+                .raw_content,
+            "# Second"
+        );
+    }
+
+    #[test]
+    fn test_load_uses_render_cache() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("doc.md");
+        fs::write(&path, "# Cached").unwrap();
+        let path = path.to_string_lossy();
+        let state = AppState::default();
+
+        let first = state
+            .load_document(&path, state.begin_load(), &LoadLimits::default(), |_| {})
+            .unwrap();
+        let second = state
+            .load_document(&path, state.begin_load(), &LoadLimits::default(), |_| {})
+            .unwrap();
+        assert_eq!(first.html_content, second.html_content);
+
+        let stats = state.render_cache_stats();
+        assert_eq!(stats.misses, 1);
+        assert_eq!(stats.hits, 1);
+
+        fs::write(temp_dir.path().join("doc.md"), "# Changed").unwrap();
+        let changed = state
+            .load_document(&path, state.begin_load(), &LoadLimits::default(), |_| {})
+            .unwrap();
+        assert!(changed.html_content.contains("Changed"));
+        assert_eq!(state.render_cache_stats().misses, 2);
+    }
+
+    #[test]
+    fn test_large_document_is_chunked() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("changelog.md");
+        let entry = format!("## Release\n\n{}\n\n", "Fixed a bug. ".repeat(50));
+        fs::write(
+            &path,
+            entry.repeat(chunks::CHUNKED_THRESHOLD / entry.len() + 1),
+        )
+        .unwrap();
+        let state = AppState::default();
+
+        let ticket = state.begin_load();
+        let document = state
+            .load_document(
+                &path.to_string_lossy(),
+                ticket,
+                &LoadLimits::default(),
+                |_| {},
+            )
+            .unwrap();
+        let view = state.set_current_document(document, ticket).unwrap();
+
+        // The webview gets an outline and the first chunks; the backend keeps the full HTML
+        assert!(view.html_content.is_empty());
+        let chunked = view.chunked.unwrap();
+        assert!(chunked.chunks.len() > chunked.initial.len());
+        assert!(!state
+            .current_document
+            .lock()
+            .unwrap()
+            .as_ref()
+            .unwrap()
+            .html_content
+            .is_empty());
+        assert_eq!(state.current_view().unwrap().chunked.unwrap(), chunked);
+
+        let count = chunked.chunks.len();
+        let rest = state.chunks(chunked.initial.len(), count + 10);
+        assert_eq!(rest.len(), count - chunked.initial.len());
+        assert_eq!(rest.last().unwrap().id, chunked.chunks[count - 1].id);
+        assert!(state.chunks(count + 1, count + 5).is_empty());
+    }
+
+    #[test]
+    fn test_load_document_from_archive() {
+        use std::io::Write;
+
+        let temp_dir = tempdir().unwrap();
+        let archive = temp_dir.path().canonicalize().unwrap().join("bundle.zip");
+        let mut zip = zip::ZipWriter::new(fs::File::create(&archive).unwrap());
+        let options = zip::write::SimpleFileOptions::default();
+        zip.start_file("docs/index.md", options).unwrap();
+        zip.write_all(b"# Bundle\n\n[Guide](guide.md)\n\n![Logo](../img/logo.png)\n")
+            .unwrap();
+        zip.start_file("img/logo.png", options).unwrap();
+        zip.write_all(b"\x89PNG").unwrap();
+        zip.finish().unwrap();
+        let path = format!("{}!/docs/index.md", archive.display());
+        let state = AppState::default();
+
+        let document = state
+            .load_document(&path, state.begin_load(), &LoadLimits::default(), |_| {})
+            .unwrap();
+        assert_eq!(document.path, path);
+        assert_eq!(document.archive, Some(archive.display().to_string()));
+        assert_eq!(document.link_root, Some(format!("{}!", archive.display())));
+        assert!(document
+            .html_content
+            .contains("src=\"data:image/png;base64,iVBORw==\""));
+        assert!(document.html_content.contains("href=\"guide.md\""));
     }
 }
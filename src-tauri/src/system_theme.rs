@@ -0,0 +1,140 @@
+use crate::themes::Appearance;
+
+#[cfg(target_os = "linux")]
+use futures_util::StreamExt;
+#[cfg(target_os = "linux")]
+use zbus::zvariant::{OwnedValue, Value};
+
+/// D-Bus name of the desktop portal.
+#[cfg(target_os = "linux")]
+const PORTAL_DEST: &str = "org.freedesktop.portal.Desktop";
+
+/// Object path of the desktop portal.
+#[cfg(target_os = "linux")]
+const PORTAL_PATH: &str = "/org/freedesktop/portal/desktop";
+
+/// Interface of the portal's settings.
+#[cfg(target_os = "linux")]
+const SETTINGS_INTERFACE: &str = "org.freedesktop.portal.Settings";
+
+/// Settings namespace holding the color-scheme preference.
+const APPEARANCE_NAMESPACE: &str = "org.freedesktop.appearance";
+
+/// Key of the color-scheme preference.
+const COLOR_SCHEME_KEY: &str = "color-scheme";
+
+/// Converts the portal's `color-scheme` value into an appearance.
+///
+/// The value is `0` for no preference, `1` for dark and `2` for light.
+///
+/// # Arguments
+///
+/// * `value` - The `color-scheme` setting
+///
+/// # Returns
+///
+/// * `Option<Appearance>` - The preferred appearance, or None without a preference,
+///   in which case the window theme applies
+pub fn color_scheme(value: u32) -> Option<Appearance> {
+    match value {
+        1 => Some(Appearance::Dark),
+        2 => Some(Appearance::Light),
+        _ => None,
+    }
+}
+
+/// Returns true if a `SettingChanged` signal is about the color scheme.
+pub fn is_color_scheme(namespace: &str, key: &str) -> bool {
+    namespace == APPEARANCE_NAMESPACE && key == COLOR_SCHEME_KEY
+}
+
+/// Reads a `u32` from a portal value, which `Read` wraps in an extra variant.
+#[cfg(target_os = "linux")]
+fn as_u32(value: &Value<'_>) -> Option<u32> {
+    match value {
+        Value::U32(value) => Some(*value),
+        Value::Value(inner) => as_u32(inner),
+        _ => None,
+    }
+}
+
+/// Follows the desktop portal's color-scheme preference.
+///
+/// Reports the current preference, then each change, to `on_change`, which
+/// gets None when there is no preference. Runs until the task is dropped or
+/// the portal connection fails; callers run it on the async runtime so the
+/// D-Bus calls never block the main thread.
+///
+/// # Arguments
+///
+/// * `on_change` - Called with the current preference and each new one
+///
+/// # Returns
+///
+/// * `zbus::Result<()>` - An error if the portal is unavailable or the connection drops
+#[cfg(target_os = "linux")]
+pub async fn watch_portal<F>(on_change: F) -> zbus::Result<()>
+where
+    F: Fn(Option<Appearance>) + Send + 'static,
+{
+    let connection = zbus::Connection::session().await?;
+    let proxy = zbus::Proxy::new(&connection, PORTAL_DEST, PORTAL_PATH, SETTINGS_INTERFACE).await?;
+
+    // Subscribe first so a change during the read isn't missed
+    let mut changes = proxy.receive_signal("SettingChanged").await?;
+    let current: OwnedValue = proxy
+        .call("Read", &(APPEARANCE_NAMESPACE, COLOR_SCHEME_KEY))
+        .await?;
+    on_change(as_u32(&current).and_then(color_scheme));
+
+    while let Some(message) = changes.next().await {
+        let Ok((namespace, key, value)) =
+            message.body().deserialize::<(String, String, OwnedValue)>()
+        else {
+            continue;
+        };
+        if is_color_scheme(&namespace, &key) {
+            on_change(as_u32(&value).and_then(color_scheme));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_color_scheme() {
+        assert_eq!(color_scheme(1), Some(Appearance::Dark));
+        assert_eq!(color_scheme(2), Some(Appearance::Light));
+        // No preference follows the window theme
+        assert_eq!(color_scheme(0), None);
+        assert_eq!(color_scheme(7), None);
+    }
+
+    #[test]
+    fn test_is_color_scheme() {
+        assert!(is_color_scheme(
+            "org.freedesktop.appearance",
+            "color-scheme"
+        ));
+        assert!(!is_color_scheme(
+            "org.freedesktop.appearance",
+            "accent-color"
+        ));
+        assert!(!is_color_scheme(
+            "org.gnome.desktop.interface",
+            "color-scheme"
+        ));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_as_u32() {
+        let nested = Value::Value(Box::new(Value::Value(Box::new(Value::U32(1)))));
+        assert_eq!(as_u32(&nested), Some(1));
+        assert_eq!(as_u32(&Value::U32(2)), Some(2));
+        assert_eq!(as_u32(&Value::from("dark")), None);
+    }
+}
@@ -0,0 +1,603 @@
+use crate::md::project::ProjectConfig;
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Name of the folder in the app config directory that holds user themes.
+pub const THEMES_DIR_NAME: &str = "themes";
+
+/// Themes bundled with the application: id and stylesheet.
+///
+/// The light theme is the base stylesheet itself, so it adds no CSS.
+const BUILT_IN_THEMES: &[(&str, &str)] = &[
+    ("light", "/*\n * name: Light\n * appearance: light\n */\n"),
+    ("dark", include_str!("themes/dark.css")),
+    ("high-contrast", include_str!("themes/high-contrast.css")),
+];
+
+/// Errors that can occur when loading a theme.
+#[derive(Debug, thiserror::Error)]
+pub enum ThemeError {
+    /// No theme with the requested identifier exists
+    #[error("Unknown theme: {0}")]
+    NotFound(String),
+
+    /// The theme stylesheet could not be read
+    #[error("Failed to read theme '{id}': {source}")]
+    Io {
+        id: String,
+        #[source]
+        source: io::Error,
+    },
+
+    /// The theme folders could not be watched
+    #[error("Failed to watch the theme folders: {0}")]
+    Watch(#[from] notify::Error),
+}
+
+/// Whether a theme has a light or a dark background.
+///
+/// The frontend uses this to pick matching colors for code blocks.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Appearance {
+    #[default]
+    Light,
+    Dark,
+}
+
+/// Where a theme was found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ThemeSource {
+    /// Bundled with the application
+    BuiltIn,
+    /// The `themes` folder in the app config directory
+    User,
+    /// The `themes` folder configured in the project file
+    Project,
+}
+
+/// How the user chooses a theme, stored in `settings.json`.
+///
+/// An explicitly selected theme always applies. Without one, the theme
+/// follows the system's light or dark mode, using `light_theme` and
+/// `dark_theme` or the matching built-in theme.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ThemePreference {
+    /// Theme picked in View → Theme; unset to follow the system
+    pub theme: Option<String>,
+    /// Theme used while the system is in light mode
+    pub light_theme: Option<String>,
+    /// Theme used while the system is in dark mode
+    pub dark_theme: Option<String>,
+}
+
+impl ThemePreference {
+    /// Returns the theme the user's settings choose for a system appearance, if any.
+    ///
+    /// # Arguments
+    ///
+    /// * `system` - The system's current light or dark mode
+    ///
+    /// # Returns
+    ///
+    /// * `Option<&str>` - The explicit theme, else the theme configured for the appearance
+    pub fn theme_for(&self, system: Appearance) -> Option<&str> {
+        let per_appearance = match system {
+            Appearance::Light => &self.light_theme,
+            Appearance::Dark => &self.dark_theme,
+        };
+        self.theme.as_deref().or(per_appearance.as_deref())
+    }
+}
+
+/// A theme available for selection.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Theme {
+    /// Identifier used in settings and project files (the file stem for stylesheets)
+    pub id: String,
+    /// Display name shown in the theme menu
+    pub name: String,
+    /// Light or dark background
+    pub appearance: Appearance,
+    /// Where the theme was found
+    pub source: ThemeSource,
+    /// Stylesheet path for user and project themes
+    #[serde(skip)]
+    pub path: Option<PathBuf>,
+}
+
+/// A theme together with its stylesheet, ready to apply.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ActiveTheme {
+    #[serde(flatten)]
+    pub theme: Theme,
+    /// CSS applied on top of the base styles
+    pub css: String,
+}
+
+impl Theme {
+    /// Builds a theme from a stylesheet and its metadata header.
+    ///
+    /// A theme declares its metadata in a leading comment:
+    ///
+    /// ```css
+    /// /*
+    ///  * name: Solarized Dark
+    ///  * appearance: dark
+    ///  */
+    /// ```
+    ///
+    /// The name defaults to the id and the appearance to light.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The theme identifier
+    /// * `css` - The stylesheet contents
+    /// * `source` - Where the theme was found
+    fn from_css(id: &str, css: &str, source: ThemeSource) -> Self {
+        let mut theme = Theme {
+            id: id.to_string(),
+            name: id.to_string(),
+            appearance: Appearance::Light,
+            source,
+            path: None,
+        };
+
+        let header = css
+            .trim_start()
+            .strip_prefix("/*")
+            .and_then(|rest| rest.split_once("*/"))
+            .map(|(header, _)| header)
+            .unwrap_or_default();
+        for line in header.lines() {
+            let line = line.trim().trim_start_matches('*').trim();
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let value = value.trim();
+            match key.trim().to_ascii_lowercase().as_str() {
+                "name" if !value.is_empty() => theme.name = value.to_string(),
+                "appearance" if value.eq_ignore_ascii_case("dark") => {
+                    theme.appearance = Appearance::Dark
+                }
+                "appearance" => theme.appearance = Appearance::Light,
+                _ => {}
+            }
+        }
+
+        theme
+    }
+}
+
+/// Lists the themes available for a document.
+///
+/// Built-in themes come first, followed by user themes and project themes.
+/// A user or project theme with the same id as an earlier one replaces it,
+/// so a project can ship its own `dark.css`.
+///
+/// # Arguments
+///
+/// * `config_dir` - The application config directory, if known
+/// * `project` - The project configuration that applies to the document, if any
+///
+/// # Returns
+///
+/// * `Vec<Theme>` - The available themes
+pub fn list_themes(config_dir: Option<&Path>, project: Option<&ProjectConfig>) -> Vec<Theme> {
+    let mut themes: Vec<Theme> = BUILT_IN_THEMES
+        .iter()
+        .map(|(id, css)| Theme::from_css(id, css, ThemeSource::BuiltIn))
+        .collect();
+
+    let user_dir = config_dir.map(|dir| dir.join(THEMES_DIR_NAME));
+    let project_dir = project.and_then(ProjectConfig::themes_path);
+    let folders = [
+        (user_dir, ThemeSource::User),
+        (project_dir, ThemeSource::Project),
+    ];
+
+    for (dir, source) in folders {
+        let Some(dir) = dir else { continue };
+        for theme in themes_in_dir(&dir, source) {
+            match themes.iter_mut().find(|existing| existing.id == theme.id) {
+                Some(existing) => *existing = theme,
+                None => themes.push(theme),
+            }
+        }
+    }
+
+    themes
+}
+
+/// Returns the folders user and project themes are read from.
+///
+/// # Arguments
+///
+/// * `config_dir` - The application config directory, if known
+/// * `project` - The project configuration that applies to the document, if any
+///
+/// # Returns
+///
+/// * `Vec<PathBuf>` - The folders, which need not exist
+pub fn theme_dirs(config_dir: Option<&Path>, project: Option<&ProjectConfig>) -> Vec<PathBuf> {
+    let user_dir = config_dir.map(|dir| dir.join(THEMES_DIR_NAME));
+    let project_dir = project.and_then(ProjectConfig::themes_path);
+    user_dir.into_iter().chain(project_dir).collect()
+}
+
+/// Reports when themes are added, changed or removed in the theme folders.
+///
+/// A folder that doesn't exist yet is watched through its parent, so its
+/// creation is reported too; call `watch` again afterwards to look inside.
+pub struct ThemeWatcher {
+    watcher: RecommendedWatcher,
+    /// The theme folders, shared with the event handler
+    dirs: Arc<Mutex<Vec<PathBuf>>>,
+    /// The folders handed to `watcher`
+    watched: Vec<PathBuf>,
+}
+
+impl ThemeWatcher {
+    /// Creates a watcher that watches nothing until `watch` is called.
+    ///
+    /// # Arguments
+    ///
+    /// * `on_change` - Called on the watcher's thread after a change in a theme folder;
+    ///   it must not call `watch` there, which waits for that thread
+    ///
+    /// # Returns
+    ///
+    /// * `Result<ThemeWatcher, ThemeError>` - The watcher, or `ThemeError::Watch` if the
+    ///   platform can't watch files
+    pub fn new<F>(on_change: F) -> Result<Self, ThemeError>
+    where
+        F: Fn() + Send + 'static,
+    {
+        let dirs = Arc::new(Mutex::new(Vec::<PathBuf>::new()));
+        let handler_dirs = dirs.clone();
+        let watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+            let Ok(event) = event else { return };
+            if matches!(event.kind, EventKind::Access(_)) {
+                return;
+            }
+            let in_theme_dir = {
+                let dirs = handler_dirs.lock().unwrap();
+                event
+                    .paths
+                    .iter()
+                    .any(|path| dirs.iter().any(|dir| path.starts_with(dir)))
+            };
+            if in_theme_dir {
+                on_change();
+            }
+        })?;
+
+        Ok(Self {
+            watcher,
+            dirs,
+            watched: Vec::new(),
+        })
+    }
+
+    /// Watches a new set of theme folders in place of the previous ones.
+    ///
+    /// Folders that can't be watched are logged and skipped.
+    ///
+    /// # Arguments
+    ///
+    /// * `dirs` - The theme folders, see `theme_dirs`
+    pub fn watch(&mut self, dirs: &[PathBuf]) {
+        let mut targets = Vec::new();
+        let mut theme_dirs = Vec::new();
+        for dir in dirs {
+            let Some((target, dir)) = watch_target(dir) else {
+                continue;
+            };
+            if !targets.contains(&target) {
+                targets.push(target);
+            }
+            theme_dirs.push(dir);
+        }
+
+        for path in self.watched.iter().filter(|path| !targets.contains(path)) {
+            if let Err(e) = self.watcher.unwatch(path) {
+                eprintln!("Failed to stop watching {}: {}", path.display(), e);
+            }
+        }
+        let mut watched = Vec::new();
+        for path in targets {
+            if !self.watched.contains(&path) {
+                if let Err(e) = self.watcher.watch(&path, RecursiveMode::NonRecursive) {
+                    eprintln!("Failed to watch {}: {}", path.display(), e);
+                    continue;
+                }
+            }
+            watched.push(path);
+        }
+
+        *self.dirs.lock().unwrap() = theme_dirs;
+        self.watched = watched;
+    }
+}
+
+/// Returns the folder to watch for a theme folder and the theme folder as
+/// event paths will name it.
+///
+/// Paths are canonical, since some platforms report events that way.
+fn watch_target(dir: &Path) -> Option<(PathBuf, PathBuf)> {
+    if dir.is_dir() {
+        let dir = dir.canonicalize().ok()?;
+        return Some((dir.clone(), dir));
+    }
+    let parent = dir.parent()?.canonicalize().ok()?;
+    let name = dir.file_name()?;
+    parent.is_dir().then(|| (parent.clone(), parent.join(name)))
+}
+
+/// Loads a theme's stylesheet.
+///
+/// # Arguments
+///
+/// * `id` - The theme identifier
+/// * `config_dir` - The application config directory, if known
+/// * `project` - The project configuration that applies to the document, if any
+///
+/// # Returns
+///
+/// * `Result<ActiveTheme, ThemeError>` - The theme with its CSS, or an error
+pub fn load_theme(
+    id: &str,
+    config_dir: Option<&Path>,
+    project: Option<&ProjectConfig>,
+) -> Result<ActiveTheme, ThemeError> {
+    let theme = list_themes(config_dir, project)
+        .into_iter()
+        .find(|theme| theme.id == id)
+        .ok_or_else(|| ThemeError::NotFound(id.to_string()))?;
+
+    let css = match &theme.path {
+        Some(path) => fs::read_to_string(path).map_err(|source| ThemeError::Io {
+            id: id.to_string(),
+            source,
+        })?,
+        None => BUILT_IN_THEMES
+            .iter()
+            .find(|(built_in, _)| *built_in == id)
+            .map(|(_, css)| css.to_string())
+            .unwrap_or_default(),
+    };
+
+    Ok(ActiveTheme { theme, css })
+}
+
+/// Returns the built-in theme matching a system appearance.
+///
+/// # Arguments
+///
+/// * `system` - The system's current light or dark mode
+///
+/// # Returns
+///
+/// * `&'static str` - `light` or `dark`
+pub fn default_theme(system: Appearance) -> &'static str {
+    match system {
+        Appearance::Light => "light",
+        Appearance::Dark => "dark",
+    }
+}
+
+/// Returns the id of the theme in effect for a document.
+///
+/// A project's `theme` wins over the user's settings, which win over the
+/// built-in theme matching the system appearance.
+///
+/// # Arguments
+///
+/// * `preference` - The theme settings from the user's `settings.json`
+/// * `system` - The system's current light or dark mode
+/// * `project` - The project configuration that applies to the document, if any
+///
+/// # Returns
+///
+/// * `String` - The effective theme id
+pub fn active_theme_id(
+    preference: &ThemePreference,
+    system: Appearance,
+    project: Option<&ProjectConfig>,
+) -> String {
+    project
+        .and_then(|project| project.theme.as_deref())
+        .or(preference.theme_for(system))
+        .unwrap_or(default_theme(system))
+        .to_string()
+}
+
+/// Reads the `.css` themes in a folder, sorted by id.
+fn themes_in_dir(dir: &Path, source: ThemeSource) -> Vec<Theme> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut themes: Vec<Theme> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "css"))
+        .filter_map(|path| {
+            let id = path.file_stem()?.to_str()?.to_string();
+            match fs::read_to_string(&path) {
+                Ok(css) => Some(Theme {
+                    path: Some(path),
+                    ..Theme::from_css(&id, &css, source)
+                }),
+                Err(e) => {
+                    eprintln!("Failed to read theme {}: {}", path.display(), e);
+                    None
+                }
+            }
+        })
+        .collect();
+    themes.sort_by(|a, b| a.id.cmp(&b.id));
+    themes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_theme_metadata() {
+        let theme = Theme::from_css(
+            "solarized",
+            "/*\n * name: Solarized Dark\n * appearance: dark\n */\nbody {}",
+            ThemeSource::User,
+        );
+        assert_eq!(theme.name, "Solarized Dark");
+        assert_eq!(theme.appearance, Appearance::Dark);
+
+        let plain = Theme::from_css("plain", "body { color: red; }", ThemeSource::User);
+        assert_eq!(plain.name, "plain");
+        assert_eq!(plain.appearance, Appearance::Light);
+    }
+
+    #[test]
+    fn test_list_builtin_themes() {
+        let themes = list_themes(None, None);
+        let ids: Vec<&str> = themes.iter().map(|theme| theme.id.as_str()).collect();
+        assert_eq!(ids, vec!["light", "dark", "high-contrast"]);
+        assert_eq!(themes[1].appearance, Appearance::Dark);
+        assert!(themes
+            .iter()
+            .all(|theme| theme.source == ThemeSource::BuiltIn));
+    }
+
+    #[test]
+    fn test_user_and_project_themes() {
+        let temp_dir = tempdir().unwrap();
+        let config_dir = temp_dir.path().join("config");
+        let user_themes = config_dir.join(THEMES_DIR_NAME);
+        fs::create_dir_all(&user_themes).unwrap();
+        fs::write(
+            user_themes.join("sepia.css"),
+            "/* name: Sepia */\nbody { background: #f4ecd8; }",
+        )
+        .unwrap();
+        fs::write(user_themes.join("notes.txt"), "not a theme").unwrap();
+
+        let project_dir = temp_dir.path().join("repo");
+        fs::create_dir_all(project_dir.join("branding")).unwrap();
+        fs::write(
+            project_dir.join("branding/dark.css"),
+            "/* appearance: dark */\nbody { background: #001; }",
+        )
+        .unwrap();
+        let project = ProjectConfig {
+            path: project_dir.join(".mdview.toml"),
+            themes: Some(PathBuf::from("branding")),
+            theme: Some("dark".to_string()),
+            ..ProjectConfig::default()
+        };
+
+        let themes = list_themes(Some(&config_dir), Some(&project));
+        let ids: Vec<&str> = themes.iter().map(|theme| theme.id.as_str()).collect();
+        assert_eq!(ids, vec!["light", "dark", "high-contrast", "sepia"]);
+        assert_eq!(themes[1].source, ThemeSource::Project);
+        assert_eq!(themes[3].name, "Sepia");
+
+        let preference = ThemePreference {
+            theme: Some("sepia".to_string()),
+            ..ThemePreference::default()
+        };
+        let active = load_theme(
+            &active_theme_id(&preference, Appearance::Light, Some(&project)),
+            Some(&config_dir),
+            Some(&project),
+        )
+        .unwrap();
+        assert_eq!(active.theme.id, "dark");
+        assert!(active.css.contains("#001"));
+
+        let sepia = load_theme("sepia", Some(&config_dir), None).unwrap();
+        assert!(sepia.css.contains("#f4ecd8"));
+    }
+
+    #[test]
+    fn test_watch_theme_folders() {
+        use std::sync::mpsc;
+        use std::time::Duration;
+
+        let temp_dir = tempdir().unwrap();
+        let config_dir = temp_dir.path().join("config");
+        fs::create_dir_all(&config_dir).unwrap();
+        let dirs = theme_dirs(Some(&config_dir), None);
+        assert_eq!(dirs, vec![config_dir.join(THEMES_DIR_NAME)]);
+
+        let (sender, changes) = mpsc::channel();
+        let mut watcher = ThemeWatcher::new(move || {
+            let _ = sender.send(());
+        })
+        .unwrap();
+        let settle = || while changes.recv_timeout(Duration::from_millis(300)).is_ok() {};
+        let timeout = Duration::from_secs(5);
+
+        // The missing folder is watched through its parent
+        watcher.watch(&dirs);
+        fs::create_dir(&dirs[0]).unwrap();
+        assert!(changes.recv_timeout(timeout).is_ok());
+
+        watcher.watch(&dirs);
+        settle();
+        fs::write(dirs[0].join("sepia.css"), "body {}").unwrap();
+        assert!(changes.recv_timeout(timeout).is_ok());
+
+        // Other files next to the folder are ignored
+        settle();
+        fs::write(config_dir.join("settings.json"), "{}").unwrap();
+        assert!(changes.recv_timeout(Duration::from_millis(500)).is_err());
+
+        watcher.watch(&[]);
+        settle();
+        fs::remove_file(dirs[0].join("sepia.css")).unwrap();
+        assert!(changes.recv_timeout(Duration::from_millis(500)).is_err());
+    }
+
+    #[test]
+    fn test_unknown_theme() {
+        assert!(matches!(
+            load_theme("missing", None, None),
+            Err(ThemeError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_follow_system_appearance() {
+        let mut preference = ThemePreference::default();
+        assert_eq!(
+            active_theme_id(&preference, Appearance::Light, None),
+            "light"
+        );
+        assert_eq!(active_theme_id(&preference, Appearance::Dark, None), "dark");
+
+        preference.dark_theme = Some("high-contrast".to_string());
+        assert_eq!(
+            active_theme_id(&preference, Appearance::Dark, None),
+            "high-contrast"
+        );
+        assert_eq!(
+            active_theme_id(&preference, Appearance::Light, None),
+            "light"
+        );
+
+        preference.theme = Some("sepia".to_string());
+        assert_eq!(
+            active_theme_id(&preference, Appearance::Dark, None),
+            "sepia"
+        );
+    }
+}